@@ -41,6 +41,7 @@ pub fn large_dense_heap_mock(heap_kb: usize) -> Arc<dyn Module> {
         guard_size: 4 * 1024 * 1024,
         initial_size: heap_len as u64,
         max_size: None,
+        bounds_checked: false,
     };
 
     let mut heap = vec![0x00; heap_len];
@@ -68,6 +69,7 @@ pub fn large_sparse_heap_mock(heap_kb: usize, stride: usize) -> Arc<dyn Module>
         guard_size: 4 * 1024 * 1024,
         initial_size: heap_len as u64,
         max_size: None,
+        bounds_checked: false,
     };
 
     let mut heap = vec![0x00; heap_len];
@@ -0,0 +1,40 @@
+/// The start of a run of native code translated from a single WebAssembly instruction.
+///
+/// `wasm_offset` is the offset of that instruction from the start of the WebAssembly binary, as
+/// shown by tools like `wasm-objdump -d`. Like [`TrapSite`](struct.TrapSite.html), this is read in
+/// place from the loaded module, so it must be `repr(C)`.
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddrMapEntry {
+    pub native_offset: u32,
+    pub wasm_offset: u32,
+}
+
+/// A map from native code offsets to WebAssembly bytecode offsets for a single function (see
+/// [`FunctionSpec::addr_map`]).
+///
+/// Entries are sorted by native offset, and each one covers the code up to the next.
+#[repr(C)]
+#[derive(Clone, Debug)]
+pub struct AddrMap<'a> {
+    pub entries: &'a [AddrMapEntry],
+}
+
+impl<'a> AddrMap<'a> {
+    pub fn new(entries: &'a [AddrMapEntry]) -> AddrMap<'_> {
+        AddrMap { entries }
+    }
+    /// Find the WebAssembly offset of the instruction that native code at `addr` came from.
+    ///
+    /// Code before the first entry, such as a function prologue, has no WebAssembly offset.
+    pub fn lookup_addr(&self, addr: u32) -> Option<u32> {
+        match self
+            .entries
+            .binary_search_by(|entry| entry.native_offset.cmp(&addr))
+        {
+            Ok(i) => Some(self.entries[i].wasm_offset),
+            Err(0) => None,
+            Err(i) => Some(self.entries[i - 1].wasm_offset),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::addr_map::{AddrMap, AddrMapEntry};
 use crate::traps::{TrapManifest, TrapSite};
 use cranelift_entity::entity_impl;
 use serde::{Deserialize, Serialize};
@@ -138,7 +139,8 @@ pub struct FunctionHandle {
 
 // The layout of this struct is very tightly coupled to lucetc's `write_function_manifest`!
 //
-// Specifically, `write_function_manifest` sets up relocations on `code_addr` and `traps_addr`.
+// Specifically, `write_function_manifest` sets up relocations on `code_addr`, `traps_addr`, and
+// `addr_map_addr`.
 // It does not explicitly serialize a correctly formed `FunctionSpec`, because addresses
 // for these fields do not exist until the object is loaded in the future.
 //
@@ -151,6 +153,8 @@ pub struct FunctionSpec {
     code_len: u32,
     traps_addr: u64,
     traps_len: u64,
+    addr_map_addr: u64,
+    addr_map_len: u64,
 }
 
 impl FunctionSpec {
@@ -160,8 +164,15 @@ impl FunctionSpec {
             code_len,
             traps_addr,
             traps_len,
+            addr_map_addr: 0,
+            addr_map_len: 0,
         }
     }
+    pub fn with_addr_map(mut self, addr_map_addr: u64, addr_map_len: u64) -> Self {
+        self.addr_map_addr = addr_map_addr;
+        self.addr_map_len = addr_map_len;
+        self
+    }
     pub fn ptr(&self) -> FunctionPointer {
         FunctionPointer::from_usize(self.code_addr as usize)
     }
@@ -195,4 +206,21 @@ impl FunctionSpec {
             None
         }
     }
+    pub fn addr_map_len(&self) -> u64 {
+        self.addr_map_len
+    }
+    pub fn addr_map(&self) -> Option<AddrMap<'_>> {
+        let entries_ptr = self.addr_map_addr as *const AddrMapEntry;
+        if !entries_ptr.is_null() {
+            let entries_slice = unsafe { from_raw_parts(entries_ptr, self.addr_map_len as usize) };
+            Some(AddrMap::new(entries_slice))
+        } else {
+            None
+        }
+    }
+    /// Find the WebAssembly bytecode offset that the instruction at `addr` was translated from.
+    pub fn wasm_offset(&self, addr: u64) -> Option<u32> {
+        self.relative_addr(addr)
+            .and_then(|offset| self.addr_map()?.lookup_addr(offset))
+    }
 }
@@ -1,3 +1,5 @@
+use crate::functions::FunctionIndex;
+use crate::types::ValueType;
 use serde::{Deserialize, Serialize};
 
 /// A WebAssembly global along with its export specification.
@@ -10,24 +12,49 @@ pub struct GlobalSpec<'a> {
     #[serde(borrow)]
     global: Global<'a>,
     export_names: Vec<&'a str>,
+    ty: ValueType,
+    mutable: bool,
 }
 
 impl<'a> GlobalSpec<'a> {
+    /// Create a new global, which is immutable unless `with_mutable(true)` is applied.
     pub fn new(global: Global<'a>, export_names: Vec<&'a str>) -> Self {
+        let ty = global.value_type();
         Self {
             global,
             export_names,
+            ty,
+            mutable: false,
         }
     }
 
+    /// Set the WebAssembly type of the global, for globals whose definition does not record it,
+    /// such as null references, which are defined as `GlobalDef::I64(0)`.
+    pub fn with_value_type(mut self, ty: ValueType) -> Self {
+        self.ty = ty;
+        self
+    }
+
+    /// Set whether the WebAssembly program declares the global as mutable.
+    pub fn with_mutable(mut self, mutable: bool) -> Self {
+        self.mutable = mutable;
+        self
+    }
+
     /// Create a new global definition with an initial value and export names.
     pub fn new_def(init_val: i64, export_names: Vec<&'a str>) -> Self {
         Self::new(Global::Def(GlobalDef::I64(init_val)), export_names)
     }
 
-    /// Create a new global import definition with a module and field name, and export names.
-    pub fn new_import(module: &'a str, field: &'a str, export_names: Vec<&'a str>) -> Self {
-        Self::new(Global::Import { module, field }, export_names)
+    /// Create a new global import definition with a module and field name, the type of the
+    /// imported value, and export names.
+    pub fn new_import(
+        module: &'a str,
+        field: &'a str,
+        ty: ValueType,
+        export_names: Vec<&'a str>,
+    ) -> Self {
+        Self::new(Global::Import { module, field, ty }, export_names)
     }
 
     pub fn global(&self) -> &Global<'_> {
@@ -38,6 +65,16 @@ impl<'a> GlobalSpec<'a> {
         &self.export_names
     }
 
+    /// The WebAssembly type of the global.
+    pub fn value_type(&self) -> ValueType {
+        self.ty
+    }
+
+    /// Whether the global is declared as mutable.
+    pub fn is_mutable(&self) -> bool {
+        self.mutable
+    }
+
     pub fn is_internal(&self) -> bool {
         self.export_names.is_empty()
     }
@@ -52,7 +89,21 @@ impl<'a> GlobalSpec<'a> {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Global<'a> {
     Def(GlobalDef),
-    Import { module: &'a str, field: &'a str },
+    Import {
+        module: &'a str,
+        field: &'a str,
+        ty: ValueType,
+    },
+}
+
+impl<'a> Global<'a> {
+    /// The WebAssembly type of the global's value, as far as its definition records it.
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Global::Def(def) => def.value_type(),
+            Global::Import { ty, .. } => *ty,
+        }
+    }
 }
 
 /// Definition for a global in this module (not imported).
@@ -62,25 +113,52 @@ pub enum GlobalDef {
     I64(i64),
     F32(f32),
     F64(f64),
+    V128(u128),
+    /// A reference to a function of the module, as `ref.func` gives. The runtime resolves it to
+    /// the function's `TableElement` when it initializes the global.
+    FuncRef(FunctionIndex),
 }
 
 impl GlobalDef {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            GlobalDef::I32(_) => ValueType::I32,
+            GlobalDef::I64(_) => ValueType::I64,
+            GlobalDef::F32(_) => ValueType::F32,
+            GlobalDef::F64(_) => ValueType::F64,
+            GlobalDef::V128(_) => ValueType::V128,
+            GlobalDef::FuncRef(_) => ValueType::FuncRef,
+        }
+    }
+
+    /// The value the global starts out with.
+    ///
+    /// A `FuncRef` has no value until the runtime resolves it, so this gives a null reference for
+    /// one.
     pub fn init_val(&self) -> GlobalValue {
         match self {
             GlobalDef::I32(i) => GlobalValue { i_32: *i },
             GlobalDef::I64(i) => GlobalValue { i_64: *i },
             GlobalDef::F32(f) => GlobalValue { f_32: *f },
             GlobalDef::F64(f) => GlobalValue { f_64: *f },
+            GlobalDef::V128(v) => GlobalValue { v_128: *v },
+            GlobalDef::FuncRef(_) => GlobalValue { i_64: 0 },
         }
     }
 }
 
+/// The value of a global, as stored in an instance.
+///
+/// Every global occupies `size_of::<GlobalValue>()` bytes, which is enough for a `v128`, so the
+/// globals of an instance can be treated as a slice of `GlobalValue`s.
 #[derive(Copy, Clone)]
+#[repr(C)]
 pub union GlobalValue {
     pub i_32: i32,
     pub i_64: i64,
     pub f_32: f32,
     pub f_64: f64,
+    pub v_128: u128,
 }
 
 impl std::fmt::Debug for GlobalValue {
@@ -99,6 +177,7 @@ impl std::fmt::Debug for GlobalValue {
             writeln!(f, "  i_64: {},", self.i_64)?;
             writeln!(f, "  f_32: {},", self.f_32)?;
             writeln!(f, "  f_64: {},", self.f_64)?;
+            writeln!(f, "  v_128: {},", self.v_128)?;
         }
         writeln!(f, "}}")
     }
@@ -112,24 +191,48 @@ impl std::fmt::Debug for GlobalValue {
 pub struct OwnedGlobalSpec {
     global: OwnedGlobal,
     export_names: Vec<String>,
+    ty: ValueType,
+    mutable: bool,
 }
 
 impl OwnedGlobalSpec {
     pub fn new(global: OwnedGlobal, export_names: Vec<String>) -> Self {
+        let ty = global.to_ref().value_type();
         Self {
             global,
             export_names,
+            ty,
+            mutable: false,
         }
     }
 
+    /// Set the WebAssembly type of the global; see
+    /// [`GlobalSpec::with_value_type()`](../struct.GlobalSpec.html#method.with_value_type).
+    pub fn with_value_type(mut self, ty: ValueType) -> Self {
+        self.ty = ty;
+        self
+    }
+
+    /// Set whether the WebAssembly program declares the global as mutable.
+    pub fn with_mutable(mut self, mutable: bool) -> Self {
+        self.mutable = mutable;
+        self
+    }
+
     /// Create a new global definition with an initial value and export names.
     pub fn new_def(init_val: i64, export_names: Vec<String>) -> Self {
         Self::new(OwnedGlobal::Def(GlobalDef::I64(init_val)), export_names)
     }
 
-    /// Create a new global import definition with a module and field name, and export names.
-    pub fn new_import(module: String, field: String, export_names: Vec<String>) -> Self {
-        Self::new(OwnedGlobal::Import { module, field }, export_names)
+    /// Create a new global import definition with a module and field name, the type of the
+    /// imported value, and export names.
+    pub fn new_import(
+        module: String,
+        field: String,
+        ty: ValueType,
+        export_names: Vec<String>,
+    ) -> Self {
+        Self::new(OwnedGlobal::Import { module, field, ty }, export_names)
     }
 
     /// Create a [`GlobalSpec`](../struct.GlobalSpec.html) backed by the values in this
@@ -139,6 +242,8 @@ impl OwnedGlobalSpec {
             self.global.to_ref(),
             self.export_names.iter().map(|x| x.as_str()).collect(),
         )
+        .with_value_type(self.ty)
+        .with_mutable(self.mutable)
     }
 }
 
@@ -147,7 +252,11 @@ impl OwnedGlobalSpec {
 /// This type is useful when directly building up a value to be serialized.
 pub enum OwnedGlobal {
     Def(GlobalDef),
-    Import { module: String, field: String },
+    Import {
+        module: String,
+        field: String,
+        ty: ValueType,
+    },
 }
 
 impl OwnedGlobal {
@@ -155,9 +264,10 @@ impl OwnedGlobal {
     pub fn to_ref<'a>(&'a self) -> Global<'a> {
         match self {
             OwnedGlobal::Def(def) => Global::Def(def.clone()),
-            OwnedGlobal::Import { module, field } => Global::Import {
+            OwnedGlobal::Import { module, field, ty } => Global::Import {
                 module: module.as_str(),
                 field: field.as_str(),
+                ty: *ty,
             },
         }
     }
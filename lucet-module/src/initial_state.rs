@@ -0,0 +1,35 @@
+use crate::error::Error;
+use crate::globals::GlobalDef;
+use serde::{Deserialize, Serialize};
+
+/// The heap and globals of an instance whose initialization has already run.
+///
+/// This is captured by the runtime after running a module's start function and any other
+/// initialization exports, and can then be compiled back into the module by `lucetc` in place of
+/// its data segments, global initializers, and start function. Every instance of the resulting
+/// module starts out already initialized.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InitialState {
+    /// The length of the heap in bytes.
+    pub heap_len: u64,
+    /// The contents of the heap, in 4096-byte pages.
+    ///
+    /// Pages that are all zeroes are `None`, and trailing zero pages may be omitted.
+    pub heap_pages: Vec<Option<Vec<u8>>>,
+    /// The value of each global, in index order.
+    ///
+    /// Imported globals are `None`; they keep taking their values from the host at instantiation.
+    pub globals: Vec<Option<GlobalDef>>,
+}
+
+impl InitialState {
+    /// Serialize the state, for example to write it to a file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        bincode::serialize(self).map_err(Error::SerializationError)
+    }
+
+    /// Deserialize a state produced by [`InitialState::to_bytes()`](#method.to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        bincode::deserialize(bytes).map_err(Error::DeserializationError)
+    }
+}
@@ -72,6 +72,13 @@ pub struct HeapSpec {
     /// The program may optionally define this value. If it does not, the max size is left up to
     /// the runtime, and is allowed to be less than `reserved_size`.
     pub max_size: Option<u64>,
+
+    /// Whether the module was compiled to check every heap access against the current heap length.
+    ///
+    /// Such modules do not rely on `reserved_size` and `guard_size` being mapped to catch
+    /// out-of-bounds accesses, so their instances only need as much address space as their heap
+    /// can grow to. Their `reserved_size` is their initial size, and their `guard_size` is 0.
+    pub bounds_checked: bool,
 }
 
 impl HeapSpec {
@@ -86,6 +93,7 @@ impl HeapSpec {
             guard_size,
             initial_size,
             max_size,
+            bounds_checked: false,
         }
     }
 
@@ -96,6 +104,7 @@ impl HeapSpec {
             guard_size: 0,
             initial_size: 0,
             max_size: None,
+            bounds_checked: false,
         }
     }
 }
@@ -4,6 +4,7 @@ use crate::{
     },
     globals::GlobalSpec,
     linear_memory::{HeapSpec, LinearMemorySpec, SparseData},
+    tables::TableSpec,
     types::Signature,
     Error,
 };
@@ -46,6 +47,44 @@ pub struct ModuleData<'a> {
     module_signature: [u8; SignatureBones::BYTES],
     features: ModuleFeatures,
     start_function: Option<FunctionIndex>,
+    /// The contents of each data segment, indexed by data segment index. Active segments are
+    /// `None`, as they are dropped once they have been applied at instantiation, unless they are
+    /// in `global_base_data`.
+    #[serde(borrow)]
+    passive_data: Vec<Option<&'a [u8]>>,
+    /// The functions in each element segment, indexed by element segment index. Active segments
+    /// are `None`, as they are dropped once they have been applied at instantiation, unless they
+    /// are in `global_base_elements`.
+    passive_elements: Vec<Option<Vec<FunctionIndex>>>,
+    tables: Vec<TableSpec>,
+    /// Active data segments whose offset is given by a global, and those that follow them, in data
+    /// segment index order.
+    global_base_data: Vec<GlobalBaseSegment>,
+    /// Active element segments whose offset is given by a global, and those that follow them, in
+    /// element segment index order.
+    global_base_elements: Vec<GlobalBaseSegment>,
+}
+
+/// An active data or element segment whose offset is the value of a global, so that it can only be
+/// applied once the values of an instance's imported globals are known.
+///
+/// Segments must be applied in order, so the active segments with a constant offset that follow
+/// the first global-based one are described this way too, with no `global`.
+///
+/// The contents of the segment are kept with the passive segments, at index `segment`. When an
+/// instance is created or reset, the contents are copied into place as `memory.init` or
+/// `table.init` would, and the segment is then dropped.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GlobalBaseSegment {
+    /// The index of the segment among the module's data or element segments.
+    pub segment: u32,
+    /// The table the segment initializes; always 0 for data segments.
+    pub target: u32,
+    /// The global whose value is the base offset of the segment, if it has one.
+    pub global: Option<u32>,
+    /// A constant added to the value of the global, or the offset of the segment if it has no
+    /// global.
+    pub offset: u32,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -60,6 +99,10 @@ pub struct ModuleFeatures {
     pub lzcnt: bool,
     pub popcnt: bool,
     pub instruction_count: bool,
+    pub epoch_interruption: bool,
+    /// Whether imported functions are called through the instance's import table, bound to host
+    /// functions at runtime by a `Linker`, rather than resolved as symbols when loading.
+    pub linked_imports: bool,
     _hidden: (),
 }
 
@@ -76,6 +119,8 @@ impl ModuleFeatures {
             lzcnt: false,
             popcnt: false,
             instruction_count: false,
+            epoch_interruption: false,
+            linked_imports: false,
             _hidden: (),
         }
     }
@@ -91,6 +136,11 @@ impl<'a> ModuleData<'a> {
         signatures: Vec<Signature>,
         features: ModuleFeatures,
         start_function: Option<FunctionIndex>,
+        passive_data: Vec<Option<&'a [u8]>>,
+        passive_elements: Vec<Option<Vec<FunctionIndex>>>,
+        tables: Vec<TableSpec>,
+        global_base_data: Vec<GlobalBaseSegment>,
+        global_base_elements: Vec<GlobalBaseSegment>,
     ) -> Self {
         Self {
             linear_memory,
@@ -102,6 +152,11 @@ impl<'a> ModuleData<'a> {
             module_signature: [0u8; SignatureBones::BYTES],
             features,
             start_function,
+            passive_data,
+            passive_elements,
+            tables,
+            global_base_data,
+            global_base_elements,
         }
     }
 
@@ -153,10 +208,50 @@ impl<'a> ModuleData<'a> {
             .map(|export| export.fn_idx)
     }
 
+    /// A human-readable name for a function: its first export name if it is exported, otherwise
+    /// the name recorded in its metadata.
+    pub fn get_function_name(&self, fn_id: FunctionIndex) -> Option<&str> {
+        self.export_functions
+            .iter()
+            .find(|export| export.fn_idx == fn_id)
+            .and_then(|export| export.names.first().copied())
+            .or_else(|| {
+                self.function_info
+                    .get(fn_id.as_u32() as usize)
+                    .and_then(|info| info.name)
+            })
+    }
+
     pub fn get_start_func_id(&self) -> Option<FunctionIndex> {
         self.start_function
     }
 
+    /// Data segments, indexed by data segment index; only passive segments and the segments in
+    /// `global_base_data()` are `Some`.
+    pub fn passive_data(&self) -> &[Option<&'a [u8]>] {
+        &self.passive_data
+    }
+
+    /// Element segments, indexed by element segment index; only passive segments and the segments
+    /// in `global_base_elements()` are `Some`.
+    pub fn passive_elements(&self) -> &[Option<Vec<FunctionIndex>>] {
+        &self.passive_elements
+    }
+
+    pub fn tables(&self) -> &[TableSpec] {
+        &self.tables
+    }
+
+    /// Active data segments whose offset is given by a global, and those that follow them.
+    pub fn global_base_data(&self) -> &[GlobalBaseSegment] {
+        &self.global_base_data
+    }
+
+    /// Active element segments whose offset is given by a global, and those that follow them.
+    pub fn global_base_elements(&self) -> &[GlobalBaseSegment] {
+        &self.global_base_elements
+    }
+
     pub fn signatures(&self) -> &[Signature] {
         &self.signatures
     }
@@ -219,6 +314,11 @@ pub struct OwnedModuleData {
     signatures: Vec<Signature>,
     features: ModuleFeatures,
     start_function: Option<FunctionIndex>,
+    passive_data: Vec<Option<Vec<u8>>>,
+    passive_elements: Vec<Option<Vec<FunctionIndex>>>,
+    tables: Vec<TableSpec>,
+    global_base_data: Vec<GlobalBaseSegment>,
+    global_base_elements: Vec<GlobalBaseSegment>,
 }
 
 impl OwnedModuleData {
@@ -231,6 +331,11 @@ impl OwnedModuleData {
         signatures: Vec<Signature>,
         features: ModuleFeatures,
         start_function: Option<FunctionIndex>,
+        passive_data: Vec<Option<Vec<u8>>>,
+        passive_elements: Vec<Option<Vec<FunctionIndex>>>,
+        tables: Vec<TableSpec>,
+        global_base_data: Vec<GlobalBaseSegment>,
+        global_base_elements: Vec<GlobalBaseSegment>,
     ) -> Self {
         Self {
             linear_memory,
@@ -241,6 +346,11 @@ impl OwnedModuleData {
             signatures,
             features,
             start_function,
+            passive_data,
+            passive_elements,
+            tables,
+            global_base_data,
+            global_base_elements,
         }
     }
 
@@ -263,6 +373,14 @@ impl OwnedModuleData {
             self.signatures.clone(),
             self.features.clone(),
             self.start_function,
+            self.passive_data
+                .iter()
+                .map(|data| data.as_ref().map(|d| d.as_slice()))
+                .collect(),
+            self.passive_elements.clone(),
+            self.tables.clone(),
+            self.global_base_data.clone(),
+            self.global_base_elements.clone(),
         )
     }
 
@@ -276,6 +394,11 @@ impl OwnedModuleData {
             vec![],
             ModuleFeatures::none(),
             None,
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
         )
     }
 
@@ -1,8 +1,50 @@
+use crate::globals::GlobalValue;
+use std::ffi::c_void;
+
 /// This struct describes the handful of fields that Lucet-compiled programs may directly interact with, but
 /// are provided through VMContext.
 #[repr(C)]
 #[repr(align(8))]
 pub struct InstanceRuntimeData {
-    pub globals_ptr: *mut i64,
+    pub globals_ptr: *mut GlobalValue,
     pub instruction_count: u64,
+    /// The `instruction_count` at which an instrumented instance runs out of fuel.
+    pub fuel_limit: u64,
+    /// The epoch counter checked by modules compiled with epoch interruption.
+    pub epoch_ptr: *const u64,
+    /// The epoch at which an epoch-interruptible instance stops running.
+    pub epoch_deadline: u64,
+    /// The current size of the heap in bytes, checked by modules with bounds-checked heaps.
+    pub heap_len: u64,
+    pub tables_ptr: *const TableRef,
+    /// The host functions bound to the module's imports, in import order, for modules compiled
+    /// with linked imports.
+    pub import_funcs_ptr: *const ImportFuncRef,
+    /// The `data` of the import most recently called through `import_funcs_ptr`, stored by the
+    /// calling code just before the call.
+    pub import_data: *const c_void,
+}
+
+/// A host function bound to one of an instance's imports, as read by Lucet-compiled programs
+/// through `InstanceRuntimeData::import_funcs_ptr`.
+///
+/// `func` is called with the vmctx and the import's arguments. Before calling it, the program
+/// stores `data`, which the host function may use to find its own state, in
+/// `InstanceRuntimeData::import_data`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ImportFuncRef {
+    pub func: *const c_void,
+    pub data: *const c_void,
+}
+
+/// The location and length of one of an instance's tables, as read by Lucet-compiled programs
+/// through `InstanceRuntimeData::tables_ptr`.
+///
+/// Table elements are references: a pointer to a `TableElement` for `funcref` tables, or a value
+/// provided by the host for `externref` tables. Null references are 0.
+#[repr(C)]
+pub struct TableRef {
+    pub elements: *mut u64,
+    pub len: u64,
 }
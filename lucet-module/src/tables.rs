@@ -1,4 +1,6 @@
-use crate::functions::FunctionPointer;
+use crate::functions::{FunctionIndex, FunctionPointer, UniqueSignatureIndex};
+use crate::types::ValueType;
+use serde::{Deserialize, Serialize};
 
 #[repr(C)]
 #[derive(Clone, Debug)]
@@ -8,7 +10,28 @@ pub struct TableElement {
 }
 
 impl TableElement {
+    pub fn new(signature: UniqueSignatureIndex, func: FunctionPointer) -> Self {
+        TableElement {
+            ty: signature.as_u32() as u64,
+            func: func.as_usize() as u64,
+        }
+    }
+
     pub fn function_pointer(&self) -> FunctionPointer {
         FunctionPointer::from_usize(self.func as usize)
     }
 }
+
+/// The specification of a WebAssembly table, and its contents once the module's active element
+/// segments have been applied.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TableSpec {
+    /// The type of the table's elements: either `FuncRef` or `ExternRef`.
+    pub elem_ty: ValueType,
+    /// The initial number of elements in the table.
+    pub minimum: u32,
+    /// The maximum number of elements the table may grow to, if it is bounded.
+    pub maximum: Option<u32>,
+    /// The initial elements of the table, where `None` is a null reference.
+    pub elements: Vec<Option<FunctionIndex>>,
+}
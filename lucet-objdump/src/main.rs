@@ -1,8 +1,8 @@
 #![deny(bare_trait_objects)]
 
 use lucet_module::{
-    FunctionSpec, Module, ModuleData, SerializedModule, TableElement, TrapManifest, TrapSite,
-    VersionInfo,
+    AddrMap, AddrMapEntry, FunctionSpec, Module, ModuleData, SerializedModule, TableElement,
+    TrapManifest, TrapSite, VersionInfo,
 };
 
 use byteorder::{LittleEndian, ReadBytesExt};
@@ -166,6 +166,34 @@ fn parse_trap_manifest<'a>(
     }
 }
 
+/// Parse the address map for function `f`, if it has one.
+///
+/// Like trap manifests, the address map's pointer is only meaningful relative to the start of the
+/// file, so the slice is rebuilt from the buffer.
+fn parse_addr_map<'a>(summary: &'a ArtifactSummary<'a>, f: &FunctionSpec) -> Option<AddrMap<'a>> {
+    if let Some(faulty_addr_map) = f.addr_map() {
+        let entries_ptr = faulty_addr_map.entries.as_ptr();
+        let entries_count = faulty_addr_map.entries.len();
+        let entries_byte_count = entries_count * std::mem::size_of::<AddrMapEntry>();
+        if let Some(entries_byte_slice) =
+            summary.read_memory(entries_ptr as u64, entries_byte_count as u64)
+        {
+            let real_entries_ptr = entries_byte_slice.as_ptr() as *const AddrMapEntry;
+            Some(AddrMap {
+                entries: unsafe { std::slice::from_raw_parts(real_entries_ptr, entries_count) },
+            })
+        } else {
+            println!(
+                "Failed to read address map bytes for function {:?}, at {:p}",
+                f, entries_ptr
+            );
+            None
+        }
+    } else {
+        None
+    }
+}
+
 fn load_module<'b, 'a: 'b>(
     summary: &'a ArtifactSummary<'a>,
     serialized_module: &SerializedModule,
@@ -355,6 +383,17 @@ fn summarize_module<'a, 'b: 'a>(summary: &'a ArtifactSummary<'a>, module: &Modul
                 println!("      No traps for this function");
             }
         }
+        if let Some(addr_map) = parse_addr_map(&summary, f) {
+            println!("    Address map (native offset: wasm offset):");
+            for entry in addr_map.entries {
+                println!(
+                    "        $+{:#06x}: {:#x}",
+                    entry.native_offset, entry.wasm_offset
+                );
+            }
+        } else {
+            println!("    No address map for this function");
+        }
     }
 
     println!("");
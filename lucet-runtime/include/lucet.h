@@ -17,6 +17,62 @@ enum lucet_error lucet_dl_module_load(const char *path, struct lucet_dl_module *
 
 void lucet_dl_module_release(const struct lucet_dl_module *module);
 
+/**
+ * Copies the functions and globals exported by the module to `exports_out`, which has room for
+ * `exports_len` exports. The total number of exports is written to `exports_count_out`, even if
+ * it exceeds `exports_len`. The export names point into the module, and are valid until it is
+ * released.
+ */
+enum lucet_error lucet_dl_module_exports(const struct lucet_dl_module *module,
+                                         struct lucet_module_export *  exports_out,
+                                         uintptr_t                     exports_len,
+                                         uintptr_t *                   exports_count_out);
+
+/**
+ * Copies the functions and globals imported by the module to `imports_out`, which has room for
+ * `imports_len` imports. The total number of imports is written to `imports_count_out`, even if
+ * it exceeds `imports_len`. The module and import names point into the module, and are valid
+ * until it is released.
+ */
+enum lucet_error lucet_dl_module_imports(const struct lucet_dl_module *module,
+                                         struct lucet_module_import *  imports_out,
+                                         uintptr_t                     imports_len,
+                                         uintptr_t *                   imports_count_out);
+
+/**
+ * Copies the module's globals to `globals_out`, which has room for `globals_len` globals, in
+ * index order. The total number of globals is written to `globals_count_out`, even if it exceeds
+ * `globals_len`.
+ */
+enum lucet_error lucet_dl_module_globals(const struct lucet_dl_module *module,
+                                         struct lucet_module_global *  globals_out,
+                                         uintptr_t                     globals_len,
+                                         uintptr_t *                   globals_count_out);
+
+/**
+ * Writes the module's linear memory to `memory_out`, and whether it has one to
+ * `has_memory_out`. `memory_out` is left untouched if the module has no memory.
+ */
+enum lucet_error lucet_dl_module_memory(const struct lucet_dl_module *module,
+                                        bool *                        has_memory_out,
+                                        struct lucet_module_memory *  memory_out);
+
+/**
+ * Copies the parameter and result types of the function with index `func_index`, as given by
+ * `lucet_dl_module_exports()` or `lucet_dl_module_imports()`, to `params_out` and `results_out`,
+ * which have room for `params_len` and `results_len` types. The total numbers of parameters and
+ * results are written to `params_count_out` and `results_count_out`, even if they exceed the room
+ * available.
+ */
+enum lucet_error lucet_dl_module_func_signature(const struct lucet_dl_module *module,
+                                                uint32_t                      func_index,
+                                                enum lucet_value_type *       params_out,
+                                                uintptr_t                     params_len,
+                                                uintptr_t *                   params_count_out,
+                                                enum lucet_value_type *       results_out,
+                                                uintptr_t                     results_len,
+                                                uintptr_t *                   results_count_out);
+
 const char *lucet_error_name(enum lucet_error e);
 
 bool lucet_instance_check_heap(const struct lucet_instance *inst, const void *ptr, uintptr_t len);
@@ -50,6 +106,50 @@ enum lucet_error lucet_instance_run_func_idx(struct lucet_instance * inst,
                                              const struct lucet_val *argv,
                                              struct lucet_result *   result_out);
 
+/**
+ * Copies the values returned by the most recent entrypoint that ran to completion to
+ * `results_out`, which has room for `results_len` values. The total number of values returned
+ * is written to `results_count_out`, even if it exceeds `results_len`.
+ */
+enum lucet_error lucet_instance_results(const struct lucet_instance *inst,
+                                        struct lucet_val *           results_out,
+                                        uintptr_t                    results_len,
+                                        uintptr_t *                  results_count_out);
+
+/**
+ * Copies the WebAssembly backtrace of a faulted instance to `frames_out`, which has room for
+ * `frames_len` frames, innermost first. The total number of frames is written to
+ * `frames_count_out`, even if it exceeds `frames_len`; it is zero if the instance is not faulted.
+ */
+enum lucet_error lucet_instance_backtrace(const struct lucet_instance *inst,
+                                          struct lucet_wasm_frame *    frames_out,
+                                          uintptr_t                    frames_len,
+                                          uintptr_t *                  frames_count_out);
+
+enum lucet_error lucet_instance_stats(const struct lucet_instance *inst,
+                                      struct lucet_instance_stats * stats_out);
+
+/**
+ * Starts or stops measuring the CPU time reported in the instance's runtime statistics, which is
+ * off by default.
+ */
+enum lucet_error lucet_instance_set_cpu_stats(struct lucet_instance *inst, bool enabled);
+
+/**
+ * Writes the value of the global exported as `name` to `val_out`.
+ */
+enum lucet_error lucet_instance_get_global(const struct lucet_instance *inst,
+                                           const char *                 name,
+                                           struct lucet_val *           val_out);
+
+/**
+ * Sets the global exported as `name` to `val`, which must be of the global's type. Immutable
+ * globals cannot be set.
+ */
+enum lucet_error lucet_instance_set_global(struct lucet_instance * inst,
+                                           const char *            name,
+                                           const struct lucet_val *val);
+
 enum lucet_error
 lucet_instance_resume(struct lucet_instance *inst, void *val, struct lucet_result *result_out);
 
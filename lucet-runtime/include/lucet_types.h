@@ -50,6 +50,8 @@ enum lucet_terminated_reason {
     lucet_terminated_reason_borrow_error,
     lucet_terminated_reason_provided,
     lucet_terminated_reason_remote,
+    lucet_terminated_reason_out_of_fuel,
+    lucet_terminated_reason_timeout,
 };
 
 enum lucet_trapcode {
@@ -82,6 +84,9 @@ enum lucet_val_type {
     lucet_val_type_bool,
     lucet_val_type_f32,
     lucet_val_type_f64,
+    lucet_val_type_funcref,
+    lucet_val_type_externref,
+    lucet_val_type_v128,
 };
 
 union lucet_val_inner_val {
@@ -90,6 +95,7 @@ union lucet_val_inner_val {
     int64_t  as_i64;
     float    as_f32;
     double   as_f64;
+    uint8_t  as_v128[16];
 };
 
 struct lucet_val {
@@ -121,7 +127,7 @@ struct lucet_alloc_limits {
      */
     uint64_t stack_size;
     /**
-     * Size of the globals region in bytes; each global uses 8 bytes. (default 4K)
+     * Size of the globals region in bytes; each global uses 16 bytes. (default 4K)
      */
     uint64_t globals_size;
     /**
@@ -133,6 +139,86 @@ struct lucet_alloc_limits {
     uint64_t signal_stack_size;
 };
 
+/**
+ * Runtime statistics for an instance, accumulated since it was created or last reset. Times are
+ * in nanoseconds. CPU times are zero unless enabled with `lucet_instance_set_cpu_stats()`.
+ */
+struct lucet_instance_stats {
+    uint64_t guest_wall_time_ns;
+    uint64_t guest_cpu_time_ns;
+    uint64_t hostcall_wall_time_ns;
+    uint64_t hostcall_cpu_time_ns;
+    uint64_t hostcalls;
+    uint64_t yields;
+    uint64_t resumes;
+    uint64_t peak_heap_size;
+    uint64_t memory_grows;
+    uint64_t page_faults;
+    uint64_t signals;
+};
+
+enum lucet_value_type {
+    lucet_value_type_i32,
+    lucet_value_type_i64,
+    lucet_value_type_f32,
+    lucet_value_type_f64,
+    lucet_value_type_v128,
+    lucet_value_type_funcref,
+    lucet_value_type_externref,
+};
+
+enum lucet_extern_kind {
+    lucet_extern_kind_func,
+    lucet_extern_kind_global,
+};
+
+/**
+ * A name from a module's data, which is valid for as long as the module is. The name is UTF-8 and
+ * is not nul-terminated, as WebAssembly names may contain nul characters.
+ */
+struct lucet_module_name {
+    const char *ptr;
+    uintptr_t   len;
+};
+
+/**
+ * A function or global exported by a module. `index` is the function index, which can be passed
+ * to `lucet_dl_module_func_signature()`, or the global index.
+ */
+struct lucet_module_export {
+    enum lucet_extern_kind   kind;
+    uint32_t                 index;
+    struct lucet_module_name name;
+};
+
+struct lucet_module_import {
+    enum lucet_extern_kind   kind;
+    uint32_t                 index;
+    struct lucet_module_name module;
+    struct lucet_module_name name;
+};
+
+/**
+ * A global of a module. Its export names and the names it is imported under are given by the
+ * module's exports and imports.
+ */
+struct lucet_module_global {
+    uint32_t              index;
+    enum lucet_value_type ty;
+    bool                  is_mutable;
+    bool                  imported;
+    bool                  exported;
+};
+
+/**
+ * The linear memory of a module. `max_pages` is only meaningful if `has_max_pages` is set.
+ */
+struct lucet_module_memory {
+    uint32_t initial_pages;
+    bool     has_max_pages;
+    uint32_t max_pages;
+};
+
 typedef enum lucet_signal_behavior (*lucet_signal_handler)(struct lucet_instance *   inst,
                                                            const enum lucet_trapcode trap,
                                                            int signum, const siginfo_t *siginfo,
@@ -154,11 +240,20 @@ struct lucet_module_addr_details {
     char sym_name[LUCET_MODULE_ADDR_DETAILS_NAME_LEN];
 };
 
+struct lucet_wasm_frame {
+    uint32_t func_index;
+    uint32_t offset;
+    bool     has_wasm_offset;
+    uint32_t wasm_offset;
+    char     func_name[LUCET_MODULE_ADDR_DETAILS_NAME_LEN];
+};
+
 struct lucet_runtime_faulted {
     bool                             fatal;
     enum lucet_trapcode              trapcode;
     uintptr_t                        rip_addr;
     struct lucet_module_addr_details rip_addr_details;
+    uintptr_t                        backtrace_len;
 };
 
 struct lucet_terminated {
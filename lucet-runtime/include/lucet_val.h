@@ -50,6 +50,9 @@
 #define LUCET_VAL_F32(X) LUCET_VAL_T(f32, as_f32, X)
 #define LUCET_VAL_F64(X) LUCET_VAL_T(f64, as_f64, X)
 
+#define LUCET_VAL_FUNCREF(X) LUCET_VAL_T(funcref, as_c_ptr, X)
+#define LUCET_VAL_EXTERNREF(X) LUCET_VAL_T(externref, as_c_ptr, X)
+
 // Converts a lucet_val value to the given type
 
 #define LUCET_VAL_TO_T(T, C, V) ((T)((V).inner_val.C))
@@ -75,6 +78,9 @@
 #define LUCET_VAL_TO_F32(X) LUCET_VAL_TO_T(float, as_f32, X)
 #define LUCET_VAL_TO_F64(X) LUCET_VAL_TO_T(double, as_f64, X)
 
+#define LUCET_VAL_TO_FUNCREF(X) LUCET_VAL_TO_T(void *, as_c_ptr, X)
+#define LUCET_VAL_TO_EXTERNREF(X) LUCET_VAL_TO_T(void *, as_c_ptr, X)
+
 // Converts an untyped return value to the given type
 
 #define LUCET_UNTYPED_RETVAL_TO_GP_T(T, C, X) ((T) lucet_retval_gp(&(X)).C)
@@ -100,4 +106,7 @@
 #define LUCET_UNTYPED_RETVAL_TO_F32(X) lucet_retval_f32(&(X))
 #define LUCET_UNTYPED_RETVAL_TO_F64(X) lucet_retval_f64(&(X))
 
+#define LUCET_UNTYPED_RETVAL_TO_FUNCREF(X) LUCET_UNTYPED_RETVAL_TO_GP_T(void *, as_c_ptr, X)
+#define LUCET_UNTYPED_RETVAL_TO_EXTERNREF(X) LUCET_UNTYPED_RETVAL_TO_GP_T(void *, as_c_ptr, X)
+
 #endif
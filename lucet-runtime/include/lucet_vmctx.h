@@ -35,11 +35,59 @@ uint32_t lucet_vmctx_current_memory(struct lucet_vmctx const *);
 // the call on success, or -1 on failure.
 int32_t lucet_vmctx_grow_memory(struct lucet_vmctx const *, uint32_t additional_pages);
 
+// bulk memory operations. the memory operations return 0 on success, or 1 if
+// the source or destination range is out of bounds.
+int32_t lucet_vmctx_memory_copy(struct lucet_vmctx const *, uint32_t dst, uint32_t src,
+                                uint32_t len);
+int32_t lucet_vmctx_memory_fill(struct lucet_vmctx const *, uint32_t dst, uint32_t val,
+                                uint32_t len);
+int32_t lucet_vmctx_memory_init(struct lucet_vmctx const *, uint32_t segment, uint32_t dst,
+                                uint32_t src, uint32_t len);
+void lucet_vmctx_data_drop(struct lucet_vmctx const *, uint32_t segment);
+void lucet_vmctx_elem_drop(struct lucet_vmctx const *, uint32_t segment);
+
+// reference type operations. references are pointers to a `funcref`, or values
+// provided by the host for an `externref`; null references are NULL. table.grow
+// returns the number of elements before the call on success, or -1 on failure.
+// table.fill, table.copy, and table.init return 0 on success, or 1 if the source
+// or destination range is out of bounds.
+void *lucet_vmctx_table_get(struct lucet_vmctx const *, uint32_t table, uint32_t index);
+void lucet_vmctx_table_set(struct lucet_vmctx const *, uint32_t table, uint32_t index,
+                           void *val);
+int32_t lucet_vmctx_table_grow(struct lucet_vmctx const *, uint32_t table, uint32_t delta,
+                               void *init);
+int32_t lucet_vmctx_table_fill(struct lucet_vmctx const *, uint32_t table, uint32_t dst,
+                               void *val, uint32_t len);
+int32_t lucet_vmctx_table_copy(struct lucet_vmctx const *, uint32_t dst_table,
+                               uint32_t src_table, uint32_t dst, uint32_t src, uint32_t len);
+int32_t lucet_vmctx_table_init(struct lucet_vmctx const *, uint32_t table, uint32_t segment,
+                               uint32_t dst, uint32_t src, uint32_t len);
+void *lucet_vmctx_ref_func(struct lucet_vmctx const *, uint32_t func);
+
+// called by instruction-counted code when the instance runs out of fuel. yields to the host, and
+// terminates the instance if it is resumed without more fuel.
+void lucet_vmctx_out_of_fuel(struct lucet_vmctx const *);
+
+// called by epoch-interruptible code when the instance's epoch reaches its deadline. terminates the
+// instance.
+void lucet_vmctx_epoch_interrupt(struct lucet_vmctx const *);
+
 // returns the address of a function given its ID
 void *lucet_vmctx_get_func_from_idx(struct lucet_vmctx const *ctx, uint32_t table_id,
                                     uint32_t func_id);
 
-// Mostly for tests - this conversion is builtin to lucetc
-int64_t *lucet_vmctx_get_globals(struct lucet_vmctx const *ctx);
+// The value of a global, as stored in an instance. Each global occupies 16 bytes, so that `v128`
+// globals fit; narrower globals are stored in the low bytes of their slot.
+union lucet_global_value {
+    int32_t i_32;
+    int64_t i_64;
+    float   f_32;
+    double  f_64;
+    uint8_t v_128[16];
+};
+
+// Mostly for tests - this conversion is builtin to lucetc. Returns the instance's globals, in index
+// order.
+union lucet_global_value *lucet_vmctx_get_globals(struct lucet_vmctx const *ctx);
 
 #endif // LUCET_VMCTX_H
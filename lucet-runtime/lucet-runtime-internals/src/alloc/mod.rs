@@ -432,7 +432,7 @@ pub struct Limits {
     pub heap_address_space_size: usize,
     /// Size of the guest stack. (default 128K)
     pub stack_size: usize,
-    /// Size of the globals region in bytes; each global uses 8 bytes. (default 4K)
+    /// Size of the globals region in bytes; each global uses 16 bytes. (default 4K)
     pub globals_size: usize,
     /// Size of the signal stack in bytes. (default SIGSTKSZ for release builds, at least 12K for
     /// debug builds; minimum MINSIGSTKSZ)
@@ -488,6 +488,19 @@ impl Limits {
             signal_stack_size: DEFAULT_SIGNAL_STACK_SIZE,
         }
     }
+
+    /// The default limits, but with only as much heap address space as `heap_memory_size`.
+    ///
+    /// This packs instances densely, but can only run modules compiled with bounds-checked heaps,
+    /// as other modules rely on guard pages after the heap to catch out-of-bounds accesses. If you
+    /// change `heap_memory_size`, change `heap_address_space_size` to match.
+    pub const fn bounds_checked() -> Limits {
+        let limits = Limits::default();
+        Limits {
+            heap_address_space_size: limits.heap_memory_size,
+            ..limits
+        }
+    }
 }
 
 impl Limits {
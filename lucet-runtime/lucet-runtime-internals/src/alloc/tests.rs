@@ -5,6 +5,7 @@ macro_rules! alloc_tests {
         use rand::rngs::StdRng;
         use rand::{thread_rng, Rng, SeedableRng};
         use std::sync::{Arc, Mutex};
+        use std::time::Duration;
         use $TestRegion as TestRegion;
         use $crate::alloc::{AllocStrategy, Limits, MINSIGSTKSZ};
         use $crate::context::{Context, ContextHandle};
@@ -13,7 +14,7 @@ macro_rules! alloc_tests {
         use $crate::module::{
             FunctionPointer, GlobalValue, HeapSpec, MockExportBuilder, MockModuleBuilder, Module,
         };
-        use $crate::region::{Region, RegionCreate};
+        use $crate::region::{ElasticCapacity, Region, RegionCreate};
         use $crate::sysdeps::host_page_size;
         use $crate::val::Val;
         use $crate::vmctx::lucet_vmctx;
@@ -43,6 +44,7 @@ macro_rules! alloc_tests {
             guard_size: SPEC_HEAP_GUARD_SIZE,
             initial_size: ONEPAGE_INITIAL_SIZE,
             max_size: Some(ONEPAGE_MAX_SIZE),
+            bounds_checked: false,
         };
 
         const THREEPAGE_INITIAL_SIZE: u64 = 64 * 1024;
@@ -53,6 +55,7 @@ macro_rules! alloc_tests {
             guard_size: 0,
             initial_size: THREEPAGE_INITIAL_SIZE,
             max_size: Some(THREEPAGE_MAX_SIZE),
+            bounds_checked: false,
         };
 
         /// This test shows an `AllocHandle` passed to `Region::allocate_runtime` will have its heap
@@ -241,6 +244,7 @@ macro_rules! alloc_tests {
             guard_size: SPEC_HEAP_GUARD_SIZE,
             initial_size: EXPANDPASTLIMIT_INITIAL_SIZE,
             max_size: Some(EXPANDPASTLIMIT_MAX_SIZE),
+            bounds_checked: false,
         };
 
         /// This test shows that a heap refuses to grow past the region's limits, even if the
@@ -337,6 +341,7 @@ macro_rules! alloc_tests {
             guard_size: SPEC_HEAP_GUARD_SIZE,
             initial_size: SPEC_HEAP_RESERVED_SIZE + (64 * 1024),
             max_size: None,
+            bounds_checked: false,
         };
 
         /// This test shows that a heap refuses to grow past the alloc limits, even if the runtime
@@ -372,6 +377,7 @@ macro_rules! alloc_tests {
             guard_size: SPEC_HEAP_GUARD_SIZE - 1,
             initial_size: LIMITS_HEAP_MEM_SIZE as u64,
             max_size: None,
+            bounds_checked: false,
         };
 
         /// This test shows that a heap spec with a guard size smaller than the limits is
@@ -393,6 +399,7 @@ macro_rules! alloc_tests {
             guard_size: SPEC_HEAP_GUARD_SIZE + 1,
             initial_size: ONEPAGE_INITIAL_SIZE,
             max_size: None,
+            bounds_checked: false,
         };
 
         /// This test shows that a `HeapSpec` with a guard size larger than the limits is not
@@ -590,6 +597,7 @@ macro_rules! alloc_tests {
             guard_size: 0,
             initial_size: ONEPAGE_INITIAL_SIZE,
             max_size: None,
+            bounds_checked: false,
         };
 
         /// This test shows the alloc works even with a zero guard size.
@@ -640,6 +648,7 @@ macro_rules! alloc_tests {
             guard_size: SPEC_HEAP_GUARD_SIZE,
             initial_size: 0,
             max_size: None,
+            bounds_checked: false,
         };
 
         /// This test shows an initially-empty heap works properly after a single expand.
@@ -653,6 +662,7 @@ macro_rules! alloc_tests {
             guard_size: 0,
             initial_size: 0,
             max_size: None,
+            bounds_checked: false,
         };
 
         /// This test shows an initially-empty, guardless heap works properly after a single
@@ -675,6 +685,7 @@ macro_rules! alloc_tests {
             guard_size: 4096,
             initial_size: CONTEXT_TEST_INITIAL_SIZE,
             max_size: Some(4096),
+            bounds_checked: false,
         };
 
         /// This test shows that alloced memory will create a heap and a stack that child context
@@ -998,6 +1009,131 @@ macro_rules! alloc_tests {
             }
         }
 
+        /// This test exercises an elastic region growing one chunk at a time up to its maximum
+        /// capacity.
+        #[test]
+        fn elastic_region_grows() {
+            let module = MockModuleBuilder::new()
+                .with_heap_spec(ONE_PAGE_HEAP)
+                .build();
+            let capacity = ElasticCapacity::new(1, 5).with_chunk_size(2);
+            let region = <TestRegion as RegionCreate>::create_elastic(&capacity, &LIMITS)
+                .expect("region created");
+            assert_eq!(region.capacity(), 1);
+            assert_eq!(region.free_slots(), 1);
+            assert_eq!(region.used_slots(), 0);
+
+            let mut insts = Vec::new();
+            for (used, capacity) in [(1, 1), (2, 3), (3, 3), (4, 5), (5, 5)].iter() {
+                insts.push(
+                    region
+                        .new_instance(module.clone())
+                        .expect("new_instance succeeds"),
+                );
+                assert_eq!(region.capacity(), *capacity);
+                assert_eq!(region.used_slots(), *used);
+                assert_eq!(region.free_slots(), capacity - used);
+            }
+
+            match region.new_instance(module.clone()) {
+                Err(Error::RegionFull(5)) => (),
+                Err(e) => panic!("unexpected error: {}", e),
+                Ok(_) => panic!("region grew past its maximum capacity"),
+            }
+
+            // slots from the new chunks can be reused
+            drop(insts.pop());
+            assert_eq!(region.free_slots(), 1);
+            let mut inst = region.new_instance(module).expect("new_instance succeeds");
+            let heap = unsafe { inst.alloc_mut().heap_mut() };
+            assert_eq!(heap[0], 0);
+        }
+
+        /// This test exercises an elastic region releasing idle chunks, but never shrinking below
+        /// its minimum capacity.
+        #[test]
+        fn elastic_region_shrinks() {
+            let module = MockModuleBuilder::new()
+                .with_heap_spec(ONE_PAGE_HEAP)
+                .build();
+            let capacity = ElasticCapacity::new(1, 3)
+                .with_chunk_size(2)
+                .with_idle_timeout(Duration::from_secs(0));
+            let region = <TestRegion as RegionCreate>::create_elastic(&capacity, &LIMITS)
+                .expect("region created");
+
+            let inst1 = region
+                .new_instance(module.clone())
+                .expect("new_instance succeeds");
+            let inst2 = region
+                .new_instance(module.clone())
+                .expect("new_instance succeeds");
+            assert_eq!(region.capacity(), 3);
+            assert_eq!(region.used_slots(), 2);
+
+            // dropping an instance leaves a chunk's worth of free slots, which is released right
+            // away as the timeout is zero
+            drop(inst1);
+            assert_eq!(region.capacity(), 1);
+            assert_eq!(region.free_slots(), 0);
+            assert_eq!(region.used_slots(), 1);
+            assert_eq!(region.release_idle_slots(), 0);
+
+            drop(inst2);
+            assert_eq!(region.capacity(), 1);
+            assert_eq!(region.free_slots(), 1);
+            assert_eq!(region.release_idle_slots(), 0);
+
+            // the region can grow again after shrinking
+            let _insts = (0..3)
+                .map(|_| {
+                    region
+                        .new_instance(module.clone())
+                        .expect("new_instance succeeds")
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(region.capacity(), 3);
+            assert_eq!(region.free_slots(), 0);
+        }
+
+        /// This test checks that idle slots are kept until the idle timeout has passed.
+        #[test]
+        fn elastic_region_keeps_slots_until_timeout() {
+            let module = MockModuleBuilder::new()
+                .with_heap_spec(ONE_PAGE_HEAP)
+                .build();
+            let capacity = ElasticCapacity::new(0, 2)
+                .with_chunk_size(2)
+                .with_idle_timeout(Duration::from_secs(3600));
+            let region = <TestRegion as RegionCreate>::create_elastic(&capacity, &LIMITS)
+                .expect("region created");
+            assert_eq!(region.capacity(), 0);
+
+            let inst = region.new_instance(module).expect("new_instance succeeds");
+            assert_eq!(region.capacity(), 2);
+            drop(inst);
+            assert_eq!(region.release_idle_slots(), 0);
+            assert_eq!(region.capacity(), 2);
+            assert_eq!(region.free_slots(), 2);
+        }
+
+        #[test]
+        fn reject_invalid_elastic_capacity() {
+            for capacity in [
+                ElasticCapacity::new(0, 0),
+                ElasticCapacity::new(2, 1),
+                ElasticCapacity::new(1, 2).with_chunk_size(0),
+            ]
+            .iter()
+            {
+                match <TestRegion as RegionCreate>::create_elastic(capacity, &LIMITS) {
+                    Err(Error::InvalidArgument(_)) => (),
+                    Err(e) => panic!("unexpected error: {}", e),
+                    Ok(_) => panic!("region created with {:?}", capacity),
+                }
+            }
+        }
+
         fn do_nothing_module() -> Arc<dyn Module> {
             extern "C" fn do_nothing(_vmctx: *const lucet_vmctx) -> () {}
 
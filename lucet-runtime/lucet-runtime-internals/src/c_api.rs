@@ -6,7 +6,9 @@ pub use self::lucet_val::*;
 use crate::alloc::Limits;
 use crate::error::Error;
 use crate::instance::signals::SignalBehavior;
-use libc::{c_int, c_void};
+use crate::instance::InstanceStats;
+use crate::module::{Export, ExportKind, GlobalInfo, Import, ImportKind, MemoryInfo, ValueType};
+use libc::{c_char, c_int, c_void};
 use num_derive::FromPrimitive;
 
 #[macro_export]
@@ -100,10 +102,15 @@ impl From<&Error> for lucet_error {
             Error::LimitsExceeded(_) => lucet_error::LimitsExceeded,
             Error::NoLinearMemory(_) => lucet_error::NoLinearMemory,
             Error::SymbolNotFound(_) => lucet_error::SymbolNotFound,
+            Error::GlobalImportNotProvided(_) => lucet_error::InvalidArgument,
+            Error::FunctionImportNotProvided(_) => lucet_error::InvalidArgument,
+            Error::FunctionImportMismatch(_) => lucet_error::InvalidArgument,
             Error::FuncNotFound(_, _) => lucet_error::FuncNotFound,
             Error::RuntimeFault(_) => lucet_error::RuntimeFault,
             Error::RuntimeTerminated(_) => lucet_error::RuntimeTerminated,
             Error::DlError(_) => lucet_error::Dl,
+            Error::ObjectError(_) => lucet_error::Module,
+            Error::SnapshotError(_) => lucet_error::InvalidArgument,
             Error::InstanceNotReturned => lucet_error::InstanceNotReturned,
             Error::InstanceNotYielded => lucet_error::InstanceNotYielded,
             Error::InstanceNeedsStart => lucet_error::InstanceNeedsStart,
@@ -142,7 +149,7 @@ pub struct lucet_alloc_limits {
     pub heap_address_space_size: u64,
     /// Size of the guest stack. (default 128K)
     pub stack_size: u64,
-    /// Size of the globals region in bytes; each global uses 8 bytes. (default 4K)
+    /// Size of the globals region in bytes; each global uses 16 bytes. (default 4K)
     pub globals_size: u64,
     /// Size of the signal stack in bytes. (default SIGSTKSZ for release builds, at least 12K for
     /// debug builds; minimum MINSIGSTKSZ)
@@ -192,6 +199,190 @@ impl From<&lucet_alloc_limits> for Limits {
     }
 }
 
+/// Runtime statistics for an instance; see `Instance::stats()`.
+///
+/// Times are given in nanoseconds.
+#[derive(Clone, Debug, Default)]
+#[repr(C)]
+pub struct lucet_instance_stats {
+    pub guest_wall_time_ns: u64,
+    pub guest_cpu_time_ns: u64,
+    pub hostcall_wall_time_ns: u64,
+    pub hostcall_cpu_time_ns: u64,
+    pub hostcalls: u64,
+    pub yields: u64,
+    pub resumes: u64,
+    pub peak_heap_size: u64,
+    pub memory_grows: u64,
+    pub page_faults: u64,
+    pub signals: u64,
+}
+
+impl From<&InstanceStats> for lucet_instance_stats {
+    fn from(stats: &InstanceStats) -> lucet_instance_stats {
+        lucet_instance_stats {
+            guest_wall_time_ns: stats.guest_wall_time.as_nanos() as u64,
+            guest_cpu_time_ns: stats.guest_cpu_time.as_nanos() as u64,
+            hostcall_wall_time_ns: stats.hostcall_wall_time.as_nanos() as u64,
+            hostcall_cpu_time_ns: stats.hostcall_cpu_time.as_nanos() as u64,
+            hostcalls: stats.hostcalls,
+            yields: stats.yields,
+            resumes: stats.resumes,
+            peak_heap_size: stats.peak_heap_size,
+            memory_grows: stats.memory_grows,
+            page_faults: stats.page_faults,
+            signals: stats.signals,
+        }
+    }
+}
+
+/// A name from a module's data, which is valid for as long as the module is.
+///
+/// The name is UTF-8 and is not nul-terminated, as WebAssembly names may contain nul characters.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct lucet_module_name {
+    pub ptr: *const c_char,
+    pub len: usize,
+}
+
+impl From<&str> for lucet_module_name {
+    fn from(name: &str) -> lucet_module_name {
+        lucet_module_name {
+            ptr: name.as_ptr() as *const c_char,
+            len: name.len(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum lucet_value_type {
+    I32,
+    I64,
+    F32,
+    F64,
+    V128,
+    FuncRef,
+    ExternRef,
+}
+
+impl From<ValueType> for lucet_value_type {
+    fn from(ty: ValueType) -> lucet_value_type {
+        match ty {
+            ValueType::I32 => lucet_value_type::I32,
+            ValueType::I64 => lucet_value_type::I64,
+            ValueType::F32 => lucet_value_type::F32,
+            ValueType::F64 => lucet_value_type::F64,
+            ValueType::V128 => lucet_value_type::V128,
+            ValueType::FuncRef => lucet_value_type::FuncRef,
+            ValueType::ExternRef => lucet_value_type::ExternRef,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum lucet_extern_kind {
+    Func,
+    Global,
+}
+
+/// A function or global exported by a module; see `Module::exports()`.
+///
+/// `index` is the function index, which can be passed to `lucet_dl_module_func_signature()`, or
+/// the global index.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct lucet_module_export {
+    pub kind: lucet_extern_kind,
+    pub index: u32,
+    pub name: lucet_module_name,
+}
+
+impl From<&Export<'_>> for lucet_module_export {
+    fn from(export: &Export<'_>) -> lucet_module_export {
+        let (kind, index) = match export.kind {
+            ExportKind::Func { index, .. } => (lucet_extern_kind::Func, index.as_u32()),
+            ExportKind::Global { index, .. } => (lucet_extern_kind::Global, index),
+        };
+        lucet_module_export {
+            kind,
+            index,
+            name: export.name.into(),
+        }
+    }
+}
+
+/// A function or global imported by a module; see `Module::imports()`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct lucet_module_import {
+    pub kind: lucet_extern_kind,
+    pub index: u32,
+    pub module: lucet_module_name,
+    pub name: lucet_module_name,
+}
+
+impl From<&Import<'_>> for lucet_module_import {
+    fn from(import: &Import<'_>) -> lucet_module_import {
+        let (kind, index) = match import.kind {
+            ImportKind::Func { index, .. } => (lucet_extern_kind::Func, index.as_u32()),
+            ImportKind::Global { index, .. } => (lucet_extern_kind::Global, index),
+        };
+        lucet_module_import {
+            kind,
+            index,
+            module: import.module.into(),
+            name: import.name.into(),
+        }
+    }
+}
+
+/// A global of a module; see `Module::globals()`. Its export names and the names it is imported
+/// under are given by the module's exports and imports.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct lucet_module_global {
+    pub index: u32,
+    pub ty: lucet_value_type,
+    pub is_mutable: bool,
+    pub imported: bool,
+    pub exported: bool,
+}
+
+impl From<&GlobalInfo<'_>> for lucet_module_global {
+    fn from(global: &GlobalInfo<'_>) -> lucet_module_global {
+        lucet_module_global {
+            index: global.index,
+            ty: global.ty.into(),
+            is_mutable: global.mutable,
+            imported: global.import.is_some(),
+            exported: !global.export_names.is_empty(),
+        }
+    }
+}
+
+/// The linear memory of a module; see `Module::memory()`. `max_pages` is only meaningful if
+/// `has_max_pages` is set.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct lucet_module_memory {
+    pub initial_pages: u32,
+    pub has_max_pages: bool,
+    pub max_pages: u32,
+}
+
+impl From<&MemoryInfo> for lucet_module_memory {
+    fn from(memory: &MemoryInfo) -> lucet_module_memory {
+        lucet_module_memory {
+            initial_pages: memory.initial_pages,
+            has_max_pages: memory.max_pages.is_some(),
+            max_pages: memory.max_pages.unwrap_or(0),
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub enum lucet_signal_behavior {
@@ -244,7 +435,7 @@ pub mod lucet_result {
     use super::lucet_error;
     use crate::c_api::{lucet_val, CTerminationDetails, CYieldedVal};
     use crate::error::Error;
-    use crate::instance::{RunResult, TerminationDetails};
+    use crate::instance::{RunResult, TerminationDetails, WasmFrame};
     use crate::module::{AddrDetails, TrapCode};
     use libc::{c_uchar, c_void};
     use num_derive::FromPrimitive;
@@ -280,6 +471,7 @@ pub mod lucet_result {
                             trapcode: details.trapcode.into(),
                             rip_addr: details.rip_addr,
                             rip_addr_details: details.rip_addr_details.into(),
+                            backtrace_len: details.backtrace.len(),
                         },
                     },
                 },
@@ -315,6 +507,14 @@ pub mod lucet_result {
                                 reason: lucet_terminated_reason::Remote,
                                 provided: std::ptr::null_mut(),
                             },
+                            TerminationDetails::OutOfFuel => lucet_terminated {
+                                reason: lucet_terminated_reason::OutOfFuel,
+                                provided: std::ptr::null_mut(),
+                            },
+                            TerminationDetails::Timeout => lucet_terminated {
+                                reason: lucet_terminated_reason::Timeout,
+                                provided: std::ptr::null_mut(),
+                            },
                         },
                     },
                 },
@@ -369,6 +569,8 @@ pub mod lucet_result {
         BorrowError,
         Provided,
         Remote,
+        OutOfFuel,
+        Timeout,
     }
 
     #[repr(C)]
@@ -384,6 +586,7 @@ pub mod lucet_result {
         pub trapcode: lucet_trapcode,
         pub rip_addr: libc::uintptr_t,
         pub rip_addr_details: lucet_module_addr_details,
+        pub backtrace_len: usize,
     }
 
     #[repr(C)]
@@ -492,6 +695,35 @@ pub mod lucet_result {
             ret
         }
     }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    pub struct lucet_wasm_frame {
+        pub func_index: u32,
+        pub offset: u32,
+        pub has_wasm_offset: bool,
+        pub wasm_offset: u32,
+        pub func_name: [c_uchar; ADDR_DETAILS_NAME_LEN],
+    }
+
+    impl From<&WasmFrame> for lucet_wasm_frame {
+        fn from(frame: &WasmFrame) -> Self {
+            let mut ret = lucet_wasm_frame {
+                func_index: frame.func_index.as_u32(),
+                offset: frame.offset,
+                has_wasm_offset: frame.wasm_offset.is_some(),
+                wasm_offset: frame.wasm_offset.unwrap_or(0),
+                func_name: [0; ADDR_DETAILS_NAME_LEN],
+            };
+            // truncate the name so it fits with a trailing nul; names from a wasm module may
+            // contain interior nuls, which C simply sees as the end of the string
+            if let Some(name) = frame.func_name.as_ref() {
+                let len = name.len().min(ADDR_DETAILS_NAME_LEN - 1);
+                ret.func_name[..len].copy_from_slice(&name.as_bytes()[..len]);
+            }
+            ret
+        }
+    }
 }
 
 pub mod lucet_val {
@@ -500,26 +732,29 @@ pub mod lucet_val {
 
     // Note on the value associated with each type: the most significant bits represent the "class"
     // of the type (1: a C pointer, 2: something unsigned that fits in 64 bits, 3: something signed
-    // that fits in 64 bits, 4: f32, 5: f64). The remain bits can be anything as long as it is
-    // unique.
+    // that fits in 64 bits, 4: f32, 5: f64, 6: v128). The remain bits can be anything as long as
+    // it is unique.
     #[repr(C)]
     #[derive(Clone, Copy, Debug)]
     pub enum lucet_val_type {
-        C_Ptr,    // = (1 << 16) | 0x0100,
-        GuestPtr, // = (2 << 16) | 0x0101,
-        U8,       // = (2 << 16) | 0x0201,
-        U16,      // = (2 << 16) | 0x0202,
-        U32,      // = (2 << 16) | 0x0203,
-        U64,      // = (2 << 16) | 0x0204,
-        I8,       // = (3 << 16) | 0x0300,
-        I16,      // = (3 << 16) | 0x0301,
-        I32,      // = (3 << 16) | 0x0302,
-        I64,      // = (3 << 16) | 0x0303,
-        USize,    // = (2 << 16) | 0x0400,
-        ISize,    // = (3 << 16) | 0x0401,
-        Bool,     // = (2 << 16) | 0x0700,
-        F32,      // = (4 << 16) | 0x0800,
-        F64,      // = (5 << 16) | 0x0801,
+        C_Ptr,     // = (1 << 16) | 0x0100,
+        GuestPtr,  // = (2 << 16) | 0x0101,
+        U8,        // = (2 << 16) | 0x0201,
+        U16,       // = (2 << 16) | 0x0202,
+        U32,       // = (2 << 16) | 0x0203,
+        U64,       // = (2 << 16) | 0x0204,
+        I8,        // = (3 << 16) | 0x0300,
+        I16,       // = (3 << 16) | 0x0301,
+        I32,       // = (3 << 16) | 0x0302,
+        I64,       // = (3 << 16) | 0x0303,
+        USize,     // = (2 << 16) | 0x0400,
+        ISize,     // = (3 << 16) | 0x0401,
+        Bool,      // = (2 << 16) | 0x0700,
+        F32,       // = (4 << 16) | 0x0800,
+        F64,       // = (5 << 16) | 0x0801,
+        FuncRef,   // = (1 << 16) | 0x0900,
+        ExternRef, // = (1 << 16) | 0x0901,
+        V128,      // = (6 << 16) | 0x0a00,
     }
 
     #[repr(C)]
@@ -530,6 +765,7 @@ pub mod lucet_val {
         as_i64: i64,           // (3 << 16)
         as_f32: f32,           // (4 << 16)
         as_f64: f64,           // (5 << 16)
+        as_v128: [u8; 16],     // (6 << 16)
     }
 
     #[repr(C)]
@@ -563,6 +799,11 @@ pub mod lucet_val {
                 lucet_val_type::Bool => Val::Bool(unsafe { val.inner_val.as_u64 } != 0),
                 lucet_val_type::F32 => Val::F32(unsafe { val.inner_val.as_f32 } as _),
                 lucet_val_type::F64 => Val::F64(unsafe { val.inner_val.as_f64 } as _),
+                lucet_val_type::FuncRef => Val::FuncRef(unsafe { val.inner_val.as_u64 } as _),
+                lucet_val_type::ExternRef => Val::ExternRef(unsafe { val.inner_val.as_u64 } as _),
+                lucet_val_type::V128 => {
+                    Val::V128(u128::from_le_bytes(unsafe { val.inner_val.as_v128 }))
+                }
             }
         }
     }
@@ -636,6 +877,20 @@ pub mod lucet_val {
                     ty: lucet_val_type::F64,
                     inner_val: lucet_val_inner_val { as_f64: *a as _ },
                 },
+                Val::FuncRef(a) => lucet_val {
+                    ty: lucet_val_type::FuncRef,
+                    inner_val: lucet_val_inner_val { as_u64: *a as _ },
+                },
+                Val::ExternRef(a) => lucet_val {
+                    ty: lucet_val_type::ExternRef,
+                    inner_val: lucet_val_inner_val { as_u64: *a as _ },
+                },
+                Val::V128(a) => lucet_val {
+                    ty: lucet_val_type::V128,
+                    inner_val: lucet_val_inner_val {
+                        as_v128: a.to_le_bytes(),
+                    },
+                },
             }
         }
     }
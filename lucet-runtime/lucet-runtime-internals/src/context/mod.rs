@@ -6,6 +6,7 @@ mod tests;
 use crate::instance::Instance;
 use crate::val::{val_to_reg, val_to_stack, RegVal, UntypedRetVal, Val};
 
+use serde::{Deserialize, Serialize};
 use std::arch::x86_64::{__m128, _mm_setzero_ps};
 use std::ptr::NonNull;
 use std::{mem, ptr};
@@ -92,6 +93,22 @@ impl FpRegs {
     }
 }
 
+/// The registers of a context that is suspended in `swap`, as returned by
+/// `Context::saved_regs()`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct SavedRegs {
+    /// The general-purpose registers, in the order they appear in `GpRegs`.
+    pub(crate) gpr: [u64; 9],
+    fpr: [[u8; 16]; 8],
+}
+
+impl SavedRegs {
+    /// The saved stack pointer.
+    pub(crate) fn rsp(&self) -> u64 {
+        self.gpr[1]
+    }
+}
+
 /// Everything we need to make a context switch: a signal mask, and the registers and return values
 /// that are manipulated directly by assembly code.
 ///
@@ -117,7 +134,7 @@ pub struct Context {
     pub(crate) gpr: GpRegs,
     fpr: FpRegs,
     retvals_gp: [u64; 2],
-    retval_fp: __m128,
+    retvals_fp: [__m128; 2],
     parent_ctx: *mut Context,
     // TODO ACF 2019-10-23: make Instance into a generic parameter?
     backstop_callback: *const unsafe extern "C" fn(*mut Instance),
@@ -131,7 +148,7 @@ impl Context {
             gpr: GpRegs::new(),
             fpr: FpRegs::new(),
             retvals_gp: [0; 2],
-            retval_fp: unsafe { _mm_setzero_ps() },
+            retvals_fp: unsafe { [_mm_setzero_ps(); 2] },
             parent_ctx: ptr::null_mut(),
             backstop_callback: Context::default_backstop_callback as *const _,
             callback_data: ptr::null_mut(),
@@ -421,6 +438,9 @@ impl Context {
                 }
                 RegVal::FpReg(v) => {
                     if fp_args_ix >= 8 {
+                        if let Val::V128(_) = arg {
+                            return Err(Error::SpilledV128Arg);
+                        }
                         spilled_args.push(val_to_stack(arg));
                     } else {
                         child.bootstrap_fp_ix_arg(fp_args_ix, v);
@@ -619,11 +639,56 @@ impl Context {
         lucet_context_set(to as *const Context);
     }
 
+    /// Get the registers that were saved by `swap` when this context was last swapped away from.
+    ///
+    /// Together with the contents of the context's stack, these are enough to swap back to it
+    /// later; see `Context::restore_regs()`.
+    pub(crate) fn saved_regs(&self) -> SavedRegs {
+        let gpr = &self.gpr;
+        SavedRegs {
+            gpr: [
+                gpr.rbx, gpr.rsp, gpr.rbp, gpr.rdi, gpr.r12, gpr.r13, gpr.r14, gpr.r15, gpr.rsi,
+            ],
+            fpr: unsafe { mem::transmute_copy(&self.fpr) },
+        }
+    }
+
+    /// Overwrite the registers of this context with ones returned by `saved_regs()`, passing each
+    /// general-purpose register through `relocate` first.
+    ///
+    /// The return values and parent context are cleared, and the backstop callback is replaced as
+    /// it is by `init_with_callback()`.
+    pub(crate) fn restore_regs(
+        &mut self,
+        regs: &SavedRegs,
+        relocate: impl Fn(u64) -> u64,
+        backstop_callback: unsafe extern "C" fn(*mut Instance),
+        callback_data: *mut Instance,
+    ) {
+        let [rbx, rsp, rbp, rdi, r12, r13, r14, r15, rsi] = regs.gpr;
+        self.gpr = GpRegs {
+            rbx: relocate(rbx),
+            rsp: relocate(rsp),
+            rbp: relocate(rbp),
+            rdi: relocate(rdi),
+            r12: relocate(r12),
+            r13: relocate(r13),
+            r14: relocate(r14),
+            r15: relocate(r15),
+            rsi: relocate(rsi),
+        };
+        self.fpr = unsafe { mem::transmute::<[[u8; 16]; 8], FpRegs>(regs.fpr) };
+        self.clear_retvals();
+        self.parent_ctx = ptr::null_mut();
+        self.backstop_callback = backstop_callback as *const _;
+        self.callback_data = callback_data;
+    }
+
     /// Clear (zero) return values.
     pub fn clear_retvals(&mut self) {
         self.retvals_gp = [0; 2];
         let zero = unsafe { _mm_setzero_ps() };
-        self.retval_fp = zero;
+        self.retvals_fp = [zero; 2];
     }
 
     /// Get the general-purpose return value at index `idx`.
@@ -639,7 +704,15 @@ impl Context {
     /// If this method is called before the context has returned from its original entrypoint, the
     /// result will be `0.0`.
     pub fn get_retval_fp(&self) -> __m128 {
-        self.retval_fp
+        self.get_retval_fp_ix(0)
+    }
+
+    /// Get the floating point return value at index `idx`.
+    ///
+    /// If this method is called before the context has returned from its original entrypoint, the
+    /// result will be `0.0`.
+    pub fn get_retval_fp_ix(&self, idx: usize) -> __m128 {
+        self.retvals_fp[idx]
     }
 
     /// Get the return value as an `UntypedRetVal`.
@@ -676,6 +749,9 @@ pub enum Error {
     /// Raised when the bottom of the stack provided to `Context::init` is not 16-byte aligned
     #[error("context initialized with unaligned stack")]
     UnalignedStack,
+    /// Raised when a `v128` argument does not fit in the floating-point argument registers
+    #[error("v128 arguments cannot be passed on the stack")]
+    SpilledV128Arg,
 }
 
 /// Check whether the bottom (highest address) of the stack is 16-byte aligned, as required by the
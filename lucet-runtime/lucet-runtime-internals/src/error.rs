@@ -29,6 +29,20 @@ pub enum Error {
     #[error("Symbol not found: {0}")]
     SymbolNotFound(String),
 
+    /// A module imports a global that was not given a value when the instance was built.
+    #[error("Global import not provided: {0}")]
+    GlobalImportNotProvided(String),
+
+    /// A module compiled with linked imports imports a function that the instance's `Linker` does
+    /// not provide.
+    #[error("Function import not provided: {0}")]
+    FunctionImportNotProvided(String),
+
+    /// A module compiled with linked imports imports a function that the instance's `Linker`
+    /// provides with a different signature.
+    #[error("Function import signature mismatch: {0}")]
+    FunctionImportMismatch(String),
+
     /// An attempt to look up a WebAssembly function by its table index failed.
     #[error("Function not found: (table {0}, func {1}")]
     FuncNotFound(u32, u32),
@@ -53,6 +67,19 @@ pub enum Error {
         crate::module::DlError,
     ),
 
+    /// Errors arising while loading a relocatable object with
+    /// [`ObjectModule`](struct.ObjectModule.html).
+    #[error("Object loading error: {0}")]
+    ObjectError(
+        #[from]
+        #[source]
+        crate::module::ObjectError,
+    ),
+
+    /// An instance snapshot could not be taken, or could not be restored into a new instance.
+    #[error("Snapshot error: {0}")]
+    SnapshotError(String),
+
     #[error("Instance not returned")]
     InstanceNotReturned,
 
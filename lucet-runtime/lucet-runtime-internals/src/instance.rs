@@ -1,33 +1,56 @@
+mod backtrace;
+mod deadline;
+pub mod epoch;
 pub mod execution;
 mod siginfo_ext;
 pub mod signals;
+mod snapshot;
 pub mod state;
+mod stats;
+mod typed_func;
 
+pub use crate::instance::backtrace::WasmFrame;
+pub use crate::instance::epoch::EpochHandle;
 pub use crate::instance::execution::{KillError, KillState, KillSuccess, KillSwitch};
 pub use crate::instance::signals::{signal_handler_none, SignalBehavior, SignalHandler};
+pub use crate::instance::snapshot::InstanceSnapshot;
 pub use crate::instance::state::State;
+pub use crate::instance::stats::InstanceStats;
+pub use crate::instance::typed_func::TypedFunc;
 
 use crate::alloc::Alloc;
 use crate::context::Context;
 use crate::embed_ctx::CtxMap;
 use crate::error::Error;
+use crate::instance::stats::StatsRecorder;
+use crate::linker::{HostFunc, Linker};
 #[cfg(feature = "concurrent_testpoints")]
 use crate::lock_testpoints::LockTestpoints;
-use crate::module::{self, FunctionHandle, Global, GlobalValue, Module, TrapCode};
+use crate::module::{
+    self, FunctionHandle, FunctionIndex, Global, GlobalBaseSegment, GlobalSpec, GlobalValue,
+    Module, TableElement, TrapCode,
+};
 use crate::region::RegionInternal;
 use crate::sysdeps::HOST_PAGE_SIZE_EXPECTED;
-use crate::val::{UntypedRetVal, Val};
+use crate::val::{
+    val_from_bits, val_from_fp_reg, val_from_global, val_to_global, UntypedRetVal, Val, WasmParams,
+    WasmResults,
+};
 use crate::WASM_PAGE_SIZE;
 use libc::{c_void, pthread_self, siginfo_t, uintptr_t};
-use lucet_module::InstanceRuntimeData;
+use lucet_module::{
+    GlobalDef, ImportFuncRef, InitialState, InstanceRuntimeData, Signature, TableRef, ValueType,
+};
 use memoffset::offset_of;
 use std::any::Any;
 use std::cell::{BorrowError, BorrowMutError, Ref, RefCell, RefMut, UnsafeCell};
-use std::marker::PhantomData;
+use std::collections::HashMap;
 use std::mem;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
 use std::ptr::{self, NonNull};
+use std::slice;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub const LUCET_INSTANCE_MAGIC: u64 = 746_932_922;
 
@@ -66,6 +89,10 @@ pub struct InstanceHandle {
 // raw pointer lint
 unsafe impl Send for InstanceHandle {}
 
+/// The values of an instance's imported globals, keyed by the module and field names they are
+/// imported as.
+pub type GlobalImports = HashMap<(String, String), Val>;
+
 /// Create a new `InstanceHandle`.
 ///
 /// This is not meant for public consumption, but rather is used to make implementations of
@@ -75,6 +102,7 @@ pub fn new_instance_handle(
     module: Arc<dyn Module>,
     alloc: Alloc,
     embed_ctx: CtxMap,
+    global_imports: GlobalImports,
 ) -> Result<InstanceHandle, Error> {
     let inst = NonNull::new(instance)
         .ok_or_else(|| lucet_format_err!("instance pointer is null; this is a bug"))?;
@@ -89,7 +117,7 @@ pub fn new_instance_handle(
         needs_inst_drop: false,
     };
 
-    let inst = Instance::new(alloc, module, embed_ctx);
+    let inst = Instance::new(alloc, module, embed_ctx, global_imports);
 
     unsafe {
         // this is wildly unsafe! you must be very careful to not let the drop impls run on the
@@ -227,6 +255,25 @@ pub struct Instance {
     /// Small mutexed state used for remote kill switch functionality
     pub(crate) kill_state: Arc<KillState>,
 
+    /// The epoch counter checked by epoch-interruptible guest code.
+    epoch: EpochHandle,
+
+    /// Runtime statistics, reported by `stats()`.
+    pub(crate) stats: StatsRecorder,
+
+    /// The longest any single run or resume may take before the instance is terminated.
+    timeout: Option<Duration>,
+
+    /// The number of calls from hostcalls back into the guest that are in progress.
+    pub(crate) nested_calls: u32,
+
+    /// The most calls from hostcalls back into the guest that may be in progress at once.
+    max_nested_calls: u32,
+
+    /// The time by which the current call started with `run_with_deadline()` must finish,
+    /// including any resumes after it yields.
+    deadline: Option<Instant>,
+
     #[cfg(feature = "concurrent_testpoints")]
     /// Conditionally-present helpers to force permutations of possible races in testing.
     pub lock_testpoints: Arc<LockTestpoints>,
@@ -264,6 +311,37 @@ pub struct Instance {
     /// The value passed back to the guest when resuming a yielded instance.
     pub(crate) resumed_val: Option<Box<dyn Any + 'static>>,
 
+    /// The values of the module's imported globals, applied each time the instance is reset.
+    global_imports: GlobalImports,
+
+    /// Whether each data segment has been dropped, indexed by data segment index.
+    dropped_data: Vec<bool>,
+
+    /// Whether each element segment has been dropped, indexed by element segment index.
+    dropped_elements: Vec<bool>,
+
+    /// The values returned by the most recent entrypoint that ran to completion.
+    results: Vec<Val>,
+
+    /// Space for the entrypoint to write its results into, if it returns too many to fit in
+    /// registers. See `uses_return_area()`.
+    return_area: Vec<u64>,
+
+    /// The elements of each table, indexed by table index.
+    tables: Vec<Vec<u64>>,
+
+    /// The location and length of each table, which guest code reads through the `tables_ptr`
+    /// implicit. Must be kept in sync with `tables`.
+    table_refs: Vec<TableRef>,
+
+    /// The host functions bound to the module's imported functions, if it was compiled with linked
+    /// imports. These keep the state of closures alive while `import_funcs` points into them.
+    host_funcs: Vec<HostFunc>,
+
+    /// The function and data pointers of each imported function, which guest code reads through
+    /// the `import_funcs_ptr` implicit. Must be kept in sync with `host_funcs`.
+    import_funcs: Vec<ImportFuncRef>,
+
     /// `_padding` must be the last member of the structure.
     /// This marks where the padding starts to make the structure exactly 4096 bytes long.
     /// It is also used to compute the size of the structure up to that point, i.e. without padding.
@@ -482,10 +560,61 @@ impl Instance {
         func_idx: u32,
         args: &[Val],
     ) -> Result<RunResult, Error> {
-        let func = self.module.get_func_from_idx(table_idx, func_idx)?;
+        let func = self.get_func_from_idx(table_idx, func_idx)?;
         self.run_func(func, &args)
     }
 
+    /// Look up the exported function `entrypoint`, checking that it takes `Params` and returns
+    /// `Results`, and return a handle that calls it with Rust values.
+    ///
+    /// `Params` is a single [`WasmTy`](../val/trait.WasmTy.html) or a tuple of up to 16 of them, and
+    /// `Results` is `()` or a single `WasmTy`. If the function's signature does not match, this
+    /// fails with `Error::InvalidArgument`.
+    ///
+    /// ```no_run
+    /// # use lucet_runtime_internals::instance::InstanceHandle;
+    /// fn example(instance: &mut InstanceHandle) {
+    ///     let scale = instance.typed_func::<(i32, i64), f64>("scale").unwrap();
+    ///     let result: f64 = scale.call(instance, (3, 5)).unwrap();
+    /// }
+    /// ```
+    ///
+    /// The handle can be kept and called again with any instance of the same module; see
+    /// [`TypedFunc::call()`](struct.TypedFunc.html#method.call).
+    pub fn typed_func<Params: WasmParams, Results: WasmResults>(
+        &self,
+        entrypoint: &str,
+    ) -> Result<TypedFunc<Params, Results>, Error> {
+        let func = self.module.get_export_func(entrypoint)?;
+        TypedFunc::new(self.module.clone(), func)
+    }
+
+    /// Run a function with arguments in the guest context at the given entrypoint, terminating the
+    /// instance if it has not finished by `deadline`.
+    ///
+    /// If the function yields, the deadline continues to apply when the instance is resumed, though
+    /// the instance cannot be terminated while it is yielded. An instance resumed after its deadline
+    /// has passed is terminated as soon as it returns to the guest. A timeout set with
+    /// [`Instance::set_timeout()`](struct.Instance.html#method.set_timeout) applies as well;
+    /// whichever expires first terminates the instance with `TerminationDetails::Timeout`.
+    ///
+    /// # Safety
+    ///
+    /// The same safety caveats of [`Instance::run()`](struct.Instance.html#method.run) apply.
+    pub fn run_with_deadline(
+        &mut self,
+        deadline: Instant,
+        entrypoint: &str,
+        args: &[Val],
+    ) -> Result<RunResult, Error> {
+        self.deadline = Some(deadline);
+        let res = self.run(entrypoint, args);
+        if !self.is_yielded() {
+            self.deadline = None;
+        }
+        res
+    }
+
     /// Resume execution of an instance that has yielded without providing a value to the guest.
     ///
     /// This should only be used when the guest yielded with
@@ -518,7 +647,7 @@ impl Instance {
         match &self.state {
             State::Yielded { expecting, .. } => {
                 // make sure the resumed value is of the right type
-                if !expecting.is::<PhantomData<A>>() {
+                if !snapshot::expects::<A>(expecting.as_ref()) {
                     return Err(Error::InvalidArgument(
                         "type mismatch between yielded instance expected value and resumed value",
                     ));
@@ -528,6 +657,7 @@ impl Instance {
         }
 
         self.resumed_val = Some(Box::new(val) as Box<dyn Any + 'static>);
+        self.stats.record_resume();
 
         self.swap_and_return()
     }
@@ -590,23 +720,68 @@ impl Instance {
     /// It is the embedder's responsibility to initialize new `KillSwitch`es after resetting an
     /// instance.
     ///
+    /// The counters reported by [`Instance::stats()`](struct.Instance.html#method.stats) are
+    /// cleared.
+    ///
+    /// Any fuel limit set with [`Instance::set_fuel()`](struct.Instance.html#method.set_fuel) is
+    /// lifted, so the instance has unlimited fuel again, as when it was created.
+    ///
     /// [run_start]: struct.Instance.html#method.run
     pub fn reset(&mut self) -> Result<(), Error> {
         self.alloc.reset_heap(self.module.as_ref())?;
+        self.sync_heap_len();
+        self.stats.reset(self.alloc.heap_len());
         let globals = unsafe { self.alloc.globals_mut() };
-        let mod_globals = self.module.globals();
+        let func_refs = self.module.func_refs();
+        let mod_globals = self.module.global_specs();
         for (i, v) in mod_globals.iter().enumerate() {
             globals[i] = match v.global() {
-                Global::Import { .. } => {
-                    return Err(Error::Unsupported(format!(
-                        "global imports are unsupported; found: {:?}",
-                        v
-                    )));
+                Global::Import { module, field, ty } => {
+                    let val = self
+                        .global_imports
+                        .get(&(module.to_string(), field.to_string()))
+                        .ok_or_else(|| {
+                            Error::GlobalImportNotProvided(format!("{}::{}", module, field))
+                        })?;
+                    if val.value_type() != *ty {
+                        return Err(Error::InvalidArgument(
+                            "global import value does not match the declared type",
+                        ));
+                    }
+                    val_to_global(val)
                 }
+                Global::Def(GlobalDef::FuncRef(func)) => GlobalValue {
+                    i_64: func_ref_ptr(func_refs, *func) as i64,
+                },
                 Global::Def(def) => def.init_val(),
             };
         }
 
+        // Active segments are dropped as soon as they have been applied, so only passive segments
+        // start out available.
+        self.dropped_data = self
+            .module
+            .passive_data()
+            .iter()
+            .map(Option::is_none)
+            .collect();
+        self.dropped_elements = self
+            .module
+            .passive_elements()
+            .iter()
+            .map(Option::is_none)
+            .collect();
+
+        self.tables = self.module_tables();
+        self.sync_table_refs();
+
+        self.apply_global_base_segments()?;
+
+        self.results.clear();
+        self.deadline = None;
+        // a reset abandons any calls from hostcalls back into the guest that were yielded
+        self.nested_calls = 0;
+
         if self.module.get_start_func()?.is_some() {
             self.state = State::NotStarted;
         } else {
@@ -621,10 +796,151 @@ impl Instance {
         {
             self.kill_state = Arc::new(KillState::new());
         }
+        self.set_epoch_deadline(1);
+        self.set_fuel(u64::MAX);
 
         Ok(())
     }
 
+    /// Save the instance's heap, globals, and tables, and if it is yielded, the call that yielded.
+    ///
+    /// The snapshot can be serialized with
+    /// [`InstanceSnapshot::to_bytes()`](struct.InstanceSnapshot.html#method.to_bytes) and restored
+    /// into a new instance with
+    /// [`Region::restore_instance()`](trait.Region.html#method.restore_instance), in this process or
+    /// another one that has loaded the same module. A yielded instance is restored yielded, and can
+    /// be resumed as if it had never stopped.
+    ///
+    /// Embedder contexts, signal handlers, and imported global values are not saved; provide them
+    /// again when restoring.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidArgument` unless the instance is ready or yielded.
+    ///
+    /// Restoring the snapshot is `unsafe`; see
+    /// [`Region::restore_instance()`](trait.Region.html#method.restore_instance) for what it
+    /// requires.
+    pub fn snapshot(&self) -> Result<InstanceSnapshot, Error> {
+        snapshot::capture(self)
+    }
+
+    /// Capture the instance's heap and globals as the initial state for a new build of its
+    /// module.
+    ///
+    /// This is meant to be called after running the start function with
+    /// [`Instance::run_start()`](struct.Instance.html#method.run_start) and any initialization
+    /// exports. Compiling the module again with the state, using `LucetcOpts::initial_state()` in
+    /// `lucetc`, gives a module whose instances start out already initialized.
+    ///
+    /// Only the heap and the values of defined globals are captured, so the state can't be
+    /// captured if initialization did anything else that would have to be preserved.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidArgument` unless the instance is ready.
+    ///
+    /// Returns `Error::Unsupported` if initialization changed a table, dropped a passive segment,
+    /// or changed a global that is initialized from an imported global, whose value is taken
+    /// from the import again in every instance of the preinitialized module.
+    pub fn initial_state(&self) -> Result<InitialState, Error> {
+        if !self.is_ready() {
+            return Err(Error::InvalidArgument(
+                "can only capture the initial state of a ready instance",
+            ));
+        }
+        self.check_initialized_tables_and_segments()?;
+
+        let heap = self.heap();
+        let mut heap_pages = heap
+            .chunks(HOST_PAGE_SIZE_EXPECTED)
+            .map(|page| {
+                if page.iter().all(|b| *b == 0) {
+                    None
+                } else {
+                    Some(page.to_vec())
+                }
+            })
+            .collect::<Vec<_>>();
+        while let Some(None) = heap_pages.last() {
+            heap_pages.pop();
+        }
+
+        let func_refs = self.module.func_refs();
+        let globals = self
+            .module
+            .global_specs()
+            .iter()
+            .zip(self.globals())
+            .zip(0..)
+            .map(|((spec, value), index)| match spec.global() {
+                // a `funcref` is captured as the function it refers to, whatever it started as
+                Global::Def(_) if spec.value_type() == ValueType::FuncRef => {
+                    match unsafe { value.i_64 } as u64 {
+                        0 => Ok(Some(GlobalDef::I64(0))),
+                        ptr => func_ref_index(func_refs, ptr)
+                            .map(|func| Some(GlobalDef::FuncRef(func)))
+                            .ok_or_else(|| {
+                                Error::Unsupported(format!(
+                                    "initial states where global {} refers to a function of \
+                                     another module",
+                                    index
+                                ))
+                            }),
+                    }
+                }
+                Global::Def(def) => Ok(Some(unsafe {
+                    match def {
+                        GlobalDef::I32(_) => GlobalDef::I32(value.i_32),
+                        GlobalDef::I64(_) => GlobalDef::I64(value.i_64),
+                        GlobalDef::F32(_) => GlobalDef::F32(value.f_32),
+                        GlobalDef::F64(_) => GlobalDef::F64(value.f_64),
+                        GlobalDef::V128(_) => GlobalDef::V128(value.v_128),
+                        GlobalDef::FuncRef(func) => GlobalDef::FuncRef(*func),
+                    }
+                })),
+                Global::Import { .. } => Ok(None),
+            })
+            .collect::<Result<_, Error>>()?;
+        for ((spec, value), index) in self
+            .module
+            .global_specs()
+            .iter()
+            .zip(self.globals())
+            .zip(0..)
+        {
+            if let Global::Import { module, field, ty } = spec.global() {
+                let import = self
+                    .global_imports
+                    .get(&(module.to_string(), field.to_string()))
+                    .map(val_to_global);
+                if !import.map_or(false, |import| same_global_value(*ty, value, &import)) {
+                    return Err(Error::Unsupported(format!(
+                        "initial states of instances whose initialization changed global {}, \
+                         which is initialized from an imported global",
+                        index
+                    )));
+                }
+            }
+        }
+
+        Ok(InitialState {
+            heap_len: heap.len() as u64,
+            heap_pages,
+            globals,
+        })
+    }
+
+    /// Get the values returned by the most recent entrypoint that ran to completion, typed
+    /// according to its signature.
+    ///
+    /// Unlike the `UntypedRetVal` in [`RunResult::Returned`](enum.RunResult.html), this includes
+    /// every result of a function that returns multiple values. It is empty if no entrypoint has
+    /// returned since the instance was created or last reset.
+    pub fn results(&self) -> &[Val] {
+        &self.results
+    }
+
     /// Grow the guest memory by the given number of WebAssembly pages.
     ///
     /// On success, returns the number of pages that existed before the call.
@@ -632,12 +948,213 @@ impl Instance {
         let additional_bytes = additional_pages
             .checked_mul(WASM_PAGE_SIZE)
             .ok_or_else(|| lucet_format_err!("additional pages larger than wasm address space",))?;
-        let orig_len = self
+        let res = self
             .alloc
-            .expand_heap(additional_bytes, self.module.as_ref())?;
+            .expand_heap(additional_bytes, self.module.as_ref());
+        self.stats.record_memory_grow(self.alloc.heap_len());
+        let orig_len = res?;
+        self.sync_heap_len();
         Ok(orig_len / WASM_PAGE_SIZE)
     }
 
+    /// Copy `len` bytes of the heap from offset `src` to offset `dst`, as `memory.copy` does. The
+    /// ranges may overlap.
+    ///
+    /// Returns `false`, leaving the heap untouched, if either range is out of bounds.
+    #[doc(hidden)]
+    pub fn memory_copy(&mut self, dst: u32, src: u32, len: u32) -> bool {
+        let heap = self.heap_mut();
+        match (checked_range(heap, dst, len), checked_range(heap, src, len)) {
+            (Some(dst), Some(src)) => {
+                heap.copy_within(src, dst.start);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Set `len` bytes of the heap starting at offset `dst` to `val`, as `memory.fill` does.
+    ///
+    /// Returns `false`, leaving the heap untouched, if the range is out of bounds.
+    #[doc(hidden)]
+    pub fn memory_fill(&mut self, dst: u32, val: u8, len: u32) -> bool {
+        let heap = self.heap_mut();
+        if let Some(dst) = checked_range(heap, dst, len) {
+            for byte in &mut heap[dst] {
+                *byte = val;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Copy `len` bytes from offset `src` of data segment `segment` to offset `dst` of the heap,
+    /// as `memory.init` does. Dropped segments are treated as empty.
+    ///
+    /// Returns `false`, leaving the heap untouched, if either range is out of bounds.
+    #[doc(hidden)]
+    pub fn memory_init(&mut self, segment: u32, dst: u32, src: u32, len: u32) -> bool {
+        let segment = segment as usize;
+        let data: &[u8] = match self.dropped_data.get(segment) {
+            Some(false) => self.module.passive_data()[segment].unwrap_or(&[]),
+            _ => &[],
+        };
+        let heap = unsafe { self.alloc.heap_mut() };
+        match (checked_range(heap, dst, len), checked_range(data, src, len)) {
+            (Some(dst), Some(src)) => {
+                heap[dst].copy_from_slice(&data[src]);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Drop data segment `segment`, as `data.drop` does.
+    #[doc(hidden)]
+    pub fn data_drop(&mut self, segment: u32) {
+        if let Some(dropped) = self.dropped_data.get_mut(segment as usize) {
+            *dropped = true;
+        }
+    }
+
+    /// Drop element segment `segment`, as `elem.drop` does.
+    #[doc(hidden)]
+    pub fn elem_drop(&mut self, segment: u32) {
+        if let Some(dropped) = self.dropped_elements.get_mut(segment as usize) {
+            *dropped = true;
+        }
+    }
+
+    /// Get element `index` of table `table`, as `table.get` does.
+    ///
+    /// Returns `None` if the element is out of bounds.
+    #[doc(hidden)]
+    pub fn table_get(&self, table: u32, index: u32) -> Option<u64> {
+        self.tables
+            .get(table as usize)?
+            .get(index as usize)
+            .copied()
+    }
+
+    /// Set element `index` of table `table` to `val`, as `table.set` does.
+    ///
+    /// Returns `false`, leaving the table untouched, if the element is out of bounds.
+    #[doc(hidden)]
+    pub fn table_set(&mut self, table: u32, index: u32, val: u64) -> bool {
+        match self
+            .tables
+            .get_mut(table as usize)
+            .and_then(|elements| elements.get_mut(index as usize))
+        {
+            Some(element) => {
+                *element = val;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Grow table `table` by `delta` elements set to `init`, as `table.grow` does.
+    ///
+    /// On success, returns the number of elements that existed before the call. Returns `None`,
+    /// leaving the table untouched, if the table would exceed its maximum size.
+    #[doc(hidden)]
+    pub fn table_grow(&mut self, table: u32, delta: u32, init: u64) -> Option<u32> {
+        let max_len = self.module.table_specs()[table as usize]
+            .maximum
+            .map_or(TABLE_ELEMENTS_MAX, |max| max.min(TABLE_ELEMENTS_MAX));
+        let elements = &mut self.tables[table as usize];
+        let orig_len = elements.len() as u32;
+        let new_len = orig_len.checked_add(delta).filter(|len| *len <= max_len)?;
+        elements.resize(new_len as usize, init);
+        // growing may have moved the elements
+        self.sync_table_refs();
+        Some(orig_len)
+    }
+
+    /// Set `len` elements of table `table` starting at `dst` to `val`, as `table.fill` does.
+    ///
+    /// Returns `false`, leaving the table untouched, if the range is out of bounds.
+    #[doc(hidden)]
+    pub fn table_fill(&mut self, table: u32, dst: u32, val: u64, len: u32) -> bool {
+        let elements = &mut self.tables[table as usize];
+        if let Some(dst) = checked_range(elements, dst, len) {
+            for element in &mut elements[dst] {
+                *element = val;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Copy `len` elements from offset `src` of table `src_table` to offset `dst` of table
+    /// `dst_table`, as `table.copy` does. The ranges may overlap.
+    ///
+    /// Returns `false`, leaving the tables untouched, if either range is out of bounds.
+    #[doc(hidden)]
+    pub fn table_copy(
+        &mut self,
+        dst_table: u32,
+        src_table: u32,
+        dst: u32,
+        src: u32,
+        len: u32,
+    ) -> bool {
+        let (dst_table, src_table) = (dst_table as usize, src_table as usize);
+        match (
+            checked_range(&self.tables[dst_table], dst, len),
+            checked_range(&self.tables[src_table], src, len),
+        ) {
+            (Some(dst), Some(src)) => {
+                if dst_table == src_table {
+                    self.tables[dst_table].copy_within(src, dst.start);
+                } else {
+                    let src = self.tables[src_table][src].to_vec();
+                    self.tables[dst_table][dst].copy_from_slice(&src);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Copy `len` elements from offset `src` of element segment `segment` to offset `dst` of table
+    /// `table`, as `table.init` does. Dropped segments are treated as empty.
+    ///
+    /// Returns `false`, leaving the table untouched, if either range is out of bounds.
+    #[doc(hidden)]
+    pub fn table_init(&mut self, table: u32, segment: u32, dst: u32, src: u32, len: u32) -> bool {
+        let segment = segment as usize;
+        let funcs: &[FunctionIndex] = match self.dropped_elements.get(segment) {
+            Some(false) => self.module.passive_elements()[segment]
+                .as_deref()
+                .unwrap_or(&[]),
+            _ => &[],
+        };
+        let func_refs = self.module.func_refs();
+        let elements = &mut self.tables[table as usize];
+        match (
+            checked_range(elements, dst, len),
+            checked_range(funcs, src, len),
+        ) {
+            (Some(dst), Some(src)) => {
+                for (element, func) in elements[dst].iter_mut().zip(&funcs[src]) {
+                    *element = func_ref_ptr(func_refs, *func);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Get the `funcref` for function `func`, as `ref.func` does.
+    #[doc(hidden)]
+    pub fn ref_func(&self, func: u32) -> u64 {
+        func_ref_ptr(self.module.func_refs(), FunctionIndex::from_u32(func))
+    }
+
     /// Return the WebAssembly heap as a slice of bytes.
     pub fn heap(&self) -> &[u8] {
         unsafe { self.alloc.heap() }
@@ -659,6 +1176,9 @@ impl Instance {
     }
 
     /// Return the WebAssembly globals as a slice of `i64`s.
+    ///
+    /// [`Instance::get_global()`](struct.Instance.html#method.get_global) reads an exported global
+    /// by name instead, without needing to know its index or type.
     pub fn globals(&self) -> &[GlobalValue] {
         unsafe { self.alloc.globals() }
     }
@@ -668,6 +1188,40 @@ impl Instance {
         unsafe { self.alloc.globals_mut() }
     }
 
+    /// Get the value of the global exported as `name`.
+    ///
+    /// Fails with `Error::SymbolNotFound` if the module exports no global by that name.
+    pub fn get_global(&self, name: &str) -> Result<Val, Error> {
+        let (idx, spec) = self.exported_global(name)?;
+        Ok(val_from_global(spec.value_type(), &self.globals()[idx]))
+    }
+
+    /// Set the global exported as `name` to `val`.
+    ///
+    /// Fails with `Error::SymbolNotFound` if the module exports no global by that name, and with
+    /// `Error::InvalidArgument` if the global is immutable or `val` is not of the global's type.
+    /// Globals of reference types cannot be set.
+    pub fn set_global(&mut self, name: &str, val: Val) -> Result<(), Error> {
+        let (idx, spec) = self.exported_global(name)?;
+        let (ty, mutable) = (spec.value_type(), spec.is_mutable());
+        if !mutable {
+            return Err(Error::InvalidArgument("global is immutable"));
+        }
+        if let ValueType::FuncRef | ValueType::ExternRef = ty {
+            return Err(Error::Unsupported(format!(
+                "setting global `{}` of type {}",
+                name, ty
+            )));
+        }
+        if val.value_type() != ty {
+            return Err(Error::InvalidArgument(
+                "global value does not match the global's type",
+            ));
+        }
+        self.globals_mut()[idx] = val_to_global(&val);
+        Ok(())
+    }
+
     /// Check whether a given range in the host address space overlaps with the memory that backs
     /// the instance heap.
     pub fn check_heap<T>(&self, ptr: *const T, len: usize) -> bool {
@@ -800,6 +1354,87 @@ impl Instance {
         KillSwitch::new(Arc::downgrade(&self.kill_state))
     }
 
+    /// Terminate the instance if any single run or resume takes longer than `timeout`, or remove
+    /// the timeout with `None`.
+    ///
+    /// Time the instance spends yielded does not count towards the timeout, which starts again
+    /// from zero each time the instance is resumed. The instance is terminated with
+    /// `TerminationDetails::Timeout`. Unlike kill switches, the timeout is kept when the instance is
+    /// reset.
+    ///
+    /// All instances in the process share a single timer thread, so this is much cheaper than
+    /// arranging to call a [`KillSwitch`](struct.KillSwitch.html) from a thread per instance.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// The timeout set by [`Instance::set_timeout()`](struct.Instance.html#method.set_timeout), if
+    /// any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Set the most calls from hostcalls back into the guest, made with
+    /// [`Vmctx::call_export()`](vmctx/struct.Vmctx.html#method.call_export) or
+    /// [`Vmctx::call_func_idx()`](vmctx/struct.Vmctx.html#method.call_func_idx), that may be in
+    /// progress at once.
+    ///
+    /// Each of these calls runs on the guest stack below the hostcall that made it, so a guest and
+    /// its hostcalls that keep calling each other would otherwise only be stopped by running out of
+    /// stack. Calls beyond the limit fail with `Error::LimitsExceeded`. The default is 16, and zero
+    /// prevents hostcalls from calling back into the guest at all.
+    pub fn set_max_nested_calls(&mut self, max: u32) {
+        self.max_nested_calls = max;
+    }
+
+    /// The limit set by
+    /// [`Instance::set_max_nested_calls()`](struct.Instance.html#method.set_max_nested_calls).
+    pub fn max_nested_calls(&self) -> u32 {
+        self.max_nested_calls
+    }
+
+    /// Measure the CPU time spent in guest code and in hostcalls, as reported by
+    /// [`Instance::stats()`](struct.Instance.html#method.stats), or stop measuring it.
+    ///
+    /// This is off by default, as reading the thread's CPU time costs a system call each time
+    /// the instance enters or leaves a hostcall. The other statistics are always kept.
+    pub fn set_cpu_stats(&mut self, enabled: bool) {
+        self.stats.set_cpu_time(enabled);
+    }
+
+    /// Whether CPU time is being measured, as set by
+    /// [`Instance::set_cpu_stats()`](struct.Instance.html#method.set_cpu_stats).
+    pub fn cpu_stats(&self) -> bool {
+        self.stats.cpu_time()
+    }
+
+    /// Get a handle to the epoch counter this instance checks.
+    ///
+    /// If the module was compiled with epoch interruption, incrementing the counter past the
+    /// instance's deadline makes the guest terminate with `TerminationDetails::Remote` at its next
+    /// function entry or loop header. No signal is sent. Otherwise, the counter has no effect.
+    pub fn epoch_handle(&self) -> EpochHandle {
+        self.epoch.clone()
+    }
+
+    /// Check the counter behind `handle` rather than the instance's current one.
+    ///
+    /// Sharing a handle between instances lets one increment interrupt all of them. This resets
+    /// the deadline to one increment past the handle's current epoch.
+    pub fn set_epoch_handle(&mut self, handle: EpochHandle) {
+        self.get_instance_implicits_mut().epoch_ptr = handle.as_ptr();
+        self.epoch = handle;
+        self.set_epoch_deadline(1);
+    }
+
+    /// Let the instance run until its epoch counter has been incremented `ticks` more times.
+    ///
+    /// The deadline is set to one increment when the instance is created or reset.
+    pub fn set_epoch_deadline(&mut self, ticks: u64) {
+        let deadline = self.epoch.current().saturating_add(ticks);
+        self.get_instance_implicits_mut().epoch_deadline = deadline;
+    }
+
     pub fn is_not_started(&self) -> bool {
         self.state.is_not_started()
     }
@@ -820,13 +1455,26 @@ impl Instance {
         self.state.is_terminated()
     }
 
+    /// Get the details of the fault that stopped the instance, if it is faulted.
+    ///
+    /// These are the same details returned in `Error::RuntimeFault`, including the backtrace.
+    pub fn fault_details(&self) -> Option<&FaultDetails> {
+        if let State::Faulted { ref details, .. } = self.state {
+            Some(details)
+        } else {
+            None
+        }
+    }
+
     // This needs to be public as it's used in the expansion of `lucet_hostcalls`, available for
     // external use. But you *really* shouldn't have to call this yourself, so we're going to keep
     // it out of rustdoc.
     #[doc(hidden)]
     pub fn uninterruptable<T, F: FnOnce() -> T>(&mut self, f: F) -> T {
         self.kill_state.begin_hostcall();
+        self.stats.begin_hostcall();
         let res = f();
+        self.stats.end_hostcall();
         let stop_reason = self.kill_state.end_hostcall();
 
         if let Some(termination_details) = stop_reason {
@@ -851,12 +1499,189 @@ impl Instance {
     pub fn set_instruction_count(&mut self, instruction_count: u64) {
         self.get_instance_implicits_mut().instruction_count = instruction_count;
     }
+
+    /// Get the number of instructions the instance may still run before it yields
+    /// [`OutOfFuel`](struct.OutOfFuel.html).
+    ///
+    /// Returns `None` if the module was not compiled with instruction counting. An instance that
+    /// has never had its fuel set has effectively unlimited fuel.
+    #[inline]
+    pub fn get_fuel(&self) -> Option<u64> {
+        self.get_instruction_count().map(|count| {
+            self.get_instance_implicits()
+                .fuel_limit
+                .saturating_sub(count)
+        })
+    }
+
+    /// Allow the instance to run `fuel` more instructions, counted from its current instruction
+    /// count.
+    ///
+    /// Fuel is checked at loop headers and before calls, so the instance may run slightly past its
+    /// budget before it notices. When it runs out, the guest yields
+    /// [`OutOfFuel`](struct.OutOfFuel.html); set more fuel and `resume()` to continue.
+    ///
+    /// This has no effect unless the module was compiled with instruction counting.
+    #[inline]
+    pub fn set_fuel(&mut self, fuel: u64) {
+        let implicits = self.get_instance_implicits_mut();
+        implicits.fuel_limit = implicits.instruction_count.saturating_add(fuel);
+    }
+
+    /// Get the instance's runtime statistics, accumulated across calls to `run()` and `resume()`
+    /// since the instance was created or last reset.
+    ///
+    /// Time is measured from when the host switches into the guest until it switches back, and is
+    /// split between guest code and hostcalls. CPU time is that of the thread running the instance,
+    /// and is only measured once enabled with
+    /// [`Instance::set_cpu_stats()`](struct.Instance.html#method.set_cpu_stats).
+    pub fn stats(&self) -> InstanceStats {
+        self.stats.snapshot()
+    }
+}
+
+/// Whether a function with the given results returns them through a return area rather than in
+/// registers.
+///
+/// This follows the Cranelift x86-64 convention: results are returned in `rax` and `rdx`, or `xmm0`
+/// and `xmm1` for floating-point values, when they fit. Otherwise, every result is written to an
+/// area whose address the caller passes in the first argument register, ahead of the vmctx, with
+/// one 8-byte slot per result.
+fn uses_return_area(results: &[ValueType]) -> bool {
+    let fp_results = results.iter().filter(|ty| is_fp_type(**ty)).count();
+    let gp_results = results.len() - fp_results;
+    gp_results > 2 || fp_results > 2
+}
+
+/// Check that `args` match the parameters of a function with signature `sig`.
+fn typecheck_args(sig: &Signature, args: &[Val]) -> Result<(), Error> {
+    // in typechecking these values, we can only really check that arguments are correct.
+    // in the future we might want to make return value use more type safe as well.
+
+    if sig.params.len() != args.len() {
+        return Err(Error::InvalidArgument(
+            "entrypoint function signature mismatch (number of arguments is incorrect)",
+        ));
+    }
+
+    for (param_ty, arg) in sig.params.iter().zip(args.iter()) {
+        if param_ty != &arg.value_type() {
+            return Err(Error::InvalidArgument(
+                "entrypoint function signature mismatch",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The results of type `results` returned by a function that ran in `ctx`, with `return_area`
+/// passed as its return area if it needed one.
+fn results_from(results: &[ValueType], ctx: &Context, return_area: &[u64]) -> Vec<Val> {
+    if uses_return_area(results) {
+        results
+            .iter()
+            .zip(return_area.iter())
+            .map(|(ty, bits)| val_from_bits(*ty, *bits))
+            .collect()
+    } else {
+        let mut gp_ix = 0;
+        let mut fp_ix = 0;
+        results
+            .iter()
+            .map(|ty| {
+                if is_fp_type(*ty) {
+                    fp_ix += 1;
+                    val_from_fp_reg(*ty, ctx.get_retval_fp_ix(fp_ix - 1))
+                } else {
+                    gp_ix += 1;
+                    val_from_bits(*ty, ctx.get_retval_gp(gp_ix - 1))
+                }
+            })
+            .collect()
+    }
+}
+
+fn is_fp_type(ty: ValueType) -> bool {
+    match ty {
+        ValueType::F32 | ValueType::F64 | ValueType::V128 => true,
+        _ => false,
+    }
+}
+
+/// The default for `Instance::set_max_nested_calls()`.
+const DEFAULT_MAX_NESTED_CALLS: u32 = 16;
+
+/// The guest stack left free between a hostcall calling back into the guest and the frames of that
+/// call, for the host code that prepares the call.
+///
+/// The hostcall runs on the guest stack, but its stack pointer is not available directly, so the
+/// call's frames start this far below a variable in the frame of `Instance::call_nested()`.
+const NESTED_CALL_STACK_GAP: usize = 8 * 1024;
+
+/// The most elements a table may grow to, regardless of its declared maximum.
+const TABLE_ELEMENTS_MAX: u32 = 10_000_000;
+
+/// The range of `len` items starting at `offset`, if it lies within `items`.
+fn checked_range<T>(items: &[T], offset: u32, len: u32) -> Option<Range<usize>> {
+    let start = offset as usize;
+    let end = start.checked_add(len as usize)?;
+    if end <= items.len() {
+        Some(start..end)
+    } else {
+        None
+    }
+}
+
+/// The error for an active segment that does not fit in its memory or table, which traps
+/// instantiation.
+fn segment_trap(trapcode: TrapCode) -> Error {
+    Error::RuntimeFault(FaultDetails {
+        fatal: false,
+        trapcode: Some(trapcode),
+        rip_addr: 0,
+        rip_addr_details: None,
+        backtrace: vec![],
+    })
+}
+
+/// The `funcref` for function `func`: a pointer to its element in `func_refs`.
+fn func_ref_ptr(func_refs: &[TableElement], func: FunctionIndex) -> u64 {
+    &func_refs[func.as_u32() as usize] as *const TableElement as u64
+}
+
+/// The function whose `funcref` is `ptr`, if it is one of `func_refs`.
+fn func_ref_index(func_refs: &[TableElement], ptr: u64) -> Option<FunctionIndex> {
+    let elem_size = mem::size_of::<TableElement>() as u64;
+    let offset = ptr.checked_sub(func_refs.as_ptr() as u64)?;
+    if offset % elem_size == 0 && offset / elem_size < func_refs.len() as u64 {
+        Some(FunctionIndex::from_u32((offset / elem_size) as u32))
+    } else {
+        None
+    }
+}
+
+/// Whether two globals of type `ty` hold the same value, comparing floats bit for bit.
+fn same_global_value(ty: ValueType, a: &GlobalValue, b: &GlobalValue) -> bool {
+    unsafe {
+        match ty {
+            ValueType::I32 => a.i_32 == b.i_32,
+            ValueType::I64 | ValueType::FuncRef | ValueType::ExternRef => a.i_64 == b.i_64,
+            ValueType::F32 => a.f_32.to_bits() == b.f_32.to_bits(),
+            ValueType::F64 => a.f_64.to_bits() == b.f_64.to_bits(),
+            ValueType::V128 => a.v_128 == b.v_128,
+        }
+    }
 }
 
 // Private API
 impl Instance {
-    fn new(alloc: Alloc, module: Arc<dyn Module>, embed_ctx: CtxMap) -> Self {
-        let globals_ptr = alloc.slot().globals as *mut i64;
+    fn new(
+        alloc: Alloc,
+        module: Arc<dyn Module>,
+        embed_ctx: CtxMap,
+        global_imports: GlobalImports,
+    ) -> Self {
+        let globals_ptr = alloc.slot().globals as *mut GlobalValue;
 
         #[cfg(feature = "concurrent_testpoints")]
         let lock_testpoints = Arc::new(LockTestpoints::new());
@@ -873,6 +1698,12 @@ impl Instance {
             ctx: Context::new(),
             state: State::Ready,
             kill_state,
+            epoch: EpochHandle::new(),
+            stats: StatsRecorder::new(),
+            timeout: None,
+            nested_calls: 0,
+            max_nested_calls: DEFAULT_MAX_NESTED_CALLS,
+            deadline: None,
             #[cfg(feature = "concurrent_testpoints")]
             lock_testpoints,
             alloc,
@@ -883,14 +1714,27 @@ impl Instance {
             ensure_sigstack_installed: true,
             entrypoint: None,
             resumed_val: None,
+            global_imports,
+            dropped_data: vec![],
+            dropped_elements: vec![],
+            results: vec![],
+            return_area: vec![],
+            tables: vec![],
+            table_refs: vec![],
+            host_funcs: vec![],
+            import_funcs: vec![],
             _padding: (),
         };
         inst.set_globals_ptr(globals_ptr);
+        inst.sync_heap_len();
+        inst.sync_table_refs();
         inst.set_instruction_count(0);
+        inst.set_fuel(u64::MAX);
+        inst.set_epoch_handle(inst.epoch_handle());
 
         assert_eq!(mem::size_of::<Instance>(), HOST_PAGE_SIZE_EXPECTED);
         let unpadded_size = offset_of!(Instance, _padding);
-        assert!(unpadded_size <= HOST_PAGE_SIZE_EXPECTED - mem::size_of::<*mut i64>());
+        assert!(unpadded_size <= HOST_PAGE_SIZE_EXPECTED - mem::size_of::<InstanceRuntimeData>());
         inst
     }
 
@@ -922,17 +1766,274 @@ impl Instance {
 
     #[allow(dead_code)]
     #[inline]
-    fn get_globals_ptr(&self) -> *mut i64 {
+    fn get_globals_ptr(&self) -> *mut GlobalValue {
         self.get_instance_implicits().globals_ptr
     }
 
     #[inline]
-    fn set_globals_ptr(&mut self, globals_ptr: *mut i64) {
+    fn set_globals_ptr(&mut self, globals_ptr: *mut GlobalValue) {
         self.get_instance_implicits_mut().globals_ptr = globals_ptr
     }
 
+    /// Gather the values returned by the entrypoint, typed according to its signature.
+    fn collect_results(&self) -> Vec<Val> {
+        let entrypoint = self
+            .entrypoint
+            .expect("we always have an entrypoint by now");
+        let results = &self.module.get_signature(entrypoint.id).results;
+        results_from(results, &self.ctx, &self.return_area)
+    }
+
+    /// The first value returned by the entrypoint, once its results have been gathered.
+    fn first_result(&self) -> UntypedRetVal {
+        let entrypoint = self
+            .entrypoint
+            .expect("we always have an entrypoint by now");
+        if uses_return_area(&self.module.get_signature(entrypoint.id).results) {
+            // none of the results are in registers
+            UntypedRetVal::from(self.results[0])
+        } else {
+            self.ctx.get_untyped_retval()
+        }
+    }
+
+    /// The index and specification of the global exported as `name`.
+    fn exported_global(&self, name: &str) -> Result<(usize, &GlobalSpec<'_>), Error> {
+        self.module
+            .global_specs()
+            .iter()
+            .enumerate()
+            .find(|(_, spec)| spec.export_names().iter().any(|&export| export == name))
+            .ok_or_else(|| Error::SymbolNotFound(name.to_owned()))
+    }
+
+    /// Overwrite a freshly created instance with the contents of a snapshot of an instance of the
+    /// same module.
+    pub(crate) fn restore_snapshot(&mut self, snapshot: &InstanceSnapshot) -> Result<(), Error> {
+        snapshot::restore(self, snapshot)
+    }
+
+    /// Update the `heap_len` implicit, which bounds-checked guest code compares heap accesses
+    /// against, to the current size of the heap.
+    fn sync_heap_len(&mut self) {
+        let heap_len = self.alloc.heap_len() as u64;
+        self.get_instance_implicits_mut().heap_len = heap_len;
+    }
+
+    /// Point the `tables_ptr` implicit at the current location and length of each table.
+    ///
+    /// The table list is updated in place, and only resized when the number of tables changes,
+    /// which happens only when the instance is created. Guest code loads `tables_ptr` once per
+    /// function, so the list must not move while a guest function may be running, such as across
+    /// a `table.grow`.
+    fn sync_table_refs(&mut self) {
+        if self.table_refs.len() != self.tables.len() {
+            self.table_refs.resize_with(self.tables.len(), || TableRef {
+                elements: ptr::null_mut(),
+                len: 0,
+            });
+        }
+        for (table_ref, elements) in self.table_refs.iter_mut().zip(self.tables.iter_mut()) {
+            table_ref.elements = elements.as_mut_ptr();
+            table_ref.len = elements.len() as u64;
+        }
+        let tables_ptr = self.table_refs.as_ptr();
+        self.get_instance_implicits_mut().tables_ptr = tables_ptr;
+    }
+
+    /// Look up the function at `func_idx` in the table `table_idx`, as the table currently is.
+    ///
+    /// Unlike `Module::get_func_from_idx()`, this reflects any changes the guest has made to the
+    /// table since the instance was created.
+    pub(crate) fn get_func_from_idx(
+        &self,
+        table_idx: u32,
+        func_idx: u32,
+    ) -> Result<FunctionHandle, Error> {
+        let not_found = || Error::FuncNotFound(table_idx, func_idx);
+        let is_funcref = self
+            .module
+            .table_specs()
+            .get(table_idx as usize)
+            .map_or(false, |table| table.elem_ty == ValueType::FuncRef);
+        if !is_funcref {
+            return Err(not_found());
+        }
+        let element = *self.tables[table_idx as usize]
+            .get(func_idx as usize)
+            .ok_or_else(not_found)?;
+        // a `funcref` is a pointer to the function's element in `func_refs`, or null, which the
+        // subtraction rejects
+        let func_refs = self.module.func_refs();
+        let offset = element
+            .checked_sub(func_refs.as_ptr() as u64)
+            .ok_or_else(not_found)?;
+        let index = offset / mem::size_of::<TableElement>() as u64;
+        if index >= func_refs.len() as u64 {
+            return Err(not_found());
+        }
+        let id = FunctionIndex::from_u32(index as u32);
+        Ok(FunctionHandle {
+            ptr: self.module.function_manifest()[index as usize].ptr(),
+            id,
+            is_start_func: false,
+        })
+    }
+
+    /// Bind the module's imported functions to the host functions provided by `linker`, and point
+    /// the `import_funcs_ptr` implicit at them.
+    ///
+    /// Modules compiled without linked imports have their imports resolved when they are loaded,
+    /// so nothing is bound for them.
+    pub(crate) fn bind_imports(&mut self, linker: Option<&Linker>) -> Result<(), Error> {
+        let host_funcs = match linker {
+            Some(linker) => linker.resolve(self.module.as_ref())?,
+            None => Linker::new().resolve(self.module.as_ref())?,
+        };
+        self.import_funcs = host_funcs.iter().map(HostFunc::import_func_ref).collect();
+        self.host_funcs = host_funcs;
+        let import_funcs_ptr = self.import_funcs.as_ptr();
+        self.get_instance_implicits_mut().import_funcs_ptr = import_funcs_ptr;
+        Ok(())
+    }
+
+    /// The data pointer of the imported function the guest most recently called.
+    ///
+    /// Only meaningful while a host function bound by a `Linker` is running, before it calls back
+    /// into the guest.
+    pub(crate) fn import_data(&self) -> *const c_void {
+        self.get_instance_implicits().import_data
+    }
+
+    /// The module's tables as they are before any global-based element segments are applied.
+    fn module_tables(&self) -> Vec<Vec<u64>> {
+        let func_refs = self.module.func_refs();
+        self.module
+            .table_specs()
+            .iter()
+            .map(|spec| {
+                spec.elements
+                    .iter()
+                    .map(|element| element.map_or(0, |func| func_ref_ptr(func_refs, func)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Check that the tables, and which passive segments have been dropped, are as they are when
+    /// the instance is reset, since an `InitialState` can't capture changes to them.
+    fn check_initialized_tables_and_segments(&self) -> Result<(), Error> {
+        let module = self.module.as_ref();
+        let func_refs = module.func_refs();
+        let mut tables = self.module_tables();
+        let mut dropped_data: Vec<bool> =
+            module.passive_data().iter().map(Option::is_none).collect();
+        let mut dropped_elements: Vec<bool> = module
+            .passive_elements()
+            .iter()
+            .map(Option::is_none)
+            .collect();
+        for segment in module.global_base_data() {
+            dropped_data[segment.segment as usize] = true;
+        }
+        for segment in module.global_base_elements() {
+            dropped_elements[segment.segment as usize] = true;
+            let funcs = module.passive_elements()[segment.segment as usize]
+                .as_deref()
+                .unwrap_or(&[]);
+            // the segment fit when the instance was reset, and its base can only be an imported
+            // global, which the guest can't change
+            if let Some(dst) = self.global_base_offset(segment) {
+                let elements = tables[segment.target as usize]
+                    .iter_mut()
+                    .skip(dst as usize);
+                for (element, func) in elements.zip(funcs) {
+                    *element = func_ref_ptr(func_refs, *func);
+                }
+            }
+        }
+
+        if self.tables != tables {
+            return Err(Error::Unsupported(
+                "initial states of instances whose initialization changed a table".to_owned(),
+            ));
+        }
+        if self.dropped_data != dropped_data || self.dropped_elements != dropped_elements {
+            return Err(Error::Unsupported(
+                "initial states of instances whose initialization dropped a passive segment"
+                    .to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Copy the module's global-based data and element segments into place, now that the globals
+    /// they are based on have their values, and then drop them as active segments are.
+    ///
+    /// As in WebAssembly, a segment that does not fit traps, and the instance cannot be used.
+    fn apply_global_base_segments(&mut self) -> Result<(), Error> {
+        let module = self.module.clone();
+        for segment in module.global_base_data() {
+            let len = module.passive_data()[segment.segment as usize].map_or(0, <[u8]>::len);
+            let applied = match self.global_base_offset(segment) {
+                Some(dst) => self.memory_init(segment.segment, dst, 0, len as u32),
+                None => false,
+            };
+            if !applied {
+                return Err(segment_trap(TrapCode::HeapOutOfBounds));
+            }
+            self.data_drop(segment.segment);
+        }
+        for segment in module.global_base_elements() {
+            let len = module.passive_elements()[segment.segment as usize]
+                .as_ref()
+                .map_or(0, Vec::len);
+            let applied = match self.global_base_offset(segment) {
+                Some(dst) => self.table_init(segment.target, segment.segment, dst, 0, len as u32),
+                None => false,
+            };
+            if !applied {
+                return Err(segment_trap(TrapCode::TableOutOfBounds));
+            }
+            self.elem_drop(segment.segment);
+        }
+        Ok(())
+    }
+
+    /// The offset at which a global-based segment starts, or `None` if it overflows.
+    fn global_base_offset(&self, segment: &GlobalBaseSegment) -> Option<u32> {
+        match segment.global {
+            Some(global) => {
+                let base = unsafe { self.alloc.globals()[global as usize].i_32 } as u32;
+                base.checked_add(segment.offset)
+            }
+            None => Some(segment.offset),
+        }
+    }
+
     /// Run a function in guest context at the given entrypoint.
     fn run_func(&mut self, func: FunctionHandle, args: &[Val]) -> Result<RunResult, Error> {
+        self.check_runnable(func)?;
+        typecheck_args(self.module.get_signature(func.id), args)?;
+        self.enter_func(func, |vals| vals.extend_from_slice(args))
+    }
+
+    /// Run a function in guest context at the given entrypoint, with arguments whose types are
+    /// already known to match its signature.
+    ///
+    /// This is for [`TypedFunc`](struct.TypedFunc.html), whose types are checked when it is
+    /// created, so the arguments are neither checked again nor collected into a `Vec` first.
+    pub(crate) fn run_func_unchecked<Params: WasmParams>(
+        &mut self,
+        func: FunctionHandle,
+        args: Params,
+    ) -> Result<RunResult, Error> {
+        self.check_runnable(func)?;
+        self.enter_func(func, |vals| args.push_vals(vals))
+    }
+
+    /// Check that the instance is in a state where `func` can be run.
+    fn check_runnable(&self, func: FunctionHandle) -> Result<(), Error> {
         let needs_start = self.state.is_not_started() && !func.is_start_func;
         if needs_start {
             return Err(Error::InstanceNeedsStart);
@@ -951,30 +2052,32 @@ impl Instance {
                 "entrypoint function cannot be null; this is probably a malformed module",
             ));
         }
+        Ok(())
+    }
 
+    /// Run `func`, whose arguments `push_args` adds after the ones the runtime passes.
+    fn enter_func(
+        &mut self,
+        func: FunctionHandle,
+        push_args: impl FnOnce(&mut Vec<Val>),
+    ) -> Result<RunResult, Error> {
         let sig = self.module.get_signature(func.id);
+        self.entrypoint = Some(func);
+        self.results.clear();
 
-        // in typechecking these values, we can only really check that arguments are correct.
-        // in the future we might want to make return value use more type safe as well.
-
-        if sig.params.len() != args.len() {
-            return Err(Error::InvalidArgument(
-                "entrypoint function signature mismatch (number of arguments is incorrect)",
-            ));
-        }
-
-        for (param_ty, arg) in sig.params.iter().zip(args.iter()) {
-            if param_ty != &arg.value_type() {
-                return Err(Error::InvalidArgument(
-                    "entrypoint function signature mismatch",
+        let mut args_with_vmctx = vec![];
+        if uses_return_area(&sig.results) {
+            if sig.results.contains(&ValueType::V128) {
+                return Err(Error::Unsupported(
+                    "v128 results returned through the return area".to_owned(),
                 ));
             }
+            // the return area pointer is passed ahead of the vmctx
+            self.return_area = vec![0; sig.results.len()];
+            args_with_vmctx.push(Val::CPtr(self.return_area.as_ptr() as *const c_void));
         }
-
-        self.entrypoint = Some(func);
-
-        let mut args_with_vmctx = vec![Val::from(self.alloc.slot().heap)];
-        args_with_vmctx.extend_from_slice(args);
+        args_with_vmctx.push(Val::from(self.alloc.slot().heap));
+        push_args(&mut args_with_vmctx);
 
         let self_ptr = self as *mut _;
         Context::init_with_callback(
@@ -990,6 +2093,104 @@ impl Instance {
         self.swap_and_return()
     }
 
+    /// Call a guest function from a hostcall, with its frames on the guest stack below the
+    /// hostcall's.
+    ///
+    /// If the function returns, so does this, and the hostcall continues. If it faults, or the
+    /// instance is terminated while it runs, control goes straight back to the host context,
+    /// abandoning the hostcall, and the outer run reports the fault or termination.
+    pub(crate) fn call_nested(
+        &mut self,
+        func: FunctionHandle,
+        args: &[Val],
+    ) -> Result<Vec<Val>, Error> {
+        if !self.state.is_running() {
+            return Err(Error::InvalidArgument(
+                "guest functions can only be called from a hostcall of a running instance",
+            ));
+        }
+        if self.nested_calls >= self.max_nested_calls {
+            return Err(Error::LimitsExceeded(format!(
+                "hostcalls may only call back into the guest {} deep",
+                self.max_nested_calls
+            )));
+        }
+        if func.ptr.as_usize() == 0 {
+            return Err(Error::InvalidArgument(
+                "entrypoint function cannot be null; this is probably a malformed module",
+            ));
+        }
+
+        let sig = self.module.get_signature(func.id).clone();
+        typecheck_args(&sig, args)?;
+
+        let mut return_area = vec![];
+        let mut args_with_vmctx = vec![];
+        if uses_return_area(&sig.results) {
+            if sig.results.contains(&ValueType::V128) {
+                return Err(Error::Unsupported(
+                    "v128 results returned through the return area".to_owned(),
+                ));
+            }
+            return_area = vec![0u64; sig.results.len()];
+            args_with_vmctx.push(Val::CPtr(return_area.as_ptr() as *const c_void));
+        }
+        args_with_vmctx.push(Val::from(self.alloc.slot().heap));
+        args_with_vmctx.extend_from_slice(args);
+
+        // Everything on the guest stack from here up belongs to the hostcall, so the call gets the
+        // rest of the stack, less the room needed to set it up.
+        let frame_addr = &args_with_vmctx as *const Vec<Val> as usize;
+        let stack_bottom = self.alloc.slot().stack as usize;
+        let stack_top = frame_addr.saturating_sub(NESTED_CALL_STACK_GAP) & !15;
+        if stack_top <= stack_bottom + NESTED_CALL_STACK_GAP {
+            return Err(Error::LimitsExceeded(
+                "not enough guest stack left to call back into the guest".to_owned(),
+            ));
+        }
+        let stack = unsafe {
+            slice::from_raw_parts_mut(
+                stack_bottom as *mut u64,
+                (stack_top - stack_bottom) / mem::size_of::<u64>(),
+            )
+        };
+
+        let mut hostcall_ctx = Context::new();
+        let mut guest_ctx = Context::new();
+        Context::init(stack, &mut guest_ctx, func.ptr.as_usize(), &args_with_vmctx)?;
+
+        // Leave the hostcall's execution domain as if it were returning to the guest, so that the
+        // call can be terminated like any other guest code.
+        if let Some(details) = self.kill_state.end_hostcall() {
+            // the instance will be terminated once the hostcall returns
+            return Err(Error::RuntimeTerminated(details));
+        }
+        self.nested_calls += 1;
+        self.stats.end_hostcall();
+
+        // The guest context's backstop swaps back to `hostcall_ctx` when the function returns.
+        // Yielding swaps from `self.ctx` to the host context as usual, so the call can be suspended
+        // and resumed along with the rest of the instance.
+        unsafe { Context::swap(&mut hostcall_ctx, &mut guest_ctx) };
+
+        // and enter the hostcall's domain again as if the guest had just called it
+        self.stats.return_to_hostcall();
+        self.kill_state.begin_hostcall();
+        self.nested_calls -= 1;
+
+        Ok(results_from(&sig.results, &guest_ctx, &return_area))
+    }
+
+    /// The time at which the run about to start should be terminated: the earlier of the deadline
+    /// given to `run_with_deadline()` and the end of the instance's timeout.
+    fn next_deadline(&self) -> Option<Instant> {
+        let timeout = self.timeout.map(|timeout| Instant::now() + timeout);
+        match (self.deadline, timeout) {
+            (Some(deadline), Some(timeout)) => Some(deadline.min(timeout)),
+            (deadline, timeout) => deadline.or(timeout),
+        }
+    }
+
     /// Prepare the guest so that it will update its execution domain upon entry.
     ///
     /// This mutates the context's registers so that an activation function that will be run after
@@ -1040,8 +2241,17 @@ impl Instance {
                 || (self.state.is_faulted() && !self.state.is_fatal())
                 || self.state.is_yielded()
         );
+        let resuming = self.state.is_yielded();
         self.state = State::Running;
 
+        // Dropping the timer once the guest has switched back cancels the deadline, though it may
+        // already have fired in the meantime; that is dealt with by the kill state just like a
+        // remote termination that arrives too late.
+        let timer = self
+            .next_deadline()
+            .map(|deadline| deadline::schedule(deadline, self.kill_switch()));
+
+        self.stats.start_running(resuming);
         let res = self.with_current_instance(|i| {
             i.with_signals_on(|i| {
                 HOST_CTX.with(|host_ctx| {
@@ -1053,6 +2263,8 @@ impl Instance {
                 })
             })
         });
+        self.stats.stop_running();
+        mem::drop(timer);
 
         #[cfg(feature = "concurrent_testpoints")]
         self.lock_testpoints
@@ -1102,11 +2314,15 @@ impl Instance {
             {
                 self.kill_state = Arc::new(KillState::default());
             }
+            self.deadline = None;
+            // a fault or termination abandons any calls from hostcalls back into the guest
+            self.nested_calls = 0;
         }
 
         match st {
             State::Running => {
-                let retval = self.ctx.get_untyped_retval();
+                self.results = self.collect_results();
+                let retval = self.first_result();
                 self.state = State::Ready;
                 Ok(RunResult::Returned(retval))
             }
@@ -1115,6 +2331,7 @@ impl Instance {
                 Err(Error::RuntimeTerminated(details))
             }
             State::Yielding { val, expecting } => {
+                self.stats.record_yield();
                 self.state = State::Yielded { expecting };
                 Ok(RunResult::Yielded(val))
             }
@@ -1122,6 +2339,7 @@ impl Instance {
                 mut details,
                 siginfo,
                 context,
+                frame_pointer,
             } => {
                 // Sandbox is no longer runnable. It's unsafe to determine all error details in the signal
                 // handler, so we fill in extra details here.
@@ -1131,12 +2349,23 @@ impl Instance {
                 details.rip_addr_details = self
                     .module
                     .addr_details(details.rip_addr as *const c_void)?;
+                // safety: the guest stack is left as it was at the time of the fault until the
+                // instance is reset or run again
+                details.backtrace = unsafe {
+                    backtrace::walk(
+                        self.module.as_ref(),
+                        &self.alloc,
+                        details.rip_addr as u64,
+                        frame_pointer as u64,
+                    )
+                };
 
                 // fill the state back in with the updated details in case fatal handlers need it
                 self.state = State::Faulted {
                     details: details.clone(),
                     siginfo,
                     context,
+                    frame_pointer,
                 };
 
                 if details.fatal {
@@ -1211,6 +2440,23 @@ pub struct FaultDetails {
     pub rip_addr: uintptr_t,
     /// Extra information about the instruction pointer's location, if available.
     pub rip_addr_details: Option<module::AddrDetails>,
+    /// The WebAssembly call stack at the time of the fault, innermost frame first.
+    ///
+    /// Empty if the fault did not occur in module code.
+    pub backtrace: Vec<WasmFrame>,
+}
+
+impl FaultDetails {
+    /// The index of the function containing the faulting instruction, and the offset in the
+    /// WebAssembly binary of the instruction it was translated from.
+    ///
+    /// This is `None` if the fault did not occur in module code, or if the module has no address
+    /// map for that code.
+    pub fn wasm_location(&self) -> Option<(FunctionIndex, u32)> {
+        self.backtrace
+            .first()
+            .and_then(|frame| Some((frame.func_index, frame.wasm_offset?)))
+    }
 }
 
 impl std::fmt::Display for FaultDetails {
@@ -1235,13 +2481,21 @@ impl std::fmt::Display for FaultDetails {
                 write!(f, " (symbol {}:{})", fname, sname)?;
             }
             if addr_details.in_module_code {
-                write!(f, " (inside module code)")
+                write!(f, " (inside module code)")?;
             } else {
-                write!(f, " (not inside module code)")
+                write!(f, " (not inside module code)")?;
             }
         } else {
-            write!(f, " (unknown whether in module)")
+            write!(f, " (unknown whether in module)")?;
         }
+
+        if !self.backtrace.is_empty() {
+            write!(f, "\nwasm backtrace:")?;
+            for (i, frame) in self.backtrace.iter().enumerate() {
+                write!(f, "\n  {}: {}", i, frame)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -1265,6 +2519,12 @@ pub enum TerminationDetails {
     Provided(Box<dyn Any + 'static>),
     /// The instance was terminated by its `KillSwitch`.
     Remote,
+    /// The instance ran out of fuel and was resumed without being given more.
+    OutOfFuel,
+    /// The instance reached the deadline set by
+    /// [`Instance::run_with_deadline()`](struct.Instance.html#method.run_with_deadline) or
+    /// [`Instance::set_timeout()`](struct.Instance.html#method.set_timeout).
+    Timeout,
 }
 
 impl TerminationDetails {
@@ -1299,6 +2559,8 @@ impl PartialEq for TerminationDetails {
             (Signal, Signal) => true,
             (BorrowError(msg1), BorrowError(msg2)) => msg1 == msg2,
             (CtxNotFound, CtxNotFound) => true,
+            (OutOfFuel, OutOfFuel) => true,
+            (Timeout, Timeout) => true,
             // can't compare `Any`
             _ => false,
         }
@@ -1315,6 +2577,8 @@ impl std::fmt::Debug for TerminationDetails {
             TerminationDetails::YieldTypeMismatch => write!(f, "YieldTypeMismatch"),
             TerminationDetails::Provided(_) => write!(f, "Provided(Any)"),
             TerminationDetails::Remote => write!(f, "Remote"),
+            TerminationDetails::OutOfFuel => write!(f, "OutOfFuel"),
+            TerminationDetails::Timeout => write!(f, "Timeout"),
         }
     }
 }
@@ -1369,6 +2633,13 @@ impl YieldedVal {
     }
 }
 
+/// The value yielded by an instance that has used up the fuel given to it by
+/// [`Instance::set_fuel()`](struct.Instance.html#method.set_fuel).
+///
+/// Resuming the instance without adding fuel terminates it with `TerminationDetails::OutOfFuel`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfFuel;
+
 /// A marker value to indicate a yield or resume with no value.
 ///
 /// This exists to unify the implementations of the various operators, and should only ever be
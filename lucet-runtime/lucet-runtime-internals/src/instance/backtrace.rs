@@ -0,0 +1,112 @@
+//! Reconstructing the WebAssembly call stack of a faulted guest.
+//!
+//! Code generated by `lucetc` maintains a frame pointer chain: every guest function pushes the
+//! caller's `rbp` in its prologue and points `rbp` at the saved value. Starting from the frame
+//! pointer captured by the signal handler, we follow that chain up the guest stack until we reach
+//! a return address outside of the module, which is the host entry frame.
+
+use crate::alloc::Alloc;
+use crate::module::{FunctionIndex, Module};
+use std::fmt;
+
+/// A frame of a guest's WebAssembly call stack.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WasmFrame {
+    /// The index of the function executing in this frame.
+    pub func_index: FunctionIndex,
+    /// The function's export name if it has one, otherwise its name from the module's name section
+    /// or the name `lucetc` gave it.
+    pub func_name: Option<String>,
+    /// The offset of this frame's instruction from the start of the function's native code.
+    ///
+    /// For the innermost frame this is the faulting instruction; for the others it is the call
+    /// instruction that has not returned yet.
+    pub offset: u32,
+    /// The offset in the WebAssembly binary of the instruction this frame's instruction was
+    /// translated from, if the module has an address map covering it.
+    pub wasm_offset: Option<u32>,
+}
+
+impl fmt::Display for WasmFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "func[{}] {}+{:#x}",
+            self.func_index.as_u32(),
+            self.func_name.as_deref().unwrap_or("<unknown>"),
+            self.offset
+        )?;
+        if let Some(wasm_offset) = self.wasm_offset {
+            write!(f, " (wasm offset {:#x})", wasm_offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the function containing `addr`, if it is in the module's code.
+fn frame_at(module: &dyn Module, addr: u64) -> Option<WasmFrame> {
+    module
+        .function_manifest()
+        .iter()
+        .enumerate()
+        .find_map(|(fn_id, fn_spec)| {
+            fn_spec.relative_addr(addr).map(|offset| {
+                let func_index = FunctionIndex::from_u32(fn_id as u32);
+                WasmFrame {
+                    func_index,
+                    func_name: module.get_function_name(func_index).map(str::to_owned),
+                    offset,
+                    wasm_offset: fn_spec.addr_map().and_then(|map| map.lookup_addr(offset)),
+                }
+            })
+        })
+}
+
+/// Walk the guest stack of `alloc` from a fault at `rip` with frame pointer `fp`.
+///
+/// The frames are ordered innermost first. The walk stops at the first frame pointer that does not
+/// point into the guest stack or return address that is not in module code, so a corrupted guest
+/// stack yields a truncated backtrace rather than reading arbitrary memory. If the fault happened
+/// in a function prologue before the frame pointer was set up, that function's caller is omitted.
+///
+/// # Safety
+///
+/// The guest stack of `alloc` must be the one that was active when the fault occurred, and must not
+/// have been modified since.
+pub(crate) unsafe fn walk(module: &dyn Module, alloc: &Alloc, rip: u64, fp: u64) -> Vec<WasmFrame> {
+    let mut frames = vec![];
+
+    match frame_at(module, rip) {
+        Some(frame) => frames.push(frame),
+        // not a guest fault, so `fp` is not necessarily part of a guest frame chain
+        None => return frames,
+    }
+
+    let stack_bottom = alloc.slot().stack as u64;
+    let stack_top = alloc.slot().stack_top() as u64;
+
+    let mut fp = fp;
+    // each frame is the saved frame pointer followed by the return address
+    while fp >= stack_bottom && fp + 16 <= stack_top && fp % 8 == 0 {
+        let next_fp = *(fp as *const u64);
+        let return_addr = *((fp + 8) as *const u64);
+
+        // look up the call instruction rather than the return address, which may be the start of
+        // the following function if the call was in tail position
+        match return_addr
+            .checked_sub(1)
+            .and_then(|addr| frame_at(module, addr))
+        {
+            Some(frame) => frames.push(frame),
+            None => break,
+        }
+
+        // frames further up the stack are at higher addresses; anything else is a corrupted chain
+        if next_fp <= fp {
+            break;
+        }
+        fp = next_fp;
+    }
+
+    frames
+}
@@ -0,0 +1,134 @@
+//! A timer thread, shared by every instance in the process, that terminates instances when they
+//! reach their deadlines.
+//!
+//! Instances register a deadline each time they start running, and cancel it when they switch back
+//! to the host. The timer thread keeps the outstanding deadlines in a heap, sleeps until the
+//! earliest one, and fires the instance's `KillSwitch` so that it ends with
+//! `TerminationDetails::Timeout`.
+
+use crate::instance::execution::KillSwitch;
+use lazy_static::lazy_static;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::mem;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+
+lazy_static! {
+    static ref SCHEDULER: DeadlineScheduler = DeadlineScheduler::new();
+}
+
+/// Once the heap holds this many more entries than there are outstanding deadlines, entries for
+/// cancelled deadlines are cleared out of it rather than waiting for them to expire.
+const COMPACT_SLACK: usize = 64;
+
+struct DeadlineScheduler {
+    state: Mutex<SchedulerState>,
+    wakeup: Condvar,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    next_id: u64,
+    /// Deadlines ordered earliest first, including those that have since been cancelled.
+    deadlines: BinaryHeap<Reverse<(Instant, u64)>>,
+    /// The kill switches of the outstanding deadlines.
+    outstanding: HashMap<u64, KillSwitch>,
+    thread_started: bool,
+}
+
+/// A deadline registered with the timer thread; dropping it cancels the deadline.
+pub(crate) struct DeadlineTimer {
+    id: u64,
+}
+
+impl Drop for DeadlineTimer {
+    fn drop(&mut self) {
+        SCHEDULER.cancel(self.id);
+    }
+}
+
+/// Terminate the instance that `kill_switch` belongs to at `deadline`, unless the returned timer is
+/// dropped first.
+pub(crate) fn schedule(deadline: Instant, kill_switch: KillSwitch) -> DeadlineTimer {
+    DeadlineTimer {
+        id: SCHEDULER.schedule(deadline, kill_switch),
+    }
+}
+
+impl DeadlineScheduler {
+    fn new() -> Self {
+        DeadlineScheduler {
+            state: Mutex::new(SchedulerState::default()),
+            wakeup: Condvar::new(),
+        }
+    }
+
+    fn schedule(&'static self, deadline: Instant, kill_switch: KillSwitch) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        if !state.thread_started {
+            thread::Builder::new()
+                .name("lucet deadline timer".into())
+                .spawn(move || self.run())
+                .expect("error spawning deadline timer thread");
+            state.thread_started = true;
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.outstanding.insert(id, kill_switch);
+        state.deadlines.push(Reverse((deadline, id)));
+
+        // only wake the timer thread if it needs to sleep for less time than it already is
+        if let Some(Reverse((earliest, _))) = state.deadlines.peek() {
+            if *earliest == deadline {
+                self.wakeup.notify_one();
+            }
+        }
+        id
+    }
+
+    fn cancel(&self, id: u64) {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        state.outstanding.remove(&id);
+        if state.deadlines.len() > 2 * state.outstanding.len() + COMPACT_SLACK {
+            let outstanding = &state.outstanding;
+            state.deadlines = mem::replace(&mut state.deadlines, BinaryHeap::new())
+                .into_iter()
+                .filter(|Reverse((_, id))| outstanding.contains_key(id))
+                .collect();
+        }
+    }
+
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            while let Some(&Reverse((deadline, id))) = state.deadlines.peek() {
+                if deadline > now {
+                    break;
+                }
+                state.deadlines.pop();
+                if let Some(kill_switch) = state.outstanding.remove(&id) {
+                    // Terminating waits for the instance to stop running, which may in turn need
+                    // to cancel its own deadline, so don't hold the lock while doing it. The
+                    // instance may have stopped on its own in the meantime, in which case there
+                    // is nothing to do.
+                    mem::drop(state);
+                    kill_switch.timeout().ok();
+                    state = self.state.lock().unwrap();
+                }
+            }
+
+            state = match state.deadlines.peek() {
+                Some(&Reverse((deadline, _))) => {
+                    let wait = deadline.saturating_duration_since(Instant::now());
+                    self.wakeup.wait_timeout(state, wait).unwrap().0
+                }
+                None => self.wakeup.wait(state).unwrap(),
+            };
+        }
+    }
+}
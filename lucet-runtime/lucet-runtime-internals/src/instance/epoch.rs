@@ -0,0 +1,48 @@
+//! Epoch-based interruption, for modules compiled with `--epoch-interruption`.
+//!
+//! Such modules compare an epoch counter against the instance's deadline at every function entry
+//! and loop header. Once the counter reaches the deadline, the guest terminates itself with
+//! `TerminationDetails::Remote`. Unlike a `KillSwitch`, this never sends a signal to the thread
+//! running the guest.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A handle to the epoch counter checked by epoch-interruptible instances.
+///
+/// Handles are cheap to clone and may be used from any thread. Each instance starts with its own
+/// counter, available through [`Instance::epoch_handle()`](../struct.Instance.html#method.epoch_handle);
+/// a counter can be shared by many instances with
+/// [`Instance::set_epoch_handle()`](../struct.Instance.html#method.set_epoch_handle), so that a
+/// single increment interrupts all of them.
+#[derive(Clone, Debug, Default)]
+pub struct EpochHandle {
+    epoch: Arc<AtomicU64>,
+}
+
+impl EpochHandle {
+    /// Create a new epoch counter, starting at 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advance the epoch by one, returning the new epoch.
+    ///
+    /// Instances using this counter stop at their next check once the epoch reaches their
+    /// deadline.
+    pub fn increment(&self) -> u64 {
+        self.epoch.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Get the current epoch.
+    pub fn current(&self) -> u64 {
+        self.epoch.load(Ordering::SeqCst)
+    }
+
+    /// The address guest code reads the epoch from.
+    ///
+    /// `AtomicU64` has the same in-memory representation as `u64`.
+    pub(crate) fn as_ptr(&self) -> *const u64 {
+        self.epoch.as_ref() as *const AtomicU64 as *const u64
+    }
+}
@@ -77,6 +77,11 @@
 //!     been terminated.
 //! * `Hostcall yields`, or `Hostcall resumes`
 //!   - These are specific points in "Hostcall executing" and has no further semantics.
+//! * `Hostcall calls back into the guest`
+//!   - The hostcall leaves its domain as if it were returning, so the called guest function is in
+//!     `Guest` and terminable as usual, and enters it again as if called when the function
+//!     returns. If the instance was terminated during the hostcall, the call is not made, and the
+//!     instance terminates once the hostcall returns.
 //! * `Hostcall returns`
 //!   - termination result: `Ok(KillSuccess::Signalled)`
 //!   - execution_domain: `Guest`
@@ -156,6 +161,10 @@ pub struct KillState {
     /// pending, masked by Lucet's sigaction's signal mask, OR a SIGLARM will be imminent after
     /// handling the signal.
     ignore_alarm: AtomicBool,
+    /// `timed_out` indicates that the instance was terminated because it reached a deadline, rather
+    /// than by a `KillSwitch`. It is set before the instance is signalled or marked as terminated,
+    /// so whichever path ends the instance reports `TerminationDetails::Timeout`.
+    timed_out: AtomicBool,
     #[cfg(feature = "concurrent_testpoints")]
     /// When testing race permutations, `KillState` keeps a reference to the `LockTestpoints` its
     /// associated instance holds.
@@ -231,7 +240,8 @@ pub unsafe extern "C" fn enter_guest_region(instance: *mut Instance) {
             //
             // Note that we manually drop the domain because`Instance::terminate` never returns.
             mem::drop(current_domain);
-            instance.terminate(TerminationDetails::Remote);
+            let details = instance.kill_state.termination_details();
+            instance.terminate(details);
         }
     }
 }
@@ -320,6 +330,7 @@ impl Default for KillState {
             execution_domain: Mutex::new(Domain::Pending),
             thread_id: Mutex::new(None),
             ignore_alarm: AtomicBool::new(false),
+            timed_out: AtomicBool::new(false),
         }
     }
 }
@@ -339,6 +350,7 @@ impl KillState {
             execution_domain: Mutex::new(Domain::Pending),
             thread_id: Mutex::new(None),
             ignore_alarm: AtomicBool::new(false),
+            timed_out: AtomicBool::new(false),
             lock_testpoints,
         }
     }
@@ -412,6 +424,15 @@ impl KillState {
             .check();
     }
 
+    /// Set the execution domain to signify that we are in the middle of a hostcall, without
+    /// checking the current domain.
+    ///
+    /// This is for instances restored from a snapshot taken while they were yielded: the guest
+    /// resumes inside the hostcall that yielded, which calls `end_hostcall()` when it returns.
+    pub(crate) fn restore_hostcall(&self) {
+        *self.execution_domain.lock().unwrap() = Domain::Hostcall;
+    }
+
     /// Set the execution domain to signify that we are finished executing a hostcall.
     ///
     /// If the instance was terminated during the hostcall, then we will return termination details
@@ -441,7 +462,7 @@ impl KillState {
             Domain::Terminated => {
                 // The instance was stopped in the hostcall we were executing.
                 debug_assert!(!self.terminable.load(Ordering::SeqCst));
-                Some(TerminationDetails::Remote)
+                Some(self.termination_details())
             }
             Domain::Cancelled => {
                 panic!("Invalid state: Instance marked as cancelled while exiting a hostcall.");
@@ -459,6 +480,18 @@ impl KillState {
         res
     }
 
+    /// The details a terminated instance should report: `Timeout` if it was stopped by reaching a
+    /// deadline, and `Remote` if it was stopped by a `KillSwitch`.
+    ///
+    /// This is called from the signal handler, so it must remain async-signal-safe.
+    pub(crate) fn termination_details(&self) -> TerminationDetails {
+        if self.timed_out.load(Ordering::SeqCst) {
+            TerminationDetails::Timeout
+        } else {
+            TerminationDetails::Remote
+        }
+    }
+
     pub fn schedule(&self, tid: pthread_t) {
         *self.thread_id.lock().unwrap() = Some(tid);
         self.tid_change_notifier.notify_all();
@@ -569,6 +602,16 @@ impl KillSwitch {
     /// host code will see `State::Faulted` as an instance state, where `KillSwitch::terminate`
     /// would return `Ok(KillSuccess::Signalled)`.
     pub fn terminate(&self) -> KillResult {
+        self.terminate_with(false)
+    }
+
+    /// Stop the instance as `terminate()` does, but have it report `TerminationDetails::Timeout`
+    /// rather than `TerminationDetails::Remote`.
+    pub(crate) fn timeout(&self) -> KillResult {
+        self.terminate_with(true)
+    }
+
+    fn terminate_with(&self, timed_out: bool) -> KillResult {
         // Get the underlying kill state. If this fails, it means the instance exited and was
         // discarded, so we can not terminate.
         let state = self.state.upgrade().ok_or(KillError::Invalid)?;
@@ -597,6 +640,12 @@ impl KillSwitch {
             .kill_switch_after_acquiring_termination
             .check();
 
+        // holding `terminable` means no other termination can be reported, so record why this one
+        // happened before the instance can observe it
+        if timed_out {
+            state.timed_out.store(true, Ordering::SeqCst);
+        }
+
         // we got it! we can signal the instance.
         //
         // Now check what domain the instance is in. We can signal in guest code, but want
@@ -242,6 +242,7 @@ extern "C" fn handle_signal(signum: c_int, siginfo_ptr: *mut siginfo_t, ucontext
                 .expect("current instance exists")
                 .as_mut()
         };
+        inst.stats.record_signal();
 
         if signal == Signal::SIGALRM {
             #[cfg(feature = "concurrent_testpoints")]
@@ -250,7 +251,7 @@ extern "C" fn handle_signal(signum: c_int, siginfo_ptr: *mut siginfo_t, ucontext
                 .check();
             if inst.kill_state.alarm_active() {
                 inst.state = State::Terminating {
-                    details: TerminationDetails::Remote,
+                    details: inst.kill_state.termination_details(),
                 };
                 return true;
             } else {
@@ -315,9 +316,12 @@ extern "C" fn handle_signal(signum: c_int, siginfo_ptr: *mut siginfo_t, ucontext
                             // Details set to `None` here: have to wait until `verify_trap_safety` to
                             // fill in these details, because access may not be signal safe.
                             rip_addr_details: None,
+                            // Likewise, the backtrace is walked from `frame_pointer` later.
+                            backtrace: vec![],
                         },
                         siginfo,
                         context: ctx.into(),
+                        frame_pointer: ctx.get_fp() as usize,
                     };
                 };
 
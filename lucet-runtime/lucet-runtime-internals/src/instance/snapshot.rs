@@ -0,0 +1,544 @@
+//! Saving an instance so that it can be restored later, possibly in another process.
+//!
+//! A ready instance is described entirely by its heap, globals, and tables. A yielded instance is
+//! also partway through a call: its guest stack holds the WebAssembly frames and the frames of the
+//! hostcall that yielded, and its `Context` holds the registers saved when it swapped back to the
+//! host. Those contain raw addresses of the instance's memory, of the module's code, and of the
+//! host program's code, which are all likely to be somewhere else in the restoring process.
+//!
+//! We do not know which stack slots and registers hold pointers, so we treat each of them as though
+//! it might: any value that falls in one of the address ranges recorded in the snapshot is moved to
+//! the same offset in the corresponding range of the restored instance or process. This is
+//! conservative rather than exact, which has some consequences:
+//!
+//! - The restoring process must be running the same build of the host program, with the same
+//!   module loaded.
+//!
+//! - The hostcall that yielded must not hold pointers to host memory that is not part of the
+//!   instance, such as a `Box` it allocated, across the yield.
+//!
+//! - An integer on the stack that happens to fall in one of the ranges is adjusted as though it
+//!   were a pointer. The ranges are at addresses that WebAssembly values rarely take.
+
+use crate::alloc::instance_heap_offset;
+use crate::context::SavedRegs;
+use crate::error::Error;
+use crate::instance::{execution, func_ref_ptr, Instance, State};
+use crate::module::{FunctionHandle, FunctionIndex, GlobalValue, Module, TableElement, ValueType};
+use crate::sysdeps::loaded_objects;
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+use std::collections::hash_map::DefaultHasher;
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Range;
+use std::ptr;
+use std::slice;
+
+/// The granularity at which the heap is saved; pages that are all zeroes are left out.
+const SNAPSHOT_PAGE_SIZE: usize = 4096;
+
+/// A saved copy of a ready or yielded instance.
+///
+/// Snapshots are taken by [`Instance::snapshot()`](struct.Instance.html#method.snapshot), and
+/// restored into a new instance by
+/// [`Region::restore_instance()`](trait.Region.html#method.restore_instance).
+#[derive(Clone, Deserialize, Serialize)]
+pub struct InstanceSnapshot {
+    module: ModuleIdentity,
+    heap_len: u64,
+    /// The index and contents of each page of the heap that is not all zeroes.
+    heap_pages: Vec<(u32, Vec<u8>)>,
+    /// The raw bytes of the module's globals.
+    globals: Vec<u8>,
+    /// The elements of each table. `funcref` elements are stored as one more than the index of
+    /// the function they refer to, or zero if they are null.
+    tables: Vec<Vec<u64>>,
+    dropped_data: Vec<bool>,
+    dropped_elements: Vec<bool>,
+    instruction_count: u64,
+    fuel: u64,
+    epoch_ticks: u64,
+    /// The call in progress, if the instance was yielded.
+    suspended: Option<Suspended>,
+}
+
+impl InstanceSnapshot {
+    /// Serialize the snapshot, for example to store it or send it to another host.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        bincode::serialize(self).map_err(snapshot_error)
+    }
+
+    /// Deserialize a snapshot serialized by [`to_bytes()`](#method.to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        bincode::deserialize(bytes).map_err(snapshot_error)
+    }
+
+    /// Returns `true` if the instance was yielded when the snapshot was taken, and so will be
+    /// yielded once it is restored.
+    pub fn is_yielded(&self) -> bool {
+        self.suspended.is_some()
+    }
+}
+
+/// Enough about a module to tell whether a snapshot was taken of an instance of it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+struct ModuleIdentity {
+    /// The serialized `VersionInfo` of the `lucetc` that compiled the module.
+    version: Vec<u8>,
+    /// The module's signature, which is all zeroes if it was not signed.
+    signature: Vec<u8>,
+    /// A hash of the module's metadata and the size of each of its functions.
+    fingerprint: u64,
+}
+
+impl ModuleIdentity {
+    fn of(module: &dyn Module) -> Result<Self, Error> {
+        let mut version = vec![];
+        module
+            .version()
+            .write_to(&mut version)
+            .map_err(snapshot_error)?;
+        let mut hasher = DefaultHasher::new();
+        module.module_data().serialize()?.hash(&mut hasher);
+        for fn_spec in module.function_manifest() {
+            fn_spec.code_len().hash(&mut hasher);
+        }
+        Ok(ModuleIdentity {
+            version,
+            signature: module.module_data().get_module_signature().to_vec(),
+            fingerprint: hasher.finish(),
+        })
+    }
+}
+
+/// The state of a call that yielded.
+#[derive(Clone, Deserialize, Serialize)]
+struct Suspended {
+    entrypoint: u32,
+    is_start_func: bool,
+    /// A hash of the type of value the guest expects to be resumed with.
+    expecting: u64,
+    regs: SavedRegs,
+    /// The live part of the guest stack, from the saved stack pointer to the top of the stack.
+    stack: Vec<u64>,
+    return_area: Vec<u64>,
+    /// The address ranges of the instance that the stack and registers may point into, in the
+    /// order given by `instance_ranges()`.
+    instance_ranges: Vec<Range<u64>>,
+    /// The executable and shared objects that were loaded into the process.
+    objects: Vec<(String, Range<u64>)>,
+}
+
+/// The type of value a restored instance expects to be resumed with.
+///
+/// This stands in for the `PhantomData` that a yield stores in `State::Yielded`, which only has a
+/// meaning within the process that created it.
+struct RestoredExpecting(u64);
+
+fn type_hash(type_id: TypeId) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    type_id.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn expecting_hash(expecting: &dyn Any) -> u64 {
+    match expecting.downcast_ref::<RestoredExpecting>() {
+        Some(RestoredExpecting(hash)) => *hash,
+        None => type_hash(Any::type_id(expecting)),
+    }
+}
+
+/// Whether a yielded instance that stored `expecting` in its state can be resumed with a value of
+/// type `A`.
+pub(crate) fn expects<A: Any>(expecting: &dyn Any) -> bool {
+    if expecting.is::<PhantomData<A>>() {
+        return true;
+    }
+    match expecting.downcast_ref::<RestoredExpecting>() {
+        Some(RestoredExpecting(hash)) => *hash == type_hash(TypeId::of::<PhantomData<A>>()),
+        None => false,
+    }
+}
+
+fn snapshot_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::SnapshotError(e.to_string())
+}
+
+fn slice_range<T>(items: &[T]) -> Range<u64> {
+    let start = items.as_ptr() as u64;
+    start..start + mem::size_of_val(items) as u64
+}
+
+/// The address ranges of an instance that a suspended call may have pointers into, with the live
+/// part of the guest stack taken to be `stack_len` bytes.
+///
+/// The ranges are in a fixed order, so that the ranges of two instances of the same module
+/// correspond to each other.
+fn instance_ranges(inst: &Instance, stack_len: usize) -> Vec<Range<u64>> {
+    let slot = inst.alloc.slot();
+    let start = slot.start as u64;
+    let heap = slot.heap as u64;
+    let stack_top = slot.stack_top() as u64;
+    let globals = slot.globals as u64;
+
+    let manifest = inst.module.function_manifest();
+    let code_start = manifest.iter().map(|f| f.ptr().as_usize() as u64).min();
+    let code_end = manifest
+        .iter()
+        .map(|f| f.ptr().as_usize() as u64 + u64::from(f.code_len()))
+        .max();
+
+    let mut ranges = vec![
+        start..start + instance_heap_offset() as u64,
+        // only the accessible part of the heap, so that fewer guest values look like pointers
+        heap..heap + inst.alloc.heap_len() as u64,
+        stack_top - stack_len as u64..stack_top,
+        globals..globals + slot.limits.globals_size as u64,
+        // the module's functions are laid out the same way wherever it is loaded, so this also
+        // covers the return address of a call at the very end of a function
+        code_start.unwrap_or(0)..code_end.unwrap_or(0),
+        slice_range(inst.module.func_refs()),
+        slice_range(&inst.return_area),
+        slice_range(&inst.table_refs),
+    ];
+    ranges.extend(inst.tables.iter().map(|elements| slice_range(elements)));
+    ranges
+}
+
+/// A map from the addresses recorded in a snapshot to the corresponding addresses of a restored
+/// instance.
+struct Relocations(Vec<(Range<u64>, u64)>);
+
+impl Relocations {
+    fn new(suspended: &Suspended, inst: &Instance) -> Result<Self, Error> {
+        let stack_len = suspended.stack.len() * mem::size_of::<u64>();
+        let new_ranges = instance_ranges(inst, stack_len);
+        if new_ranges.len() != suspended.instance_ranges.len() {
+            return Err(snapshot_error(
+                "the restored instance does not have the same tables as the snapshot",
+            ));
+        }
+        let mut relocations = vec![];
+        for (old, new) in suspended.instance_ranges.iter().zip(new_ranges) {
+            if new.end - new.start < old.end - old.start {
+                return Err(snapshot_error(
+                    "the restored instance's memory is smaller than the snapshot's",
+                ));
+            }
+            relocations.push((old.clone(), new.start));
+        }
+
+        let objects = loaded_objects().ok_or_else(|| {
+            Error::Unsupported("restoring yielded instances on this platform".to_owned())
+        })?;
+        for (name, old) in &suspended.objects {
+            let new = objects.iter().find(|object| {
+                object.name == *name && (object.range.len() as u64) == old.end - old.start
+            });
+            match new {
+                Some(new) => relocations.push((old.clone(), new.range.start as u64)),
+                // the main executable has no name
+                None if name.is_empty() => {
+                    return Err(snapshot_error(
+                        "the snapshot was taken in a different build of the host program",
+                    ))
+                }
+                // other objects may have been unloaded or loaded from another path, which only
+                // matters if the suspended call points into them
+                None => (),
+            }
+        }
+        Ok(Relocations(relocations))
+    }
+
+    fn apply(&self, val: u64) -> u64 {
+        for (old, new_start) in &self.0 {
+            if old.contains(&val) {
+                return new_start + (val - old.start);
+            }
+        }
+        val
+    }
+}
+
+impl Suspended {
+    fn capture(inst: &Instance, expecting: u64) -> Result<Self, Error> {
+        let entrypoint = inst
+            .entrypoint
+            .ok_or_else(|| lucet_format_err!("yielded instance has no entrypoint"))?;
+        let regs = inst.ctx.saved_regs();
+
+        let slot = inst.alloc.slot();
+        let stack_bottom = slot.stack as u64;
+        let stack_top = slot.stack_top() as u64;
+        let rsp = regs.rsp();
+        lucet_ensure!(
+            rsp >= stack_bottom && rsp <= stack_top && rsp % 8 == 0,
+            "yielded instance's stack pointer is not in its stack"
+        );
+        // safety: the instance is yielded, so everything from its stack pointer up is in use
+        let stack = unsafe {
+            slice::from_raw_parts(rsp as *const u64, ((stack_top - rsp) / 8) as usize).to_vec()
+        };
+
+        let objects = loaded_objects()
+            .ok_or_else(|| {
+                Error::Unsupported("snapshots of yielded instances on this platform".to_owned())
+            })?
+            .into_iter()
+            .map(|object| {
+                let range = object.range.start as u64..object.range.end as u64;
+                (object.name, range)
+            })
+            .collect();
+
+        Ok(Suspended {
+            entrypoint: entrypoint.id.as_u32(),
+            is_start_func: entrypoint.is_start_func,
+            expecting,
+            regs,
+            instance_ranges: instance_ranges(inst, (stack_top - rsp) as usize),
+            stack,
+            return_area: inst.return_area.clone(),
+            objects,
+        })
+    }
+
+    fn restore(&self, inst: &mut Instance) -> Result<(), Error> {
+        let fn_spec = inst
+            .module
+            .function_manifest()
+            .get(self.entrypoint as usize)
+            .ok_or_else(|| snapshot_error("the snapshot's entrypoint is not in the module"))?;
+        inst.entrypoint = Some(FunctionHandle {
+            ptr: fn_spec.ptr(),
+            id: FunctionIndex::from_u32(self.entrypoint),
+            is_start_func: self.is_start_func,
+        });
+        // the return area is written by the guest, so it must be in place before we work out
+        // where it is
+        inst.return_area = self.return_area.clone();
+
+        let stack_len = self.stack.len() * mem::size_of::<u64>();
+        if stack_len > inst.alloc.slot().limits.stack_size {
+            return Err(snapshot_error(
+                "the snapshot's guest stack does not fit in the restored instance's stack",
+            ));
+        }
+        let relocations = Relocations::new(self, inst)?;
+
+        let stack_top = inst.alloc.slot().stack_top() as usize;
+        // safety: we just checked that this is within the stack, which is not in use
+        let stack = unsafe {
+            slice::from_raw_parts_mut((stack_top - stack_len) as *mut u64, self.stack.len())
+        };
+        for (dst, word) in stack.iter_mut().zip(&self.stack) {
+            *dst = relocations.apply(*word);
+        }
+
+        let inst_ptr = inst as *mut Instance;
+        inst.ctx.restore_regs(
+            &self.regs,
+            |reg| relocations.apply(reg),
+            execution::exit_guest_region,
+            inst_ptr,
+        );
+
+        // the guest resumes in the middle of the hostcall that yielded
+        inst.kill_state.restore_hostcall();
+        inst.state = State::Yielded {
+            expecting: Box::new(RestoredExpecting(self.expecting)),
+        };
+        Ok(())
+    }
+}
+
+/// Save the elements of an instance's tables, as stored in the `tables` field of a snapshot.
+fn capture_tables(inst: &Instance) -> Result<Vec<Vec<u64>>, Error> {
+    let func_refs = inst.module.func_refs();
+    let func_refs_range = slice_range(func_refs);
+    let elem_size = mem::size_of::<TableElement>() as u64;
+    inst.module
+        .table_specs()
+        .iter()
+        .zip(&inst.tables)
+        .map(|(spec, elements)| {
+            if spec.elem_ty != ValueType::FuncRef {
+                return Ok(elements.clone());
+            }
+            elements
+                .iter()
+                .map(|element| match *element {
+                    0 => Ok(0),
+                    ptr if func_refs_range.contains(&ptr)
+                        && (ptr - func_refs_range.start) % elem_size == 0 =>
+                    {
+                        Ok((ptr - func_refs_range.start) / elem_size + 1)
+                    }
+                    _ => Err(lucet_format_err!(
+                        "table element is not a function of the module"
+                    )),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Rebuild instance tables from the `tables` field of a snapshot.
+fn restore_tables(module: &dyn Module, tables: &[Vec<u64>]) -> Result<Vec<Vec<u64>>, Error> {
+    let specs = module.table_specs();
+    if tables.len() != specs.len() {
+        return Err(snapshot_error(
+            "the snapshot does not have the same tables as the module",
+        ));
+    }
+    let func_refs = module.func_refs();
+    specs
+        .iter()
+        .zip(tables)
+        .map(|(spec, elements)| {
+            if spec.elem_ty != ValueType::FuncRef {
+                return Ok(elements.clone());
+            }
+            elements
+                .iter()
+                .map(|element| match *element {
+                    0 => Ok(0),
+                    index if index <= func_refs.len() as u64 => Ok(func_ref_ptr(
+                        func_refs,
+                        FunctionIndex::from_u32(index as u32 - 1),
+                    )),
+                    _ => Err(snapshot_error(
+                        "a table element in the snapshot is not a function of the module",
+                    )),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Take a snapshot of a ready or yielded instance.
+pub(crate) fn capture(inst: &Instance) -> Result<InstanceSnapshot, Error> {
+    if inst.nested_calls > 0 {
+        // the hostcall frames between the nested calls hold pointers into the stack
+        return Err(Error::Unsupported(
+            "snapshots of instances yielded during a call from a hostcall back into the guest"
+                .to_owned(),
+        ));
+    }
+    let expecting = match &inst.state {
+        State::Ready => None,
+        State::Yielded { expecting, .. } => Some(expecting_hash(expecting.as_ref())),
+        _ => {
+            return Err(Error::InvalidArgument(
+                "can only snapshot a ready or yielded instance",
+            ))
+        }
+    };
+
+    let heap = inst.heap();
+    let heap_pages = heap
+        .chunks(SNAPSHOT_PAGE_SIZE)
+        .enumerate()
+        .filter(|(_, page)| page.iter().any(|byte| *byte != 0))
+        .map(|(i, page)| (i as u32, page.to_vec()))
+        .collect();
+
+    let globals_len = inst.module.global_specs().len() * mem::size_of::<GlobalValue>();
+    let globals = unsafe {
+        slice::from_raw_parts(inst.alloc.slot().globals as *const u8, globals_len).to_vec()
+    };
+
+    let implicits = inst.get_instance_implicits();
+    let suspended = match expecting {
+        Some(expecting) => Some(Suspended::capture(inst, expecting)?),
+        None => None,
+    };
+
+    Ok(InstanceSnapshot {
+        module: ModuleIdentity::of(inst.module.as_ref())?,
+        heap_len: heap.len() as u64,
+        heap_pages,
+        globals,
+        tables: capture_tables(inst)?,
+        dropped_data: inst.dropped_data.clone(),
+        dropped_elements: inst.dropped_elements.clone(),
+        instruction_count: implicits.instruction_count,
+        fuel: implicits
+            .fuel_limit
+            .saturating_sub(implicits.instruction_count),
+        epoch_ticks: implicits
+            .epoch_deadline
+            .saturating_sub(inst.epoch.current()),
+        suspended,
+    })
+}
+
+/// Overwrite a freshly created instance with the contents of a snapshot.
+pub(crate) fn restore(inst: &mut Instance, snapshot: &InstanceSnapshot) -> Result<(), Error> {
+    if snapshot.module != ModuleIdentity::of(inst.module.as_ref())? {
+        return Err(snapshot_error(
+            "the snapshot was taken of an instance of a different module",
+        ));
+    }
+
+    let heap_len = inst.alloc.heap_len() as u64;
+    if snapshot.heap_len < heap_len {
+        return Err(snapshot_error(
+            "the snapshot's heap is smaller than the module's initial heap",
+        ));
+    }
+    if snapshot.heap_len > heap_len {
+        let additional = u32::try_from(snapshot.heap_len - heap_len).map_err(snapshot_error)?;
+        inst.alloc.expand_heap(additional, inst.module.as_ref())?;
+        inst.sync_heap_len();
+    }
+    let heap = inst.heap_mut();
+    // safety: the heap is `heap.len()` accessible bytes
+    unsafe { ptr::write_bytes(heap.as_mut_ptr(), 0, heap.len()) };
+    for (page, contents) in &snapshot.heap_pages {
+        let start = *page as usize * SNAPSHOT_PAGE_SIZE;
+        heap.get_mut(start..start + contents.len())
+            .ok_or_else(|| snapshot_error("a page of the snapshot's heap is out of bounds"))?
+            .copy_from_slice(contents);
+    }
+
+    let globals_len = inst.module.global_specs().len() * mem::size_of::<GlobalValue>();
+    if snapshot.globals.len() != globals_len {
+        return Err(snapshot_error(
+            "the snapshot does not have the same globals as the module",
+        ));
+    }
+    unsafe {
+        slice::from_raw_parts_mut(inst.alloc.slot().globals as *mut u8, globals_len)
+            .copy_from_slice(&snapshot.globals);
+    }
+
+    inst.tables = restore_tables(inst.module.as_ref(), &snapshot.tables)?;
+    inst.sync_table_refs();
+    if snapshot.dropped_data.len() != inst.dropped_data.len()
+        || snapshot.dropped_elements.len() != inst.dropped_elements.len()
+    {
+        return Err(snapshot_error(
+            "the snapshot does not have the same segments as the module",
+        ));
+    }
+    inst.dropped_data = snapshot.dropped_data.clone();
+    inst.dropped_elements = snapshot.dropped_elements.clone();
+
+    inst.set_instruction_count(snapshot.instruction_count);
+    inst.set_fuel(snapshot.fuel);
+    inst.set_epoch_deadline(snapshot.epoch_ticks);
+    inst.results.clear();
+
+    match &snapshot.suspended {
+        Some(suspended) => suspended.restore(inst),
+        None => {
+            inst.state = State::Ready;
+            Ok(())
+        }
+    }
+}
@@ -32,6 +32,8 @@ pub enum State {
         details: FaultDetails,
         siginfo: libc::siginfo_t,
         context: UContext,
+        /// The guest's frame pointer at the time of the fault, used to walk its stack.
+        frame_pointer: libc::uintptr_t,
     },
 
     /// The instance is in the process of terminating.
@@ -66,7 +68,8 @@ pub enum State {
         /// A phantom value carrying the type of the expected resumption value.
         ///
         /// Concretely, this should only ever be `Box<PhantomData<R>>` where `R` is the type
-        /// the guest expects upon resumption.
+        /// the guest expects upon resumption, or a stand-in for one if the instance was restored
+        /// from a snapshot.
         expecting: Box<dyn Any>,
     },
 
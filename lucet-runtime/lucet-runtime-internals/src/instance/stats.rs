@@ -0,0 +1,211 @@
+//! Runtime statistics for an instance, accumulated across runs and cleared on reset.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counters describing the work an instance has done since it was created or last reset.
+///
+/// Returned by [`Instance::stats()`](../struct.Instance.html#method.stats).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstanceStats {
+    /// Wall-clock time spent running guest code.
+    pub guest_wall_time: Duration,
+    /// CPU time the running thread spent in guest code.
+    ///
+    /// Measuring CPU time costs a system call each time the instance enters or leaves a hostcall,
+    /// so this is always zero unless it is enabled with
+    /// [`Instance::set_cpu_stats()`](../struct.Instance.html#method.set_cpu_stats).
+    pub guest_cpu_time: Duration,
+    /// Wall-clock time spent in hostcalls, including time spent in a hostcall before it yields and
+    /// after the instance is resumed, but not the time the instance spends yielded.
+    pub hostcall_wall_time: Duration,
+    /// CPU time the running thread spent in hostcalls.
+    ///
+    /// Like `guest_cpu_time`, this is always zero unless CPU time measurement is enabled.
+    pub hostcall_cpu_time: Duration,
+    /// The number of hostcalls made by the guest.
+    pub hostcalls: u64,
+    /// The number of times the instance yielded.
+    pub yields: u64,
+    /// The number of times the instance was resumed after yielding.
+    pub resumes: u64,
+    /// The largest size the heap has had, in bytes.
+    pub peak_heap_size: u64,
+    /// The number of attempts to grow the heap, whether by `memory.grow` or by the host, including
+    /// attempts that failed.
+    pub memory_grows: u64,
+    /// The number of page faults in the instance's memory handled by its region.
+    ///
+    /// Only regions that handle page faults themselves, such as
+    /// [`UffdRegion`](../struct.UffdRegion.html), count these; it is always zero otherwise.
+    pub page_faults: u64,
+    /// The number of times the Lucet signal handler ran while the instance was running, including
+    /// for signals the instance's signal handler chose to continue from.
+    pub signals: u64,
+}
+
+/// The instance's live counters, and the clocks for the time segment currently being measured.
+pub(crate) struct StatsRecorder {
+    stats: InstanceStats,
+    /// Incremented by the region's page fault handler, which may run on another thread.
+    page_faults: AtomicU64,
+    /// Incremented by the signal handler, which may interrupt the host while it is updating the
+    /// other counters.
+    signals: AtomicU64,
+    /// The wall-clock and thread CPU time at which the current segment started, while the instance
+    /// is running.
+    segment_start: Option<(Instant, Duration)>,
+    /// Whether the current segment is being spent in a hostcall rather than in guest code.
+    in_hostcall: bool,
+    /// Whether thread CPU time is measured as well as wall-clock time.
+    cpu_time: bool,
+}
+
+impl StatsRecorder {
+    pub(crate) fn new() -> Self {
+        StatsRecorder {
+            stats: InstanceStats::default(),
+            page_faults: AtomicU64::new(0),
+            signals: AtomicU64::new(0),
+            segment_start: None,
+            in_hostcall: false,
+            cpu_time: false,
+        }
+    }
+
+    pub(crate) fn cpu_time(&self) -> bool {
+        self.cpu_time
+    }
+
+    /// Start or stop measuring thread CPU time.
+    pub(crate) fn set_cpu_time(&mut self, enabled: bool) {
+        self.cpu_time = enabled;
+        // a segment in progress carries on from the current CPU time, rather than from one that
+        // was never read
+        let cpu_now = self.cpu_now();
+        if let Some((_, cpu_start)) = &mut self.segment_start {
+            *cpu_start = cpu_now;
+        }
+    }
+
+    /// Clear the counters, starting from a heap of `heap_size` bytes.
+    pub(crate) fn reset(&mut self, heap_size: usize) {
+        self.stats = InstanceStats {
+            peak_heap_size: heap_size as u64,
+            ..InstanceStats::default()
+        };
+        self.page_faults.store(0, Ordering::Relaxed);
+        self.signals.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> InstanceStats {
+        InstanceStats {
+            page_faults: self.page_faults.load(Ordering::Relaxed),
+            signals: self.signals.load(Ordering::Relaxed),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Start measuring time when switching into the guest context.
+    ///
+    /// A resumed instance continues inside the hostcall that yielded, so its time is counted
+    /// towards hostcalls until that hostcall returns.
+    pub(crate) fn start_running(&mut self, resuming: bool) {
+        self.in_hostcall = resuming;
+        self.segment_start = Some(self.now());
+    }
+
+    /// Stop measuring time after switching back to the host context.
+    pub(crate) fn stop_running(&mut self) {
+        self.end_segment();
+        self.segment_start = None;
+    }
+
+    pub(crate) fn begin_hostcall(&mut self) {
+        self.stats.hostcalls += 1;
+        self.end_segment();
+        self.in_hostcall = true;
+    }
+
+    pub(crate) fn end_hostcall(&mut self) {
+        self.end_segment();
+        self.in_hostcall = false;
+    }
+
+    /// Go back to counting time towards the hostcall after it calls back into the guest, without
+    /// counting another hostcall.
+    pub(crate) fn return_to_hostcall(&mut self) {
+        self.end_segment();
+        self.in_hostcall = true;
+    }
+
+    pub(crate) fn record_yield(&mut self) {
+        self.stats.yields += 1;
+    }
+
+    pub(crate) fn record_resume(&mut self) {
+        self.stats.resumes += 1;
+    }
+
+    pub(crate) fn record_memory_grow(&mut self, heap_size: usize) {
+        self.stats.memory_grows += 1;
+        self.stats.peak_heap_size = self.stats.peak_heap_size.max(heap_size as u64);
+    }
+
+    /// Count a page fault; safe to call from the region's fault handler thread.
+    pub(crate) fn record_page_fault(&self) {
+        self.page_faults.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count an invocation of the signal handler.
+    ///
+    /// This is async-signal-safe: the counter is a lock-free atomic, so the increment can't be
+    /// torn or lost even if the signal interrupts the host in the middle of updating the stats.
+    pub(crate) fn record_signal(&self) {
+        self.signals.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add the time since the current segment started to the guest or hostcall totals, and start
+    /// a new segment.
+    fn end_segment(&mut self) {
+        if let Some((wall_start, cpu_start)) = self.segment_start {
+            let (wall_end, cpu_end) = self.now();
+            let wall = wall_end.duration_since(wall_start);
+            let cpu = cpu_end.checked_sub(cpu_start).unwrap_or_default();
+            if self.in_hostcall {
+                self.stats.hostcall_wall_time += wall;
+                self.stats.hostcall_cpu_time += cpu;
+            } else {
+                self.stats.guest_wall_time += wall;
+                self.stats.guest_cpu_time += cpu;
+            }
+            self.segment_start = Some((wall_end, cpu_end));
+        }
+    }
+
+    /// The current wall-clock time, and the CPU time used by the current thread if it is being
+    /// measured.
+    fn now(&self) -> (Instant, Duration) {
+        (Instant::now(), self.cpu_now())
+    }
+
+    fn cpu_now(&self) -> Duration {
+        if self.cpu_time {
+            thread_cpu_time()
+        } else {
+            Duration::default()
+        }
+    }
+}
+
+fn thread_cpu_time() -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let res = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) };
+    if res != 0 {
+        return Duration::default();
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
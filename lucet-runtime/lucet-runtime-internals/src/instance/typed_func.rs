@@ -0,0 +1,75 @@
+//! Handles to guest functions whose signatures have been checked against Rust types.
+
+use crate::error::Error;
+use crate::instance::Instance;
+use crate::module::Module;
+use crate::val::{WasmParams, WasmResults};
+use lucet_module::{FunctionHandle, Signature};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// A guest function that takes `Params` and returns `Results`, created by
+/// [`Instance::typed_func()`](struct.Instance.html#method.typed_func).
+///
+/// The function's signature is checked when the handle is created, so calling it needs neither a
+/// lookup by name nor a check of the arguments, and its results are returned as Rust values.
+pub struct TypedFunc<Params, Results> {
+    func: FunctionHandle,
+    /// The module the function belongs to, so that the handle can't be used with instances of
+    /// other modules.
+    module: Arc<dyn Module>,
+    _types: PhantomData<fn(Params) -> Results>,
+}
+
+impl<Params, Results> Clone for TypedFunc<Params, Results> {
+    fn clone(&self) -> Self {
+        TypedFunc {
+            func: self.func,
+            module: self.module.clone(),
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<Params: WasmParams, Results: WasmResults> TypedFunc<Params, Results> {
+    pub(crate) fn new(module: Arc<dyn Module>, func: FunctionHandle) -> Result<Self, Error> {
+        let signature = Signature {
+            params: Params::value_types(),
+            results: Results::value_types(),
+        };
+        if *module.get_signature(func.id) != signature {
+            return Err(Error::InvalidArgument(
+                "function signature does not match the requested types",
+            ));
+        }
+        Ok(TypedFunc {
+            func,
+            module,
+            _types: PhantomData,
+        })
+    }
+
+    /// Run the function in `inst`, which must be an instance of the module the handle was created
+    /// from.
+    ///
+    /// This runs the function like [`Instance::run()`](struct.Instance.html#method.run) does, and
+    /// the same safety caveats apply. If the function yields, this returns
+    /// `Error::InstanceNotReturned` and leaves the instance yielded; once it is resumed and
+    /// returns, the results are available from
+    /// [`Instance::results()`](struct.Instance.html#method.results).
+    pub fn call(&self, inst: &mut Instance, args: Params) -> Result<Results, Error> {
+        if !same_module(self.module.as_ref(), inst.module.as_ref()) {
+            return Err(Error::InvalidArgument(
+                "typed function called on an instance of a different module",
+            ));
+        }
+        inst.run_func_unchecked(self.func, args)?.returned()?;
+        Results::from_vals(inst.results())
+            .ok_or_else(|| lucet_format_err!("function results do not match its signature"))
+    }
+}
+
+fn same_module(a: &dyn Module, b: &dyn Module) -> bool {
+    // compare only the data pointers, as the vtable pointers of the same module may differ
+    a as *const dyn Module as *const u8 == b as *const dyn Module as *const u8
+}
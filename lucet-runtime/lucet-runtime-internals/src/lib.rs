@@ -19,6 +19,7 @@ pub mod c_api;
 pub mod context;
 pub mod embed_ctx;
 pub mod instance;
+pub mod linker;
 #[cfg(feature = "concurrent_testpoints")]
 pub mod lock_testpoints;
 pub mod module;
@@ -0,0 +1,266 @@
+//! Binding host functions to the imports of modules compiled with linked imports.
+//!
+//! Modules compiled with `lucetc --linked-imports` call their imported functions through a table
+//! in each instance, rather than through symbols resolved by the dynamic linker. A
+//! [`Linker`](struct.Linker.html) holds the host functions for each `(module, field)` import, and
+//! the table is filled in from it when an instance is built with
+//! [`InstanceBuilder::with_linker()`](../region/struct.InstanceBuilder.html#method.with_linker).
+//! Different instances, even of the same module, can be given different host functions.
+
+use crate::error::Error;
+use crate::instance::TerminationDetails;
+use crate::module::Module;
+use crate::val::{WasmResults, WasmTy};
+use crate::vmctx::{lucet_vmctx, Vmctx, VmctxInternal};
+use libc::c_void;
+use lucet_module::{ImportFuncRef, Signature};
+use std::any::Any;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::Arc;
+
+/// A host function that can be bound to an imported function.
+///
+/// These are created by [`Linker::func()`](struct.Linker.html#method.func) and
+/// [`Linker::hostcall()`](struct.Linker.html#method.hostcall).
+#[derive(Clone)]
+pub struct HostFunc {
+    /// The function called by the guest, with the vmctx and the import's arguments.
+    func: *const c_void,
+    /// The state of a closure, which `func` finds through `InstanceRuntimeData::import_data`.
+    data: Option<Arc<dyn Any + Send + Sync>>,
+    signature: Signature,
+}
+
+// the function pointer is only ever called by guest code
+unsafe impl Send for HostFunc {}
+unsafe impl Sync for HostFunc {}
+
+impl HostFunc {
+    /// The WebAssembly signature of the function.
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// The entry for this function in an instance's import table.
+    pub(crate) fn import_func_ref(&self) -> ImportFuncRef {
+        let data = match &self.data {
+            Some(data) => &**data as *const (dyn Any + Send + Sync) as *const c_void,
+            None => ptr::null(),
+        };
+        ImportFuncRef {
+            func: self.func,
+            data,
+        }
+    }
+}
+
+/// Host functions to bind to the imported functions of modules compiled with linked imports,
+/// keyed by the import's module and field names.
+///
+/// ```no_run
+/// # use lucet_runtime_internals::linker::Linker;
+/// # use lucet_runtime_internals::vmctx::Vmctx;
+/// let mut linker = Linker::new();
+/// let offset = 10;
+/// linker.func("env", "add_offset", move |_vmctx: &Vmctx, x: u32| x + offset);
+/// ```
+///
+/// A module compiled without linked imports resolves its imports as symbols when it is loaded, so
+/// building an instance of it with a linker leaves the linker unused.
+#[derive(Clone, Default)]
+pub struct Linker {
+    funcs: HashMap<(String, String), HostFunc>,
+}
+
+impl Linker {
+    /// Create a linker with no host functions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind a closure to the function that modules import as `field` from `module`.
+    ///
+    /// The closure takes the `Vmctx` followed by the function's arguments, each of a
+    /// [`WasmTy`](../val/trait.WasmTy.html) type, and returns `()` or a single `WasmTy`. It runs
+    /// like a `#[lucet_hostcall]` function: the instance cannot be terminated while it runs, and
+    /// it may terminate the instance with `lucet_hostcall_terminate!()`.
+    ///
+    /// The guest finds the closure's state through a single `import_data` slot in the instance,
+    /// which it overwrites just before each call to an imported function. The closure is found
+    /// before it runs, so it may call back into the guest, but other code that runs on the
+    /// instance's behalf can't rely on the slot to find a particular closure.
+    ///
+    /// If a function was already bound to the same import, it is replaced.
+    pub fn func<Params, Results>(
+        &mut self,
+        module: &str,
+        field: &str,
+        func: impl IntoHostFunc<Params, Results>,
+    ) -> &mut Self {
+        self.define(module, field, func.into_host_func())
+    }
+
+    /// Bind a function defined with `#[lucet_hostcall]` to the function that modules import as
+    /// `field` from `module`.
+    ///
+    /// The hostcall must be passed as a function pointer, for example
+    /// `my_hostcall as unsafe extern "C" fn(*const lucet_vmctx, u32) -> u32`, so that its
+    /// WebAssembly signature can be checked against the import's.
+    ///
+    /// If a function was already bound to the same import, it is replaced.
+    pub fn hostcall<Params, Results>(
+        &mut self,
+        module: &str,
+        field: &str,
+        hostcall: impl Hostcall<Params, Results>,
+    ) -> &mut Self {
+        self.define(module, field, hostcall.into_host_func())
+    }
+
+    /// Bind a host function, such as one taken from another linker, to the function that modules
+    /// import as `field` from `module`.
+    pub fn define(&mut self, module: &str, field: &str, func: HostFunc) -> &mut Self {
+        self.funcs
+            .insert((module.to_owned(), field.to_owned()), func);
+        self
+    }
+
+    /// The host function bound to the function imported as `field` from `module`, if any.
+    pub fn get(&self, module: &str, field: &str) -> Option<&HostFunc> {
+        self.funcs.get(&(module.to_owned(), field.to_owned()))
+    }
+
+    /// The host functions for each of `module`'s imported functions, in import order.
+    ///
+    /// This is empty if the module was not compiled with linked imports.
+    pub(crate) fn resolve(&self, module: &dyn Module) -> Result<Vec<HostFunc>, Error> {
+        let module_data = module.module_data();
+        if !module_data.features().linked_imports {
+            return Ok(vec![]);
+        }
+        module_data
+            .import_functions()
+            .iter()
+            .map(|import| {
+                let func = self.get(import.module, import.name).ok_or_else(|| {
+                    Error::FunctionImportNotProvided(format!("{}::{}", import.module, import.name))
+                })?;
+                let expected = module.get_signature(import.fn_idx);
+                if func.signature != *expected {
+                    return Err(Error::FunctionImportMismatch(format!(
+                        "{}::{} is imported as {}, but the linker provides {}",
+                        import.module, import.name, expected, func.signature
+                    )));
+                }
+                Ok(func.clone())
+            })
+            .collect()
+    }
+}
+
+/// Closures that can be bound to imported functions with
+/// [`Linker::func()`](struct.Linker.html#method.func).
+///
+/// This is implemented for `Fn(&Vmctx, A1, ..., An) -> R` closures of up to 16 arguments.
+pub trait IntoHostFunc<Params, Results> {
+    fn into_host_func(self) -> HostFunc;
+}
+
+/// Function pointers to `#[lucet_hostcall]` functions that can be bound to imported functions with
+/// [`Linker::hostcall()`](struct.Linker.html#method.hostcall).
+///
+/// This is implemented for `unsafe extern "C" fn(*const lucet_vmctx, A1, ..., An) -> R` of up to
+/// 16 arguments.
+pub trait Hostcall<Params, Results> {
+    fn into_host_func(self) -> HostFunc;
+}
+
+/// Run a host function the way `#[lucet_hostcall]` runs a hostcall: without allowing the instance
+/// to be terminated in the meantime, and terminating it if the function panics with
+/// `TerminationDetails`.
+unsafe fn call_host_func<R>(vmctx_raw: *const lucet_vmctx, f: impl FnOnce() -> R) -> R {
+    let vmctx = Vmctx::from_raw(vmctx_raw);
+    vmctx
+        .instance_mut()
+        .uninterruptable(|| match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(res) => res,
+            Err(e) => match e.downcast::<TerminationDetails>() {
+                Ok(details) => Vmctx::from_raw(vmctx_raw).terminate_no_unwind(*details),
+                Err(e) => panic::resume_unwind(e),
+            },
+        })
+}
+
+macro_rules! impl_host_funcs {
+    ( $( $arg:ident )* ) => {
+        impl<F, $( $arg, )* R> IntoHostFunc<( $( $arg, )* ), R> for F
+        where
+            F: Fn(&Vmctx, $( $arg ),*) -> R + Send + Sync + 'static,
+            $( $arg: WasmTy, )*
+            R: WasmResults,
+        {
+            fn into_host_func(self) -> HostFunc {
+                #[allow(non_snake_case)]
+                unsafe extern "C" fn trampoline<F, $( $arg, )* R>(
+                    vmctx_raw: *const lucet_vmctx,
+                    $( $arg: $arg, )*
+                ) -> R
+                where
+                    F: Fn(&Vmctx, $( $arg ),*) -> R,
+                {
+                    // the import data is overwritten by the next import the guest calls, so it
+                    // must be read before the closure runs
+                    let func = &*(Vmctx::from_raw(vmctx_raw).instance().import_data() as *const F);
+                    call_host_func(vmctx_raw, || func(&Vmctx::from_raw(vmctx_raw), $( $arg ),*))
+                }
+
+                HostFunc {
+                    func: trampoline::<F, $( $arg, )* R> as *const c_void,
+                    data: Some(Arc::new(self)),
+                    signature: Signature {
+                        params: vec![$( <$arg as WasmTy>::VALUE_TYPE ),*],
+                        results: R::value_types(),
+                    },
+                }
+            }
+        }
+
+        impl<$( $arg, )* R> Hostcall<( $( $arg, )* ), R>
+            for unsafe extern "C" fn(*const lucet_vmctx, $( $arg ),*) -> R
+        where
+            $( $arg: WasmTy, )*
+            R: WasmResults,
+        {
+            fn into_host_func(self) -> HostFunc {
+                HostFunc {
+                    func: self as *const c_void,
+                    data: None,
+                    signature: Signature {
+                        params: vec![$( <$arg as WasmTy>::VALUE_TYPE ),*],
+                        results: R::value_types(),
+                    },
+                }
+            }
+        }
+    };
+}
+
+impl_host_funcs!();
+impl_host_funcs!(A1);
+impl_host_funcs!(A1 A2);
+impl_host_funcs!(A1 A2 A3);
+impl_host_funcs!(A1 A2 A3 A4);
+impl_host_funcs!(A1 A2 A3 A4 A5);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7 A8);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7 A8 A9);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13 A14);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13 A14 A15);
+impl_host_funcs!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13 A14 A15 A16);
@@ -1,17 +1,26 @@
 mod dl;
+mod heap_image;
+mod info;
 mod mock;
+mod object;
 mod sparse_page_data;
 
 pub use crate::module::dl::{DlError, DlModule};
+pub use crate::module::heap_image::HeapImage;
+pub use crate::module::info::{Export, ExportKind, GlobalInfo, Import, ImportKind, MemoryInfo};
 pub use crate::module::mock::{MockExportBuilder, MockModuleBuilder};
+pub use crate::module::object::{ObjectError, ObjectModule, ObjectSymbols};
 pub use lucet_module::{
-    FunctionHandle, FunctionIndex, FunctionPointer, FunctionSpec, Global, GlobalSpec, GlobalValue,
-    HeapSpec, Signature, TableElement, TrapCode, TrapManifest, ValueType,
+    FunctionHandle, FunctionIndex, FunctionPointer, FunctionSpec, Global, GlobalBaseSegment,
+    GlobalSpec, GlobalValue, HeapSpec, Signature, TableElement, TableSpec, TrapCode, TrapManifest,
+    ValueType,
 };
 
 use crate::alloc::Limits;
 use crate::error::Error;
 use libc::c_void;
+use lucet_module::{ModuleData, VersionInfo};
+use std::sync::Arc;
 
 /// Details about a program address.
 ///
@@ -33,7 +42,88 @@ pub struct AddrDetails {
 pub trait Module: ModuleInternal {
     /// Calculate the initial size in bytes of the module's Wasm globals.
     fn initial_globals_size(&self) -> usize {
-        self.globals().len() * std::mem::size_of::<u64>()
+        self.global_specs().len() * std::mem::size_of::<GlobalValue>()
+    }
+
+    /// Get the functions and globals the module exports, functions first.
+    fn exports(&self) -> Box<dyn Iterator<Item = Export<'_>> + '_> {
+        let module_data = self.module_data();
+        let funcs = module_data
+            .export_functions()
+            .iter()
+            .flat_map(move |export| {
+                let signature = module_data.get_signature(export.fn_idx);
+                export.names.iter().map(move |&name| Export {
+                    name,
+                    kind: ExportKind::Func {
+                        index: export.fn_idx,
+                        signature,
+                    },
+                })
+            });
+        let globals = self.globals().flat_map(|global| {
+            let kind = ExportKind::Global {
+                index: global.index,
+                ty: global.ty,
+            };
+            global.export_names.iter().map(move |&name| Export {
+                name,
+                kind: kind.clone(),
+            })
+        });
+        Box::new(funcs.chain(globals))
+    }
+
+    /// Get the functions and globals the module imports, functions first.
+    fn imports(&self) -> Box<dyn Iterator<Item = Import<'_>> + '_> {
+        let module_data = self.module_data();
+        let funcs = module_data
+            .import_functions()
+            .iter()
+            .map(move |import| Import {
+                module: import.module,
+                name: import.name,
+                kind: ImportKind::Func {
+                    index: import.fn_idx,
+                    signature: module_data.get_signature(import.fn_idx),
+                },
+            });
+        let globals = self.globals().filter_map(|global| {
+            global.import.map(|(module, name)| Import {
+                module,
+                name,
+                kind: ImportKind::Global {
+                    index: global.index,
+                    ty: global.ty,
+                },
+            })
+        });
+        Box::new(funcs.chain(globals))
+    }
+
+    /// Get each of the module's globals, in index order, whether defined by the module or
+    /// imported.
+    fn globals(&self) -> Box<dyn Iterator<Item = GlobalInfo<'_>> + '_> {
+        Box::new(
+            self.global_specs()
+                .iter()
+                .enumerate()
+                .map(|(index, spec)| GlobalInfo {
+                    index: index as u32,
+                    ty: spec.value_type(),
+                    mutable: spec.is_mutable(),
+                    import: match spec.global() {
+                        Global::Import { module, field, .. } => Some((*module, *field)),
+                        Global::Def(_) => None,
+                    },
+                    export_names: spec.export_names(),
+                }),
+        )
+    }
+
+    /// Get the module's linear memory, if it has one.
+    fn memory(&self) -> Option<MemoryInfo> {
+        self.heap_spec().map(MemoryInfo::from)
     }
 }
 
@@ -49,18 +139,56 @@ pub trait ModuleInternal: Send + Sync {
     ///
     /// The indices into the returned slice correspond to the WebAssembly indices of the globals
     /// (<https://webassembly.github.io/spec/core/syntax/modules.html#syntax-globalidx>)
-    fn globals(&self) -> &[GlobalSpec<'_>];
+    fn global_specs(&self) -> &[GlobalSpec<'_>];
 
     fn get_sparse_page_data(&self, page: usize) -> Option<&[u8]>;
 
     /// Get the number of pages in the sparse page data.
     fn sparse_page_data_len(&self) -> usize;
 
+    /// Get an image of the sparse page data that can be mapped copy-on-write into a heap.
+    ///
+    /// Regions copy the sparse page data into the heap instead when this is `None`.
+    fn heap_image(&self) -> Option<Arc<HeapImage>> {
+        None
+    }
+
     /// Get the table elements from the module.
     fn table_elements(&self) -> Result<&[TableElement], Error>;
 
+    /// Get the specifications of the module's tables, including their initial elements.
+    fn table_specs(&self) -> &[TableSpec];
+
+    /// Get the `funcref` for each function in the module, indexed by `FunctionIndex`.
+    ///
+    /// Instance tables and `ref.func` refer to functions by pointers to these elements, so they
+    /// must live as long as the module.
+    fn func_refs(&self) -> &[TableElement];
+
+    /// Get the data segments of the module, indexed by data segment index.
+    ///
+    /// Only passive segments, which are copied into the heap by `memory.init`, and the segments
+    /// in `global_base_data()` are `Some`.
+    fn passive_data(&self) -> &[Option<&[u8]>];
+
+    /// Get the element segments of the module, indexed by element segment index.
+    ///
+    /// Only passive segments, which are copied into a table by `table.init`, and the segments in
+    /// `global_base_elements()` are `Some`.
+    fn passive_elements(&self) -> &[Option<Vec<FunctionIndex>>];
+
+    /// Get the active data segments whose offset is given by a global, and those that follow them,
+    /// which are applied in order when an instance is created or reset.
+    fn global_base_data(&self) -> &[GlobalBaseSegment];
+
+    /// Get the active element segments whose offset is given by a global, and those that follow
+    /// them, which are applied in order when an instance is created or reset.
+    fn global_base_elements(&self) -> &[GlobalBaseSegment];
+
     fn get_export_func(&self, sym: &str) -> Result<FunctionHandle, Error>;
 
+    /// Look up a function in a table as the module initializes it; instances may since have
+    /// changed their tables.
     fn get_func_from_idx(&self, table_id: u32, func_id: u32) -> Result<FunctionHandle, Error>;
 
     fn get_start_func(&self) -> Result<Option<FunctionHandle>, Error>;
@@ -71,6 +199,15 @@ pub trait ModuleInternal: Send + Sync {
 
     fn get_signature(&self, fn_id: FunctionIndex) -> &Signature;
 
+    /// Get a human-readable name for a function, preferring its export name.
+    fn get_function_name(&self, fn_id: FunctionIndex) -> Option<&str>;
+
+    /// Get the version of `lucetc` that compiled the module.
+    fn version(&self) -> &VersionInfo;
+
+    /// Get the module's metadata, including its signature if it was signed.
+    fn module_data(&self) -> &ModuleData<'_>;
+
     fn function_handle_from_ptr(&self, ptr: FunctionPointer) -> FunctionHandle {
         let id = self
             .function_manifest()
@@ -135,7 +272,20 @@ pub trait ModuleInternal: Send + Sync {
             if heap.reserved_size as usize + heap.guard_size as usize
                 > limits.heap_address_space_size
             {
-                bail_limits_exceeded!("heap spec reserved and guard size: {:?}", heap);
+                bail_limits_exceeded!(
+                    "heap spec reserved and guard size: {:?}; modules without bounds-checked heaps \
+                     need this much heap address space",
+                    heap
+                );
+            }
+
+            // Bounds-checked code only reads the low 32 bits of the heap length, so the heap must
+            // never reach 4GiB.
+            if heap.bounds_checked && heap_memory_size > std::u32::MAX as usize {
+                bail_limits_exceeded!(
+                    "heap memory size {} is too large for a bounds-checked heap",
+                    heap_memory_size
+                );
             }
 
             if heap.initial_size as usize > heap_memory_size {
@@ -150,10 +300,23 @@ pub trait ModuleInternal: Send + Sync {
             }
         }
 
-        if self.globals().len() * std::mem::size_of::<u64>() > limits.globals_size {
+        if self.global_specs().len() * std::mem::size_of::<GlobalValue>() > limits.globals_size {
             bail_limits_exceeded!("globals exceed limits");
         }
 
         Ok(())
     }
 }
+
+/// Build the `funcref` for each function described by `module_data` and `function_manifest`.
+pub(crate) fn build_func_refs(
+    module_data: &ModuleData<'_>,
+    function_manifest: &[FunctionSpec],
+) -> Vec<TableElement> {
+    module_data
+        .function_info()
+        .iter()
+        .zip(function_manifest)
+        .map(|(info, spec)| TableElement::new(info.signature, spec.ptr()))
+        .collect()
+}
@@ -1,10 +1,14 @@
 use crate::error::Error;
-use crate::module::{AddrDetails, GlobalSpec, HeapSpec, Module, ModuleInternal, TableElement};
+use crate::module::heap_image::HeapImageCell;
+use crate::module::{
+    build_func_refs, AddrDetails, GlobalBaseSegment, GlobalSpec, HeapImage, HeapSpec, Module,
+    ModuleInternal, TableElement, TableSpec,
+};
 use libc::c_void;
 use libloading::Library;
 use lucet_module::{
     FunctionHandle, FunctionIndex, FunctionSpec, ModuleData, ModuleFeatures, ModuleSignature,
-    PublicKey, SerializedModule, Signature, VersionInfo, LUCET_MODULE_SYM,
+    PublicKey, SerializedModule, Signature, ValueType, VersionInfo, LUCET_MODULE_SYM,
 };
 use std::ffi::CStr;
 use std::mem::MaybeUninit;
@@ -106,6 +110,12 @@ pub struct DlModule {
 
     /// Metadata decoded from inside the module
     module: lucet_module::Module<'static>,
+
+    /// The `funcref` for each function in the module
+    func_refs: Vec<TableElement>,
+
+    /// The image of the module's initial heap, once a region has asked for it
+    heap_image: HeapImageCell,
 }
 
 // for the one raw pointer only
@@ -142,45 +152,21 @@ impl DlModule {
                 })?
         };
 
-        let serialized_module: &SerializedModule =
+        let serialized_module: &'static SerializedModule =
             unsafe { serialized_module_ptr.as_ref().unwrap() };
 
-        let module_version = serialized_module.version.clone();
-
-        let runtime_version =
-            VersionInfo::current(include_str!(concat!(env!("OUT_DIR"), "/commit_hash")).as_bytes());
-
-        if !module_version.valid() {
-            return Err(lucet_incorrect_module!("reserved bit is not set. This module is likely too old for this lucet-runtime to load."));
-        } else if !runtime_version.compatible_with(&module_version) {
-            return Err(lucet_incorrect_module!(
-                "version mismatch. module has version {}, while this runtime is version {}",
-                module_version,
-                runtime_version,
-            ));
-        }
-
-        // Deserialize the slice into ModuleData, which will hold refs into the loaded
-        // shared object file in `module_data_slice`. Both of these get a 'static lifetime because
-        // Rust doesn't have a safe way to describe that their lifetime matches the containing
-        // struct (and the dll).
+        // The module metadata holds refs into the loaded shared object file. They get a 'static
+        // lifetime because Rust doesn't have a safe way to describe that their lifetime matches
+        // the containing struct (and the dll).
         //
-        // The exposed lifetime of ModuleData will be the same as the lifetime of the
+        // The exposed lifetime of the metadata will be the same as the lifetime of the
         // dynamically loaded library. This makes the interface safe.
-        let module_data_slice: &'static [u8] = unsafe {
-            slice::from_raw_parts(
-                serialized_module.module_data_ptr as *const u8,
-                serialized_module.module_data_len as usize,
-            )
-        };
-        let module_data = ModuleData::deserialize(module_data_slice)?;
-
-        check_feature_support(module_data.features())?;
+        let module = unsafe { read_serialized_module(serialized_module)? };
 
         // If a public key has been provided, verify the module signature
         // The TOCTOU issue is unavoidable without reimplenting `dlopen(3)`
         if let Some(pk) = pk {
-            ModuleSignature::verify(so_path, &pk, &module_data)?;
+            ModuleSignature::verify(so_path, &pk, &module.module_data)?;
         }
 
         let fbase = if let Some(dli) =
@@ -191,40 +177,76 @@ impl DlModule {
             std::ptr::null()
         };
 
-        if serialized_module.tables_len > std::u32::MAX as u64 {
-            lucet_incorrect_module!("table segment too long: {}", serialized_module.tables_len);
-        }
-        let tables: &'static [&'static [TableElement]] = unsafe {
-            from_raw_parts(
-                serialized_module.tables_ptr as *const &[TableElement],
-                serialized_module.tables_len as usize,
-            )
-        };
-
-        let function_manifest = if serialized_module.function_manifest_ptr != 0 {
-            unsafe {
-                from_raw_parts(
-                    serialized_module.function_manifest_ptr as *const FunctionSpec,
-                    serialized_module.function_manifest_len as usize,
-                )
-            }
-        } else {
-            &[]
-        };
+        let func_refs = build_func_refs(&module.module_data, module.function_manifest);
 
         Ok(Arc::new(DlModule {
             _lib: lib,
             fbase,
-            module: lucet_module::Module {
-                version: module_version,
-                module_data,
-                tables,
-                function_manifest,
-            },
+            func_refs,
+            module,
+            heap_image: HeapImageCell::default(),
         }))
     }
 }
 
+/// Check and decode the module metadata that a `lucet_module` symbol points to.
+///
+/// # Safety
+///
+/// Every pointer in `serialized_module` must be valid, and the memory they point to must outlive
+/// the returned metadata.
+pub(crate) unsafe fn read_serialized_module(
+    serialized_module: &'static SerializedModule,
+) -> Result<lucet_module::Module<'static>, Error> {
+    let module_version = serialized_module.version.clone();
+
+    let runtime_version =
+        VersionInfo::current(include_str!(concat!(env!("OUT_DIR"), "/commit_hash")).as_bytes());
+
+    if !module_version.valid() {
+        return Err(lucet_incorrect_module!("reserved bit is not set. This module is likely too old for this lucet-runtime to load."));
+    } else if !runtime_version.compatible_with(&module_version) {
+        return Err(lucet_incorrect_module!(
+            "version mismatch. module has version {}, while this runtime is version {}",
+            module_version,
+            runtime_version,
+        ));
+    }
+
+    // Deserialize the slice into ModuleData, which will hold refs into `module_data_slice`.
+    let module_data_slice: &'static [u8] = slice::from_raw_parts(
+        serialized_module.module_data_ptr as *const u8,
+        serialized_module.module_data_len as usize,
+    );
+    let module_data = ModuleData::deserialize(module_data_slice)?;
+
+    check_feature_support(module_data.features())?;
+
+    if serialized_module.tables_len > std::u32::MAX as u64 {
+        lucet_incorrect_module!("table segment too long: {}", serialized_module.tables_len);
+    }
+    let tables: &'static [&'static [TableElement]] = from_raw_parts(
+        serialized_module.tables_ptr as *const &[TableElement],
+        serialized_module.tables_len as usize,
+    );
+
+    let function_manifest = if serialized_module.function_manifest_ptr != 0 {
+        from_raw_parts(
+            serialized_module.function_manifest_ptr as *const FunctionSpec,
+            serialized_module.function_manifest_len as usize,
+        )
+    } else {
+        &[]
+    };
+
+    Ok(lucet_module::Module {
+        version: module_version,
+        module_data,
+        tables,
+        function_manifest,
+    })
+}
+
 impl Module for DlModule {}
 
 impl ModuleInternal for DlModule {
@@ -236,7 +258,7 @@ impl ModuleInternal for DlModule {
         self.module.module_data.heap_spec()
     }
 
-    fn globals(&self) -> &[GlobalSpec<'_>] {
+    fn global_specs(&self) -> &[GlobalSpec<'_>] {
         self.module.module_data.globals_spec()
     }
 
@@ -256,6 +278,10 @@ impl ModuleInternal for DlModule {
             .unwrap_or(0)
     }
 
+    fn heap_image(&self) -> Option<Arc<HeapImage>> {
+        self.heap_image.get(self)
+    }
+
     fn table_elements(&self) -> Result<&[TableElement], Error> {
         match self.module.tables.get(0) {
             Some(table) => Ok(table),
@@ -263,6 +289,30 @@ impl ModuleInternal for DlModule {
         }
     }
 
+    fn table_specs(&self) -> &[TableSpec] {
+        self.module.module_data.tables()
+    }
+
+    fn func_refs(&self) -> &[TableElement] {
+        &self.func_refs
+    }
+
+    fn passive_data(&self) -> &[Option<&[u8]>] {
+        self.module.module_data.passive_data()
+    }
+
+    fn passive_elements(&self) -> &[Option<Vec<FunctionIndex>>] {
+        self.module.module_data.passive_elements()
+    }
+
+    fn global_base_data(&self) -> &[GlobalBaseSegment] {
+        self.module.module_data.global_base_data()
+    }
+
+    fn global_base_elements(&self) -> &[GlobalBaseSegment] {
+        self.module.module_data.global_base_elements()
+    }
+
     fn get_export_func(&self, sym: &str) -> Result<FunctionHandle, Error> {
         self.module
             .module_data
@@ -279,16 +329,19 @@ impl ModuleInternal for DlModule {
     }
 
     fn get_func_from_idx(&self, table_id: u32, func_id: u32) -> Result<FunctionHandle, Error> {
-        if table_id != 0 {
-            return Err(Error::FuncNotFound(table_id, func_id));
-        }
-        let table = self.table_elements()?;
-        let func = table
-            .get(func_id as usize)
-            .map(|element| element.function_pointer())
+        let id = self
+            .table_specs()
+            .get(table_id as usize)
+            .filter(|table| table.elem_ty == ValueType::FuncRef)
+            .and_then(|table| table.elements.get(func_id as usize))
+            .and_then(|element| *element)
             .ok_or(Error::FuncNotFound(table_id, func_id))?;
 
-        Ok(self.function_handle_from_ptr(func))
+        Ok(FunctionHandle {
+            ptr: self.function_manifest()[id.as_u32() as usize].ptr(),
+            id,
+            is_start_func: false,
+        })
     }
 
     fn get_start_func(&self) -> Result<Option<FunctionHandle>, Error> {
@@ -331,6 +384,18 @@ impl ModuleInternal for DlModule {
     fn get_signature(&self, fn_id: FunctionIndex) -> &Signature {
         self.module.module_data.get_signature(fn_id)
     }
+
+    fn get_function_name(&self, fn_id: FunctionIndex) -> Option<&str> {
+        self.module.module_data.get_function_name(fn_id)
+    }
+
+    fn version(&self) -> &VersionInfo {
+        &self.module.version
+    }
+
+    fn module_data(&self) -> &ModuleData<'_> {
+        &self.module.module_data
+    }
 }
 
 // TODO: PR to nix or libloading?
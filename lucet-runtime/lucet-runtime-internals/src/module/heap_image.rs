@@ -0,0 +1,100 @@
+use crate::error::Error;
+use crate::module::Module;
+use crate::sysdeps::{host_page_size, memfd_create};
+use libc::c_void;
+use nix::sys::mman::{mmap, MapFlags, ProtFlags};
+use nix::sys::uio::pwrite;
+use nix::unistd::{close, ftruncate};
+use std::os::unix::io::RawFd;
+use std::sync::{Arc, Mutex};
+
+/// A memfd holding the initial contents of a module's heap.
+///
+/// [`MmapRegion`](../region/mmap/struct.MmapRegion.html) maps the image `MAP_PRIVATE` over the
+/// start of an instance's heap, so that instantiating or resetting the heap is a single `mmap`
+/// call, and the physical pages are shared between instances until they are written.
+pub struct HeapImage {
+    fd: RawFd,
+    len: usize,
+}
+
+impl HeapImage {
+    /// Create an image of the module's sparse page data.
+    ///
+    /// Returns `None` if the module has no data to put in an image, or if memfds are not
+    /// available, in which case regions copy the sparse page data into each heap instead.
+    fn new(module: &dyn Module) -> Option<HeapImage> {
+        let page_size = host_page_size();
+        let pages = module.sparse_page_data_len();
+        if (0..pages).all(|page| module.get_sparse_page_data(page).is_none()) {
+            return None;
+        }
+
+        // construct the image right away so that the fd is closed if anything below fails
+        let image = HeapImage {
+            fd: memfd_create("lucet_heap_image")?,
+            len: pages * page_size,
+        };
+        // pages that are never written are holes in the memfd, which read as zeroes
+        ftruncate(image.fd, image.len as libc::off_t).ok()?;
+        for page in 0..pages {
+            if let Some(contents) = module.get_sparse_page_data(page) {
+                let mut written = 0;
+                while written < contents.len() {
+                    let offset = (page * page_size + written) as libc::off_t;
+                    match pwrite(image.fd, &contents[written..], offset) {
+                        Ok(n) if n > 0 => written += n,
+                        _ => return None,
+                    }
+                }
+            }
+        }
+        Some(image)
+    }
+
+    /// The length of the image in bytes, which is a multiple of the host page size.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Map the image read/write over the start of `heap`, replacing any existing mapping.
+    ///
+    /// # Safety
+    ///
+    /// `heap` must be page-aligned, and the `len()` bytes starting at it must be reserved for the
+    /// heap.
+    pub(crate) unsafe fn map_into(&self, heap: *mut c_void) -> Result<(), Error> {
+        mmap(
+            heap,
+            self.len,
+            ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+            MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED,
+            self.fd,
+            0,
+        )?;
+        Ok(())
+    }
+}
+
+impl Drop for HeapImage {
+    fn drop(&mut self) {
+        // mappings of the image stay valid after the fd is closed
+        let _ = close(self.fd);
+    }
+}
+
+/// A module's `HeapImage`, created the first time a region asks for it.
+#[derive(Default)]
+pub(crate) struct HeapImageCell {
+    image: Mutex<Option<Option<Arc<HeapImage>>>>,
+}
+
+impl HeapImageCell {
+    pub(crate) fn get(&self, module: &dyn Module) -> Option<Arc<HeapImage>> {
+        self.image
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| HeapImage::new(module).map(Arc::new))
+            .clone()
+    }
+}
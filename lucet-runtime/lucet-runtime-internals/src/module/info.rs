@@ -0,0 +1,82 @@
+//! Descriptions of the imports, exports, globals, and memory of a module, as returned by the
+//! introspection methods of [`Module`](trait.Module.html).
+
+use crate::WASM_PAGE_SIZE;
+use lucet_module::{FunctionIndex, HeapSpec, Signature, ValueType};
+
+/// An item that a module exports under `name`.
+///
+/// An item exported under several names appears once for each name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Export<'a> {
+    pub name: &'a str,
+    pub kind: ExportKind<'a>,
+}
+
+/// The kind of an exported item.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExportKind<'a> {
+    /// A function, as can be run with
+    /// [`Instance::run()`](../instance/struct.Instance.html#method.run).
+    Func {
+        index: FunctionIndex,
+        signature: &'a Signature,
+    },
+    /// A global, with its index among the module's [`globals()`](trait.Module.html#method.globals).
+    Global { index: u32, ty: ValueType },
+}
+
+/// An item that a module imports as `name` from `module`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Import<'a> {
+    pub module: &'a str,
+    pub name: &'a str,
+    pub kind: ImportKind<'a>,
+}
+
+/// The kind of an imported item.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportKind<'a> {
+    /// A function, which must be provided by a hostcall or a
+    /// [`Linker`](../linker/struct.Linker.html).
+    Func {
+        index: FunctionIndex,
+        signature: &'a Signature,
+    },
+    /// A global, which must be provided with `InstanceBuilder::with_global_import()`.
+    Global { index: u32, ty: ValueType },
+}
+
+/// A global of a module, whether defined by the module or imported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlobalInfo<'a> {
+    /// The WebAssembly index of the global.
+    pub index: u32,
+    pub ty: ValueType,
+    /// Whether the global is declared as mutable.
+    pub mutable: bool,
+    /// The module and name the global is imported from, if it is imported.
+    pub import: Option<(&'a str, &'a str)>,
+    /// The names the global is exported under, if any.
+    pub export_names: &'a [&'a str],
+}
+
+/// The linear memory of a module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryInfo {
+    /// The size of the memory when an instance is created, in WebAssembly pages.
+    pub initial_pages: u32,
+    /// The most WebAssembly pages the memory may grow to, if the module declares a maximum.
+    pub max_pages: Option<u32>,
+}
+
+impl From<&HeapSpec> for MemoryInfo {
+    fn from(heap: &HeapSpec) -> Self {
+        MemoryInfo {
+            initial_pages: (heap.initial_size / WASM_PAGE_SIZE as u64) as u32,
+            max_pages: heap
+                .max_size
+                .map(|max_size| (max_size / WASM_PAGE_SIZE as u64) as u32),
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use crate::error::Error;
-use crate::module::{AddrDetails, GlobalSpec, HeapSpec, Module, ModuleInternal, TableElement};
+use crate::module::{
+    build_func_refs, AddrDetails, GlobalBaseSegment, GlobalSpec, HeapSpec, Module, ModuleInternal,
+    TableElement, TableSpec,
+};
 use libc::c_void;
 use lucet_module::owned::{
     OwnedExportFunction, OwnedFunctionMetadata, OwnedGlobalSpec, OwnedImportFunction,
@@ -7,7 +10,7 @@ use lucet_module::owned::{
 };
 use lucet_module::{
     FunctionHandle, FunctionIndex, FunctionPointer, FunctionSpec, ModuleData, ModuleFeatures,
-    Signature, TrapSite, UniqueSignatureIndex,
+    Signature, TrapSite, UniqueSignatureIndex, ValueType, VersionInfo,
 };
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
@@ -31,6 +34,9 @@ pub struct MockModuleBuilder {
     imports: Vec<OwnedImportFunction>,
     exports: Vec<OwnedExportFunction>,
     signatures: Vec<Signature>,
+    passive_data: Vec<Option<Vec<u8>>>,
+    passive_elements: Vec<Option<Vec<FunctionIndex>>>,
+    tables: Vec<TableSpec>,
 }
 
 impl MockModuleBuilder {
@@ -40,6 +46,7 @@ impl MockModuleBuilder {
             guard_size: 4 * 1024 * 1024,
             initial_size: 64 * 1024,
             max_size: Some(64 * 1024),
+            bounds_checked: false,
         };
         MockModuleBuilder::default().with_heap_spec(DEFAULT_HEAP_SPEC)
     }
@@ -67,26 +74,32 @@ impl MockModuleBuilder {
         self
     }
 
+    /// Declare global `idx` as a mutable `i64` global, as mock functions are free to change it.
     pub fn with_global(mut self, idx: u32, init_val: i64) -> Self {
-        self.globals
-            .insert(idx as usize, OwnedGlobalSpec::new_def(init_val, vec![]));
+        self.globals.insert(
+            idx as usize,
+            OwnedGlobalSpec::new_def(init_val, vec![]).with_mutable(true),
+        );
         self
     }
 
+    /// Declare global `idx` as a mutable `i64` global exported as `export_name`.
     pub fn with_exported_global(mut self, idx: u32, init_val: i64, export_name: &str) -> Self {
         self.globals.insert(
             idx as usize,
-            OwnedGlobalSpec::new_def(init_val, vec![export_name.to_string()]),
+            OwnedGlobalSpec::new_def(init_val, vec![export_name.to_string()]).with_mutable(true),
         );
         self
     }
 
+    /// Declare global `idx` as an `i64` global imported from `import_module`.
     pub fn with_import(mut self, idx: u32, import_module: &str, import_field: &str) -> Self {
         self.globals.insert(
             idx as usize,
             OwnedGlobalSpec::new_import(
                 import_module.to_string(),
                 import_field.to_string(),
+                ValueType::I64,
                 vec![],
             ),
         );
@@ -105,6 +118,7 @@ impl MockModuleBuilder {
             OwnedGlobalSpec::new_import(
                 import_module.to_string(),
                 import_field.to_string(),
+                ValueType::I64,
                 vec![export_name.to_string()],
             ),
         );
@@ -116,6 +130,34 @@ impl MockModuleBuilder {
         self
     }
 
+    /// Declare the next table of the module, including its initial elements.
+    pub fn with_table(mut self, table: TableSpec) -> Self {
+        self.tables.push(table);
+        self
+    }
+
+    /// Declare data segment `idx` as a passive segment with the given contents. Any undeclared
+    /// segments with lower indices are treated as active.
+    pub fn with_passive_data(mut self, idx: u32, data: &[u8]) -> Self {
+        let idx = idx as usize;
+        if self.passive_data.len() <= idx {
+            self.passive_data.resize(idx + 1, None);
+        }
+        self.passive_data[idx] = Some(data.to_vec());
+        self
+    }
+
+    /// Declare element segment `idx` as a passive segment with the given functions. Any undeclared
+    /// segments with lower indices are treated as active.
+    pub fn with_passive_elements(mut self, idx: u32, elements: &[FunctionIndex]) -> Self {
+        let idx = idx as usize;
+        if self.passive_elements.len() <= idx {
+            self.passive_elements.resize(idx + 1, None);
+        }
+        self.passive_elements[idx] = Some(elements.to_vec());
+        self
+    }
+
     fn record_sig(&mut self, sig: Signature) -> UniqueSignatureIndex {
         let idx = self
             .signatures
@@ -236,6 +278,11 @@ impl MockModuleBuilder {
             self.signatures,
             ModuleFeatures::none(),
             self.start_func.map(|x| x.0),
+            self.passive_data,
+            self.passive_elements,
+            self.tables,
+            vec![],
+            vec![],
         );
         let serialized_module_data = owned_module_data
             .to_ref()
@@ -244,9 +291,14 @@ impl MockModuleBuilder {
         let module_data = ModuleData::deserialize(&serialized_module_data)
             .map(|md| unsafe { std::mem::transmute(md) })
             .expect("module data can be deserialized");
+        let func_refs = build_func_refs(&module_data, &self.function_manifest);
         let mock = MockModule {
+            version: VersionInfo::current(
+                include_str!(concat!(env!("OUT_DIR"), "/commit_hash")).as_bytes(),
+            ),
             serialized_module_data,
             module_data,
+            func_refs,
             table_elements,
             export_funcs: self.export_funcs,
             func_table: self.func_table,
@@ -258,9 +310,11 @@ impl MockModuleBuilder {
 }
 
 pub struct MockModule {
+    version: VersionInfo,
     #[allow(dead_code)]
     serialized_module_data: Vec<u8>,
     module_data: ModuleData<'static>,
+    func_refs: Vec<TableElement>,
     pub table_elements: Vec<TableElement>,
     pub export_funcs: HashMap<&'static str, FunctionPointer>,
     pub func_table: HashMap<(u32, u32), FunctionPointer>,
@@ -282,7 +336,7 @@ impl ModuleInternal for MockModule {
         self.module_data.heap_spec()
     }
 
-    fn globals(&self) -> &[GlobalSpec<'_>] {
+    fn global_specs(&self) -> &[GlobalSpec<'_>] {
         self.module_data.globals_spec()
     }
 
@@ -302,6 +356,30 @@ impl ModuleInternal for MockModule {
         Ok(&self.table_elements)
     }
 
+    fn table_specs(&self) -> &[TableSpec] {
+        self.module_data.tables()
+    }
+
+    fn func_refs(&self) -> &[TableElement] {
+        &self.func_refs
+    }
+
+    fn passive_data(&self) -> &[Option<&[u8]>] {
+        self.module_data.passive_data()
+    }
+
+    fn passive_elements(&self) -> &[Option<Vec<FunctionIndex>>] {
+        self.module_data.passive_elements()
+    }
+
+    fn global_base_data(&self) -> &[GlobalBaseSegment] {
+        self.module_data.global_base_data()
+    }
+
+    fn global_base_elements(&self) -> &[GlobalBaseSegment] {
+        self.module_data.global_base_elements()
+    }
+
     fn get_export_func(&self, sym: &str) -> Result<FunctionHandle, Error> {
         let ptr = *self
             .export_funcs
@@ -338,6 +416,18 @@ impl ModuleInternal for MockModule {
     fn get_signature(&self, fn_id: FunctionIndex) -> &Signature {
         self.module_data.get_signature(fn_id)
     }
+
+    fn get_function_name(&self, fn_id: FunctionIndex) -> Option<&str> {
+        self.module_data.get_function_name(fn_id)
+    }
+
+    fn version(&self) -> &VersionInfo {
+        &self.version
+    }
+
+    fn module_data(&self) -> &ModuleData<'_> {
+        &self.module_data
+    }
 }
 
 pub struct MockExportBuilder {
@@ -357,7 +447,7 @@ impl MockExportBuilder {
             traps: None,
             sig: Signature {
                 params: vec![],
-                ret_ty: None,
+                results: vec![],
             },
         }
     }
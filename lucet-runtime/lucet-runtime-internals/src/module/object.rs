@@ -0,0 +1,655 @@
+use crate::error::Error;
+use crate::module::dl::read_serialized_module;
+use crate::module::heap_image::HeapImageCell;
+use crate::module::{
+    build_func_refs, AddrDetails, GlobalBaseSegment, GlobalSpec, HeapImage, HeapSpec, Module,
+    ModuleInternal, TableElement, TableSpec,
+};
+use crate::sysdeps::host_page_size;
+use libc::c_void;
+use lucet_module::{
+    FunctionHandle, FunctionIndex, FunctionSpec, ModuleData, SerializedModule, Signature,
+    ValueType, VersionInfo, LUCET_MODULE_SYM,
+};
+use nix::sys::mman::{mmap, mprotect, munmap, MapFlags, ProtFlags};
+use object::{
+    Architecture, BinaryFormat, Object, ObjectSection, RelocationKind, RelocationTarget,
+    SectionKind, SymbolKind,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ObjectError {
+    #[error("Parsing: {0}")]
+    Parse(
+        #[from]
+        #[source]
+        object::read::Error,
+    ),
+    #[error("IO: {0}")]
+    Io(
+        #[from]
+        #[source]
+        std::io::Error,
+    ),
+    #[error("Undefined symbol: {0}")]
+    UndefinedSymbol(String),
+    #[error("Unsupported relocation: {0}")]
+    UnsupportedRelocation(String),
+    #[error("Relocation out of range for symbol: {0}")]
+    RelocationOutOfRange(String),
+    #[error("Unsupported object: {0}")]
+    UnsupportedObject(String),
+}
+
+/// The addresses that an [`ObjectModule`](struct.ObjectModule.html)'s imports resolve to.
+///
+/// Every symbol the object leaves undefined, whether a hostcall, a runtime function, or a libcall
+/// emitted by the code generator, must have an entry here. Nothing is looked up in the global
+/// symbol namespace of the process.
+///
+/// `ObjectSymbols::new()` starts out with the libcalls that compiled code may use;
+/// `lucet_runtime::runtime_symbols()` also adds the runtime's own functions.
+#[derive(Clone, Debug)]
+pub struct ObjectSymbols {
+    symbols: HashMap<String, usize>,
+}
+
+impl ObjectSymbols {
+    /// Create a symbol table containing the libcalls that compiled code may use.
+    pub fn new() -> Self {
+        extern "C" {
+            fn ceilf(x: f32) -> f32;
+            fn ceil(x: f64) -> f64;
+            fn floorf(x: f32) -> f32;
+            fn floor(x: f64) -> f64;
+            fn truncf(x: f32) -> f32;
+            fn trunc(x: f64) -> f64;
+            fn nearbyintf(x: f32) -> f32;
+            fn nearbyint(x: f64) -> f64;
+        }
+
+        let libcalls: &[(&str, *const c_void)] = &[
+            ("ceilf", ceilf as _),
+            ("ceil", ceil as _),
+            ("floorf", floorf as _),
+            ("floor", floor as _),
+            ("truncf", truncf as _),
+            ("trunc", trunc as _),
+            ("nearbyintf", nearbyintf as _),
+            ("nearbyint", nearbyint as _),
+            ("memcpy", libc::memcpy as _),
+            ("memset", libc::memset as _),
+            ("memmove", libc::memmove as _),
+        ];
+
+        let mut symbols = ObjectSymbols {
+            symbols: HashMap::new(),
+        };
+        for (name, addr) in libcalls {
+            symbols.insert(*name, *addr);
+        }
+        symbols
+    }
+
+    /// Create a symbol table with no entries at all.
+    pub fn empty() -> Self {
+        ObjectSymbols {
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// Add a symbol, replacing any previous address for the same name.
+    pub fn insert(&mut self, name: impl Into<String>, addr: *const c_void) {
+        self.symbols.insert(name.into(), addr as usize);
+    }
+
+    /// Add a symbol, replacing any previous address for the same name.
+    pub fn with_symbol(mut self, name: impl Into<String>, addr: *const c_void) -> Self {
+        self.insert(name, addr);
+        self
+    }
+
+    /// Look up the address of a symbol.
+    pub fn get(&self, name: &str) -> Option<*const c_void> {
+        self.symbols.get(name).map(|addr| *addr as *const c_void)
+    }
+}
+
+impl Default for ObjectSymbols {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The memory an object's sections are loaded into.
+///
+/// This is a single anonymous mapping that is unmapped when dropped, so the code and data of an
+/// `ObjectModule` go away deterministically with the module.
+struct Mapping {
+    base: *mut u8,
+    len: usize,
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            munmap(self.base as *mut c_void, self.len).expect("unmapping object module");
+        }
+    }
+}
+
+/// A function defined by the object, used to describe addresses in `addr_details`.
+struct FunctionSymbol {
+    start: usize,
+    end: usize,
+    name: String,
+}
+
+/// A Lucet module loaded directly from the relocatable object that `lucetc` emits.
+///
+/// Unlike [`DlModule`](struct.DlModule.html), this does not use the system dynamic linker: the
+/// object's sections are mapped by the runtime, relocations are applied here, and undefined
+/// symbols are resolved only through the [`ObjectSymbols`](struct.ObjectSymbols.html) given when
+/// loading. The object is read from a byte buffer, and its memory is released as soon as the
+/// module is dropped.
+///
+/// Only x86-64 ELF objects are supported; others fail to load with
+/// `ObjectError::UnsupportedObject`.
+pub struct ObjectModule {
+    /// Metadata decoded from inside the mapping
+    module: lucet_module::Module<'static>,
+
+    /// The `funcref` for each function in the module
+    func_refs: Vec<TableElement>,
+
+    /// Functions defined by the object, sorted by address
+    functions: Vec<FunctionSymbol>,
+
+    /// The range of addresses holding the module's code
+    code: (usize, usize),
+
+    /// The image of the module's initial heap, once a region has asked for it
+    heap_image: HeapImageCell,
+
+    /// The loaded sections. This must outlive everything above, which points into it.
+    _mapping: Mapping,
+}
+
+// for the raw pointers in the mapping only
+unsafe impl Send for ObjectModule {}
+unsafe impl Sync for ObjectModule {}
+
+/// Size of a stub for a call to an imported function: `jmp *0(%rip)` followed by the address.
+const STUB_SIZE: usize = 16;
+
+/// Where each kind of section goes in the mapping.
+#[derive(Clone, Copy)]
+enum Segment {
+    Text,
+    ReadOnly,
+    Writable,
+}
+
+impl ObjectModule {
+    /// Create a module, loading code from a relocatable object on the filesystem.
+    pub fn load<P: AsRef<Path>>(obj_path: P, symbols: &ObjectSymbols) -> Result<Arc<Self>, Error> {
+        let bytes = std::fs::read(obj_path).map_err(ObjectError::Io)?;
+        Self::load_from_bytes(&bytes, symbols)
+    }
+
+    /// Create a module from the contents of a relocatable object.
+    ///
+    /// The contents are copied into memory owned by the module, so `bytes` need not outlive it.
+    pub fn load_from_bytes(bytes: &[u8], symbols: &ObjectSymbols) -> Result<Arc<Self>, Error> {
+        let obj = object::File::parse(bytes).map_err(ObjectError::Parse)?;
+        // the code is run as is and the relocations are applied as x86-64 ones
+        if obj.format() != BinaryFormat::Elf || obj.architecture() != Architecture::X86_64 {
+            return Err(ObjectError::UnsupportedObject(format!(
+                "{:?} object for {:?}; only x86-64 ELF objects are supported",
+                obj.format(),
+                obj.architecture()
+            ))
+            .into());
+        }
+        let page_size = host_page_size();
+
+        // Decide where each loaded section goes. Code, read-only data, and writable data are kept
+        // in separate page-aligned segments so that each can be given its own protection.
+        let mut sections = HashMap::new();
+        let mut segment_sizes = [0usize; 3];
+        for section in obj.sections() {
+            let segment = match section.kind() {
+                SectionKind::Text => Segment::Text,
+                SectionKind::ReadOnlyData | SectionKind::ReadOnlyString => Segment::ReadOnly,
+                SectionKind::Data | SectionKind::UninitializedData => Segment::Writable,
+                _ => continue,
+            };
+            let size = &mut segment_sizes[segment as usize];
+            let align = std::cmp::max(section.align() as usize, 1);
+            let offset = round_up(*size, align);
+            *size = offset + section.size() as usize;
+            sections.insert(section.index().0, (segment, offset));
+        }
+
+        // Imports get a stub in the text segment, so that calls to them can reach anywhere in the
+        // address space, and a GOT slot in the read-only segment. Defined symbols that are
+        // referenced through the GOT get a slot too.
+        let mut imports = Vec::new();
+        let mut import_ids = HashMap::new();
+        let mut got_ids = HashMap::new();
+        let mut symbol_table = HashMap::new();
+        for (index, symbol) in obj.symbols() {
+            if symbol.is_undefined() {
+                if let Some(name) = symbol.name().filter(|name| !name.is_empty()) {
+                    let addr = symbols
+                        .get(name)
+                        .ok_or_else(|| ObjectError::UndefinedSymbol(name.to_string()))?;
+                    import_ids.insert(index.0, imports.len());
+                    got_ids.insert(index.0, got_ids.len());
+                    imports.push(addr as usize);
+                }
+            }
+            symbol_table.insert(index.0, symbol);
+        }
+        for section in obj.sections() {
+            if !sections.contains_key(&section.index().0) {
+                continue;
+            }
+            for (_, reloc) in section.relocations() {
+                if let (RelocationKind::GotRelative, RelocationTarget::Symbol(index)) =
+                    (reloc.kind(), reloc.target())
+                {
+                    let next = got_ids.len();
+                    got_ids.entry(index.0).or_insert(next);
+                }
+            }
+        }
+
+        let stubs_offset = round_up(segment_sizes[Segment::Text as usize], STUB_SIZE);
+        segment_sizes[Segment::Text as usize] = stubs_offset + imports.len() * STUB_SIZE;
+        let got_offset = round_up(segment_sizes[Segment::ReadOnly as usize], 8);
+        segment_sizes[Segment::ReadOnly as usize] = got_offset + got_ids.len() * 8;
+
+        let mut segment_offsets = [0usize; 3];
+        let mut len = 0;
+        for (offset, size) in segment_offsets.iter_mut().zip(segment_sizes.iter()) {
+            *offset = len;
+            len += round_up(*size, page_size);
+        }
+        // an object with nothing to load still needs a valid mapping
+        let len = std::cmp::max(len, page_size);
+
+        let base = unsafe {
+            mmap(
+                ptr::null_mut(),
+                len,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_ANONYMOUS | MapFlags::MAP_PRIVATE,
+                0,
+                0,
+            )?
+        } as *mut u8;
+        let mapping = Mapping { base, len };
+
+        let section_addr = |index: usize| -> Option<usize> {
+            sections.get(&index).map(|(segment, offset)| {
+                base as usize + segment_offsets[*segment as usize] + offset
+            })
+        };
+        let stub_addr = |import: usize| -> usize {
+            base as usize
+                + segment_offsets[Segment::Text as usize]
+                + stubs_offset
+                + import * STUB_SIZE
+        };
+        let got_addr = |slot: usize| -> usize {
+            base as usize + segment_offsets[Segment::ReadOnly as usize] + got_offset + slot * 8
+        };
+        let symbol_name = |index: usize| -> String {
+            symbol_table
+                .get(&index)
+                .and_then(|symbol| symbol.name())
+                .unwrap_or("(no name)")
+                .to_string()
+        };
+        // The address a symbol is defined at, whether in this object or by an import.
+        let symbol_addr = |index: usize| -> Result<usize, ObjectError> {
+            if let Some(import) = import_ids.get(&index) {
+                return Ok(imports[*import]);
+            }
+            symbol_table
+                .get(&index)
+                .and_then(|symbol| {
+                    symbol
+                        .section_index()
+                        .and_then(|section| section_addr(section.0))
+                        .map(|addr| addr + symbol.address() as usize)
+                })
+                .ok_or_else(|| ObjectError::UndefinedSymbol(symbol_name(index)))
+        };
+
+        // Copy in the section contents, then the stubs and GOT entries.
+        for section in obj.sections() {
+            if let Some(addr) = section_addr(section.index().0) {
+                if section.kind() != SectionKind::UninitializedData {
+                    let data = section.data().map_err(ObjectError::Parse)?;
+                    unsafe {
+                        ptr::copy_nonoverlapping(data.as_ptr(), addr as *mut u8, data.len());
+                    }
+                }
+            }
+        }
+        for (import, addr) in imports.iter().enumerate() {
+            let stub = stub_addr(import) as *mut u8;
+            unsafe {
+                ptr::copy_nonoverlapping([0xff, 0x25, 0, 0, 0, 0].as_ptr(), stub, 6);
+                ptr::write_unaligned(stub.add(6) as *mut u64, *addr as u64);
+            }
+        }
+        for (index, slot) in got_ids.iter() {
+            let addr = symbol_addr(*index)?;
+            unsafe { ptr::write(got_addr(*slot) as *mut u64, addr as u64) };
+        }
+
+        // Apply the relocations of every loaded section.
+        for section in obj.sections() {
+            let section_base = match section_addr(section.index().0) {
+                Some(addr) => addr,
+                None => continue,
+            };
+            for (offset, reloc) in section.relocations() {
+                let place = section_base + offset as usize;
+                let (target, got_slot, name) = match reloc.target() {
+                    RelocationTarget::Symbol(index) => (
+                        symbol_addr(index.0)?,
+                        got_ids.get(&index.0).copied(),
+                        symbol_name(index.0),
+                    ),
+                    RelocationTarget::Section(index) => (
+                        section_addr(index.0).ok_or_else(|| {
+                            ObjectError::UnsupportedRelocation(format!(
+                                "relocation against unloaded section {}",
+                                index.0
+                            ))
+                        })?,
+                        None,
+                        format!("section {}", index.0),
+                    ),
+                };
+                let addend = if reloc.has_implicit_addend() {
+                    match reloc.size() {
+                        32 => unsafe { ptr::read_unaligned(place as *const i32) as i64 },
+                        64 => unsafe { ptr::read_unaligned(place as *const i64) },
+                        _ => 0,
+                    }
+                } else {
+                    reloc.addend()
+                };
+
+                match (reloc.kind(), reloc.size()) {
+                    (RelocationKind::Absolute, 64) => {
+                        let value = (target as i64).wrapping_add(addend);
+                        unsafe { ptr::write_unaligned(place as *mut i64, value) };
+                    }
+                    (RelocationKind::Relative, 64) => {
+                        let value = (target as i64)
+                            .wrapping_add(addend)
+                            .wrapping_sub(place as i64);
+                        unsafe { ptr::write_unaligned(place as *mut i64, value) };
+                    }
+                    (RelocationKind::Relative, 32) | (RelocationKind::PltRelative, 32) => {
+                        // calls to imports go through their stub, which is always within reach
+                        let target = match reloc.target() {
+                            RelocationTarget::Symbol(index) => import_ids
+                                .get(&index.0)
+                                .map(|import| stub_addr(*import))
+                                .unwrap_or(target),
+                            RelocationTarget::Section(_) => target,
+                        };
+                        write_rel32(place, target, addend, &name)?;
+                    }
+                    (RelocationKind::GotRelative, 32) => {
+                        let slot =
+                            got_slot.expect("GOT slots are assigned for every GOT relocation");
+                        write_rel32(place, got_addr(slot), addend, &name)?;
+                    }
+                    (kind, size) => {
+                        return Err(ObjectError::UnsupportedRelocation(format!(
+                            "{:?} ({} bits) for {}",
+                            kind, size, name
+                        ))
+                        .into());
+                    }
+                }
+            }
+        }
+
+        // Now that relocation is done, code becomes executable and the read-only data read-only.
+        for (segment, prot) in &[
+            (Segment::Text, ProtFlags::PROT_READ | ProtFlags::PROT_EXEC),
+            (Segment::ReadOnly, ProtFlags::PROT_READ),
+        ] {
+            let size = round_up(segment_sizes[*segment as usize], page_size);
+            if size > 0 {
+                unsafe {
+                    mprotect(
+                        base.add(segment_offsets[*segment as usize]) as *mut c_void,
+                        size,
+                        *prot,
+                    )?
+                };
+            }
+        }
+
+        let mut serialized_module = None;
+        let mut functions = Vec::new();
+        for (index, symbol) in symbol_table.iter() {
+            let name = match symbol.name() {
+                Some(name) if !name.is_empty() && !import_ids.contains_key(index) => name,
+                _ => continue,
+            };
+            if name == LUCET_MODULE_SYM {
+                serialized_module = Some(symbol_addr(*index)?);
+            } else if symbol.kind() == SymbolKind::Text {
+                let start = symbol_addr(*index)?;
+                functions.push(FunctionSymbol {
+                    start,
+                    end: start + symbol.size() as usize,
+                    name: name.to_string(),
+                });
+            }
+        }
+        functions.sort_by_key(|f| f.start);
+
+        let serialized_module = serialized_module
+            .ok_or_else(|| lucet_incorrect_module!("missing required symbol `lucet_module`"))?;
+        // The metadata refers to the mapping, which lives as long as the module. See the comment
+        // in `DlModule::load_and_maybe_verify` for why its lifetime is 'static.
+        let module =
+            unsafe { read_serialized_module(&*(serialized_module as *const SerializedModule))? };
+
+        let func_refs = build_func_refs(&module.module_data, module.function_manifest);
+
+        let code_start = base as usize + segment_offsets[Segment::Text as usize];
+        let code = (
+            code_start,
+            code_start + segment_sizes[Segment::Text as usize],
+        );
+
+        Ok(Arc::new(ObjectModule {
+            module,
+            func_refs,
+            functions,
+            code,
+            heap_image: HeapImageCell::default(),
+            _mapping: mapping,
+        }))
+    }
+}
+
+fn round_up(value: usize, align: usize) -> usize {
+    (value + align - 1) / align * align
+}
+
+fn write_rel32(place: usize, target: usize, addend: i64, name: &str) -> Result<(), ObjectError> {
+    let value = (target as i64)
+        .wrapping_add(addend)
+        .wrapping_sub(place as i64);
+    if value < std::i32::MIN as i64 || value > std::i32::MAX as i64 {
+        return Err(ObjectError::RelocationOutOfRange(name.to_string()));
+    }
+    unsafe { ptr::write_unaligned(place as *mut i32, value as i32) };
+    Ok(())
+}
+
+impl Module for ObjectModule {}
+
+impl ModuleInternal for ObjectModule {
+    fn is_instruction_count_instrumented(&self) -> bool {
+        self.module.module_data.features().instruction_count
+    }
+
+    fn heap_spec(&self) -> Option<&HeapSpec> {
+        self.module.module_data.heap_spec()
+    }
+
+    fn global_specs(&self) -> &[GlobalSpec<'_>] {
+        self.module.module_data.globals_spec()
+    }
+
+    fn get_sparse_page_data(&self, page: usize) -> Option<&[u8]> {
+        if let Some(ref sparse_data) = self.module.module_data.sparse_data() {
+            *sparse_data.get_page(page)
+        } else {
+            None
+        }
+    }
+
+    fn sparse_page_data_len(&self) -> usize {
+        self.module
+            .module_data
+            .sparse_data()
+            .map(|d| d.len())
+            .unwrap_or(0)
+    }
+
+    fn heap_image(&self) -> Option<Arc<HeapImage>> {
+        self.heap_image.get(self)
+    }
+
+    fn table_elements(&self) -> Result<&[TableElement], Error> {
+        match self.module.tables.get(0) {
+            Some(table) => Ok(table),
+            None => Err(lucet_incorrect_module!("table 0 is not present")),
+        }
+    }
+
+    fn table_specs(&self) -> &[TableSpec] {
+        self.module.module_data.tables()
+    }
+
+    fn func_refs(&self) -> &[TableElement] {
+        &self.func_refs
+    }
+
+    fn passive_data(&self) -> &[Option<&[u8]>] {
+        self.module.module_data.passive_data()
+    }
+
+    fn passive_elements(&self) -> &[Option<Vec<FunctionIndex>>] {
+        self.module.module_data.passive_elements()
+    }
+
+    fn global_base_data(&self) -> &[GlobalBaseSegment] {
+        self.module.module_data.global_base_data()
+    }
+
+    fn global_base_elements(&self) -> &[GlobalBaseSegment] {
+        self.module.module_data.global_base_elements()
+    }
+
+    fn get_export_func(&self, sym: &str) -> Result<FunctionHandle, Error> {
+        self.module
+            .module_data
+            .get_export_func_id(sym)
+            .ok_or_else(|| Error::SymbolNotFound(sym.to_string()))
+            .map(|id| {
+                let ptr = self.function_manifest()[id.as_u32() as usize].ptr();
+                FunctionHandle {
+                    ptr,
+                    id,
+                    is_start_func: false,
+                }
+            })
+    }
+
+    fn get_func_from_idx(&self, table_id: u32, func_id: u32) -> Result<FunctionHandle, Error> {
+        let id = self
+            .table_specs()
+            .get(table_id as usize)
+            .filter(|table| table.elem_ty == ValueType::FuncRef)
+            .and_then(|table| table.elements.get(func_id as usize))
+            .and_then(|element| *element)
+            .ok_or(Error::FuncNotFound(table_id, func_id))?;
+
+        Ok(FunctionHandle {
+            ptr: self.function_manifest()[id.as_u32() as usize].ptr(),
+            id,
+            is_start_func: false,
+        })
+    }
+
+    fn get_start_func(&self) -> Result<Option<FunctionHandle>, Error> {
+        Ok(self.module.module_data.get_start_func_id().map(|id| {
+            let ptr = self.function_manifest()[id.as_u32() as usize].ptr();
+            FunctionHandle {
+                ptr,
+                id,
+                is_start_func: true,
+            }
+        }))
+    }
+
+    fn function_manifest(&self) -> &[FunctionSpec] {
+        self.module.function_manifest
+    }
+
+    fn addr_details(&self, addr: *const c_void) -> Result<Option<AddrDetails>, Error> {
+        let addr = addr as usize;
+        let (code_start, code_end) = self.code;
+        if addr < code_start || addr >= code_end {
+            return Ok(None);
+        }
+        let sym_name = self
+            .functions
+            .iter()
+            .find(|f| f.start <= addr && addr < f.end)
+            .map(|f| f.name.clone());
+        Ok(Some(AddrDetails {
+            in_module_code: true,
+            file_name: None,
+            sym_name,
+        }))
+    }
+
+    fn get_signature(&self, fn_id: FunctionIndex) -> &Signature {
+        self.module.module_data.get_signature(fn_id)
+    }
+
+    fn get_function_name(&self, fn_id: FunctionIndex) -> Option<&str> {
+        self.module.module_data.get_function_name(fn_id)
+    }
+
+    fn version(&self) -> &VersionInfo {
+        &self.module.version
+    }
+
+    fn module_data(&self) -> &ModuleData<'_> {
+        &self.module.module_data
+    }
+}
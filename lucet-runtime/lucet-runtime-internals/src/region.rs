@@ -6,10 +6,13 @@ pub mod uffd;
 use crate::alloc::{Alloc, AllocStrategy, Limits, Slot};
 use crate::embed_ctx::CtxMap;
 use crate::error::Error;
-use crate::instance::InstanceHandle;
+use crate::instance::{GlobalImports, InstanceHandle, InstanceSnapshot};
+use crate::linker::Linker;
 use crate::module::Module;
+use crate::val::Val;
 use std::any::Any;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// A memory region in which Lucet instances are created and run.
 ///
@@ -26,6 +29,38 @@ pub trait Region: RegionInternal {
         self.new_instance_builder(module).build()
     }
 
+    /// Create a new instance within the region from a snapshot taken by
+    /// [`Instance::snapshot()`](struct.Instance.html#method.snapshot).
+    ///
+    /// `module` must be the same module the snapshot was taken of, though it may have been loaded
+    /// by another process. Calling `region.restore_instance(module, snapshot)` is shorthand for
+    /// `region.new_instance_builder(module).restore(snapshot)`, which can be used instead to
+    /// provide embedder contexts or imported globals.
+    ///
+    /// # Safety
+    ///
+    /// The snapshot must have been taken by `Instance::snapshot()` and not modified since; the
+    /// stack and registers of a yielded instance are resumed as they are in the snapshot, so a
+    /// snapshot from an untrusted source can run arbitrary code.
+    ///
+    /// Restoring a yielded instance in another process is only sound if that process is running
+    /// the same build of the host program, and the hostcall that yielded does not hold any
+    /// pointers to host memory outside the instance, such as to a `Box` it allocated, across the
+    /// yield. The stack of the call is relocated to the new instance, but such pointers cannot be.
+    ///
+    /// Relocation is a heuristic: every word on the stack and in the registers that falls within
+    /// the old instance's accessible heap, stack, globals, tables, or the code of the module or
+    /// host is taken to be a pointer and rewritten. A guest or hostcall value that merely happens
+    /// to look like such an address, such as an integer computed from a pointer, is rewritten
+    /// too, so the resumed call may see a different value than it saved.
+    unsafe fn restore_instance(
+        &self,
+        module: Arc<dyn Module>,
+        snapshot: &InstanceSnapshot,
+    ) -> Result<InstanceHandle, Error> {
+        self.new_instance_builder(module).restore(snapshot)
+    }
+
     /// Return an [`InstanceBuilder`](struct.InstanceBuilder.html) for the given module.
     fn new_instance_builder<'a>(&'a self, module: Arc<dyn Module>) -> InstanceBuilder<'a> {
         InstanceBuilder::new(self.as_dyn_internal(), module)
@@ -46,7 +81,18 @@ pub trait Region: RegionInternal {
     fn used_slots(&self) -> usize;
 
     /// Return the total instance slot capacity of the region.
+    ///
+    /// For regions created with an [`ElasticCapacity`](struct.ElasticCapacity.html), this is the
+    /// number of slots currently mapped, which changes as the region grows and shrinks.
     fn capacity(&self) -> usize;
+
+    /// Release any chunks of slots that have been idle for longer than the region's idle timeout,
+    /// returning the number of slots released.
+    ///
+    /// Elastic regions check for idle chunks whenever an instance is created or dropped, so this
+    /// only needs to be called to shrink a region that has otherwise gone quiet. Regions with a
+    /// fixed capacity never release slots.
+    fn release_idle_slots(&self) -> usize;
 }
 
 /// A `RegionInternal` is a collection of `Slot`s which are managed as a whole.
@@ -57,6 +103,7 @@ pub trait RegionInternal: Send + Sync {
         embed_ctx: CtxMap,
         heap_memory_size_limit: usize,
         alloc_strategy: AllocStrategy,
+        global_imports: GlobalImports,
     ) -> Result<InstanceHandle, Error>;
 
     /// Unmaps the heap, stack, and globals of an `Alloc`, while retaining the virtual address
@@ -85,6 +132,157 @@ pub trait RegionCreate: Region {
     /// Create a new `Region` that can support a given number instances, each subject to the same
     /// runtime limits.
     fn create(instance_capacity: usize, limits: &Limits) -> Result<Arc<Self>, Error>;
+
+    /// Create a new `Region` whose capacity grows and shrinks within the given bounds, with each
+    /// instance subject to the same runtime limits.
+    fn create_elastic(capacity: &ElasticCapacity, limits: &Limits) -> Result<Arc<Self>, Error>;
+}
+
+/// The bounds within which an elastic region's instance capacity can change.
+///
+/// An elastic region starts out with `min_capacity` slots. When an instance is created and no slots
+/// are free, the region maps up to `chunk_size` new slots, as long as that does not take it past
+/// `max_capacity`. Once at least `chunk_size` slots have been free for `idle_timeout`, that many
+/// slots can be released again, as long as that does not take the region below `min_capacity`.
+///
+/// There is no timer behind `idle_timeout`: idle chunks are only checked for when an instance is
+/// created or dropped, or when
+/// [`Region::release_idle_slots()`](trait.Region.html#method.release_idle_slots) is called. A
+/// region that goes quiet keeps its slots until one of those happens.
+#[derive(Clone, Debug)]
+pub struct ElasticCapacity {
+    /// The number of slots the region starts out with, and never shrinks below.
+    pub min_capacity: usize,
+    /// The number of slots the region never grows beyond.
+    pub max_capacity: usize,
+    /// The number of slots the region maps or releases at once.
+    pub chunk_size: usize,
+    /// How long a chunk's worth of slots must go unused before it can be released, the next time
+    /// the region checks for idle chunks.
+    pub idle_timeout: Duration,
+}
+
+impl ElasticCapacity {
+    /// The default number of slots mapped or released at once.
+    pub const DEFAULT_CHUNK_SIZE: usize = 16;
+
+    /// The default time a chunk of slots must go unused before it is released.
+    pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Bounds for a region that holds between `min_capacity` and `max_capacity` instances.
+    pub fn new(min_capacity: usize, max_capacity: usize) -> Self {
+        ElasticCapacity {
+            min_capacity,
+            max_capacity,
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            idle_timeout: Self::DEFAULT_IDLE_TIMEOUT,
+        }
+    }
+
+    /// Bounds for a region whose capacity never changes.
+    pub fn fixed(capacity: usize) -> Self {
+        Self::new(capacity, capacity)
+    }
+
+    /// Map or release `chunk_size` slots at once.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Allow a chunk of slots to be released once it has gone unused for `idle_timeout`.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Check that the bounds describe a region that can hold at least one instance.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_capacity == 0 {
+            return Err(Error::InvalidArgument(
+                "region must be able to hold at least one instance",
+            ));
+        }
+        if self.min_capacity > self.max_capacity {
+            return Err(Error::InvalidArgument(
+                "minimum capacity must not exceed maximum capacity",
+            ));
+        }
+        if self.chunk_size == 0 {
+            return Err(Error::InvalidArgument(
+                "chunk size must be greater than zero",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Bookkeeping for a region's current capacity, shared by the region implementations.
+///
+/// This must be kept behind the same lock as the region's freelist, so that the capacity and the
+/// number of free slots stay consistent with each other.
+pub(crate) struct ElasticState {
+    bounds: ElasticCapacity,
+    capacity: usize,
+    idle_since: Option<Instant>,
+}
+
+impl ElasticState {
+    pub(crate) fn new(bounds: ElasticCapacity) -> Self {
+        ElasticState {
+            capacity: bounds.min_capacity,
+            bounds,
+            idle_since: None,
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Return the number of slots the region should map, given its number of free slots.
+    ///
+    /// The new slots are counted towards the capacity immediately, so the caller must map them or
+    /// fail.
+    pub(crate) fn grow(&mut self, free_slots: usize) -> usize {
+        if free_slots > 0 {
+            return 0;
+        }
+        let count = self
+            .bounds
+            .chunk_size
+            .min(self.bounds.max_capacity - self.capacity);
+        self.capacity += count;
+        count
+    }
+
+    /// Undo a call to `grow()` for slots that could not be mapped.
+    pub(crate) fn grow_failed(&mut self, unmapped: usize) {
+        self.capacity -= unmapped;
+    }
+
+    /// Return the number of free slots the region should release at time `now`.
+    ///
+    /// The released slots are removed from the capacity immediately, so the caller must release
+    /// them.
+    pub(crate) fn shrink(&mut self, free_slots: usize, now: Instant) -> usize {
+        let count = self
+            .bounds
+            .chunk_size
+            .min(self.capacity - self.bounds.min_capacity);
+        if count == 0 || free_slots < count {
+            // there is no idle chunk, so restart the clock the next time there is one
+            self.idle_since = None;
+            return 0;
+        }
+        let idle_since = *self.idle_since.get_or_insert(now);
+        if now.duration_since(idle_since) < self.bounds.idle_timeout {
+            return 0;
+        }
+        self.idle_since = None;
+        self.capacity -= count;
+        count
+    }
 }
 
 /// A builder for instances; created by
@@ -95,6 +293,11 @@ pub struct InstanceBuilder<'a> {
     embed_ctx: CtxMap,
     heap_memory_size_limit: usize,
     alloc_strategy: AllocStrategy,
+    global_imports: GlobalImports,
+    timeout: Option<Duration>,
+    max_nested_calls: Option<u32>,
+    cpu_stats: bool,
+    linker: Option<&'a Linker>,
 }
 
 impl<'a> InstanceBuilder<'a> {
@@ -105,6 +308,11 @@ impl<'a> InstanceBuilder<'a> {
             embed_ctx: CtxMap::default(),
             heap_memory_size_limit: region.get_limits().heap_memory_size,
             alloc_strategy: AllocStrategy::Linear,
+            global_imports: GlobalImports::new(),
+            timeout: None,
+            max_nested_calls: None,
+            cpu_stats: false,
+            linker: None,
         }
     }
 
@@ -137,13 +345,82 @@ impl<'a> InstanceBuilder<'a> {
         self
     }
 
+    /// Provide the value of the global that the module imports as `field` from `module`.
+    ///
+    /// Every global the module imports must be given a value of the type it was declared with, or
+    /// building the instance fails. The value is used again each time the instance is reset. If a
+    /// value was already provided for the same import, it is replaced by the new value.
+    pub fn with_global_import(mut self, module: &str, field: &str, val: Val) -> Self {
+        self.global_imports
+            .insert((module.to_owned(), field.to_owned()), val);
+        self
+    }
+
+    /// Terminate the built instance if any single run or resume takes longer than `timeout`.
+    ///
+    /// See [`Instance::set_timeout()`](struct.Instance.html#method.set_timeout).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Limit how many calls from hostcalls back into the guest may be in progress at once in the
+    /// built instance.
+    ///
+    /// See [`Instance::set_max_nested_calls()`](struct.Instance.html#method.set_max_nested_calls).
+    pub fn with_max_nested_calls(mut self, max: u32) -> Self {
+        self.max_nested_calls = Some(max);
+        self
+    }
+
+    /// Measure the CPU time the built instance spends in guest code and in hostcalls.
+    ///
+    /// See [`Instance::set_cpu_stats()`](struct.Instance.html#method.set_cpu_stats).
+    pub fn with_cpu_stats(mut self) -> Self {
+        self.cpu_stats = true;
+        self
+    }
+
+    /// Bind the imported functions of the built instance to the host functions in `linker`.
+    ///
+    /// This is required for modules compiled with linked imports that import any functions, and
+    /// building the instance fails if the linker does not provide every one of them with a
+    /// matching signature. Modules compiled without linked imports ignore the linker.
+    pub fn with_linker(mut self, linker: &'a Linker) -> Self {
+        self.linker = Some(linker);
+        self
+    }
+
     /// Build the instance.
     pub fn build(self) -> Result<InstanceHandle, Error> {
-        self.region.new_instance_with(
+        let mut inst = self.region.new_instance_with(
             self.module,
             self.embed_ctx,
             self.heap_memory_size_limit,
             self.alloc_strategy,
-        )
+            self.global_imports,
+        )?;
+        inst.set_timeout(self.timeout);
+        if let Some(max) = self.max_nested_calls {
+            inst.set_max_nested_calls(max);
+        }
+        inst.set_cpu_stats(self.cpu_stats);
+        inst.bind_imports(self.linker)?;
+        Ok(inst)
+    }
+
+    /// Build the instance, and then overwrite it with the contents of a snapshot.
+    ///
+    /// The snapshot must have been taken of an instance of the same module. See
+    /// [`Region::restore_instance()`](trait.Region.html#method.restore_instance).
+    ///
+    /// # Safety
+    ///
+    /// The same requirements apply as for
+    /// [`Region::restore_instance()`](trait.Region.html#method.restore_instance).
+    pub unsafe fn restore(self, snapshot: &InstanceSnapshot) -> Result<InstanceHandle, Error> {
+        let mut inst = self.build()?;
+        inst.restore_snapshot(snapshot)?;
+        Ok(inst)
     }
 }
@@ -1,9 +1,9 @@
 use crate::alloc::{instance_heap_offset, Alloc, AllocStrategy, Limits, Slot};
 use crate::embed_ctx::CtxMap;
 use crate::error::Error;
-use crate::instance::{new_instance_handle, Instance, InstanceHandle};
+use crate::instance::{new_instance_handle, GlobalImports, Instance, InstanceHandle};
 use crate::module::Module;
-use crate::region::{Region, RegionCreate, RegionInternal};
+use crate::region::{ElasticCapacity, ElasticState, Region, RegionCreate, RegionInternal};
 use crate::sysdeps::host_page_size;
 use libc::c_void;
 #[cfg(not(target_os = "linux"))]
@@ -11,6 +11,7 @@ use libc::memset;
 use nix::sys::mman::{madvise, mmap, munmap, MapFlags, MmapAdvise, ProtFlags};
 use std::ptr;
 use std::sync::{Arc, RwLock, Weak};
+use std::time::Instant;
 
 /// A [`Region`](../trait.Region.html) backed by `mmap`.
 ///
@@ -35,6 +36,13 @@ use std::sync::{Arc, RwLock, Weak};
 /// 0x0XXX: |  ...                  |
 /// 0x0XXX: |  .globals    = 0xM000 | <-- InstanceRuntimeData
 /// 0x0XXX: |  .inst_count = 0x0000 |
+/// 0x0XXX: |  .fuel_limit          |
+/// 0x0XXX: |  .epoch_ptr           |
+/// 0x0XXX: |  .epoch_deadline      |
+/// 0x0XXX: |  .heap_len            |
+/// 0x0XXX: |  .tables_ptr          |
+/// 0x0XXX: |  .import_funcs_ptr    |
+/// 0x0XXX: |  .import_data         |
 /// 0x1000: +-----------------------+ <-- Heap, and `lucet_vmctx`. One page into the allocation.
 /// 0x1XXX: |                       |
 /// 0xXXXX: ~  .......heap.......   ~ // heap size is governed by limits.heap_address_space_size
@@ -53,24 +61,40 @@ use std::sync::{Arc, RwLock, Weak};
 /// 0xSXXX: |  ......sigstack....   | // sigstack is governed by limits.signal_stack_size
 /// 0xSXXX: +-----------------------|
 /// ```
+///
+/// Each slot is mapped separately, so a region created with
+/// [`MmapRegion::create_elastic()`](struct.MmapRegion.html#method.create_elastic) can map and
+/// unmap slots individually as its capacity changes.
 pub struct MmapRegion {
-    capacity: usize,
-    freelist: RwLock<Vec<Slot>>,
+    slots: RwLock<MmapSlots>,
     limits: Limits,
     min_heap_alignment: usize,
 }
 
+/// The free slots of an `MmapRegion`, along with the bookkeeping for its capacity.
+struct MmapSlots {
+    free: Vec<Slot>,
+    elastic: ElasticState,
+    /// The region these slots belong to, used to create new slots as the region grows.
+    region: Weak<MmapRegion>,
+}
+
 impl Region for MmapRegion {
     fn free_slots(&self) -> usize {
-        self.freelist.read().unwrap().len()
+        self.slots.read().unwrap().free.len()
     }
 
     fn used_slots(&self) -> usize {
-        self.capacity() - self.free_slots()
+        let slots = self.slots.read().unwrap();
+        slots.elastic.capacity() - slots.free.len()
     }
 
     fn capacity(&self) -> usize {
-        self.capacity
+        self.slots.read().unwrap().elastic.capacity()
+    }
+
+    fn release_idle_slots(&self) -> usize {
+        MmapRegion::release_idle(&mut self.slots.write().unwrap())
     }
 }
 
@@ -81,6 +105,7 @@ impl RegionInternal for MmapRegion {
         embed_ctx: CtxMap,
         heap_memory_size_limit: usize,
         mut alloc_strategy: AllocStrategy,
+        global_imports: GlobalImports,
     ) -> Result<InstanceHandle, Error> {
         let limits = self.get_limits();
 
@@ -90,9 +115,11 @@ impl RegionInternal for MmapRegion {
         // for this new instance.
         let slot;
         {
-            let mut free_slot_vector = self.freelist.write().unwrap();
-            let slot_index = alloc_strategy.next(free_slot_vector.len(), self.capacity)?;
-            slot = free_slot_vector.swap_remove(slot_index);
+            let mut slots = self.slots.write().unwrap();
+            MmapRegion::grow(&mut slots)?;
+            let slot_index = alloc_strategy.next(slots.free.len(), slots.elastic.capacity())?;
+            slot = slots.free.swap_remove(slot_index);
+            MmapRegion::release_idle(&mut slots);
         }
 
         assert_eq!(
@@ -140,7 +167,7 @@ impl RegionInternal for MmapRegion {
 
         // Though this is a potential early return from the function, the Drop impl
         // on the Alloc will put the slot back on the freelist.
-        let inst = new_instance_handle(inst_ptr, module, alloc, embed_ctx)?;
+        let inst = new_instance_handle(inst_ptr, module, alloc, embed_ctx, global_imports)?;
 
         Ok(inst)
     }
@@ -155,10 +182,25 @@ impl RegionInternal for MmapRegion {
             panic!("heap is not page-aligned");
         }
 
-        // clear and disable access to the heap, stack, globals, and sigstack
+        // Replace the heap with fresh inaccessible memory. Part of it may be mapped from the
+        // module's heap image, which must not be left in place for the slot's next instance. We
+        // don't ever shrink the heap, so we only need to replace up until the accessible size.
+        if alloc.heap_accessible_size > 0 {
+            unsafe {
+                mmap(
+                    slot.heap,
+                    alloc.heap_accessible_size,
+                    ProtFlags::PROT_NONE,
+                    MapFlags::MAP_ANON | MapFlags::MAP_PRIVATE | MapFlags::MAP_FIXED,
+                    -1,
+                    0,
+                )
+                .expect("mmap succeeds during drop");
+            }
+        }
+
+        // clear and disable access to the stack, globals, and sigstack
         for (ptr, len) in [
-            // We don't ever shrink the heap, so we only need to zero up until the accessible size
-            (slot.heap, alloc.heap_accessible_size),
             (slot.stack, slot.limits.stack_size),
             (slot.globals, slot.limits.globals_size),
             (slot.sigstack, slot.limits.signal_stack_size),
@@ -180,7 +222,9 @@ impl RegionInternal for MmapRegion {
             }
         }
 
-        self.freelist.write().unwrap().push(slot);
+        let mut slots = self.slots.write().unwrap();
+        slots.free.push(slot);
+        MmapRegion::release_idle(&mut slots);
     }
 
     fn expand_heap(&self, slot: &Slot, start: u32, len: u32) -> Result<(), Error> {
@@ -236,8 +280,18 @@ impl RegionInternal for MmapRegion {
         alloc.heap_accessible_size = initial_size;
         alloc.heap_inaccessible_size = alloc.slot().limits.heap_address_space_size - initial_size;
 
-        // Initialize the heap using the module sparse page data. There cannot be more pages in the
-        // sparse page data than will fit in the initial heap size.
+        // If the module has a heap image, map it over the start of the heap, so the pages are
+        // shared copy-on-write with the module's other instances. This replaces anything an earlier
+        // run of this instance wrote there.
+        if let Some(image) = module.heap_image() {
+            if image.len() <= initial_size {
+                unsafe { image.map_into(heap)? };
+                return Ok(());
+            }
+        }
+
+        // Otherwise, initialize the heap using the module sparse page data. There cannot be more
+        // pages in the sparse page data than will fit in the initial heap size.
         //
         // Pages with a corresponding Some entry in the sparse page data are initialized with
         // the contents of that data.
@@ -280,7 +334,7 @@ impl RegionInternal for MmapRegion {
 
 impl Drop for MmapRegion {
     fn drop(&mut self) {
-        for slot in self.freelist.get_mut().unwrap().drain(0..) {
+        for slot in self.slots.get_mut().unwrap().free.drain(0..) {
             Self::free_slot(slot);
         }
     }
@@ -292,6 +346,10 @@ impl RegionCreate for MmapRegion {
     fn create(instance_capacity: usize, limits: &Limits) -> Result<Arc<Self>, Error> {
         MmapRegion::create(instance_capacity, limits)
     }
+
+    fn create_elastic(capacity: &ElasticCapacity, limits: &Limits) -> Result<Arc<Self>, Error> {
+        MmapRegion::create_elastic(capacity, limits)
+    }
 }
 
 impl MmapRegion {
@@ -303,20 +361,23 @@ impl MmapRegion {
     pub fn create(instance_capacity: usize, limits: &Limits) -> Result<Arc<Self>, Error> {
         limits.validate()?;
 
-        let region = Arc::new(MmapRegion {
-            capacity: instance_capacity,
-            freelist: RwLock::new(Vec::with_capacity(instance_capacity)),
-            limits: limits.clone(),
-            min_heap_alignment: 0, // No constaints on heap alignment by default
-        });
-        {
-            let mut freelist = region.freelist.write().unwrap();
-            for _ in 0..instance_capacity {
-                freelist.push(MmapRegion::create_slot(&region)?);
-            }
-        }
+        // No constaints on heap alignment by default
+        MmapRegion::create_with(ElasticCapacity::fixed(instance_capacity), limits, 0)
+    }
 
-        Ok(region)
+    /// Create a new `MmapRegion` whose capacity grows and shrinks within the given bounds, with
+    /// each instance subject to the same runtime limits.
+    ///
+    /// The region starts out with `capacity.min_capacity` slots. See
+    /// [`ElasticCapacity`](struct.ElasticCapacity.html) for how slots are added and released.
+    ///
+    /// The region is returned in an `Arc`, because any instances created from it carry a reference
+    /// back to the region.
+    pub fn create_elastic(capacity: &ElasticCapacity, limits: &Limits) -> Result<Arc<Self>, Error> {
+        limits.validate()?;
+        capacity.validate()?;
+
+        MmapRegion::create_with(capacity.clone(), limits, 0)
     }
 
     /// Create a new `MmapRegion` that can support a given number instances, each subject to the
@@ -340,22 +401,73 @@ impl MmapRegion {
             ));
         }
 
+        MmapRegion::create_with(
+            ElasticCapacity::fixed(instance_capacity),
+            limits,
+            heap_alignment,
+        )
+    }
+
+    fn create_with(
+        capacity: ElasticCapacity,
+        limits: &Limits,
+        heap_alignment: usize,
+    ) -> Result<Arc<Self>, Error> {
+        let min_capacity = capacity.min_capacity;
         let region = Arc::new(MmapRegion {
-            capacity: instance_capacity,
-            freelist: RwLock::new(Vec::with_capacity(instance_capacity)),
+            slots: RwLock::new(MmapSlots {
+                free: Vec::with_capacity(min_capacity),
+                elastic: ElasticState::new(capacity),
+                region: Weak::new(),
+            }),
             limits: limits.clone(),
             min_heap_alignment: heap_alignment,
         });
         {
-            let mut freelist = region.freelist.write().unwrap();
-            for _ in 0..instance_capacity {
-                freelist.push(MmapRegion::create_slot(&region)?);
+            let mut slots = region.slots.write().unwrap();
+            slots.region = Arc::downgrade(&region);
+            for _ in 0..min_capacity {
+                slots.free.push(MmapRegion::create_slot(&region)?);
             }
         }
 
         Ok(region)
     }
 
+    /// Map a new chunk of slots if there are no free slots and the region has room to grow.
+    fn grow(slots: &mut MmapSlots) -> Result<(), Error> {
+        let count = slots.elastic.grow(slots.free.len());
+        if count == 0 {
+            return Ok(());
+        }
+        let region = slots
+            .region
+            .upgrade()
+            .expect("region exists while creating instances");
+        for created in 0..count {
+            match MmapRegion::create_slot(&region) {
+                Ok(slot) => slots.free.push(slot),
+                Err(e) => {
+                    slots.elastic.grow_failed(count - created);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Unmap a chunk of free slots if they have been idle for long enough.
+    ///
+    /// Slots are returned to the back of the freelist, so the ones at the front have usually been
+    /// free the longest, and are released first.
+    fn release_idle(slots: &mut MmapSlots) -> usize {
+        let count = slots.elastic.shrink(slots.free.len(), Instant::now());
+        for slot in slots.free.drain(0..count) {
+            MmapRegion::free_slot(slot);
+        }
+        count
+    }
+
     fn create_slot(region: &Arc<MmapRegion>) -> Result<Slot, Error> {
         // get the chunk of virtual memory that the `Slot` will manage
         let mem = if region.min_heap_alignment == 0 {
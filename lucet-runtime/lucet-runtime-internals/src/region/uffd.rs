@@ -1,9 +1,11 @@
 use crate::alloc::{instance_heap_offset, AddrLocation, Alloc, AllocStrategy, Limits, Slot};
 use crate::embed_ctx::CtxMap;
 use crate::error::Error;
-use crate::instance::{new_instance_handle, Instance, InstanceHandle, InstanceInternal};
+use crate::instance::{
+    new_instance_handle, GlobalImports, Instance, InstanceHandle, InstanceInternal,
+};
 use crate::module::Module;
-use crate::region::{Region, RegionCreate, RegionInternal};
+use crate::region::{ElasticCapacity, ElasticState, Region, RegionCreate, RegionInternal};
 use crate::sysdeps::host_page_size;
 use crate::WASM_PAGE_SIZE;
 use crate::{lucet_bail, lucet_ensure, lucet_format_err};
@@ -14,6 +16,7 @@ use std::os::unix::io::{AsRawFd, RawFd};
 use std::ptr;
 use std::sync::{Arc, Mutex, Weak};
 use std::thread::{self, JoinHandle};
+use std::time::Instant;
 use userfaultfd::{IoctlFlags, Uffd, UffdBuilder};
 
 /// A [`Region`](trait.Region.html) backed by `mmap` and managed by `userfaultfd`.
@@ -41,17 +44,33 @@ use userfaultfd::{IoctlFlags, Uffd, UffdBuilder};
 /// the backing physical memory. This ends up causing the guest thread to raise a SIGBUS, which is
 /// treated as a fatal error by the Lucet signal handler.
 ///
+/// A region created with [`UffdRegion::create_elastic()`](struct.UffdRegion.html#method.create_elastic)
+/// reserves and registers the address space for its maximum capacity up front, as the slots must be
+/// contiguous. Slots are only set up within that space as the region grows, and their memory is
+/// returned to the system as it shrinks.
+///
 /// [userfaultfd]: http://man7.org/linux/man-pages/man2/userfaultfd.2.html
 pub struct UffdRegion {
     uffd: Arc<Uffd>,
     start: *mut c_void,
     limits: Limits,
-    freelist: Mutex<Vec<Slot>>,
-    instance_capacity: usize,
+    slots: Mutex<UffdSlots>,
+    /// The number of slots reserved in the address space, which the capacity never exceeds.
+    max_capacity: usize,
     handler: Option<JoinHandle<Result<(), Error>>>,
     handler_pipe: RawFd,
 }
 
+/// The free slots of a `UffdRegion`, along with the bookkeeping for its capacity.
+struct UffdSlots {
+    free: Vec<Slot>,
+    elastic: ElasticState,
+    /// Indices of the reserved slots that are not currently set up.
+    unmapped: Vec<usize>,
+    /// The region these slots belong to, used to create new slots as the region grows.
+    region: Weak<UffdRegion>,
+}
+
 // the start pointer prevents these from auto-deriving
 unsafe impl Send for UffdRegion {}
 unsafe impl Sync for UffdRegion {}
@@ -138,6 +157,8 @@ fn uffd_handler(
                     lucet_bail!("instance magic incorrect");
                 }
 
+                inst.stats.record_page_fault();
+
                 let alloc = inst.alloc();
                 let loc = alloc.addr_location(fault_addr as *const c_void);
                 match loc {
@@ -178,15 +199,20 @@ fn uffd_handler(
 
 impl Region for UffdRegion {
     fn free_slots(&self) -> usize {
-        self.freelist.lock().unwrap().len()
+        self.slots.lock().unwrap().free.len()
     }
 
     fn used_slots(&self) -> usize {
-        self.capacity() - self.free_slots()
+        let slots = self.slots.lock().unwrap();
+        slots.elastic.capacity() - slots.free.len()
     }
 
     fn capacity(&self) -> usize {
-        self.instance_capacity
+        self.slots.lock().unwrap().elastic.capacity()
+    }
+
+    fn release_idle_slots(&self) -> usize {
+        self.release_idle(&mut self.slots.lock().unwrap())
     }
 }
 
@@ -197,6 +223,7 @@ impl RegionInternal for UffdRegion {
         embed_ctx: CtxMap,
         heap_memory_size_limit: usize,
         mut alloc_strategy: AllocStrategy,
+        global_imports: GlobalImports,
     ) -> Result<InstanceHandle, Error> {
         let limits = self.get_limits();
         module.validate_runtime_spec(&limits, heap_memory_size_limit)?;
@@ -205,9 +232,11 @@ impl RegionInternal for UffdRegion {
         // for this new instance.
         let slot;
         {
-            let mut free_slot_vector = self.freelist.lock().unwrap();
-            let slot_index = alloc_strategy.next(free_slot_vector.len(), self.capacity())?;
-            slot = free_slot_vector.swap_remove(slot_index);
+            let mut slots = self.slots.lock().unwrap();
+            UffdRegion::grow(&mut slots)?;
+            let slot_index = alloc_strategy.next(slots.free.len(), slots.elastic.capacity())?;
+            slot = slots.free.swap_remove(slot_index);
+            self.release_idle(&mut slots);
         }
 
         assert_eq!(
@@ -257,7 +286,7 @@ impl RegionInternal for UffdRegion {
             region,
         };
 
-        let inst = new_instance_handle(inst_ptr, module, alloc, embed_ctx)?;
+        let inst = new_instance_handle(inst_ptr, module, alloc, embed_ctx, global_imports)?;
 
         Ok(inst)
     }
@@ -280,7 +309,9 @@ impl RegionInternal for UffdRegion {
             madvise(ptr, len, MmapAdvise::MADV_DONTNEED).expect("madvise succeeds during drop");
         }
 
-        self.freelist.lock().unwrap().push(slot);
+        let mut slots = self.slots.lock().unwrap();
+        slots.free.push(slot);
+        self.release_idle(&mut slots);
     }
 
     fn expand_heap(&self, _slot: &Slot, _start: u32, _len: u32) -> Result<(), Error> {
@@ -326,6 +357,10 @@ impl RegionCreate for UffdRegion {
     fn create(instance_capacity: usize, limits: &Limits) -> Result<Arc<Self>, Error> {
         UffdRegion::create(instance_capacity, limits, WasmPageSizedUffdStrategy {})
     }
+
+    fn create_elastic(capacity: &ElasticCapacity, limits: &Limits) -> Result<Arc<Self>, Error> {
+        UffdRegion::create_elastic(capacity, limits, WasmPageSizedUffdStrategy {})
+    }
 }
 
 impl Drop for UffdRegion {
@@ -349,7 +384,7 @@ impl Drop for UffdRegion {
         // close the send end of the pipe; the handler closes the other end
         nix::unistd::close(self.handler_pipe).expect("close handler exit pipe");
 
-        let total_region_size = self.instance_capacity * self.limits.total_memory_size();
+        let total_region_size = self.max_capacity * self.limits.total_memory_size();
         unsafe {
             munmap(self.start, total_region_size).expect("unmapping region");
         }
@@ -374,12 +409,29 @@ impl UffdRegion {
         limits: &Limits,
         strategy: impl UffdStrategy,
     ) -> Result<Arc<Self>, Error> {
-        if instance_capacity == 0 {
-            return Err(Error::InvalidArgument(
-                "region must be able to hold at least one instance",
-            ));
-        }
+        UffdRegion::create_elastic(&ElasticCapacity::fixed(instance_capacity), limits, strategy)
+    }
+
+    /// Create a new `UffdRegion` whose capacity grows and shrinks within the given bounds, with
+    /// each instance subject to the same runtime limits.
+    ///
+    /// The region starts out with `capacity.min_capacity` slots, but reserves address space for
+    /// `capacity.max_capacity`. See [`ElasticCapacity`](struct.ElasticCapacity.html) for how slots
+    /// are added and released.
+    ///
+    /// The region is returned in an `Arc`, because any instances created from it carry a reference
+    /// back to the region.
+    ///
+    /// This also creates and starts a separate thread that is responsible for handling page faults
+    /// that occur within the memory region.
+    pub fn create_elastic(
+        capacity: &ElasticCapacity,
+        limits: &Limits,
+        strategy: impl UffdStrategy,
+    ) -> Result<Arc<Self>, Error> {
+        capacity.validate()?;
         limits.validate()?;
+        let max_capacity = capacity.max_capacity;
 
         let uffd = Arc::new(
             UffdBuilder::new()
@@ -391,7 +443,7 @@ impl UffdRegion {
 
         // map the chunk of virtual memory for all of the slots
         let total_region_size =
-            if let Some(sz) = instance_capacity.checked_mul(limits.total_memory_size()) {
+            if let Some(sz) = max_capacity.checked_mul(limits.total_memory_size()) {
                 sz
             } else {
                 return Err(Error::InvalidArgument("requested region size too large"));
@@ -428,7 +480,7 @@ impl UffdRegion {
                     strategy,
                     handler_uffd.clone(),
                     handler_start as *mut c_void,
-                    instance_capacity,
+                    max_capacity,
                     handler_pipe_recv,
                     handler_limits,
                 );
@@ -459,22 +511,75 @@ impl UffdRegion {
             uffd,
             start,
             limits: limits.clone(),
-            freelist: Mutex::new(Vec::with_capacity(instance_capacity)),
-            instance_capacity,
+            slots: Mutex::new(UffdSlots {
+                free: Vec::with_capacity(capacity.min_capacity),
+                elastic: ElasticState::new(capacity.clone()),
+                // slots are set up from the lowest index first
+                unmapped: (capacity.min_capacity..max_capacity).rev().collect(),
+                region: Weak::new(),
+            }),
+            max_capacity,
             handler: Some(handler),
             handler_pipe,
         });
 
         {
-            let mut freelist = region.freelist.lock().unwrap();
-            for i in 0..instance_capacity {
-                freelist.push(UffdRegion::create_slot(&region, i)?);
+            let mut slots = region.slots.lock().unwrap();
+            slots.region = Arc::downgrade(&region);
+            for i in 0..capacity.min_capacity {
+                slots.free.push(UffdRegion::create_slot(&region, i)?);
             }
         }
 
         Ok(region)
     }
 
+    /// Set up a new chunk of slots if there are no free slots and the region has room to grow.
+    fn grow(slots: &mut UffdSlots) -> Result<(), Error> {
+        let count = slots.elastic.grow(slots.free.len());
+        if count == 0 {
+            return Ok(());
+        }
+        let region = slots
+            .region
+            .upgrade()
+            .expect("region exists while creating instances");
+        for created in 0..count {
+            let index = slots
+                .unmapped
+                .pop()
+                .expect("capacity never exceeds the reserved slots");
+            match UffdRegion::create_slot(&region, index) {
+                Ok(slot) => slots.free.push(slot),
+                Err(e) => {
+                    slots.unmapped.push(index);
+                    slots.elastic.grow_failed(count - created);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Release the memory behind a chunk of free slots if they have been idle for long enough.
+    ///
+    /// The address space stays reserved and registered with `userfaultfd`, so the slots can be set
+    /// up again if the region grows.
+    fn release_idle(&self, slots: &mut UffdSlots) -> usize {
+        let count = slots.elastic.shrink(slots.free.len(), Instant::now());
+        let slot_size = self.limits.total_memory_size();
+        for slot in slots.free.drain(0..count) {
+            unsafe {
+                madvise(slot.start, slot_size, MmapAdvise::MADV_DONTNEED)
+                    .expect("madvise succeeds when releasing slot");
+            }
+            slots
+                .unmapped
+                .push((slot.start as usize - self.start as usize) / slot_size);
+        }
+        count
+    }
+
     fn create_slot(region: &Arc<UffdRegion>, index: usize) -> Result<Slot, Error> {
         // get the memory from the offset into the overall region
         let start =
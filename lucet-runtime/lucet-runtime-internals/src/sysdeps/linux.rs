@@ -1,4 +1,12 @@
-use libc::{c_void, ucontext_t, REG_RDI, REG_RIP};
+use libc::{
+    c_int, c_void, dl_iterate_phdr, dl_phdr_info, size_t, ucontext_t, PT_LOAD, REG_RBP, REG_RDI,
+    REG_RIP,
+};
+use nix::sys::memfd;
+use std::ffi::{CStr, CString};
+use std::ops::Range;
+use std::os::unix::io::RawFd;
+use std::slice;
 
 #[derive(Clone, Copy, Debug)]
 pub struct UContextPtr(*mut ucontext_t);
@@ -16,6 +24,12 @@ impl UContextPtr {
         mcontext.gregs[REG_RIP as usize] as *const _
     }
 
+    #[inline]
+    pub fn get_fp(self) -> *const c_void {
+        let mcontext = &unsafe { self.0.as_ref().unwrap() }.uc_mcontext;
+        mcontext.gregs[REG_RBP as usize] as *const _
+    }
+
     #[inline]
     pub fn set_ip(self, new_ip: *const c_void) {
         let mut mcontext = &mut unsafe { self.0.as_mut().unwrap() }.uc_mcontext;
@@ -54,3 +68,59 @@ impl Into<UContext> for UContextPtr {
         UContext { context: self.0 }
     }
 }
+
+/// An executable or shared object loaded into the process.
+pub struct LoadedObject {
+    /// The path the object was loaded from, which is empty for the main executable.
+    pub name: String,
+    /// The range of addresses spanned by the object's loaded segments.
+    pub range: Range<usize>,
+}
+
+/// List the executable and shared objects loaded into the process.
+pub fn loaded_objects() -> Option<Vec<LoadedObject>> {
+    unsafe extern "C" fn visit(info: *mut dl_phdr_info, _size: size_t, data: *mut c_void) -> c_int {
+        let info = &*info;
+        let objects = &mut *(data as *mut Vec<LoadedObject>);
+        if info.dlpi_phdr.is_null() {
+            return 0;
+        }
+        let range = slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize)
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+            .map(|phdr| {
+                let start = info.dlpi_addr as usize + phdr.p_vaddr as usize;
+                start..start + phdr.p_memsz as usize
+            })
+            .fold(None, |span: Option<Range<usize>>, segment| {
+                Some(span.map_or(segment.clone(), |span| {
+                    span.start.min(segment.start)..span.end.max(segment.end)
+                }))
+            });
+        if let Some(range) = range {
+            let name = if info.dlpi_name.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(info.dlpi_name)
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            objects.push(LoadedObject { name, range });
+        }
+        0
+    }
+
+    let mut objects: Vec<LoadedObject> = vec![];
+    unsafe {
+        dl_iterate_phdr(Some(visit), &mut objects as *mut _ as *mut c_void);
+    }
+    Some(objects)
+}
+
+/// Create an anonymous memory-backed file with `memfd_create(2)`.
+///
+/// Returns `None` if the kernel does not support memfds.
+pub fn memfd_create(name: &str) -> Option<RawFd> {
+    let name = CString::new(name).ok()?;
+    memfd::memfd_create(&name, memfd::MemFdCreateFlag::MFD_CLOEXEC).ok()
+}
@@ -1,4 +1,6 @@
 use libc::{c_int, c_short, c_void, sigset_t, size_t};
+use std::ops::Range;
+use std::os::unix::io::RawFd;
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct sigaltstack {
@@ -137,6 +139,12 @@ impl UContextPtr {
         mcontext.ss.rip as *const _
     }
 
+    #[inline]
+    pub fn get_fp(self) -> *const c_void {
+        let mcontext = unsafe { (*self.0).uc_mcontext.as_ref().unwrap() };
+        mcontext.ss.rbp as *const _
+    }
+
     #[inline]
     pub fn set_ip(self, new_ip: *const c_void) {
         let mcontext: &mut mcontext64 = unsafe { &mut (*self.0).uc_mcontext.as_mut().unwrap() };
@@ -174,3 +182,25 @@ impl Into<UContext> for UContextPtr {
         UContext::new(self.0 as *mut _)
     }
 }
+
+/// An executable or shared object loaded into the process.
+pub struct LoadedObject {
+    /// The path the object was loaded from.
+    pub name: String,
+    /// The range of addresses spanned by the object's loaded segments.
+    pub range: Range<usize>,
+}
+
+/// List the executable and shared objects loaded into the process.
+///
+/// This is not yet implemented for macOS, so it always returns `None`.
+pub fn loaded_objects() -> Option<Vec<LoadedObject>> {
+    None
+}
+
+/// Create an anonymous memory-backed file.
+///
+/// macOS has no memfds, so this always returns `None`.
+pub fn memfd_create(_name: &str) -> Option<RawFd> {
+    None
+}
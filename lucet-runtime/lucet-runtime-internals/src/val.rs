@@ -3,11 +3,11 @@
 
 use libc::c_void;
 use std::arch::x86_64::{
-    __m128, _mm_castpd_ps, _mm_castps_pd, _mm_load_pd1, _mm_load_ps1, _mm_setzero_ps,
+    __m128, _mm_castpd_ps, _mm_castps_pd, _mm_load_pd1, _mm_load_ps1, _mm_loadu_ps, _mm_setzero_ps,
     _mm_storeu_pd, _mm_storeu_ps,
 };
 
-use lucet_module::ValueType;
+use lucet_module::{GlobalValue, ValueType};
 
 impl Val {
     pub fn value_type(&self) -> ValueType {
@@ -23,6 +23,9 @@ impl Val {
             Val::Bool(_) => ValueType::I32,
             Val::F32(_) => ValueType::F32,
             Val::F64(_) => ValueType::F64,
+            Val::V128(_) => ValueType::V128,
+            Val::FuncRef(_) => ValueType::FuncRef,
+            Val::ExternRef(_) => ValueType::ExternRef,
         }
     }
 }
@@ -46,6 +49,12 @@ pub enum Val {
     Bool(bool),
     F32(f32),
     F64(f64),
+    /// A WebAssembly `v128`, with lane 0 in the least significant bits
+    V128(u128),
+    /// A WebAssembly `funcref`, or null
+    FuncRef(*const c_void),
+    /// A WebAssembly `externref`, or null
+    ExternRef(*const c_void),
 }
 
 // the pointer variant is just a wrapper; the caller will know they're still responsible for their
@@ -92,7 +101,8 @@ impl_from_scalars!({
     ISize: isize,
     Bool: bool,
     F32: f32,
-    F64: f64
+    F64: f64,
+    V128: u128
 });
 
 /// Register representation of `Val`.
@@ -111,7 +121,7 @@ pub fn val_to_reg(val: &Val) -> RegVal {
     use self::RegVal::*;
     use self::Val::*;
     match *val {
-        CPtr(v) => GpReg(v as u64),
+        CPtr(v) | FuncRef(v) | ExternRef(v) => GpReg(v as u64),
         GuestPtr(v) => GpReg(v as u64),
         U8(v) => GpReg(v as u64),
         U16(v) => GpReg(v as u64),
@@ -127,6 +137,7 @@ pub fn val_to_reg(val: &Val) -> RegVal {
         Bool(true) => GpReg(1u64),
         Val::F32(v) => FpReg(unsafe { _mm_load_ps1(&v as *const f32) }),
         Val::F64(v) => FpReg(unsafe { _mm_castpd_ps(_mm_load_pd1(&v as *const f64)) }),
+        Val::V128(v) => FpReg(unsafe { _mm_loadu_ps(&v as *const u128 as *const f32) }),
     }
 }
 
@@ -135,7 +146,7 @@ pub fn val_to_reg(val: &Val) -> RegVal {
 pub fn val_to_stack(val: &Val) -> u64 {
     use self::Val::*;
     match *val {
-        CPtr(v) => v as u64,
+        CPtr(v) | FuncRef(v) | ExternRef(v) => v as u64,
         GuestPtr(v) => v as u64,
         U8(v) => v as u64,
         U16(v) => v as u64,
@@ -151,6 +162,56 @@ pub fn val_to_stack(val: &Val) -> u64 {
         Bool(true) => 1u64,
         F32(v) => v.to_bits() as u64,
         F64(v) => v.to_bits(),
+        V128(_) => panic!("v128 values cannot be passed on the stack"),
+    }
+}
+
+/// Convert a `Val` to its representation when stored in a global.
+pub fn val_to_global(val: &Val) -> GlobalValue {
+    match *val {
+        Val::V128(v) => GlobalValue { v_128: v },
+        _ => GlobalValue {
+            v_128: val_to_stack(val) as u128,
+        },
+    }
+}
+
+/// Read the value of type `ty` stored in a global.
+pub fn val_from_global(ty: ValueType, global: &GlobalValue) -> Val {
+    unsafe {
+        match ty {
+            ValueType::I32 => Val::I32(global.i_32),
+            ValueType::I64 => Val::I64(global.i_64),
+            ValueType::F32 => Val::F32(global.f_32),
+            ValueType::F64 => Val::F64(global.f_64),
+            ValueType::V128 => Val::V128(global.v_128),
+            ValueType::FuncRef => Val::FuncRef(global.i_64 as *const c_void),
+            ValueType::ExternRef => Val::ExternRef(global.i_64 as *const c_void),
+        }
+    }
+}
+
+/// Interpret the contents of a general-purpose register or return area slot as a `Val` of type
+/// `ty`.
+pub fn val_from_bits(ty: ValueType, bits: u64) -> Val {
+    match ty {
+        ValueType::I32 => Val::I32(bits as i32),
+        ValueType::I64 => Val::I64(bits as i64),
+        ValueType::F32 => Val::F32(f32::from_bits(bits as u32)),
+        ValueType::F64 => Val::F64(f64::from_bits(bits)),
+        ValueType::FuncRef => Val::FuncRef(bits as *const c_void),
+        ValueType::ExternRef => Val::ExternRef(bits as *const c_void),
+        ValueType::V128 => panic!("v128 values do not fit in a general-purpose register"),
+    }
+}
+
+/// Interpret the contents of a floating-point register as a `Val` of type `ty`.
+pub fn val_from_fp_reg(ty: ValueType, reg: __m128) -> Val {
+    match ty {
+        ValueType::F32 => Val::F32(__m128_as_f32(reg)),
+        ValueType::F64 => Val::F64(__m128_as_f64(reg)),
+        ValueType::V128 => Val::V128(__m128_as_u128(reg)),
+        _ => panic!("{} values are not returned in floating-point registers", ty),
     }
 }
 
@@ -215,6 +276,7 @@ macro_rules! impl_from_fp {
 
 impl_from_fp!(f32, __m128_as_f32, as_f32);
 impl_from_fp!(f64, __m128_as_f64, as_f64);
+impl_from_fp!(u128, __m128_as_u128, as_v128);
 
 macro_rules! impl_from_gp {
     ( $ty:ty, $as:ident ) => {
@@ -316,3 +378,149 @@ pub fn __m128_as_f64(v: __m128) -> f64 {
     }
     out[0]
 }
+
+/// Interpret the contents of a `__m128` register as a `v128`.
+pub fn __m128_as_u128(v: __m128) -> u128 {
+    let mut out: u128 = 0;
+    unsafe {
+        _mm_storeu_ps(&mut out as *mut u128 as *mut f32, v);
+    }
+    out
+}
+
+/// Rust types that are passed to and from WebAssembly functions as a single WebAssembly value.
+///
+/// Host functions registered with a [`Linker`](../linker/struct.Linker.html) take and return these
+/// types directly, with the same representation as Lucet-compiled code uses.
+pub trait WasmTy: Copy + 'static {
+    /// The WebAssembly type of the value.
+    const VALUE_TYPE: ValueType;
+
+    /// Convert the value to a `Val` to pass to a guest function.
+    fn into_val(self) -> Val;
+
+    /// Convert a value returned by a guest function, if it has the right type.
+    fn from_val(val: &Val) -> Option<Self>;
+}
+
+macro_rules! impl_wasm_ty {
+    ( { $( $ty:ty : $value_type:ident => $ctor:ident ),* } ) => {
+        $(
+            impl WasmTy for $ty {
+                const VALUE_TYPE: ValueType = ValueType::$value_type;
+
+                fn into_val(self) -> Val {
+                    Val::$ctor(self)
+                }
+
+                fn from_val(val: &Val) -> Option<Self> {
+                    match *val {
+                        Val::$value_type(x) => Some(x as $ty),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+// the results of guest functions are always the signed variants of `Val` for integers
+impl_wasm_ty!({
+    i32: I32 => I32,
+    u32: I32 => U32,
+    i64: I64 => I64,
+    u64: I64 => U64,
+    f32: F32 => F32,
+    f64: F64 => F64
+});
+
+/// The arguments of a WebAssembly function, as a Rust type: a single
+/// [`WasmTy`](trait.WasmTy.html), or a tuple of up to 16 of them.
+pub trait WasmParams: Sized + 'static {
+    /// The WebAssembly types of the arguments.
+    fn value_types() -> Vec<ValueType>;
+
+    /// Convert the arguments to `Val`s to pass to a guest function, adding them to `vals`.
+    fn push_vals(self, vals: &mut Vec<Val>);
+}
+
+impl<T: WasmTy> WasmParams for T {
+    fn value_types() -> Vec<ValueType> {
+        vec![T::VALUE_TYPE]
+    }
+
+    fn push_vals(self, vals: &mut Vec<Val>) {
+        vals.push(self.into_val());
+    }
+}
+
+macro_rules! impl_wasm_params {
+    ( $( $arg:ident )* ) => {
+        impl<$( $arg: WasmTy, )*> WasmParams for ( $( $arg, )* ) {
+            fn value_types() -> Vec<ValueType> {
+                vec![$( $arg::VALUE_TYPE ),*]
+            }
+
+            #[allow(non_snake_case)]
+            fn push_vals(self, vals: &mut Vec<Val>) {
+                let ( $( $arg, )* ) = self;
+                vals.extend_from_slice(&[$( $arg.into_val() ),*]);
+            }
+        }
+    };
+}
+
+impl_wasm_params!();
+impl_wasm_params!(A1);
+impl_wasm_params!(A1 A2);
+impl_wasm_params!(A1 A2 A3);
+impl_wasm_params!(A1 A2 A3 A4);
+impl_wasm_params!(A1 A2 A3 A4 A5);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7 A8);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7 A8 A9);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13 A14);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13 A14 A15);
+impl_wasm_params!(A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13 A14 A15 A16);
+
+/// The results of a WebAssembly function, as a Rust type: `()` for no results, or a single
+/// [`WasmTy`](trait.WasmTy.html).
+pub trait WasmResults: Sized + 'static {
+    /// The WebAssembly types of the results.
+    fn value_types() -> Vec<ValueType>;
+
+    /// Convert the results returned by a guest function, if they have the right types.
+    fn from_vals(vals: &[Val]) -> Option<Self>;
+}
+
+impl WasmResults for () {
+    fn value_types() -> Vec<ValueType> {
+        vec![]
+    }
+
+    fn from_vals(vals: &[Val]) -> Option<Self> {
+        if vals.is_empty() {
+            Some(())
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: WasmTy> WasmResults for T {
+    fn value_types() -> Vec<ValueType> {
+        vec![T::VALUE_TYPE]
+    }
+
+    fn from_vals(vals: &[Val]) -> Option<Self> {
+        match vals {
+            [val] => T::from_val(val),
+            _ => None,
+        }
+    }
+}
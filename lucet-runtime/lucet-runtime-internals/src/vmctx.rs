@@ -12,6 +12,7 @@ use crate::instance::{
     EmptyYieldVal, Instance, InstanceInternal, State, TerminationDetails, YieldedVal,
     CURRENT_INSTANCE, HOST_CTX,
 };
+use crate::val::Val;
 use lucet_module::{FunctionHandle, GlobalValue};
 use std::any::Any;
 use std::borrow::{Borrow, BorrowMut};
@@ -175,10 +176,11 @@ impl Vmctx {
     /// heap_mut}` can't live across these calls, but we still need to update the boxed slice view
     /// to account for the length change.
     ///
-    /// TODO: There is still an unsound case, though, when a heap reference is held across a call
-    /// back into the guest via `Vmctx::get_func_from_idx()`. That guest code may grow the heap as
-    /// well, causing any outstanding heap references to become invalid. We will address this when
-    /// we rework the interface for calling back into the guest.
+    /// The same goes for calls back into the guest with `Vmctx::call_export()` and
+    /// `Vmctx::call_func_idx()`, which may grow the heap. There is still an unsound case when a
+    /// heap reference is held across a call made directly through a function pointer from
+    /// `Vmctx::get_func_from_idx()`, as that guest code may grow the heap too, causing any
+    /// outstanding heap references to become invalid.
     unsafe fn reconstitute_heap_view_if_needed(&self) {
         let inst = self.instance_mut();
         if inst.heap_mut().len() != self.heap_view.borrow().len() {
@@ -291,8 +293,9 @@ impl Vmctx {
     /// There is currently no guarantee that guest functions will return before faulting, or
     /// terminating the instance in a subsequent hostcall. This means that any Rust resources that
     /// are held open when the guest function is called might be leaked if the guest function, for
-    /// example, divides by zero. Work to make this safer is
-    /// [ongoing](https://github.com/bytecodealliance/lucet/pull/254).
+    /// example, divides by zero. The guest function also runs without the checks that
+    /// [`Vmctx::call_func_idx()`](struct.Vmctx.html#method.call_func_idx) makes, which is the
+    /// safer way to call it.
     ///
     /// ```no_run
     /// use lucet_runtime_macros::lucet_hostcall;
@@ -324,9 +327,57 @@ impl Vmctx {
         table_idx: u32,
         func_idx: u32,
     ) -> Result<FunctionHandle, Error> {
-        self.instance()
-            .module()
-            .get_func_from_idx(table_idx, func_idx)
+        self.instance().get_func_from_idx(table_idx, func_idx)
+    }
+
+    /// Call the function the guest exports as `name` with `args`, returning its results.
+    ///
+    /// This lets hostcalls use functions the guest provides, such as comparators, event handlers,
+    /// or allocators. The function runs on the guest stack below the hostcall, with the instance
+    /// able to be terminated as in any other guest code, and may itself make hostcalls and yield.
+    ///
+    /// If there are any live borrows of the heap view, globals view, or an embed_ctx, the function
+    /// will terminate the instance with `TerminationDetails::BorrowError`, as the guest function
+    /// may change their contents.
+    ///
+    /// If the guest function faults, or the instance is terminated while it runs, this does not
+    /// return: the hostcall is abandoned without unwinding, so any Rust resources it holds are
+    /// leaked, and the run that made the hostcall ends with the fault or termination.
+    ///
+    /// # Errors
+    ///
+    /// - `Error::SymbolNotFound` if the guest has no such export.
+    /// - `Error::InvalidArgument` if `args` do not match the function's parameters.
+    /// - `Error::LimitsExceeded` if there are already as many calls back into the guest in
+    ///   progress as [`Instance::set_max_nested_calls()`](../struct.Instance.html#method.set_max_nested_calls)
+    ///   allows, or too little of the guest stack is left for another.
+    /// - `Error::RuntimeTerminated` if the instance was terminated during this hostcall, in which
+    ///   case it ends as soon as the hostcall returns.
+    pub fn call_export(&self, name: &str, args: &[Val]) -> Result<Vec<Val>, Error> {
+        let func = self.instance().module().get_export_func(name)?;
+        self.call_func(func, args)
+    }
+
+    /// Call the function at `func_idx` in the guest's table `table_idx` with `args`, returning its
+    /// results.
+    ///
+    /// This is how a hostcall calls a function pointer it was given by the guest, as WebAssembly
+    /// uses table indices as its runtime representation of function pointers. It behaves as
+    /// [`Vmctx::call_export()`](struct.Vmctx.html#method.call_export) does, but fails with
+    /// `Error::FuncNotFound` if there is no function at the index.
+    pub fn call_func_idx(
+        &self,
+        table_idx: u32,
+        func_idx: u32,
+        args: &[Val],
+    ) -> Result<Vec<Val>, Error> {
+        let func = self.get_func_from_idx(table_idx, func_idx)?;
+        self.call_func(func, args)
+    }
+
+    fn call_func(&self, func: FunctionHandle, args: &[Val]) -> Result<Vec<Val>, Error> {
+        self.ensure_no_borrows();
+        unsafe { self.instance_mut().call_nested(func, args) }
     }
 
     /// Suspend the instance, returning an empty
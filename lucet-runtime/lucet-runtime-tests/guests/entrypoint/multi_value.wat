@@ -0,0 +1,40 @@
+(module
+  (import "env" "return_area_hostcall" (func $return_area_hostcall (param i32) (result i32)))
+  (table funcref (elem $pinned))
+  (func $swap (export "swap") (param i64 i64) (result i64 i64)
+    (local.get 1)
+    (local.get 0)
+  )
+  (func $mixed (export "mixed") (param i32 f64) (result f64 i32)
+    (local.get 1)
+    (local.get 0)
+  )
+  (func $many (export "many") (result i32 i64 i32 f32 f64 i64)
+    (i32.const 1)
+    (i64.const 2)
+    (i32.const 3)
+    (f32.const 4.5)
+    (f64.const 5.5)
+    (i64.const 6)
+  )
+  (func $block_sum (export "block_sum") (param i32 i32) (result i32)
+    (local.get 0)
+    (local.get 1)
+    (block (param i32 i32) (result i32)
+      (i32.add)
+    )
+  )
+  ;; more than two integer and more than two floating-point results, so they are all returned
+  ;; through the return area
+  (func $pinned (export "pinned") (param i64 f64) (result f32 i64 f64 i32 f32 i64)
+    (f32.demote_f64 (local.get 1))
+    (local.get 0)
+    (f64.add (local.get 1) (f64.const 1))
+    (i32.const -1)
+    (f32.const 2.5)
+    (i64.add (local.get 0) (i64.const 1))
+  )
+  (func $pinned_from_host (export "pinned_from_host") (result i32)
+    (call $return_area_hostcall (i32.const 0))
+  )
+)
@@ -0,0 +1,20 @@
+(module
+  (global $acc (export "acc") (mut v128) (v128.const i32x4 0 0 0 0))
+  (global $count (export "count") (mut i32) (i32.const 0))
+  (func $add (export "add") (param v128 v128) (result v128)
+    (i32x4.add (local.get 0) (local.get 1))
+  )
+  (func $accumulate (export "accumulate") (param i32) (result v128)
+    (global.set $acc (i32x4.add (global.get $acc) (i32x4.splat (local.get 0))))
+    (global.set $count (i32.add (global.get $count) (i32.const 1)))
+    (global.get $acc)
+  )
+  (func $count (export "count") (result i32)
+    (global.get $count)
+  )
+  (func $lane_sum (export "lane_sum") (param v128) (result i32)
+    (i32.add
+      (i32.add (i32x4.extract_lane 0 (local.get 0)) (i32x4.extract_lane 1 (local.get 0)))
+      (i32.add (i32x4.extract_lane 2 (local.get 0)) (i32x4.extract_lane 3 (local.get 0))))
+  )
+)
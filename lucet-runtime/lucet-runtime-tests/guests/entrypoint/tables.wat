@@ -0,0 +1,35 @@
+(module
+  (type $get_i32 (func (result i32)))
+  (table $t 2 10 funcref)
+  (elem (i32.const 0) $one $two)
+  (global $first funcref (ref.func $one))
+  (func $one (result i32)
+    (i32.const 1)
+  )
+  (func $two (result i32)
+    (i32.const 2)
+  )
+  (func $call (export "call") (param i32) (result i32)
+    (call_indirect (type $get_i32) (local.get 0))
+  )
+  (func $swap (export "swap")
+    (local $tmp funcref)
+    (local.set $tmp (table.get $t (i32.const 0)))
+    (table.set $t (i32.const 0) (table.get $t (i32.const 1)))
+    (table.set $t (i32.const 1) (local.get $tmp))
+  )
+  (func $grow (export "grow") (result i32)
+    (drop (table.grow $t (table.get $t (i32.const 0)) (i32.const 3)))
+    (table.size $t)
+  )
+  (func $call_global (export "call_global") (result i32)
+    (table.set $t (i32.const 1) (global.get $first))
+    (call_indirect (type $get_i32) (i32.const 1))
+  )
+  (func $grow_and_call (export "grow_and_call") (result i32)
+    ;; the table is used both before and after it grows, and moves, in the same function
+    (drop (call_indirect (type $get_i32) (i32.const 0)))
+    (drop (table.grow $t (table.get $t (i32.const 1)) (i32.const 6)))
+    (call_indirect (type $get_i32) (i32.const 7))
+  )
+)
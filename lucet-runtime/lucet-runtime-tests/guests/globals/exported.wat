@@ -0,0 +1,13 @@
+(module
+  (global $count (export "count") (export "counter") (mut i64) (i64.const 7))
+  (global $ratio (export "ratio") (mut f32) (f32.const 0.5))
+  (global $limit (export "limit") i32 (i32.const 100))
+  (global $hidden (mut i32) (i32.const 1))
+  (memory 1)
+  (func $get_count (export "get_count") (result i64)
+    (get_global $count)
+  )
+  (func $scale (export "scale") (param $x f32) (result f32)
+    (f32.mul (get_local $x) (get_global $ratio))
+  )
+)
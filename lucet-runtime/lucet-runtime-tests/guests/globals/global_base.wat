@@ -0,0 +1,22 @@
+(module
+  (global $base (import "env" "base") i32)
+  (memory 1)
+  (table 16 funcref)
+  (type $ret_i32 (func (result i32)))
+  (data (global.get $base) "\2a\00\00\00")
+  (data (i32.const 8) "\07\00\00\00")
+  (elem (global.get $base) $forty_two)
+  (elem (i32.const 8) $seven)
+  (func $forty_two (result i32)
+    (i32.const 42)
+  )
+  (func $seven (result i32)
+    (i32.const 7)
+  )
+  (func $load (export "load") (param i32) (result i32)
+    (i32.load (local.get 0))
+  )
+  (func $call (export "call") (param i32) (result i32)
+    (call_indirect (type $ret_i32) (local.get 0))
+  )
+)
@@ -0,0 +1,17 @@
+(module
+  (memory 1)
+  (data $hello "hello")
+  (func $init (export "init")
+    (memory.init $hello (i32.const 0) (i32.const 0) (i32.const 5))
+  )
+  (func $copy_and_fill (export "copy_and_fill")
+    (memory.copy (i32.const 8) (i32.const 0) (i32.const 5))
+    (memory.fill (i32.const 16) (i32.const 42) (i32.const 4))
+  )
+  (func $drop (export "drop")
+    (data.drop $hello)
+  )
+  (func $fill_oob (export "fill_oob")
+    (memory.fill (i32.const 65535) (i32.const 0) (i32.const 2))
+  )
+)
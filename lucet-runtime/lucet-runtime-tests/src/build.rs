@@ -44,13 +44,7 @@ where
 
     let native_build = Lucetc::new(wasm_file).with_bindings(bindings);
 
-    let so_file = workdir.path().join("out.so");
-
-    native_build.shared_object_file(so_file.clone())?;
-
-    let dlmodule = DlModule::load(so_file)?;
-
-    Ok(dlmodule)
+    lucetc_test(native_build)
 }
 
 pub fn test_module_wasm(dir: &str, wasmfile: &str) -> Result<Arc<DlModule>, Error> {
@@ -64,12 +58,17 @@ where
     P: AsRef<Path>,
     Q: AsRef<Path>,
 {
-    let workdir = TempDir::new().expect("create working directory");
-
     let bindings = Bindings::from_file(&bindings_file)?;
 
     let native_build = Lucetc::new(wasm_file).with_bindings(bindings);
 
+    lucetc_test(native_build)
+}
+
+/// Compile a module with an already configured `Lucetc`, and load the resulting shared object.
+pub fn lucetc_test(native_build: Lucetc) -> Result<Arc<DlModule>, Error> {
+    let workdir = TempDir::new().expect("create working directory");
+
     let so_file = workdir.path().join("out.so");
 
     native_build.shared_object_file(so_file.clone())?;
@@ -78,3 +77,15 @@ where
 
     Ok(dlmodule)
 }
+
+/// Compile a module with an already configured `Lucetc`, and return the bytes of the resulting
+/// relocatable object.
+pub fn lucetc_object_test(native_build: Lucetc) -> Result<Vec<u8>, Error> {
+    let workdir = TempDir::new().expect("create working directory");
+
+    let obj_file = workdir.path().join("out.o");
+
+    native_build.object_file(obj_file.clone())?;
+
+    Ok(std::fs::read(obj_file)?)
+}
@@ -225,16 +225,42 @@ macro_rules! entrypoint_tests {
             x + y + z + w
         }
 
+        // Call `pinned` from `multi_value.wat` directly, and check where it writes its results. This
+        // pins the ABI the runtime assumes for the return area: its address is passed ahead of the
+        // vmctx, and each result gets its own 8-byte slot, in order.
+        #[lucet_hostcall]
+        #[no_mangle]
+        pub unsafe extern "C" fn return_area_hostcall(vmctx: &Vmctx, func_idx: u32) -> u32 {
+            let func = vmctx
+                .get_func_from_idx(0, func_idx)
+                .expect("can get function by index");
+            let func = std::mem::transmute::<
+                usize,
+                extern "C" fn(*mut u64, *const lucet_vmctx, i64, f64),
+            >(func.ptr.as_usize());
+            let mut return_area = [0u64; 6];
+            (func)(return_area.as_mut_ptr(), vmctx.as_raw(), 7, 1.5);
+            // 32-bit results only write the low half of their slot
+            let low_half = |slot: u64| slot as u32;
+            let matches = low_half(return_area[0]) == 1.5f32.to_bits()
+                && return_area[1] == 7
+                && return_area[2] == 2.5f64.to_bits()
+                && low_half(return_area[3]) == u32::max_value()
+                && low_half(return_area[4]) == 2.5f32.to_bits()
+                && return_area[5] == 8;
+            matches as u32
+        }
+
         $(
             mod $region_id {
                 use libc::c_void;
                 use lucet_runtime::vmctx::{lucet_vmctx, Vmctx};
                 use lucet_runtime::{
-                    lucet_hostcall, DlModule, Error, Limits, Module, Region, Val, WASM_PAGE_SIZE, RegionCreate
+                    lucet_hostcall, DlModule, Error, Limits, Module, Region, Val, WASM_PAGE_SIZE, RegionCreate, TrapCode
                 };
                 use std::sync::Arc;
                 use $TestRegion as TestRegion;
-                use $crate::build::test_module_c;
+                use $crate::build::{test_module_c, test_module_wasm};
                 use $crate::entrypoint::{mock_calculator_module, wat_calculator_module};
 
                 #[test]
@@ -931,6 +957,240 @@ macro_rules! entrypoint_tests {
                     assert_eq!(u64::from(retval), 3);
                 }
 
+                #[test]
+                fn entrypoint_tables() {
+                    let module = test_module_wasm("entrypoint", "tables.wat")
+                        .expect("module builds and loads");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+
+                    let mut inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    let call = |inst: &mut lucet_runtime::InstanceHandle, idx: u32| {
+                        inst.run("call", &[idx.into()])
+                            .expect("instance runs")
+                            .unwrap_returned()
+                            .as_u32()
+                    };
+
+                    assert_eq!(call(&mut inst, 0), 1);
+                    assert_eq!(call(&mut inst, 1), 2);
+
+                    inst.run("swap", &[]).expect("instance runs");
+                    assert_eq!(call(&mut inst, 0), 2);
+                    assert_eq!(call(&mut inst, 1), 1);
+                    // the host sees the table as the guest changed it
+                    let retval = inst
+                        .run_func_idx(0, 0, &[])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(u32::from(retval), 2);
+                    // elements out of bounds are reported rather than panicking
+                    assert_eq!(inst.table_get(0, 2), None);
+                    assert_eq!(inst.table_get(1, 0), None);
+                    assert!(!inst.table_set(0, 2, 0));
+
+                    let retval = inst
+                        .run("grow", &[])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(u32::from(retval), 5);
+                    assert_eq!(call(&mut inst, 4), 2);
+
+                    // resetting the instance restores the initial table
+                    inst.reset().expect("instance resets");
+                    assert_eq!(call(&mut inst, 0), 1);
+                    match inst.run("call", &[4u32.into()]) {
+                        Err(Error::RuntimeFault(details)) => {
+                            assert_eq!(details.trapcode, Some(TrapCode::TableOutOfBounds));
+                        }
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                }
+
+                #[test]
+                fn entrypoint_global_ref_func() {
+                    let module = test_module_wasm("entrypoint", "tables.wat")
+                        .expect("module builds and loads");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+
+                    let mut inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    // the global starts out referring to the function that returns 1
+                    let retval = inst
+                        .run("call_global", &[])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(u32::from(retval), 1);
+
+                    inst.reset().expect("instance resets");
+                    let retval = inst
+                        .run("call_global", &[])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(u32::from(retval), 1);
+                }
+
+                #[test]
+                fn entrypoint_table_grow_then_call_indirect() {
+                    let module = test_module_wasm("entrypoint", "tables.wat")
+                        .expect("module builds and loads");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+
+                    let mut inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    let retval = inst
+                        .run("grow_and_call", &[])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(u32::from(retval), 2);
+                    // the new element is visible to the host too
+                    let retval = inst
+                        .run_func_idx(0, 7, &[])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(u32::from(retval), 2);
+                }
+
+                #[test]
+                fn entrypoint_multi_value() {
+                    let module = test_module_wasm("entrypoint", "multi_value.wat")
+                        .expect("module builds and loads");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+
+                    let mut inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    let retval = inst
+                        .run("swap", &[1i64.into(), 2i64.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    // the first result is still available untyped
+                    assert_eq!(i64::from(retval), 2);
+                    match inst.results() {
+                        [Val::I64(2), Val::I64(1)] => (),
+                        results => panic!("unexpected results: {:?}", results),
+                    }
+
+                    inst.run("mixed", &[7i32.into(), 2.5f64.into()])
+                        .expect("instance runs");
+                    match inst.results() {
+                        [Val::F64(x), Val::I32(7)] if *x == 2.5 => (),
+                        results => panic!("unexpected results: {:?}", results),
+                    }
+
+                    // too many results to fit in registers, so they are returned on the stack
+                    let retval = inst.run("many", &[]).expect("instance runs").unwrap_returned();
+                    assert_eq!(i32::from(retval), 1);
+                    match inst.results() {
+                        [Val::I32(1), Val::I64(2), Val::I32(3), Val::F32(x), Val::F64(y), Val::I64(6)]
+                            if *x == 4.5 && *y == 5.5 => (),
+                        results => panic!("unexpected results: {:?}", results),
+                    }
+
+                    let retval = inst
+                        .run("pinned", &[7i64.into(), 1.5f64.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    // the first result is taken from the return area too
+                    assert_eq!(f32::from(retval), 1.5);
+                    match inst.results() {
+                        [Val::F32(a), Val::I64(7), Val::F64(b), Val::I32(-1), Val::F32(c), Val::I64(8)]
+                            if *a == 1.5 && *b == 2.5 && *c == 2.5 => (),
+                        results => panic!("unexpected results: {:?}", results),
+                    }
+                    let retval = inst
+                        .run("pinned_from_host", &[])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(u32::from(retval), 1);
+
+                    let retval = inst
+                        .run("block_sum", &[3i32.into(), 4i32.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(i32::from(retval), 7);
+                    assert_eq!(inst.results().len(), 1);
+
+                    inst.reset().expect("instance resets");
+                    assert!(inst.results().is_empty());
+                }
+
+                #[test]
+                fn entrypoint_simd() {
+                    fn lanes(l: [u32; 4]) -> u128 {
+                        l.iter()
+                            .rev()
+                            .fold(0u128, |acc, lane| (acc << 32) | *lane as u128)
+                    }
+
+                    let module = test_module_wasm("entrypoint", "simd.wat")
+                        .expect("module builds and loads");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+
+                    let mut inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    let retval = inst
+                        .run(
+                            "add",
+                            &[lanes([1, 2, 3, 4]).into(), lanes([10, 20, 30, 40]).into()],
+                        )
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(retval.as_v128(), lanes([11, 22, 33, 44]));
+                    match inst.results() {
+                        [Val::V128(v)] if *v == lanes([11, 22, 33, 44]) => (),
+                        results => panic!("unexpected results: {:?}", results),
+                    }
+
+                    let retval = inst
+                        .run("lane_sum", &[Val::V128(lanes([1, 2, 3, 4]))])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(i32::from(retval), 10);
+
+                    // the v128 global sits alongside an ordinary one
+                    inst.run("accumulate", &[3i32.into()]).expect("instance runs");
+                    let retval = inst
+                        .run("accumulate", &[4i32.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(u128::from(retval), lanes([7, 7, 7, 7]));
+                    let retval = inst.run("count", &[]).expect("instance runs").unwrap_returned();
+                    assert_eq!(i32::from(retval), 2);
+                    // the host reads each global from its own 16-byte slot
+                    match inst.get_global("acc") {
+                        Ok(Val::V128(acc)) => assert_eq!(acc, lanes([7, 7, 7, 7])),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    match inst.get_global("count") {
+                        Ok(Val::I32(2)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    inst.set_global("acc", Val::V128(lanes([1, 2, 3, 4])))
+                        .expect("global can be set");
+                    let retval = inst
+                        .run("accumulate", &[1i32.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(retval.as_v128(), lanes([2, 3, 4, 5]));
+
+                    inst.reset().expect("instance resets");
+                    let retval = inst
+                        .run("accumulate", &[1i32.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(retval.as_v128(), lanes([1, 1, 1, 1]));
+                }
+
                 #[test]
                 fn ensure_linked() {
                     lucet_runtime::lucet_internal_ensure_linked();
@@ -8,7 +8,7 @@ macro_rules! globals_tests {
                 use $crate::helpers::{MockExportBuilder, MockModuleBuilder};
                 use lucet_module::{lucet_signature, FunctionPointer, GlobalValue};
                 use lucet_runtime::vmctx::{lucet_vmctx};
-                use lucet_runtime::{Error, Limits, Module, Region, RegionCreate};
+                use lucet_runtime::{Error, Limits, Module, Region, RegionCreate, TrapCode, Val};
                 use std::sync::Arc;
                 use $TestRegion as TestRegion;
 
@@ -46,6 +46,103 @@ macro_rules! globals_tests {
                     assert_eq!(heap_u32[0..=2], [3, 2, 6]);
                 }
 
+                #[test]
+                fn get_exported_globals() {
+                    let module =
+                        test_module_wasm("globals", "exported.wat").expect("module compiled and loaded");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+                    let inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    match inst.get_global("count") {
+                        Ok(Val::I64(7)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    match inst.get_global("counter") {
+                        Ok(Val::I64(7)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    match inst.get_global("ratio") {
+                        Ok(Val::F32(ratio)) => assert_eq!(ratio, 0.5),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    match inst.get_global("limit") {
+                        Ok(Val::I32(100)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    match inst.get_global("hidden") {
+                        Err(Error::SymbolNotFound(name)) => assert_eq!(name, "hidden"),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                }
+
+                #[test]
+                fn set_exported_globals() {
+                    let module =
+                        test_module_wasm("globals", "exported.wat").expect("module compiled and loaded");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+                    let mut inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    inst.set_global("count", Val::I64(42)).expect("global can be set");
+                    let retval = inst
+                        .run("get_count", &[])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(i64::from(retval), 42);
+                    match inst.get_global("counter") {
+                        Ok(Val::I64(42)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+
+                    inst.set_global("ratio", Val::F32(2.0)).expect("global can be set");
+                    let retval = inst
+                        .run("scale", &[3.0f32.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(f32::from(retval), 6.0);
+
+                    // resetting the instance restores the initial values
+                    inst.reset().expect("instance resets");
+                    match inst.get_global("count") {
+                        Ok(Val::I64(7)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                }
+
+                #[test]
+                fn reject_invalid_global_sets() {
+                    let module =
+                        test_module_wasm("globals", "exported.wat").expect("module compiled and loaded");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+                    let mut inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    match inst.set_global("limit", Val::I32(1)) {
+                        Err(Error::InvalidArgument(_)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    match inst.set_global("count", Val::I32(1)) {
+                        Err(Error::InvalidArgument(_)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    match inst.set_global("hidden", Val::I32(1)) {
+                        Err(Error::SymbolNotFound(_)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    match inst.get_global("limit") {
+                        Ok(Val::I32(100)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    match inst.get_global("count") {
+                        Ok(Val::I64(7)) => (),
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                }
+
                 fn mock_import_module() -> Arc<dyn Module> {
                     MockModuleBuilder::new()
                         .with_import(0, "something", "else")
@@ -58,7 +155,122 @@ macro_rules! globals_tests {
                     let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
                     match region.new_instance(module) {
                         Ok(_) => panic!("instance creation should not succeed"),
-                        Err(Error::Unsupported(_)) => (),
+                        Err(Error::GlobalImportNotProvided(_)) => (),
+                        Err(e) => panic!("unexpected error: {}", e),
+                    }
+                }
+
+                #[test]
+                fn reject_mistyped_import() {
+                    let module = mock_import_module();
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+                    match region
+                        .new_instance_builder(module)
+                        .with_global_import("something", "else", Val::I32(7))
+                        .build()
+                    {
+                        Ok(_) => panic!("instance creation should not succeed"),
+                        Err(Error::InvalidArgument(_)) => (),
+                        Err(e) => panic!("unexpected error: {}", e),
+                    }
+                }
+
+                #[test]
+                fn provided_import() {
+                    let module = mock_import_module();
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+                    let mut inst = region
+                        .new_instance_builder(module)
+                        .with_global_import("something", "else", Val::I64(7))
+                        .build()
+                        .expect("instance can be created");
+                    assert_eq!(unsafe { inst.globals()[0].i_64 }, 7);
+
+                    inst.globals_mut()[0] = GlobalValue { i_64: 8 };
+                    inst.reset().expect("instance resets");
+                    assert_eq!(unsafe { inst.globals()[0].i_64 }, 7);
+                }
+
+                #[test]
+                fn imported_global_in_wasm() {
+                    let module =
+                        test_module_wasm("globals", "import.wat").expect("module compiled and loaded");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+                    let mut inst = region
+                        .new_instance_builder(module)
+                        .with_global_import("env", "x", Val::I32(42))
+                        .build()
+                        .expect("instance can be created");
+
+                    inst.run_start().expect("start function runs");
+                    let heap_u32 = unsafe { inst.heap_u32() };
+                    assert_eq!(heap_u32[0], 42);
+                }
+
+                #[test]
+                fn global_base_segments() {
+                    let module =
+                        test_module_wasm("globals", "global_base.wat").expect("module compiled and loaded");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+                    let mut inst = region
+                        .new_instance_builder(module.clone())
+                        .with_global_import("env", "base", Val::I32(2))
+                        .build()
+                        .expect("instance can be created");
+
+                    let retval = inst
+                        .run("load", &[2i32.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(i32::from(retval), 42);
+                    let retval = inst
+                        .run("call", &[2i32.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(i32::from(retval), 42);
+                    drop(inst);
+
+                    // the segments with constant offsets come later, so they are applied last
+                    let mut inst = region
+                        .new_instance_builder(module.clone())
+                        .with_global_import("env", "base", Val::I32(8))
+                        .build()
+                        .expect("instance can be created");
+                    let retval = inst
+                        .run("load", &[8i32.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(i32::from(retval), 7);
+                    let retval = inst
+                        .run("call", &[8i32.into()])
+                        .expect("instance runs")
+                        .unwrap_returned();
+                    assert_eq!(i32::from(retval), 7);
+                    drop(inst);
+
+                    // the element segment no longer fits in the table
+                    match region
+                        .new_instance_builder(module.clone())
+                        .with_global_import("env", "base", Val::I32(16))
+                        .build()
+                    {
+                        Ok(_) => panic!("instance creation should not succeed"),
+                        Err(Error::RuntimeFault(details)) => {
+                            assert_eq!(details.trapcode, Some(TrapCode::TableOutOfBounds));
+                        }
+                        Err(e) => panic!("unexpected error: {}", e),
+                    }
+
+                    // nor does the data segment fit in the heap
+                    match region
+                        .new_instance_builder(module)
+                        .with_global_import("env", "base", Val::I32(-2))
+                        .build()
+                    {
+                        Ok(_) => panic!("instance creation should not succeed"),
+                        Err(Error::RuntimeFault(details)) => {
+                            assert_eq!(details.trapcode, Some(TrapCode::HeapOutOfBounds));
+                        }
                         Err(e) => panic!("unexpected error: {}", e),
                     }
                 }
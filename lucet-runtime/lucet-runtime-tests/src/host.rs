@@ -823,6 +823,7 @@ macro_rules! host_tests {
                         guard_size: 4 * 1024 * 1024,
                         initial_size: 64 * 1024,
                         max_size: Some(2 * 64 * 1024),
+                        bounds_checked: false,
                     };
                     let module = MockModuleBuilder::new()
                         .with_export_func(MockExportBuilder::new(
@@ -857,6 +858,7 @@ macro_rules! host_tests {
                         guard_size: 4 * 1024 * 1024,
                         initial_size: 64 * 1024,
                         max_size: Some(2 * 64 * 1024),
+                        bounds_checked: false,
                     };
                     let module = MockModuleBuilder::new()
                         .with_export_func(MockExportBuilder::new(
@@ -4,7 +4,7 @@ macro_rules! memory_tests {
         $(
             mod $region_id {
                 use lazy_static::lazy_static;
-                use lucet_runtime::{DlModule, Limits, Region, RegionCreate};
+                use lucet_runtime::{DlModule, Error, Limits, Region, RegionCreate, TrapCode};
                 use std::sync::Mutex;
                 use $TestRegion as TestRegion;
                 use $crate::build::test_module_wasm;
@@ -49,6 +49,55 @@ macro_rules! memory_tests {
                     // guest then puts the result of the current memory call in heap[4] (indexed by bytes)
                     assert_eq!(heap[1], 5);
                 }
+
+                #[test]
+                fn bulk_memory() {
+                    let module = test_module_wasm("memory", "bulk_memory.wat")
+                        .expect("compile and load bulk_memory.wasm");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+                    let mut inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    inst.run("init", &[]).expect("instance runs");
+                    inst.run("copy_and_fill", &[]).expect("instance runs");
+                    assert_eq!(&inst.heap()[0..5], b"hello");
+                    assert_eq!(&inst.heap()[8..13], b"hello");
+                    assert_eq!(&inst.heap()[16..20], &[42; 4]);
+
+                    // once the segment is dropped, initializing from it is out of bounds
+                    inst.run("drop", &[]).expect("instance runs");
+                    match inst.run("init", &[]) {
+                        Err(Error::RuntimeFault(details)) => {
+                            assert_eq!(details.trapcode, Some(TrapCode::HeapOutOfBounds));
+                        }
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+
+                    // resetting the instance makes the segment available again
+                    inst.reset().expect("instance resets");
+                    inst.run("init", &[]).expect("instance runs");
+                    assert_eq!(&inst.heap()[0..5], b"hello");
+                }
+
+                #[test]
+                fn bulk_memory_out_of_bounds() {
+                    let module = test_module_wasm("memory", "bulk_memory.wat")
+                        .expect("compile and load bulk_memory.wasm");
+                    let region = <TestRegion as RegionCreate>::create(1, &Limits::default()).expect("region can be created");
+                    let mut inst = region
+                        .new_instance(module)
+                        .expect("instance can be created");
+
+                    match inst.run("fill_oob", &[]) {
+                        Err(Error::RuntimeFault(details)) => {
+                            assert_eq!(details.trapcode, Some(TrapCode::HeapOutOfBounds));
+                        }
+                        res => panic!("unexpected result: {:?}", res),
+                    }
+                    // an out of bounds fill must not write any bytes
+                    assert_eq!(inst.heap()[65535], 0);
+                }
             }
         )*
     };
@@ -1,12 +1,16 @@
-use crate::{DlModule, Instance, Limits, MmapRegion, Module, Region};
+use crate::{
+    DlModule, Instance, Limits, MmapRegion, Module, ObjectSymbols, OutOfFuel, Region,
+    TerminationDetails,
+};
 #[cfg(all(target_os = "linux", feature = "uffd"))]
 use crate::{UffdRegion, WasmPageSizedUffdStrategy};
 use libc::{c_char, c_int, c_void};
-use lucet_module::TrapCode;
+use lucet_module::{FunctionIndex, GlobalValue, TrapCode};
 use lucet_runtime_internals::c_api::*;
 use lucet_runtime_internals::instance::{
     instance_handle_from_raw, instance_handle_to_raw, InstanceInternal,
 };
+use lucet_runtime_internals::module::ModuleInternal;
 use lucet_runtime_internals::vmctx::{Vmctx, VmctxInternal};
 use lucet_runtime_internals::WASM_PAGE_SIZE;
 use lucet_runtime_internals::{
@@ -184,6 +188,154 @@ pub unsafe extern "C" fn lucet_dl_module_release(module: *const lucet_dl_module)
     Arc::from_raw(module as *const DlModule);
 }
 
+/// Copies the functions and globals exported by the module to `exports_out`, which has room for
+/// `exports_len` exports. The total number of exports is written to `exports_count_out`, even if it
+/// exceeds `exports_len`. The export names point into the module, and are valid until it is
+/// released.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_dl_module_exports(
+    module: *const lucet_dl_module,
+    exports_out: *mut lucet_module_export,
+    exports_len: usize,
+    exports_count_out: *mut usize,
+) -> lucet_error {
+    if exports_len != 0 && exports_out.is_null() {
+        return lucet_error::InvalidArgument;
+    }
+    with_ffi_arcs!([module: DlModule], {
+        let mut count = 0;
+        for export in module.exports() {
+            if count < exports_len {
+                exports_out.add(count).write((&export).into());
+            }
+            count += 1;
+        }
+        if !exports_count_out.is_null() {
+            exports_count_out.write(count);
+        }
+        lucet_error::Ok
+    })
+}
+
+/// Copies the functions and globals imported by the module to `imports_out`, which has room for
+/// `imports_len` imports. The total number of imports is written to `imports_count_out`, even if it
+/// exceeds `imports_len`. The module and import names point into the module, and are valid until it
+/// is released.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_dl_module_imports(
+    module: *const lucet_dl_module,
+    imports_out: *mut lucet_module_import,
+    imports_len: usize,
+    imports_count_out: *mut usize,
+) -> lucet_error {
+    if imports_len != 0 && imports_out.is_null() {
+        return lucet_error::InvalidArgument;
+    }
+    with_ffi_arcs!([module: DlModule], {
+        let mut count = 0;
+        for import in module.imports() {
+            if count < imports_len {
+                imports_out.add(count).write((&import).into());
+            }
+            count += 1;
+        }
+        if !imports_count_out.is_null() {
+            imports_count_out.write(count);
+        }
+        lucet_error::Ok
+    })
+}
+
+/// Copies the module's globals to `globals_out`, which has room for `globals_len` globals, in index
+/// order. The total number of globals is written to `globals_count_out`, even if it exceeds
+/// `globals_len`.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_dl_module_globals(
+    module: *const lucet_dl_module,
+    globals_out: *mut lucet_module_global,
+    globals_len: usize,
+    globals_count_out: *mut usize,
+) -> lucet_error {
+    if globals_len != 0 && globals_out.is_null() {
+        return lucet_error::InvalidArgument;
+    }
+    with_ffi_arcs!([module: DlModule], {
+        let mut count = 0;
+        for global in module.globals() {
+            if count < globals_len {
+                globals_out.add(count).write((&global).into());
+            }
+            count += 1;
+        }
+        if !globals_count_out.is_null() {
+            globals_count_out.write(count);
+        }
+        lucet_error::Ok
+    })
+}
+
+/// Writes the module's linear memory to `memory_out`, and whether it has one to
+/// `has_memory_out`. `memory_out` is left untouched if the module has no memory.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_dl_module_memory(
+    module: *const lucet_dl_module,
+    has_memory_out: *mut bool,
+    memory_out: *mut lucet_module_memory,
+) -> lucet_error {
+    assert_nonnull!(has_memory_out);
+    assert_nonnull!(memory_out);
+    with_ffi_arcs!([module: DlModule], {
+        let memory = module.memory();
+        if let Some(memory) = memory.as_ref() {
+            memory_out.write(memory.into());
+        }
+        has_memory_out.write(memory.is_some());
+        lucet_error::Ok
+    })
+}
+
+/// Copies the parameter and result types of the function with index `func_index`, as given by
+/// `lucet_dl_module_exports()` or `lucet_dl_module_imports()`, to `params_out` and `results_out`,
+/// which have room for `params_len` and `results_len` types. The total numbers of parameters and
+/// results are written to `params_count_out` and `results_count_out`, even if they exceed the room
+/// available.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_dl_module_func_signature(
+    module: *const lucet_dl_module,
+    func_index: u32,
+    params_out: *mut lucet_value_type,
+    params_len: usize,
+    params_count_out: *mut usize,
+    results_out: *mut lucet_value_type,
+    results_len: usize,
+    results_count_out: *mut usize,
+) -> lucet_error {
+    if (params_len != 0 && params_out.is_null()) || (results_len != 0 && results_out.is_null()) {
+        return lucet_error::InvalidArgument;
+    }
+    with_ffi_arcs!([module: DlModule], {
+        // the module must not be released early, so this can't return from the block
+        if func_index as usize >= module.function_manifest().len() {
+            lucet_error::FuncNotFound
+        } else {
+            let sig = module.get_signature(FunctionIndex::from_u32(func_index));
+            for (i, ty) in sig.params.iter().take(params_len).enumerate() {
+                params_out.add(i).write((*ty).into());
+            }
+            for (i, ty) in sig.results.iter().take(results_len).enumerate() {
+                results_out.add(i).write((*ty).into());
+            }
+            if !params_count_out.is_null() {
+                params_count_out.write(sig.params.len());
+            }
+            if !results_count_out.is_null() {
+                results_count_out.write(sig.results.len());
+            }
+            lucet_error::Ok
+        }
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn lucet_instance_run(
     inst: *mut lucet_instance,
@@ -257,6 +409,131 @@ pub unsafe extern "C" fn lucet_instance_run_func_idx(
     })
 }
 
+/// Copies the values returned by the most recent entrypoint that ran to completion to
+/// `results_out`, which has room for `results_len` values. The total number of values returned is
+/// written to `results_count_out`, even if it exceeds `results_len`.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_instance_results(
+    inst: *const lucet_instance,
+    results_out: *mut lucet_val::lucet_val,
+    results_len: usize,
+    results_count_out: *mut usize,
+) -> lucet_error {
+    if results_len != 0 && results_out.is_null() {
+        return lucet_error::InvalidArgument;
+    }
+    with_instance_ptr!(inst, {
+        let results = inst.results();
+        for (i, val) in results.iter().take(results_len).enumerate() {
+            results_out.add(i).write(val.into());
+        }
+        if !results_count_out.is_null() {
+            results_count_out.write(results.len());
+        }
+        lucet_error::Ok
+    })
+}
+
+/// Copies the WebAssembly backtrace of a faulted instance to `frames_out`, which has room for
+/// `frames_len` frames, innermost first. The total number of frames is written to
+/// `frames_count_out`, even if it exceeds `frames_len`; it is zero if the instance is not faulted.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_instance_backtrace(
+    inst: *const lucet_instance,
+    frames_out: *mut lucet_result::lucet_wasm_frame,
+    frames_len: usize,
+    frames_count_out: *mut usize,
+) -> lucet_error {
+    if frames_len != 0 && frames_out.is_null() {
+        return lucet_error::InvalidArgument;
+    }
+    with_instance_ptr!(inst, {
+        let backtrace = inst
+            .fault_details()
+            .map(|details| details.backtrace.as_slice())
+            .unwrap_or(&[]);
+        for (i, frame) in backtrace.iter().take(frames_len).enumerate() {
+            frames_out.add(i).write(frame.into());
+        }
+        if !frames_count_out.is_null() {
+            frames_count_out.write(backtrace.len());
+        }
+        lucet_error::Ok
+    })
+}
+
+/// Writes the instance's runtime statistics, accumulated since it was created or last reset, to
+/// `stats_out`.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_instance_stats(
+    inst: *const lucet_instance,
+    stats_out: *mut lucet_instance_stats,
+) -> lucet_error {
+    assert_nonnull!(stats_out);
+    with_instance_ptr!(inst, {
+        stats_out.write((&inst.stats()).into());
+        lucet_error::Ok
+    })
+}
+
+/// Starts or stops measuring the CPU time reported in the instance's runtime statistics, which is
+/// off by default.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_instance_set_cpu_stats(
+    inst: *mut lucet_instance,
+    enabled: bool,
+) -> lucet_error {
+    with_instance_ptr!(inst, {
+        inst.set_cpu_stats(enabled);
+        lucet_error::Ok
+    })
+}
+
+/// Writes the value of the global exported as `name` to `val_out`.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_instance_get_global(
+    inst: *const lucet_instance,
+    name: *const c_char,
+    val_out: *mut lucet_val::lucet_val,
+) -> lucet_error {
+    assert_nonnull!(name);
+    assert_nonnull!(val_out);
+    let name = match CStr::from_ptr(name).to_str() {
+        Ok(name) => name,
+        Err(_) => return lucet_error::SymbolNotFound,
+    };
+    with_instance_ptr!(inst, {
+        match inst.get_global(name) {
+            Ok(val) => {
+                val_out.write(val.into());
+                lucet_error::Ok
+            }
+            Err(e) => e.into(),
+        }
+    })
+}
+
+/// Sets the global exported as `name` to `val`, which must be of the global's type. Immutable
+/// globals cannot be set.
+#[no_mangle]
+pub unsafe extern "C" fn lucet_instance_set_global(
+    inst: *mut lucet_instance,
+    name: *const c_char,
+    val: *const lucet_val::lucet_val,
+) -> lucet_error {
+    assert_nonnull!(name);
+    assert_nonnull!(val);
+    let name = match CStr::from_ptr(name).to_str() {
+        Ok(name) => name,
+        Err(_) => return lucet_error::SymbolNotFound,
+    };
+    with_instance_ptr!(inst, {
+        inst.set_global(name, (&*val).into())
+            .map(|_| lucet_error::Ok)
+            .unwrap_or_else(|e| e.into())
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn lucet_instance_resume(
     inst: *const lucet_instance,
@@ -412,6 +689,20 @@ pub fn ensure_linked() {
             lucet_vmctx_get_heap as _,
             lucet_vmctx_current_memory as _,
             lucet_vmctx_grow_memory as _,
+            lucet_vmctx_memory_copy as _,
+            lucet_vmctx_memory_fill as _,
+            lucet_vmctx_memory_init as _,
+            lucet_vmctx_data_drop as _,
+            lucet_vmctx_elem_drop as _,
+            lucet_vmctx_table_get as _,
+            lucet_vmctx_table_set as _,
+            lucet_vmctx_table_grow as _,
+            lucet_vmctx_table_fill as _,
+            lucet_vmctx_table_copy as _,
+            lucet_vmctx_table_init as _,
+            lucet_vmctx_ref_func as _,
+            lucet_vmctx_out_of_fuel as _,
+            lucet_vmctx_epoch_interrupt as _,
         ];
         for func in funcs {
             assert_ne!(*func, std::ptr::null(), "hostcall address is not null");
@@ -419,6 +710,42 @@ pub fn ensure_linked() {
     });
 }
 
+/// Symbols for loading modules with [`ObjectModule`](../struct.ObjectModule.html).
+///
+/// This contains the libcalls in `ObjectSymbols::new()` plus the runtime functions that compiled
+/// code calls. Hostcalls imported by a module must still be added by the caller.
+pub fn runtime_symbols() -> ObjectSymbols {
+    let funcs: &[(&str, *const c_void)] = &[
+        (
+            "lucet_vmctx_current_memory",
+            lucet_vmctx_current_memory as _,
+        ),
+        ("lucet_vmctx_grow_memory", lucet_vmctx_grow_memory as _),
+        ("lucet_vmctx_memory_copy", lucet_vmctx_memory_copy as _),
+        ("lucet_vmctx_memory_fill", lucet_vmctx_memory_fill as _),
+        ("lucet_vmctx_memory_init", lucet_vmctx_memory_init as _),
+        ("lucet_vmctx_data_drop", lucet_vmctx_data_drop as _),
+        ("lucet_vmctx_elem_drop", lucet_vmctx_elem_drop as _),
+        ("lucet_vmctx_table_get", lucet_vmctx_table_get as _),
+        ("lucet_vmctx_table_set", lucet_vmctx_table_set as _),
+        ("lucet_vmctx_table_grow", lucet_vmctx_table_grow as _),
+        ("lucet_vmctx_table_fill", lucet_vmctx_table_fill as _),
+        ("lucet_vmctx_table_copy", lucet_vmctx_table_copy as _),
+        ("lucet_vmctx_table_init", lucet_vmctx_table_init as _),
+        ("lucet_vmctx_ref_func", lucet_vmctx_ref_func as _),
+        ("lucet_vmctx_out_of_fuel", lucet_vmctx_out_of_fuel as _),
+        (
+            "lucet_vmctx_epoch_interrupt",
+            lucet_vmctx_epoch_interrupt as _,
+        ),
+    ];
+    let mut symbols = ObjectSymbols::new();
+    for (name, func) in funcs {
+        symbols.insert(*name, *func);
+    }
+    symbols
+}
+
 #[lucet_hostcall]
 #[no_mangle]
 pub unsafe extern "C" fn lucet_vmctx_get_heap(vmctx: &Vmctx) -> *mut u8 {
@@ -427,8 +754,8 @@ pub unsafe extern "C" fn lucet_vmctx_get_heap(vmctx: &Vmctx) -> *mut u8 {
 
 #[lucet_hostcall]
 #[no_mangle]
-pub unsafe extern "C" fn lucet_vmctx_get_globals(vmctx: &Vmctx) -> *mut i64 {
-    vmctx.instance().alloc().slot().globals as *mut i64
+pub unsafe extern "C" fn lucet_vmctx_get_globals(vmctx: &Vmctx) -> *mut GlobalValue {
+    vmctx.instance().alloc().slot().globals as *mut GlobalValue
 }
 
 #[lucet_hostcall]
@@ -451,6 +778,191 @@ pub unsafe extern "C" fn lucet_vmctx_grow_memory(vmctx: &Vmctx, additional_pages
     }
 }
 
+#[lucet_hostcall]
+#[no_mangle]
+/// Copies `len` bytes of the guest heap from `src` to `dst`; the ranges may overlap.
+///
+/// Returns `0` on success, or `1` without modifying the heap if either range is out of bounds.
+pub unsafe extern "C" fn lucet_vmctx_memory_copy(
+    vmctx: &Vmctx,
+    dst: u32,
+    src: u32,
+    len: u32,
+) -> i32 {
+    !vmctx.instance_mut().memory_copy(dst, src, len) as i32
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Sets `len` bytes of the guest heap starting at `dst` to the low byte of `val`.
+///
+/// Returns `0` on success, or `1` without modifying the heap if the range is out of bounds.
+pub unsafe extern "C" fn lucet_vmctx_memory_fill(
+    vmctx: &Vmctx,
+    dst: u32,
+    val: u32,
+    len: u32,
+) -> i32 {
+    !vmctx.instance_mut().memory_fill(dst, val as u8, len) as i32
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Copies `len` bytes starting at `src` of a passive data segment to `dst` in the guest heap.
+///
+/// Returns `0` on success, or `1` without modifying the heap if either range is out of bounds.
+pub unsafe extern "C" fn lucet_vmctx_memory_init(
+    vmctx: &Vmctx,
+    segment: u32,
+    dst: u32,
+    src: u32,
+    len: u32,
+) -> i32 {
+    !vmctx.instance_mut().memory_init(segment, dst, src, len) as i32
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Drops a data segment, making it unavailable to further `memory.init` instructions.
+pub unsafe extern "C" fn lucet_vmctx_data_drop(vmctx: &Vmctx, segment: u32) {
+    vmctx.instance_mut().data_drop(segment)
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Drops an element segment, making it unavailable to further `table.init` instructions.
+pub unsafe extern "C" fn lucet_vmctx_elem_drop(vmctx: &Vmctx, segment: u32) {
+    vmctx.instance_mut().elem_drop(segment)
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Gets an element of a table. The guest checks that the element is in bounds, so the instance is
+/// terminated if it is not.
+pub unsafe extern "C" fn lucet_vmctx_table_get(
+    vmctx: &Vmctx,
+    table: u32,
+    index: u32,
+) -> *const c_void {
+    match vmctx.instance().table_get(table, index) {
+        Some(element) => element as *const c_void,
+        None => lucet_hostcall_terminate!("table.get out of bounds"),
+    }
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Sets an element of a table. The guest checks that the element is in bounds, so the instance is
+/// terminated if it is not.
+pub unsafe extern "C" fn lucet_vmctx_table_set(
+    vmctx: &Vmctx,
+    table: u32,
+    index: u32,
+    val: *const c_void,
+) {
+    if !vmctx.instance_mut().table_set(table, index, val as u64) {
+        lucet_hostcall_terminate!("table.set out of bounds");
+    }
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Grows a table by `delta` elements set to `init`.
+///
+/// On success, returns the number of elements that existed before the call. On failure, returns
+/// `-1`.
+pub unsafe extern "C" fn lucet_vmctx_table_grow(
+    vmctx: &Vmctx,
+    table: u32,
+    delta: u32,
+    init: *const c_void,
+) -> i32 {
+    if let Some(orig_len) = vmctx.instance_mut().table_grow(table, delta, init as u64) {
+        orig_len as i32
+    } else {
+        -1
+    }
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Sets `len` elements of a table starting at `dst` to `val`.
+///
+/// Returns `0` on success, or `1` without modifying the table if the range is out of bounds.
+pub unsafe extern "C" fn lucet_vmctx_table_fill(
+    vmctx: &Vmctx,
+    table: u32,
+    dst: u32,
+    val: *const c_void,
+    len: u32,
+) -> i32 {
+    !vmctx.instance_mut().table_fill(table, dst, val as u64, len) as i32
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Copies `len` elements starting at `src` of one table to `dst` of another; the tables may be
+/// the same, and the ranges may overlap.
+///
+/// Returns `0` on success, or `1` without modifying the tables if either range is out of bounds.
+pub unsafe extern "C" fn lucet_vmctx_table_copy(
+    vmctx: &Vmctx,
+    dst_table: u32,
+    src_table: u32,
+    dst: u32,
+    src: u32,
+    len: u32,
+) -> i32 {
+    !vmctx
+        .instance_mut()
+        .table_copy(dst_table, src_table, dst, src, len) as i32
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Copies `len` elements starting at `src` of a passive element segment to `dst` in a table.
+///
+/// Returns `0` on success, or `1` without modifying the table if either range is out of bounds.
+pub unsafe extern "C" fn lucet_vmctx_table_init(
+    vmctx: &Vmctx,
+    table: u32,
+    segment: u32,
+    dst: u32,
+    src: u32,
+    len: u32,
+) -> i32 {
+    !vmctx
+        .instance_mut()
+        .table_init(table, segment, dst, src, len) as i32
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Gets the `funcref` for a function of the module.
+pub unsafe extern "C" fn lucet_vmctx_ref_func(vmctx: &Vmctx, func: u32) -> *const c_void {
+    vmctx.instance().ref_func(func) as *const c_void
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Called by instruction-counted code when the instance has used up its fuel.
+///
+/// Yields `OutOfFuel` so the host can add more fuel, and terminates the instance if it is resumed
+/// without any.
+pub unsafe extern "C" fn lucet_vmctx_out_of_fuel(vmctx: &Vmctx) {
+    vmctx.yield_val(OutOfFuel);
+    if vmctx.instance().get_fuel() == Some(0) {
+        panic!(TerminationDetails::OutOfFuel);
+    }
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+/// Called by epoch-interruptible code when the instance's epoch has reached its deadline.
+pub unsafe extern "C" fn lucet_vmctx_epoch_interrupt(_vmctx: &Vmctx) {
+    panic!(TerminationDetails::Remote);
+}
+
 #[lucet_hostcall]
 #[no_mangle]
 /// Check if a memory region is inside the instance heap.
@@ -403,26 +403,32 @@ extern crate self as lucet_runtime;
 
 pub mod c_api;
 
-pub use lucet_module::{PublicKey, TrapCode};
+pub use crate::c_api::runtime_symbols;
+pub use lucet_module::{FunctionIndex, InitialState, PublicKey, Signature, TrapCode, ValueType};
 pub use lucet_runtime_internals::alloc::{AllocStrategy, Limits, DEFAULT_SIGNAL_STACK_SIZE};
 pub use lucet_runtime_internals::error::Error;
 pub use lucet_runtime_internals::instance::signals::{
     install_lucet_signal_handler, remove_lucet_signal_handler,
 };
 pub use lucet_runtime_internals::instance::{
-    FaultDetails, Instance, InstanceHandle, KillError, KillSuccess, KillSwitch, RunResult,
-    SignalBehavior, TerminationDetails, YieldedVal,
+    EpochHandle, FaultDetails, Instance, InstanceHandle, InstanceSnapshot, InstanceStats,
+    KillError, KillSuccess, KillSwitch, OutOfFuel, RunResult, SignalBehavior, TerminationDetails,
+    TypedFunc, WasmFrame, YieldedVal,
 };
 #[allow(deprecated)]
+pub use lucet_runtime_internals::linker::{HostFunc, Hostcall, IntoHostFunc, Linker};
 pub use lucet_runtime_internals::lucet_hostcalls;
-pub use lucet_runtime_internals::module::{DlModule, Module};
+pub use lucet_runtime_internals::module::{
+    DlModule, Export, ExportKind, GlobalInfo, Import, ImportKind, MemoryInfo, Module, ObjectModule,
+    ObjectSymbols,
+};
 pub use lucet_runtime_internals::region::mmap::MmapRegion;
 #[cfg(all(target_os = "linux", feature = "uffd"))]
 pub use lucet_runtime_internals::region::uffd::{
     HostPageSizedUffdStrategy, UffdRegion, UffdStrategy, WasmPageSizedUffdStrategy,
 };
-pub use lucet_runtime_internals::region::{InstanceBuilder, Region, RegionCreate};
-pub use lucet_runtime_internals::val::{UntypedRetVal, Val};
+pub use lucet_runtime_internals::region::{ElasticCapacity, InstanceBuilder, Region, RegionCreate};
+pub use lucet_runtime_internals::val::{UntypedRetVal, Val, WasmParams, WasmResults, WasmTy};
 pub use lucet_runtime_internals::{lucet_hostcall, lucet_hostcall_terminate, WASM_PAGE_SIZE};
 
 pub mod vmctx {
@@ -0,0 +1,121 @@
+use anyhow::Error;
+use lucet_runtime::{
+    DlModule, Error as RuntimeError, FaultDetails, Limits, MmapRegion, Region, RunResult, TrapCode,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::Lucetc;
+use std::sync::Arc;
+
+fn backtrace_module() -> Result<Arc<DlModule>, Error> {
+    let native_build = Lucetc::new("./tests/backtrace/module.wat");
+
+    lucetc_test(native_build)
+}
+
+fn unwrap_fault(res: Result<RunResult, RuntimeError>) -> FaultDetails {
+    match res {
+        Err(RuntimeError::RuntimeFault(details)) => {
+            assert_eq!(details.trapcode, Some(TrapCode::Unreachable));
+            details
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn backtrace_through_calls() {
+    let module = backtrace_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let details = unwrap_fault(inst.run("outer", &[0u32.into()]));
+
+    let frames = &details.backtrace;
+    assert_eq!(frames.len(), 3, "backtrace: {:?}", frames);
+
+    assert_eq!(frames[0].func_index.as_u32(), 0);
+    assert_eq!(frames[0].func_name.as_deref(), Some("inner"));
+
+    // `middle` is not exported, so it is named after the name section
+    assert_eq!(frames[1].func_index.as_u32(), 1);
+    assert!(frames[1]
+        .func_name
+        .as_deref()
+        .expect("middle has a name")
+        .starts_with("middle"));
+
+    assert_eq!(frames[2].func_index.as_u32(), 2);
+    assert_eq!(frames[2].func_name.as_deref(), Some("outer"));
+
+    // the instance keeps the details until it is reset
+    assert_eq!(
+        inst.fault_details().map(|details| &details.backtrace),
+        Some(frames)
+    );
+    inst.reset().expect("instance can be reset");
+    assert!(inst.fault_details().is_none());
+}
+
+#[test]
+fn backtrace_from_entrypoint() {
+    let module = backtrace_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let details = unwrap_fault(inst.run("inner", &[0u32.into()]));
+
+    assert_eq!(details.backtrace.len(), 1);
+    assert_eq!(details.backtrace[0].func_name.as_deref(), Some("inner"));
+}
+
+#[test]
+fn backtrace_in_display() {
+    let module = backtrace_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let details = unwrap_fault(inst.run("outer", &[0u32.into()]));
+    let message = details.to_string();
+
+    assert!(message.contains("wasm backtrace:"), "message: {}", message);
+    assert!(
+        message.contains("0: func[0] inner+"),
+        "message: {}",
+        message
+    );
+    assert!(
+        message.contains("2: func[2] outer+"),
+        "message: {}",
+        message
+    );
+}
+
+#[test]
+fn wasm_offsets() {
+    let module = backtrace_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let details = unwrap_fault(inst.run("outer", &[0u32.into()]));
+
+    let (func_index, wasm_offset) = details.wasm_location().expect("fault has a wasm location");
+    assert_eq!(func_index.as_u32(), 0);
+
+    // the functions are laid out in order in the code section, so each caller's call instruction
+    // comes after the instruction that faulted
+    let offsets: Vec<u32> = details
+        .backtrace
+        .iter()
+        .map(|frame| frame.wasm_offset.expect("frame has a wasm offset"))
+        .collect();
+    assert_eq!(offsets[0], wasm_offset);
+    assert!(offsets[0] < offsets[1] && offsets[1] < offsets[2]);
+}
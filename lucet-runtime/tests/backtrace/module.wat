@@ -0,0 +1,10 @@
+(module
+  (func $inner (export "inner") (param i32) (result i32)
+    (if (i32.eqz (local.get 0))
+      (then unreachable))
+    (local.get 0))
+  (func $middle (param i32) (result i32)
+    (i32.add (call $inner (local.get 0)) (i32.const 1)))
+  (func $outer (export "outer") (param i32) (result i32)
+    (i32.add (call $middle (local.get 0)) (i32.const 1)))
+)
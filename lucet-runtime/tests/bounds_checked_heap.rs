@@ -0,0 +1,96 @@
+use anyhow::Error;
+use lucet_runtime::{
+    DlModule, Error as RuntimeError, Limits, MmapRegion, Region, RunResult, TrapCode,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Lucetc, LucetcOpts};
+use std::sync::Arc;
+
+fn heap_module(bounds_checked: bool) -> Result<Arc<DlModule>, Error> {
+    let native_build = Lucetc::new("./tests/bounds_checked_heap/heap.wat")
+        .with_bounds_checked_heap(bounds_checked);
+
+    lucetc_test(native_build)
+}
+
+fn assert_heap_out_of_bounds(res: Result<RunResult, RuntimeError>) {
+    match res {
+        Err(RuntimeError::RuntimeFault(details)) => {
+            assert_eq!(details.trapcode, Some(TrapCode::HeapOutOfBounds));
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn dense_limits() {
+    let limits = Limits::bounds_checked();
+    assert_eq!(limits.heap_address_space_size, limits.heap_memory_size);
+    limits.validate().expect("limits are valid");
+}
+
+#[test]
+fn in_bounds_access() {
+    let module = heap_module(true).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::bounds_checked()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    inst.heap_u32_mut()[64 * 1024 / 4 - 1] = 0xdead_beef;
+    let res = inst
+        .run("load", &[(64 * 1024 - 4u32).into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 0xdead_beef);
+}
+
+#[test]
+fn out_of_bounds_access() {
+    let module = heap_module(true).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::bounds_checked()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    // straddles the end of the heap
+    assert_heap_out_of_bounds(inst.run("load", &[(64 * 1024 - 2u32).into()]));
+
+    inst.reset().expect("instance resets");
+    assert_heap_out_of_bounds(inst.run("load", &[(64 * 1024u32).into()]));
+}
+
+#[test]
+fn access_after_grow() {
+    let module = heap_module(true).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::bounds_checked()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let res = inst
+        .run("grow", &[1u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 1);
+
+    let res = inst
+        .run("load", &[(64 * 1024u32).into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 0);
+
+    // the heap shrinks back to its initial size on reset
+    inst.reset().expect("instance resets");
+    assert_heap_out_of_bounds(inst.run("load", &[(64 * 1024u32).into()]));
+}
+
+#[test]
+fn reject_guard_page_module() {
+    let module = heap_module(false).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::bounds_checked()).expect("region can be created");
+    match region.new_instance(module) {
+        Err(RuntimeError::LimitsExceeded(_)) => (),
+        res => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+}
@@ -0,0 +1,11 @@
+(module
+  (memory 1)
+  (func $load (export "load") (param $addr i32) (result i32)
+    get_local $addr
+    i32.load
+  )
+  (func $grow (export "grow") (param $pages i32) (result i32)
+    get_local $pages
+    memory.grow
+  )
+)
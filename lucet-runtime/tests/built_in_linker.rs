@@ -0,0 +1,111 @@
+use anyhow::Error;
+use lucet_runtime::vmctx::Vmctx;
+use lucet_runtime::{
+    lucet_hostcall, DlModule, Error as RuntimeError, Limits, MmapRegion, Region, TrapCode,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Bindings, Linker, Lucetc, LucetcOpts};
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::Arc;
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn built_in_linker_double(_vmctx: &Vmctx, x: u32) -> u32 {
+    x * 2
+}
+
+fn linked_module(linker: Linker) -> Result<Arc<DlModule>, Error> {
+    let mut env = HashMap::new();
+    env.insert("double".to_owned(), "built_in_linker_double".to_owned());
+    let native_build = Lucetc::new("./tests/built_in_linker/module.wat")
+        .with_bindings(Bindings::env(env))
+        .with_linker(linker);
+
+    lucetc_test(native_build)
+}
+
+/// Whether `ld` can be run, so that the module can also be linked externally for comparison.
+fn have_ld() -> bool {
+    Command::new("ld").arg("--version").output().is_ok()
+}
+
+/// Run every export of the module, which must behave the same however it was linked.
+fn check_module(module: Arc<DlModule>) {
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let res = inst
+        .run("double_plus_one", &[20u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 41);
+
+    let res = inst
+        .run("call_indirect", &[0u32.into(), 10u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 11);
+    let res = inst
+        .run("call_indirect", &[1u32.into(), 10u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 9);
+    match inst.run("call_indirect", &[2u32.into(), 10u32.into()]) {
+        Err(RuntimeError::RuntimeFault(details)) => {
+            assert_eq!(details.trapcode, Some(TrapCode::TableOutOfBounds));
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+    inst.reset().expect("instance can be reset");
+
+    let res = inst
+        .run("load", &[16u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 42);
+
+    for expected in 6..8u32 {
+        let res = inst
+            .run("bump", &[])
+            .expect("instance runs")
+            .unwrap_returned();
+        assert_eq!(res.as_u32(), expected);
+    }
+}
+
+#[test]
+fn run_built_in_linked_module() {
+    let module = linked_module(Linker::BuiltIn).expect("can load module");
+    check_module(module);
+}
+
+#[test]
+fn run_externally_linked_module() {
+    if !have_ld() {
+        return;
+    }
+    let module = linked_module(Linker::External).expect("can load module");
+    check_module(module);
+}
+
+#[test]
+fn instances_of_built_in_linked_module_are_independent() {
+    let module = linked_module(Linker::BuiltIn).expect("can load module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+    let mut first = region
+        .new_instance(module.clone())
+        .expect("instance can be created");
+    let mut second = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    first.run("bump", &[]).expect("instance runs");
+    let res = second
+        .run("bump", &[])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 6);
+}
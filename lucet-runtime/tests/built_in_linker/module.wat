@@ -0,0 +1,27 @@
+(module
+  (import "env" "double" (func $double (param i32) (result i32)))
+  (memory 1)
+  (data (i32.const 16) "\2a\00\00\00")
+  (table 2 funcref)
+  (elem (i32.const 0) $inc $dec)
+  (global $counter (mut i32) (i32.const 5))
+  (func $inc (param i32) (result i32)
+    (i32.add (local.get 0) (i32.const 1))
+  )
+  (func $dec (param i32) (result i32)
+    (i32.sub (local.get 0) (i32.const 1))
+  )
+  (func (export "double_plus_one") (param i32) (result i32)
+    (i32.add (call $double (local.get 0)) (i32.const 1))
+  )
+  (func (export "call_indirect") (param i32 i32) (result i32)
+    (call_indirect (param i32) (result i32) (local.get 1) (local.get 0))
+  )
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0))
+  )
+  (func (export "bump") (result i32)
+    (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+    (global.get $counter)
+  )
+)
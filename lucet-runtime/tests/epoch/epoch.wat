@@ -0,0 +1,12 @@
+(module
+  (func $add (export "add") (param i32 i32) (result i32)
+    get_local 0
+    get_local 1
+    i32.add
+  )
+  (func $spin (export "spin")
+    loop $forever
+      br $forever
+    end
+  )
+)
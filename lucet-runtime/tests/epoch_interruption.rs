@@ -0,0 +1,134 @@
+use anyhow::Error;
+use lucet_runtime::{
+    DlModule, EpochHandle, Error as RuntimeError, Limits, MmapRegion, Region, RunResult,
+    TerminationDetails,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Lucetc, LucetcOpts};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn epoch_module(epoch_interruption: bool) -> Result<Arc<DlModule>, Error> {
+    let native_build =
+        Lucetc::new("./tests/epoch/epoch.wat").with_epoch_interruption(epoch_interruption);
+
+    lucetc_test(native_build)
+}
+
+fn assert_interrupted(res: Result<RunResult, RuntimeError>) {
+    match res {
+        Err(RuntimeError::RuntimeTerminated(TerminationDetails::Remote)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn runs_without_increment() {
+    let module = epoch_module(true).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let res = inst
+        .run("add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
+
+#[test]
+fn interrupt_from_another_thread() {
+    let module = epoch_module(true).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let epoch = inst.epoch_handle();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        epoch.increment();
+    });
+
+    assert_interrupted(inst.run("spin", &[]));
+    t.join().unwrap();
+
+    // the instance can run again once it has been reset
+    inst.reset().expect("instance resets");
+    let res = inst
+        .run("add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
+
+#[test]
+fn interrupt_at_function_entry() {
+    let module = epoch_module(true).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    inst.epoch_handle().increment();
+    assert_interrupted(inst.run("add", &[1u32.into(), 2u32.into()]));
+}
+
+#[test]
+fn epoch_deadline() {
+    let module = epoch_module(true).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let epoch = inst.epoch_handle();
+    inst.set_epoch_deadline(2);
+    epoch.increment();
+    let res = inst
+        .run("add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+
+    epoch.increment();
+    assert_interrupted(inst.run("add", &[1u32.into(), 2u32.into()]));
+}
+
+#[test]
+fn shared_epoch_handle() {
+    let module = epoch_module(true).expect("can load module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+    let mut inst1 = region
+        .new_instance(module.clone())
+        .expect("instance can be created");
+    let mut inst2 = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let epoch = EpochHandle::new();
+    inst1.set_epoch_handle(epoch.clone());
+    inst2.set_epoch_handle(epoch.clone());
+
+    epoch.increment();
+    assert_interrupted(inst1.run("add", &[1u32.into(), 2u32.into()]));
+    assert_interrupted(inst2.run("add", &[1u32.into(), 2u32.into()]));
+}
+
+#[test]
+fn increment_without_instrumentation() {
+    let module = epoch_module(false).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    inst.epoch_handle().increment();
+    let res = inst
+        .run("add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
@@ -0,0 +1,55 @@
+(module
+  (func $count_to (export "count_to") (param $n i32) (result i32) (local $i i32)
+    block $done
+      loop $again
+        get_local $i
+        get_local $n
+        i32.ge_u
+        br_if $done
+        get_local $i
+        i32.const 1
+        i32.add
+        set_local $i
+        br $again
+      end
+    end
+    get_local $i
+  )
+  (func $spin (export "spin")
+    loop $forever
+      br $forever
+    end
+  )
+  (func $recurse (export "recurse")
+    call $recurse
+  )
+  (global $marked (export "marked") (mut i32) (i32.const 0))
+  (func $mark
+    i32.const 1
+    set_global $marked
+  )
+  (func $straight_then_call (export "straight_then_call")
+    ;; twenty instructions with no branch or call, counted only when the count is flushed
+    i32.const 1
+    drop
+    i32.const 2
+    drop
+    i32.const 3
+    drop
+    i32.const 4
+    drop
+    i32.const 5
+    drop
+    i32.const 6
+    drop
+    i32.const 7
+    drop
+    i32.const 8
+    drop
+    i32.const 9
+    drop
+    i32.const 10
+    drop
+    call $mark
+  )
+)
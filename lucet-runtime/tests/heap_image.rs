@@ -0,0 +1,102 @@
+use anyhow::Error;
+use lucet_runtime::{DlModule, InstanceHandle, Limits, MmapRegion, Region, Val};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::Lucetc;
+use std::sync::Arc;
+
+fn load_module(path: &str) -> Result<Arc<DlModule>, Error> {
+    let native_build = Lucetc::new(path);
+
+    lucetc_test(native_build)
+}
+
+fn load8(inst: &mut InstanceHandle, addr: u32) -> u8 {
+    inst.run("load8", &[addr.into()])
+        .expect("instance runs")
+        .unwrap_returned()
+        .as_u32() as u8
+}
+
+fn store8(inst: &mut InstanceHandle, addr: u32, val: u8) {
+    inst.run("store8", &[addr.into(), Val::U32(val as u32)])
+        .expect("instance runs");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn module_has_heap_image() {
+    use lucet_runtime_internals::module::ModuleInternal;
+
+    let module = load_module("./tests/heap_image/module.wat").expect("can load module");
+    let image = module.heap_image().expect("module has a heap image");
+    // the data segments end on the 18th page
+    assert_eq!(image.len(), 18 * 4096);
+
+    let empty = load_module("./tests/heap_image/empty.wat").expect("can load module");
+    assert!(empty.heap_image().is_none());
+}
+
+#[test]
+fn instances_share_initial_heap() {
+    let module = load_module("./tests/heap_image/module.wat").expect("can load module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+    let mut inst1 = region
+        .new_instance(module.clone())
+        .expect("instance can be created");
+    let mut inst2 = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    assert_eq!(load8(&mut inst1, 0), b'l');
+    assert_eq!(load8(&mut inst1, 70000), b's');
+
+    // writes are private to each instance
+    store8(&mut inst1, 0, b'L');
+    store8(&mut inst2, 70000, b'S');
+    assert_eq!(load8(&mut inst1, 0), b'L');
+    assert_eq!(load8(&mut inst1, 70000), b's');
+    assert_eq!(load8(&mut inst2, 0), b'l');
+    assert_eq!(load8(&mut inst2, 70000), b'S');
+    assert_eq!(inst1.heap()[1..5], *b"ucet");
+}
+
+#[test]
+fn reset_restores_initial_heap() {
+    let module = load_module("./tests/heap_image/module.wat").expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    store8(&mut inst, 0, b'L');
+    store8(&mut inst, 100, 1);
+    inst.run("grow", &[]).expect("instance runs");
+    store8(&mut inst, 140000, 2);
+
+    inst.reset().expect("instance can be reset");
+    assert_eq!(inst.heap().len(), 2 * 65536);
+    assert_eq!(load8(&mut inst, 0), b'l');
+    assert_eq!(load8(&mut inst, 100), 0);
+
+    inst.run("grow", &[]).expect("instance runs");
+    assert_eq!(load8(&mut inst, 140000), 0);
+}
+
+#[test]
+fn slot_reused_by_other_module() {
+    let module = load_module("./tests/heap_image/module.wat").expect("can load module");
+    let empty = load_module("./tests/heap_image/empty.wat").expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+    assert_eq!(load8(&mut inst, 0), b'l');
+    drop(inst);
+
+    // the heap image of the first module must not show through in the reused slot
+    let mut inst = region.new_instance(empty).expect("instance can be created");
+    assert_eq!(load8(&mut inst, 0), 0);
+    assert_eq!(load8(&mut inst, 70000), 0);
+    assert!(inst.heap().iter().all(|b| *b == 0));
+}
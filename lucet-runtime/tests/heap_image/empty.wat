@@ -0,0 +1,6 @@
+(module
+  (memory 2)
+
+  (func (export "load8") (param i32) (result i32)
+    (i32.load8_u (local.get 0)))
+)
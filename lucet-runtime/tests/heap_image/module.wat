@@ -0,0 +1,12 @@
+(module
+  (memory 2)
+  (data (i32.const 0) "lucet")
+  (data (i32.const 70000) "second page")
+
+  (func (export "load8") (param i32) (result i32)
+    (i32.load8_u (local.get 0)))
+  (func (export "store8") (param i32 i32)
+    (i32.store8 (local.get 0) (local.get 1)))
+  (func (export "grow") (result i32)
+    (memory.grow (i32.const 1)))
+)
@@ -0,0 +1,151 @@
+use anyhow::Error;
+use lucet_runtime::{
+    DlModule, Error as RuntimeError, InitialState, Limits, MmapRegion, Region, UntypedRetVal, Val,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Lucetc, LucetcOpts};
+use std::sync::Arc;
+
+fn load_module(native_build: Lucetc) -> Result<Arc<DlModule>, Error> {
+    lucetc_test(native_build)
+}
+
+fn initialized_state() -> InitialState {
+    let module =
+        load_module(Lucetc::new("./tests/initial_state/module.wat")).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    // the start function has to run first
+    match inst.initial_state() {
+        Err(RuntimeError::InvalidArgument(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    inst.run_start().expect("start function runs");
+    inst.run("initialize", &[]).expect("instance runs");
+    inst.initial_state().expect("can capture initial state")
+}
+
+fn run(inst: &mut lucet_runtime::InstanceHandle, entrypoint: &str, args: &[Val]) -> UntypedRetVal {
+    inst.run(entrypoint, args)
+        .expect("instance runs")
+        .unwrap_returned()
+}
+
+#[test]
+fn capture_initial_state() {
+    let state = initialized_state();
+
+    assert_eq!(state.heap_len, 2 * 65536);
+    // only the first page has anything in it, besides the one written by `initialize`
+    assert!(state.heap_pages[0].is_some());
+    assert!(state.heap_pages[1..16].iter().all(Option::is_none));
+    assert!(state.heap_pages[16].is_some());
+    assert_eq!(state.heap_pages.len(), 17);
+    assert_eq!(state.globals.len(), 2);
+
+    let bytes = state.to_bytes().expect("can serialize state");
+    assert_eq!(
+        InitialState::from_bytes(&bytes).expect("can deserialize state"),
+        state
+    );
+}
+
+#[test]
+fn preinitialized_module() {
+    let state = initialized_state();
+    let module =
+        load_module(Lucetc::new("./tests/initial_state/module.wat").with_initial_state(state))
+            .expect("can load preinitialized module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    // there is no start function left to run
+    inst.run_start().expect("start function runs");
+
+    assert_eq!(run(&mut inst, "starts", &[]).as_u32(), 1);
+    assert_eq!(run(&mut inst, "flag", &[]).as_i64(), 42);
+    assert_eq!(run(&mut inst, "pages", &[]).as_u32(), 2);
+    assert_eq!(run(&mut inst, "load", &[(3u32 * 4).into()]).as_u32(), 9);
+    assert_eq!(run(&mut inst, "load", &[(15u32 * 4).into()]).as_u32(), 225);
+    assert_eq!(run(&mut inst, "load", &[65544u32.into()]).as_u32(), 7);
+    assert_eq!(run(&mut inst, "load8", &[1024u32.into()]).as_u32(), 74);
+    assert_eq!(
+        run(&mut inst, "load8", &[1025u32.into()]).as_u32(),
+        b'e' as u32
+    );
+
+    // resetting the instance goes back to the preinitialized state
+    run(&mut inst, "initialize", &[]);
+    assert_eq!(run(&mut inst, "pages", &[]).as_u32(), 3);
+    inst.reset().expect("instance can be reset");
+    assert_eq!(run(&mut inst, "pages", &[]).as_u32(), 2);
+    assert_eq!(run(&mut inst, "starts", &[]).as_u32(), 1);
+}
+
+#[test]
+fn initial_state_must_match_module() {
+    let state = initialized_state();
+    let res = load_module(Lucetc::new("./tests/backtrace/module.wat").with_initial_state(state));
+    assert!(res.is_err());
+}
+
+fn unsupported_instance(base: i32) -> lucet_runtime::InstanceHandle {
+    let module =
+        load_module(Lucetc::new("./tests/initial_state/unsupported.wat")).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    region
+        .new_instance_builder(module)
+        .with_global_import("env", "base", Val::I32(base))
+        .build()
+        .expect("instance can be created")
+}
+
+#[test]
+fn initial_state_refuses_uncapturable_changes() {
+    for entrypoint in &["set_table", "drop_elem", "drop_data", "bump_derived"] {
+        let mut inst = unsupported_instance(5);
+        inst.initial_state()
+            .expect("can capture state before initialization");
+        run(&mut inst, entrypoint, &[]);
+        match inst.initial_state() {
+            Err(RuntimeError::Unsupported(_)) => (),
+            res => panic!("unexpected result after {}: {:?}", entrypoint, res),
+        }
+    }
+}
+
+#[test]
+fn globals_from_imports_stay_imported() {
+    let mut inst = unsupported_instance(5);
+    run(&mut inst, "store", &[]);
+    let state = inst.initial_state().expect("can capture initial state");
+    // the global initialized from the import isn't captured
+    assert_eq!(state.globals, vec![None, None]);
+
+    let module = load_module(
+        Lucetc::new("./tests/initial_state/unsupported.wat").with_initial_state(state.clone()),
+    )
+    .expect("can load preinitialized module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_global_import("env", "base", Val::I32(9))
+        .build()
+        .expect("instance can be created");
+    // the heap was captured, but the global takes its value from the new import
+    assert_eq!(run(&mut inst, "load", &[]).as_i32(), 5);
+    assert_eq!(run(&mut inst, "derived", &[]).as_i32(), 9);
+
+    // a state can't bake in a value for it either
+    let mut baked = state;
+    baked.globals[1] = Some(lucet_module::GlobalDef::I32(5));
+    let res =
+        load_module(Lucetc::new("./tests/initial_state/unsupported.wat").with_initial_state(baked));
+    assert!(res.is_err());
+}
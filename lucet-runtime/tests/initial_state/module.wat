@@ -0,0 +1,35 @@
+(module
+  (memory 1 4)
+  (global $starts (mut i32) (i32.const 0))
+  (global $flag (mut i64) (i64.const 0))
+  (data (i32.const 1024) "hello")
+
+  ;; fill the start of the heap with a table of squares
+  (func $start (local $i i32)
+    (loop $fill
+      (i32.store
+        (i32.mul (local.get $i) (i32.const 4))
+        (i32.mul (local.get $i) (local.get $i)))
+      (local.set $i (i32.add (local.get $i) (i32.const 1)))
+      (br_if $fill (i32.lt_u (local.get $i) (i32.const 16))))
+    (global.set $starts (i32.add (global.get $starts) (i32.const 1))))
+  (start $start)
+
+  (func (export "initialize")
+    (drop (memory.grow (i32.const 1)))
+    (i32.store (i32.const 65544) (i32.const 7))
+    ;; overwrite part of the data segment
+    (i32.store8 (i32.const 1024) (i32.const 74))
+    (global.set $flag (i64.const 42)))
+
+  (func (export "starts") (result i32)
+    (global.get $starts))
+  (func (export "flag") (result i64)
+    (global.get $flag))
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0)))
+  (func (export "load8") (param i32) (result i32)
+    (i32.load8_u (local.get 0)))
+  (func (export "pages") (result i32)
+    (memory.size))
+)
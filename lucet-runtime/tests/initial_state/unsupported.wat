@@ -0,0 +1,29 @@
+(module
+  (import "env" "base" (global $base i32))
+  (memory 1)
+  (table 2 funcref)
+  (global $derived (mut i32) (global.get $base))
+  (elem (i32.const 0) $f)
+  (elem $passive func $f)
+  (data $passive_data "abc")
+
+  (func $f)
+
+  ;; each of these makes a change that an initial state can't capture
+  (func (export "set_table")
+    (table.set (i32.const 1) (ref.func $f)))
+  (func (export "drop_elem")
+    (elem.drop $passive))
+  (func (export "drop_data")
+    (data.drop $passive_data))
+  (func (export "bump_derived")
+    (global.set $derived (i32.add (global.get $derived) (i32.const 1))))
+
+  ;; this one can be captured
+  (func (export "store")
+    (i32.store (i32.const 0) (global.get $derived)))
+  (func (export "load") (result i32)
+    (i32.load (i32.const 0)))
+  (func (export "derived") (result i32)
+    (global.get $derived))
+)
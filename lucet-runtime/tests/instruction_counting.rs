@@ -1,27 +1,20 @@
 use anyhow::Error;
-use lucet_runtime::{DlModule, Limits, MmapRegion, Region, RunResult};
+use lucet_runtime::{
+    DlModule, Error as RuntimeError, Limits, MmapRegion, OutOfFuel, Region, RunResult,
+    TerminationDetails, Val,
+};
+use lucet_runtime_tests::build::lucetc_test;
 use lucetc::{Lucetc, LucetcOpts};
 use rayon::prelude::*;
 use std::fs::DirEntry;
 use std::path::Path;
 use std::sync::Arc;
-use tempfile::TempDir;
 
 pub fn wasm_test<P: AsRef<Path>>(
     wasm_file: P,
     icount_option: bool,
 ) -> Result<Arc<DlModule>, Error> {
-    let workdir = TempDir::new().expect("create working directory");
-
-    let native_build = Lucetc::new(wasm_file).with_count_instructions(icount_option);
-
-    let so_file = workdir.path().join("out.so");
-
-    native_build.shared_object_file(so_file.clone())?;
-
-    let dlmodule = DlModule::load(so_file)?;
-
-    Ok(dlmodule)
+    lucetc_test(Lucetc::new(wasm_file).with_count_instructions(icount_option))
 }
 
 pub fn get_instruction_count_test_files() -> Vec<DirEntry> {
@@ -110,6 +103,132 @@ pub fn check_instruction_count() {
     });
 }
 
+fn assert_out_of_fuel(res: Result<RunResult, RuntimeError>) {
+    match res {
+        Ok(RunResult::Yielded(val)) => {
+            assert!(val.downcast_ref::<OutOfFuel>().is_some());
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+pub fn fuel_unlimited_by_default() {
+    let module = wasm_test("./tests/fuel/fuel.wat", true).expect("can load instrumented module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let res = inst
+        .run("count_to", &[1000u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 1000);
+}
+
+#[test]
+pub fn fuel_without_instrumentation() {
+    let module = wasm_test("./tests/fuel/fuel.wat", false).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    inst.set_fuel(10);
+    assert_eq!(inst.get_fuel(), None);
+    let res = inst
+        .run("count_to", &[1000u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 1000);
+}
+
+#[test]
+pub fn fuel_refuel_and_resume() {
+    let module = wasm_test("./tests/fuel/fuel.wat", true).expect("can load instrumented module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    inst.set_fuel(100);
+    assert_eq!(inst.get_fuel(), Some(100));
+    assert_out_of_fuel(inst.run("count_to", &[1000u32.into()]));
+    assert_eq!(inst.get_fuel(), Some(0));
+
+    inst.set_fuel(1_000_000);
+    let res = inst.resume().expect("instance resumes").unwrap_returned();
+    assert_eq!(res.as_u32(), 1000);
+    assert!(inst.get_fuel().unwrap() < 1_000_000);
+}
+
+#[test]
+pub fn fuel_exhausted_in_loop() {
+    let module = wasm_test("./tests/fuel/fuel.wat", true).expect("can load instrumented module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    inst.set_fuel(1000);
+    assert_out_of_fuel(inst.run("spin", &[]));
+
+    inst.set_fuel(1000);
+    assert_out_of_fuel(inst.resume());
+
+    match inst.resume() {
+        Err(RuntimeError::RuntimeTerminated(TerminationDetails::OutOfFuel)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // resetting the instance gives it unlimited fuel again
+    inst.reset().expect("instance resets");
+    let res = inst
+        .run("count_to", &[10u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 10);
+}
+
+#[test]
+pub fn fuel_exhausted_in_calls() {
+    let module = wasm_test("./tests/fuel/fuel.wat", true).expect("can load instrumented module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    // runs out of fuel long before it runs out of stack
+    inst.set_fuel(1000);
+    assert_out_of_fuel(inst.run("recurse", &[]));
+}
+
+#[test]
+pub fn fuel_counts_straight_line_code_before_call() {
+    let module = wasm_test("./tests/fuel/fuel.wat", true).expect("can load instrumented module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    // the straight-line code alone uses up the fuel, so the check before the call must see its
+    // count, and stop the instance before the callee runs
+    inst.set_fuel(10);
+    assert_out_of_fuel(inst.run("straight_then_call", &[]));
+    match inst.get_global("marked") {
+        Ok(Val::I32(0)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    inst.set_fuel(1000);
+    inst.resume().expect("instance resumes").unwrap_returned();
+    match inst.get_global("marked") {
+        Ok(Val::I32(1)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
 #[test]
 fn ensure_linked() {
     lucet_runtime::lucet_internal_ensure_linked();
@@ -0,0 +1,199 @@
+use anyhow::Error;
+use lucet_runtime::vmctx::{lucet_vmctx, Vmctx};
+use lucet_runtime::{
+    lucet_hostcall, lucet_hostcall_terminate, DlModule, Error as RuntimeError, Limits, Linker,
+    MmapRegion, Region, TerminationDetails,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Lucetc, LucetcOpts};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn linker_sub(_vmctx: &Vmctx, a: u32, b: u32) -> u32 {
+    a.wrapping_sub(b)
+}
+
+fn linker_module() -> Result<Arc<DlModule>, Error> {
+    // no bindings are needed, as the imports are bound when each instance is built
+    let native_build = Lucetc::new("./tests/linker/module.wat").with_linked_imports(true);
+
+    lucetc_test(native_build)
+}
+
+/// A linker whose `add` adds `offset` to the sum, and whose `tick` increments `ticks`.
+fn counting_linker(offset: u32, ticks: Arc<AtomicU32>) -> Linker {
+    let mut linker = Linker::new();
+    linker
+        .func("env", "add", move |_vmctx: &Vmctx, a: u32, b: u32| {
+            a + b + offset
+        })
+        .func("env", "tick", move |_vmctx: &Vmctx| {
+            ticks.fetch_add(1, Ordering::SeqCst);
+        });
+    linker
+}
+
+#[test]
+fn closures_are_called_with_their_state() {
+    let module = linker_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let ticks = Arc::new(AtomicU32::new(0));
+    let linker = counting_linker(100, ticks.clone());
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_linker(&linker)
+        .build()
+        .expect("instance can be created");
+
+    let res = inst
+        .run("call_add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 103);
+
+    inst.run("tick_twice", &[]).expect("instance runs");
+    assert_eq!(ticks.load(Ordering::SeqCst), 2);
+
+    let res = inst
+        .run("tick_and_add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 306);
+    assert_eq!(ticks.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+fn instances_can_bind_different_functions() {
+    let module = linker_module().expect("can load module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+    let ticks_a = Arc::new(AtomicU32::new(0));
+    let ticks_b = Arc::new(AtomicU32::new(0));
+    let linker_a = counting_linker(10, ticks_a.clone());
+    let linker_b = counting_linker(20, ticks_b.clone());
+    let mut inst_a = region
+        .new_instance_builder(module.clone())
+        .with_linker(&linker_a)
+        .build()
+        .expect("instance can be created");
+    let mut inst_b = region
+        .new_instance_builder(module)
+        .with_linker(&linker_b)
+        .build()
+        .expect("instance can be created");
+
+    let res = inst_a
+        .run("call_add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 13);
+    let res = inst_b
+        .run("call_add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 23);
+
+    inst_b.run("tick_twice", &[]).expect("instance runs");
+    assert_eq!(ticks_a.load(Ordering::SeqCst), 0);
+    assert_eq!(ticks_b.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn hostcalls_can_be_bound() {
+    let module = linker_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut linker = Linker::new();
+    linker
+        .hostcall(
+            "env",
+            "add",
+            linker_sub as unsafe extern "C" fn(*const lucet_vmctx, u32, u32) -> u32,
+        )
+        .func("env", "tick", |_vmctx: &Vmctx| ());
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_linker(&linker)
+        .build()
+        .expect("instance can be created");
+
+    let res = inst
+        .run("call_add", &[5u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
+
+#[test]
+fn missing_import_fails_to_build() {
+    let module = linker_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut linker = Linker::new();
+    linker.func("env", "add", |_vmctx: &Vmctx, a: u32, b: u32| a + b);
+
+    match region
+        .new_instance_builder(module)
+        .with_linker(&linker)
+        .build()
+    {
+        Err(RuntimeError::FunctionImportNotProvided(name)) => assert_eq!(name, "env::tick"),
+        res => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+}
+
+#[test]
+fn mismatched_signature_fails_to_build() {
+    let module = linker_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut linker = Linker::new();
+    linker
+        .func("env", "add", |_vmctx: &Vmctx, a: u64, b: u64| a + b)
+        .func("env", "tick", |_vmctx: &Vmctx| ());
+
+    match region
+        .new_instance_builder(module)
+        .with_linker(&linker)
+        .build()
+    {
+        Err(RuntimeError::FunctionImportMismatch(message)) => assert_eq!(
+            message,
+            "env::add is imported as (I32, I32) -> I32, but the linker provides (I64, I64) -> I64"
+        ),
+        res => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+}
+
+#[test]
+fn closures_can_terminate() {
+    let module = linker_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut linker = Linker::new();
+    linker
+        .func("env", "add", |_vmctx: &Vmctx, a: u32, b: u32| a + b)
+        .func("env", "tick", |_vmctx: &Vmctx| {
+            lucet_hostcall_terminate!("tick refused");
+        });
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_linker(&linker)
+        .build()
+        .expect("instance can be created");
+
+    match inst.run("tick_twice", &[]) {
+        Err(RuntimeError::RuntimeTerminated(TerminationDetails::Provided(details))) => {
+            assert_eq!(
+                details.downcast_ref::<&'static str>(),
+                Some(&"tick refused")
+            );
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // the instance can be reset and run again afterwards
+    inst.reset().expect("instance resets");
+    let res = inst
+        .run("call_add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
@@ -0,0 +1,23 @@
+(module
+  (import "env" "add" (func $add (param i32 i32) (result i32)))
+  (import "env" "tick" (func $tick))
+  (memory 1)
+
+  (func (export "call_add") (param $a i32) (param $b i32) (result i32)
+    (call $add (local.get $a) (local.get $b))
+  )
+
+  (func (export "tick_twice")
+    (call $tick)
+    (call $tick)
+  )
+
+  ;; Alternate between the imports, so each must be called with its own state.
+  (func (export "tick_and_add") (param $a i32) (param $b i32) (result i32)
+    (local $sum i32)
+    (call $tick)
+    (local.set $sum (call $add (local.get $a) (local.get $b)))
+    (call $tick)
+    (call $add (local.get $sum) (local.get $sum))
+  )
+)
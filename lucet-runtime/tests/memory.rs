@@ -33,6 +33,8 @@ mod uffd_specific {
             .expect("instance can be created");
 
         inst.run("main", &[]).expect("instance runs");
+        // at least one fault for each of the two heap pages the guest wrote to
+        assert!(inst.stats().page_faults >= 2);
 
         let heap = inst.heap_u32();
         // guest puts 1 at the start of the second page
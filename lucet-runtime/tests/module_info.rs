@@ -0,0 +1,163 @@
+use anyhow::Error;
+use lucet_module::lucet_signature;
+use lucet_runtime::vmctx::Vmctx;
+use lucet_runtime::{
+    lucet_hostcall, DlModule, Export, ExportKind, FunctionIndex, GlobalInfo, Import, ImportKind,
+    MemoryInfo, Module, ValueType,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Bindings, Lucetc, LucetcOpts};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn module_info_log(_vmctx: &Vmctx, _x: u32, _counter: u64) {}
+
+fn module_info_module() -> Result<Arc<DlModule>, Error> {
+    let mut env = HashMap::new();
+    env.insert("log".to_owned(), "module_info_log".to_owned());
+    let native_build =
+        Lucetc::new("./tests/module_info/module.wat").with_bindings(Bindings::env(env));
+
+    lucetc_test(native_build)
+}
+
+#[test]
+fn module_exports() {
+    let module = module_info_module().expect("can load module");
+    let run_sig = lucet_signature!((I32) -> I32);
+
+    let mut exports = module.exports().collect::<Vec<_>>();
+    exports.sort_by_key(|export| export.name);
+    assert_eq!(
+        exports,
+        vec![
+            Export {
+                name: "counter",
+                kind: ExportKind::Global {
+                    index: 3,
+                    ty: ValueType::I64,
+                },
+            },
+            Export {
+                name: "main",
+                kind: ExportKind::Func {
+                    index: FunctionIndex::from_u32(1),
+                    signature: &run_sig,
+                },
+            },
+            Export {
+                name: "run",
+                kind: ExportKind::Func {
+                    index: FunctionIndex::from_u32(1),
+                    signature: &run_sig,
+                },
+            },
+        ]
+    );
+}
+
+#[test]
+fn module_imports() {
+    let module = module_info_module().expect("can load module");
+
+    let imports = module.imports().collect::<Vec<_>>();
+    assert_eq!(
+        imports,
+        vec![
+            Import {
+                module: "env",
+                name: "log",
+                kind: ImportKind::Func {
+                    index: FunctionIndex::from_u32(0),
+                    signature: &lucet_signature!((I32, I64) -> ()),
+                },
+            },
+            Import {
+                module: "env",
+                name: "base",
+                kind: ImportKind::Global {
+                    index: 0,
+                    ty: ValueType::I32,
+                },
+            },
+            Import {
+                module: "env",
+                name: "handler",
+                kind: ImportKind::Global {
+                    index: 1,
+                    ty: ValueType::FuncRef,
+                },
+            },
+            Import {
+                module: "env",
+                name: "host",
+                kind: ImportKind::Global {
+                    index: 2,
+                    ty: ValueType::ExternRef,
+                },
+            },
+        ]
+    );
+}
+
+#[test]
+fn module_globals() {
+    let module = module_info_module().expect("can load module");
+
+    let globals = module.globals().collect::<Vec<_>>();
+    assert_eq!(
+        globals,
+        vec![
+            GlobalInfo {
+                index: 0,
+                ty: ValueType::I32,
+                mutable: false,
+                import: Some(("env", "base")),
+                export_names: &[],
+            },
+            GlobalInfo {
+                index: 1,
+                ty: ValueType::FuncRef,
+                mutable: false,
+                import: Some(("env", "handler")),
+                export_names: &[],
+            },
+            GlobalInfo {
+                index: 2,
+                ty: ValueType::ExternRef,
+                mutable: false,
+                import: Some(("env", "host")),
+                export_names: &[],
+            },
+            GlobalInfo {
+                index: 3,
+                ty: ValueType::I64,
+                mutable: true,
+                import: None,
+                export_names: &["counter"],
+            },
+            GlobalInfo {
+                index: 4,
+                ty: ValueType::F32,
+                mutable: false,
+                import: None,
+                export_names: &[],
+            },
+        ]
+    );
+}
+
+#[test]
+fn module_memory() {
+    let module = module_info_module().expect("can load module");
+
+    assert_eq!(
+        module.memory(),
+        Some(MemoryInfo {
+            initial_pages: 2,
+            max_pages: Some(10),
+        })
+    );
+}
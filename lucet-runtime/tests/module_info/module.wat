@@ -0,0 +1,14 @@
+(module
+  (import "env" "log" (func $log (param i32 i64)))
+  (import "env" "base" (global $base i32))
+  (import "env" "handler" (global $handler funcref))
+  (import "env" "host" (global $host anyref))
+  (memory 2 10)
+  (global $counter (export "counter") (mut i64) (i64.const 0))
+  (global $scale f32 (f32.const 1.5))
+
+  (func $run (export "run") (export "main") (param $x i32) (result i32)
+    (call $log (local.get $x) (global.get $counter))
+    (i32.add (local.get $x) (global.get $base))
+  )
+)
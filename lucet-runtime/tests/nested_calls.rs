@@ -0,0 +1,253 @@
+use anyhow::Error;
+use lucet_runtime::vmctx::Vmctx;
+use lucet_runtime::{
+    lucet_hostcall, lucet_hostcall_terminate, DlModule, Error as RuntimeError, Limits, MmapRegion,
+    Region, TerminationDetails, TrapCode, Val,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Bindings, Lucetc, LucetcOpts};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn single_i32(results: Vec<Val>) -> i32 {
+    match results.as_slice() {
+        [Val::I32(x)] => *x,
+        results => panic!("unexpected results: {:?}", results),
+    }
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn nested_call_export(vmctx: &Vmctx, x: u32) -> u32 {
+    let results = vmctx
+        .call_export("double", &[Val::U32(x)])
+        .expect("export can be called");
+    single_i32(results) as u32 + 1
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn nested_call_table(vmctx: &Vmctx, idx: u32, x: u32) -> u32 {
+    match vmctx.call_func_idx(0, idx, &[Val::U32(x)]) {
+        Ok(results) => single_i32(results) as u32,
+        Err(RuntimeError::FuncNotFound(_, _)) => u32::max_value(),
+        Err(e) => panic!("unexpected error: {}", e),
+    }
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn nested_reenter(vmctx: &Vmctx, n: u32) -> u32 {
+    match vmctx.call_export("recurse", &[Val::U32(n)]) {
+        Ok(results) => single_i32(results) as u32 + 1,
+        Err(RuntimeError::LimitsExceeded(_)) => lucet_hostcall_terminate!("too deep"),
+        Err(e) => panic!("unexpected error: {}", e),
+    }
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn nested_call_trap(vmctx: &Vmctx) {
+    vmctx.call_export("trap", &[]).ok();
+    panic!("the fault should not return to the hostcall");
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn nested_call_yielder(vmctx: &Vmctx) -> u32 {
+    let results = vmctx
+        .call_export("yield_then_return", &[])
+        .expect("export can be called");
+    single_i32(results) as u32 + 1
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn nested_call_loop(vmctx: &Vmctx) {
+    vmctx.call_export("infinite_loop", &[]).ok();
+    panic!("the termination should not return to the hostcall");
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn nested_yield(vmctx: &Vmctx) {
+    vmctx.yield_();
+}
+
+fn nested_calls_module() -> Result<Arc<DlModule>, Error> {
+    let mut env = HashMap::new();
+    env.insert("call_export".to_owned(), "nested_call_export".to_owned());
+    env.insert("call_table".to_owned(), "nested_call_table".to_owned());
+    env.insert("reenter".to_owned(), "nested_reenter".to_owned());
+    env.insert("call_trap".to_owned(), "nested_call_trap".to_owned());
+    env.insert("call_yielder".to_owned(), "nested_call_yielder".to_owned());
+    env.insert("call_loop".to_owned(), "nested_call_loop".to_owned());
+    env.insert("yield".to_owned(), "nested_yield".to_owned());
+    let native_build =
+        Lucetc::new("./tests/nested_calls/module.wat").with_bindings(Bindings::env(env));
+
+    lucetc_test(native_build)
+}
+
+#[test]
+fn hostcall_calls_export() {
+    let module = nested_calls_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let res = inst
+        .run("double_via_host", &[21u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 43);
+
+    let stats = inst.stats();
+    assert_eq!(stats.hostcalls, 1);
+}
+
+#[test]
+fn hostcall_calls_table_function() {
+    let module = nested_calls_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let res = inst
+        .run("call_via_table", &[1u32.into(), 5u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 15);
+
+    let res = inst
+        .run("call_via_table", &[7u32.into(), 5u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), u32::max_value());
+}
+
+#[test]
+fn nested_calls_are_limited() {
+    let module = nested_calls_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_max_nested_calls(3)
+        .build()
+        .expect("instance can be created");
+
+    let res = inst
+        .run("recurse", &[3u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+
+    match inst.run("recurse", &[4u32.into()]) {
+        Err(RuntimeError::RuntimeTerminated(TerminationDetails::Provided(details))) => {
+            assert_eq!(details.downcast_ref::<&'static str>(), Some(&"too deep"));
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    // the calls abandoned by the termination no longer count towards the limit
+    inst.reset().expect("instance resets");
+    let res = inst
+        .run("recurse", &[3u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
+
+#[test]
+fn nested_fault_ends_outer_run() {
+    let module = nested_calls_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    match inst.run("trap_via_host", &[]) {
+        Err(RuntimeError::RuntimeFault(details)) => {
+            assert_eq!(details.trapcode, Some(TrapCode::Unreachable));
+            assert!(!details.fatal);
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    inst.reset().expect("instance resets");
+    let res = inst
+        .run("double_via_host", &[1u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
+
+#[test]
+fn nested_call_can_yield() {
+    let module = nested_calls_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    assert!(inst
+        .run("yield_via_host", &[])
+        .expect("instance runs")
+        .is_yielded());
+
+    let res = inst.resume().expect("instance resumes").unwrap_returned();
+    assert_eq!(res.as_u32(), 43);
+}
+
+#[test]
+fn reset_abandons_yielded_nested_call() {
+    let module = nested_calls_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    assert!(inst
+        .run("yield_via_host", &[])
+        .expect("instance runs")
+        .is_yielded());
+    match inst.snapshot() {
+        Err(RuntimeError::Unsupported(_)) => (),
+        res => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+
+    // once reset, the instance no longer has a call from a hostcall in progress
+    inst.reset().expect("instance resets");
+    inst.snapshot().expect("reset instance can be snapshotted");
+    let res = inst
+        .run("double_via_host", &[1u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
+
+#[test]
+fn nested_call_can_be_terminated() {
+    let module = nested_calls_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_timeout(Duration::from_millis(50))
+        .build()
+        .expect("instance can be created");
+
+    match inst.run("loop_via_host", &[]) {
+        Err(RuntimeError::RuntimeTerminated(TerminationDetails::Timeout)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    inst.reset().expect("instance resets");
+    let res = inst
+        .run("double_via_host", &[1u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
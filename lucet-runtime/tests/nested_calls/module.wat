@@ -0,0 +1,65 @@
+(module
+  (import "env" "call_export" (func $call_export (param i32) (result i32)))
+  (import "env" "call_table" (func $call_table (param i32 i32) (result i32)))
+  (import "env" "reenter" (func $reenter (param i32) (result i32)))
+  (import "env" "call_trap" (func $call_trap))
+  (import "env" "call_yielder" (func $call_yielder (result i32)))
+  (import "env" "call_loop" (func $call_loop))
+  (import "env" "yield" (func $yield))
+  (memory 1)
+  (table 2 funcref)
+  (elem (i32.const 0) $double $triple)
+
+  (func $double (export "double") (param $x i32) (result i32)
+    (i32.mul (local.get $x) (i32.const 2))
+  )
+
+  (func $triple (param $x i32) (result i32)
+    (i32.mul (local.get $x) (i32.const 3))
+  )
+
+  ;; The host calls `double` with the argument, and adds one to the result.
+  (func (export "double_via_host") (param $x i32) (result i32)
+    (call $call_export (local.get $x))
+  )
+
+  ;; The host calls the function at `$idx` in the table with the argument.
+  (func (export "call_via_table") (param $idx i32) (param $x i32) (result i32)
+    (call $call_table (local.get $idx) (local.get $x))
+  )
+
+  ;; Alternate between the guest and the host `$n` times.
+  (func (export "recurse") (param $n i32) (result i32)
+    (if (result i32) (i32.eqz (local.get $n))
+      (then (i32.const 0))
+      (else (call $reenter (i32.sub (local.get $n) (i32.const 1))))
+    )
+  )
+
+  (func (export "trap")
+    (unreachable)
+  )
+
+  (func (export "trap_via_host")
+    (call $call_trap)
+  )
+
+  (func (export "yield_then_return") (result i32)
+    (call $yield)
+    (i32.const 42)
+  )
+
+  (func (export "yield_via_host") (result i32)
+    (call $call_yielder)
+  )
+
+  (func (export "infinite_loop")
+    (loop $forever
+      (br $forever)
+    )
+  )
+
+  (func (export "loop_via_host")
+    (call $call_loop)
+  )
+)
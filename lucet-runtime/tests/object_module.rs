@@ -0,0 +1,150 @@
+use anyhow::Error;
+use lucet_runtime::vmctx::Vmctx;
+use lucet_runtime::{
+    lucet_hostcall, runtime_symbols, Error as RuntimeError, Limits, MmapRegion, ObjectModule,
+    ObjectSymbols, Region, TrapCode,
+};
+use lucet_runtime_tests::build::lucetc_object_test;
+use lucetc::{Bindings, Lucetc, LucetcOpts};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Not `#[no_mangle]`: the module finds this through its symbol table, not the dynamic linker.
+#[lucet_hostcall]
+pub fn object_module_double(_vmctx: &Vmctx, x: u32) -> u32 {
+    x * 2
+}
+
+fn object_bytes() -> Result<Vec<u8>, Error> {
+    let mut env = HashMap::new();
+    env.insert("double".to_owned(), "object_module_double".to_owned());
+    let native_build =
+        Lucetc::new("./tests/object_module/module.wat").with_bindings(Bindings::env(env));
+
+    lucetc_object_test(native_build)
+}
+
+fn symbols() -> ObjectSymbols {
+    runtime_symbols().with_symbol("object_module_double", object_module_double as _)
+}
+
+fn object_module() -> Result<Arc<ObjectModule>, Error> {
+    let module = ObjectModule::load_from_bytes(&object_bytes()?, &symbols())?;
+    Ok(module)
+}
+
+#[test]
+fn run_export() {
+    let module = object_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let res = inst
+        .run("add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
+
+#[test]
+fn call_hostcall_from_symbol_table() {
+    let module = object_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let res = inst
+        .run("double_plus_one", &[20u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 41);
+}
+
+#[test]
+fn call_runtime_function() {
+    let module = object_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let res = inst
+        .run("grow", &[1u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 1);
+    assert_eq!(inst.heap().len(), 2 * 64 * 1024);
+}
+
+#[test]
+fn fault_in_module_code() {
+    let module = object_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    match inst.run("load", &[(64 * 1024u32).into()]) {
+        Err(RuntimeError::RuntimeFault(details)) => {
+            assert_eq!(details.trapcode, Some(TrapCode::HeapOutOfBounds));
+            let addr_details = details.rip_addr_details.expect("fault has address details");
+            assert!(addr_details.in_module_code);
+            assert_eq!(addr_details.sym_name.as_deref(), Some("guest_func_load"));
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn missing_import() {
+    let bytes = object_bytes().expect("can compile module");
+    match ObjectModule::load_from_bytes(&bytes, &runtime_symbols()) {
+        Err(RuntimeError::ObjectError(e)) => {
+            assert!(e.to_string().contains("object_module_double"));
+        }
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("module loaded without its hostcall"),
+    }
+}
+
+#[test]
+fn other_architecture() {
+    let mut bytes = object_bytes().expect("can compile module");
+    // change the ELF header's `e_machine` to AArch64
+    const EM_AARCH64: u16 = 183;
+    bytes[18..20].copy_from_slice(&EM_AARCH64.to_le_bytes());
+    match ObjectModule::load_from_bytes(&bytes, &symbols()) {
+        Err(RuntimeError::ObjectError(e)) => {
+            assert!(e
+                .to_string()
+                .contains("only x86-64 ELF objects are supported"));
+        }
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("module loaded for another architecture"),
+    }
+}
+
+#[test]
+fn load_same_bytes_twice() {
+    let bytes = object_bytes().expect("can compile module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+
+    let first = ObjectModule::load_from_bytes(&bytes, &symbols()).expect("can load module");
+    let second = ObjectModule::load_from_bytes(&bytes, &symbols()).expect("can load module");
+    drop(bytes);
+
+    let mut inst = region
+        .new_instance(second)
+        .expect("instance can be created");
+    // the first module is unloaded here, which must not affect the second
+    drop(first);
+
+    let res = inst
+        .run("double_plus_one", &[1u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 3);
+}
@@ -0,0 +1,16 @@
+(module
+  (import "env" "double" (func $double (param i32) (result i32)))
+  (memory 1)
+  (func (export "add") (param i32 i32) (result i32)
+    (i32.add (local.get 0) (local.get 1))
+  )
+  (func (export "double_plus_one") (param i32) (result i32)
+    (i32.add (call $double (local.get 0)) (i32.const 1))
+  )
+  (func (export "grow") (param i32) (result i32)
+    (memory.grow (local.get 0))
+  )
+  (func (export "load") (param i32) (result i32)
+    (i32.load (local.get 0))
+  )
+)
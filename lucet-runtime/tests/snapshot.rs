@@ -0,0 +1,239 @@
+use anyhow::Error;
+use lucet_runtime::vmctx::Vmctx;
+use lucet_runtime::{
+    lucet_hostcall, DlModule, Error as RuntimeError, InstanceSnapshot, Limits, MmapRegion, Region,
+    RunResult,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Bindings, Lucetc, LucetcOpts};
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn snapshot_yield_n(vmctx: &Vmctx, n: u32) -> u32 {
+    vmctx.yield_val_expecting_val(n)
+}
+
+fn snapshot_build(path: &str) -> Lucetc {
+    let mut env = HashMap::new();
+    env.insert("yield_n".to_owned(), "snapshot_yield_n".to_owned());
+    Lucetc::new(path).with_bindings(Bindings::env(env))
+}
+
+fn build_module(path: &str) -> Result<Arc<DlModule>, Error> {
+    lucetc_test(snapshot_build(path))
+}
+
+fn snapshot_module() -> Result<Arc<DlModule>, Error> {
+    build_module("./tests/snapshot/module.wat")
+}
+
+fn expect_yielded_n(res: Result<RunResult, RuntimeError>, n: u32) {
+    let yielded = res.expect("instance runs").unwrap_yielded();
+    assert_eq!(yielded.downcast_ref::<u32>(), Some(&n));
+}
+
+#[test]
+fn restore_yielded_instance() {
+    let module = snapshot_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module.clone())
+        .expect("instance can be created");
+
+    expect_yielded_n(inst.run("accumulate", &[3u32.into()]), 3);
+    expect_yielded_n(inst.resume_with_val(10u32), 2);
+
+    let snapshot = inst.snapshot().expect("can take snapshot");
+    assert!(snapshot.is_yielded());
+    let bytes = snapshot.to_bytes().expect("can serialize snapshot");
+
+    // restore into a different region, so that the heap and stack are at different addresses
+    let other_region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let snapshot = InstanceSnapshot::from_bytes(&bytes).expect("can deserialize snapshot");
+    // safety: the snapshot was just taken, in this process
+    let mut restored =
+        unsafe { other_region.restore_instance(module, &snapshot) }.expect("can restore instance");
+    assert!(restored.is_yielded());
+
+    expect_yielded_n(restored.resume_with_val(20u32), 1);
+    let res = restored
+        .resume_with_val(30u32)
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 60 + 3 * 1000);
+
+    // the original instance carries on independently
+    expect_yielded_n(inst.resume_with_val(1u32), 1);
+    let res = inst
+        .resume_with_val(1u32)
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 12 + 3 * 1000);
+}
+
+/// The working directory of `restore_yielded_instance_in_another_process`, which
+/// `restore_in_child_process` only runs with.
+const CHILD_WORKDIR_VAR: &str = "LUCET_SNAPSHOT_TEST_WORKDIR";
+
+#[test]
+fn restore_yielded_instance_in_another_process() {
+    // the child process loads the same shared object, so it must outlive this test's module
+    let workdir = TempDir::new().expect("create working directory");
+    let so_file = workdir.path().join("module.so");
+    snapshot_build("./tests/snapshot/module.wat")
+        .shared_object_file(&so_file)
+        .expect("can compile module");
+    let module = DlModule::load(&so_file).expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    expect_yielded_n(inst.run("accumulate", &[3u32.into()]), 3);
+    expect_yielded_n(inst.resume_with_val(10u32), 2);
+    let snapshot = inst.snapshot().expect("can take snapshot");
+    std::fs::write(
+        workdir.path().join("snapshot"),
+        snapshot.to_bytes().expect("can serialize snapshot"),
+    )
+    .expect("can write snapshot");
+
+    // the child is this test binary, so the host code is the same build, but it is loaded at a
+    // different address along with the module, and the stack's return addresses into both must be
+    // relocated
+    let status = Command::new(env::current_exe().expect("test binary path"))
+        .args(&["--exact", "restore_in_child_process", "--test-threads=1"])
+        .env(CHILD_WORKDIR_VAR, workdir.path())
+        .status()
+        .expect("can run child process");
+    assert!(status.success());
+    let result = std::fs::read_to_string(workdir.path().join("result"))
+        .expect("child process wrote its result");
+    assert_eq!(result, (60 + 3 * 1000).to_string());
+}
+
+#[test]
+fn restore_in_child_process() {
+    let workdir = match env::var_os(CHILD_WORKDIR_VAR) {
+        Some(workdir) => workdir,
+        // this is only a test when run by restore_yielded_instance_in_another_process
+        None => return,
+    };
+    let workdir = Path::new(&workdir);
+    let module = DlModule::load(workdir.join("module.so")).expect("can load module");
+    let bytes = std::fs::read(workdir.join("snapshot")).expect("can read snapshot");
+    let snapshot = InstanceSnapshot::from_bytes(&bytes).expect("can deserialize snapshot");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    // safety: the snapshot was taken by the same build of this test binary, in the parent process
+    let mut restored =
+        unsafe { region.restore_instance(module, &snapshot) }.expect("can restore instance");
+
+    expect_yielded_n(restored.resume_with_val(20u32), 1);
+    let res = restored
+        .resume_with_val(30u32)
+        .expect("instance runs")
+        .unwrap_returned();
+    std::fs::write(workdir.join("result"), res.as_u32().to_string()).expect("can write result");
+}
+
+#[test]
+fn restore_ready_instance() {
+    let module = snapshot_module().expect("can load module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module.clone())
+        .expect("instance can be created");
+
+    expect_yielded_n(inst.run("accumulate", &[1u32.into()]), 1);
+    inst.resume_with_val(42u32)
+        .expect("instance runs")
+        .unwrap_returned();
+
+    let snapshot = inst.snapshot().expect("can take snapshot");
+    assert!(!snapshot.is_yielded());
+
+    // safety: the snapshot was just taken, in this process
+    let mut restored =
+        unsafe { region.restore_instance(module, &snapshot) }.expect("can restore instance");
+    assert!(restored.is_ready());
+    let res = restored
+        .run("stored_sum", &[])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 42);
+    // the global counting steps was restored too
+    let res = restored
+        .run("accumulate", &[0u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 1000);
+}
+
+#[test]
+fn restored_instance_checks_resumed_type() {
+    let module = snapshot_module().expect("can load module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module.clone())
+        .expect("instance can be created");
+
+    expect_yielded_n(inst.run("accumulate", &[1u32.into()]), 1);
+    let snapshot = inst.snapshot().expect("can take snapshot");
+    // safety: the snapshot was just taken, in this process
+    let mut restored =
+        unsafe { region.restore_instance(module, &snapshot) }.expect("can restore instance");
+
+    match restored.resume() {
+        Err(RuntimeError::InvalidArgument(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    match restored.resume_with_val(1u64) {
+        Err(RuntimeError::InvalidArgument(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    let res = restored
+        .resume_with_val(5u32)
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_u32(), 5 + 1000);
+}
+
+#[test]
+fn restore_requires_same_module() {
+    let module = snapshot_module().expect("can load module");
+    let other_module = build_module("./tests/backtrace/module.wat").expect("can load module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+    let inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let snapshot = inst.snapshot().expect("can take snapshot");
+    // safety: the snapshot was just taken, in this process
+    match unsafe { region.restore_instance(other_module, &snapshot) } {
+        Err(RuntimeError::SnapshotError(_)) => (),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("snapshot restored into a different module"),
+    }
+}
+
+#[test]
+fn cannot_snapshot_faulted_instance() {
+    let module = build_module("./tests/backtrace/module.wat").expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    assert!(inst.run("outer", &[0u32.into()]).is_err());
+    match inst.snapshot() {
+        Err(RuntimeError::InvalidArgument(_)) => (),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("snapshot taken of a faulted instance"),
+    }
+}
@@ -0,0 +1,27 @@
+(module
+  (import "env" "yield_n" (func $yield_n (param i32) (result i32)))
+  (memory 1)
+  (global $steps (mut i32) (i32.const 0))
+
+  ;; Count down from `n`, yielding each count to the host and adding up the values it resumes
+  ;; with. The running total is kept in a local, in the heap, and the number of steps in a global,
+  ;; so that all of them must survive a snapshot.
+  (func (export "accumulate") (param $n i32) (result i32)
+    (local $sum i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $n)))
+        (local.set $sum (i32.add (local.get $sum) (call $yield_n (local.get $n))))
+        (i32.store (i32.const 16) (local.get $sum))
+        (global.set $steps (i32.add (global.get $steps) (i32.const 1)))
+        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+        (br $next)
+      )
+    )
+    (i32.add (local.get $sum) (i32.mul (global.get $steps) (i32.const 1000)))
+  )
+
+  (func (export "stored_sum") (result i32)
+    (i32.load (i32.const 16))
+  )
+)
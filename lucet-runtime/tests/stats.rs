@@ -0,0 +1,184 @@
+use anyhow::Error;
+use lucet_runtime::vmctx::Vmctx;
+use lucet_runtime::{lucet_hostcall, DlModule, Limits, MmapRegion, Region};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Bindings, Lucetc, LucetcOpts};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn stats_noop(_vmctx: &Vmctx) {}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn stats_sleep_ms(_vmctx: &Vmctx, ms: u32) {
+    std::thread::sleep(Duration::from_millis(ms as u64));
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn stats_yield(vmctx: &Vmctx) {
+    vmctx.yield_();
+}
+
+fn stats_module() -> Result<Arc<DlModule>, Error> {
+    let mut env = HashMap::new();
+    env.insert("noop".to_owned(), "stats_noop".to_owned());
+    env.insert("sleep_ms".to_owned(), "stats_sleep_ms".to_owned());
+    env.insert("yield".to_owned(), "stats_yield".to_owned());
+    let native_build = Lucetc::new("./tests/stats/module.wat").with_bindings(Bindings::env(env));
+
+    lucetc_test(native_build)
+}
+
+#[test]
+fn counts_hostcalls_across_runs() {
+    let module = stats_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    assert_eq!(inst.stats().hostcalls, 0);
+    inst.run("call_host", &[3u32.into()])
+        .expect("instance runs");
+    inst.run("call_host", &[4u32.into()])
+        .expect("instance runs");
+    assert_eq!(inst.stats().hostcalls, 7);
+
+    inst.reset().expect("instance resets");
+    assert_eq!(inst.stats().hostcalls, 0);
+}
+
+#[test]
+fn counts_yields_and_resumes() {
+    let module = stats_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    assert!(inst
+        .run("yield_twice", &[])
+        .expect("instance runs")
+        .is_yielded());
+    assert!(inst.resume().expect("instance resumes").is_yielded());
+    assert!(inst.resume().expect("instance resumes").is_returned());
+
+    let stats = inst.stats();
+    assert_eq!(stats.yields, 2);
+    assert_eq!(stats.resumes, 2);
+    assert_eq!(stats.hostcalls, 2);
+}
+
+#[test]
+fn measures_hostcall_time() {
+    let module = stats_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_cpu_stats()
+        .build()
+        .expect("instance can be created");
+
+    inst.run("sleep_ms", &[20u32.into()])
+        .expect("instance runs");
+
+    let stats = inst.stats();
+    assert!(stats.hostcall_wall_time >= Duration::from_millis(20));
+    // sleeping doesn't use the CPU
+    assert!(stats.hostcall_cpu_time < stats.hostcall_wall_time);
+    assert!(stats.guest_wall_time < stats.hostcall_wall_time);
+}
+
+#[test]
+fn cpu_time_is_opt_in() {
+    let module = stats_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+    assert!(!inst.cpu_stats());
+
+    inst.run("call_host", &[1000u32.into()])
+        .expect("instance runs");
+    let stats = inst.stats();
+    assert_eq!(stats.hostcalls, 1000);
+    assert!(stats.guest_wall_time > Duration::default());
+    assert_eq!(stats.guest_cpu_time, Duration::default());
+    assert_eq!(stats.hostcall_cpu_time, Duration::default());
+
+    inst.set_cpu_stats(true);
+    inst.run("call_host", &[1000u32.into()])
+        .expect("instance runs");
+    let stats = inst.stats();
+    assert!(stats.guest_cpu_time + stats.hostcall_cpu_time > Duration::default());
+}
+
+#[test]
+fn tracks_memory_growth() {
+    let module = stats_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    assert_eq!(inst.stats().peak_heap_size, 64 * 1024);
+
+    let res = inst
+        .run("grow", &[2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_i32(), 1);
+    // growing past the maximum fails, but still counts
+    let res = inst
+        .run("grow", &[2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(res.as_i32(), -1);
+
+    let stats = inst.stats();
+    assert_eq!(stats.memory_grows, 2);
+    assert_eq!(stats.peak_heap_size, 3 * 64 * 1024);
+
+    inst.reset().expect("instance resets");
+    let stats = inst.stats();
+    assert_eq!(stats.memory_grows, 0);
+    assert_eq!(stats.peak_heap_size, 64 * 1024);
+}
+
+#[test]
+fn counts_signals() {
+    let module = stats_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    assert!(inst.run("load_oob", &[]).is_err());
+    assert_eq!(inst.stats().signals, 1);
+}
+
+#[cfg(all(target_os = "linux", feature = "uffd"))]
+mod uffd_specific {
+    use super::stats_module;
+    use lucet_runtime::{Limits, Region, UffdRegion, WasmPageSizedUffdStrategy};
+
+    #[test]
+    fn counts_page_faults() {
+        let module = stats_module().expect("can load module");
+        let region = UffdRegion::create(1, &Limits::default(), WasmPageSizedUffdStrategy {})
+            .expect("region can be created");
+        let mut inst = region
+            .new_instance(module)
+            .expect("instance can be created");
+
+        inst.run("store", &[0u32.into()]).expect("instance runs");
+        assert!(inst.stats().page_faults > 0);
+
+        inst.reset().expect("instance resets");
+        assert_eq!(inst.stats().page_faults, 0);
+    }
+}
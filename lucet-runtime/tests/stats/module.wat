@@ -0,0 +1,39 @@
+(module
+  (import "env" "noop" (func $noop))
+  (import "env" "sleep_ms" (func $sleep_ms (param i32)))
+  (import "env" "yield" (func $yield))
+  (memory 1 4)
+
+  ;; Make `n` hostcalls.
+  (func (export "call_host") (param $n i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $n)))
+        (call $noop)
+        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+        (br $next)
+      )
+    )
+  )
+
+  (func (export "sleep_ms") (param $ms i32)
+    (call $sleep_ms (local.get $ms))
+  )
+
+  (func (export "yield_twice")
+    (call $yield)
+    (call $yield)
+  )
+
+  (func (export "grow") (param $pages i32) (result i32)
+    (memory.grow (local.get $pages))
+  )
+
+  (func (export "store") (param $addr i32)
+    (i32.store (local.get $addr) (i32.const 1))
+  )
+
+  (func (export "load_oob") (result i32)
+    (i32.load (i32.const 0x7fffffff))
+  )
+)
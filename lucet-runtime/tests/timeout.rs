@@ -0,0 +1,180 @@
+use anyhow::Error;
+use lucet_runtime::vmctx::Vmctx;
+use lucet_runtime::{
+    lucet_hostcall, DlModule, Error as RuntimeError, Limits, MmapRegion, Region, TerminationDetails,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Bindings, Lucetc, LucetcOpts};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn timeout_sleep_ms(_vmctx: &Vmctx, ms: u32) {
+    thread::sleep(Duration::from_millis(ms as u64));
+}
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn timeout_yield(vmctx: &Vmctx) {
+    vmctx.yield_();
+}
+
+fn timeout_module() -> Result<Arc<DlModule>, Error> {
+    let mut env = HashMap::new();
+    env.insert("sleep_ms".to_owned(), "timeout_sleep_ms".to_owned());
+    env.insert("yield".to_owned(), "timeout_yield".to_owned());
+    let native_build = Lucetc::new("./tests/timeout/module.wat").with_bindings(Bindings::env(env));
+
+    lucetc_test(native_build)
+}
+
+fn assert_timed_out(res: Result<lucet_runtime::RunResult, RuntimeError>) {
+    match res {
+        Err(RuntimeError::RuntimeTerminated(TerminationDetails::Timeout)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn timeout_terminates_guest() {
+    let module = timeout_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_timeout(Duration::from_millis(50))
+        .build()
+        .expect("instance can be created");
+
+    assert_timed_out(inst.run("infinite_loop", &[]));
+
+    // the timeout is kept across a reset and applies to the next run as well
+    inst.reset().expect("instance resets");
+    assert_timed_out(inst.run("infinite_loop", &[]));
+}
+
+#[test]
+fn timeout_during_hostcall() {
+    let module = timeout_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_timeout(Duration::from_millis(10))
+        .build()
+        .expect("instance can be created");
+
+    // the hostcall can't be interrupted, so the instance is terminated as it returns to the guest
+    assert_timed_out(inst.run("sleep_ms", &[100u32.into()]));
+}
+
+#[test]
+fn timeout_excludes_time_yielded() {
+    let module = timeout_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_timeout(Duration::from_millis(50))
+        .build()
+        .expect("instance can be created");
+
+    assert!(inst
+        .run("yield_then_loop", &[])
+        .expect("instance runs")
+        .is_yielded());
+    thread::sleep(Duration::from_millis(100));
+    assert!(inst.is_yielded());
+
+    assert_timed_out(inst.resume());
+}
+
+#[test]
+fn timeout_cancelled_after_return() {
+    let module = timeout_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance_builder(module)
+        .with_timeout(Duration::from_millis(20))
+        .build()
+        .expect("instance can be created");
+
+    let retval = inst
+        .run("add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(u32::from(retval), 3);
+
+    // a stale deadline firing now would have nothing to terminate
+    thread::sleep(Duration::from_millis(50));
+    let retval = inst
+        .run("add", &[3u32.into(), 4u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(u32::from(retval), 7);
+}
+
+#[test]
+fn run_with_deadline() {
+    let module = timeout_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let deadline = Instant::now() + Duration::from_millis(50);
+    assert_timed_out(inst.run_with_deadline(deadline, "infinite_loop", &[]));
+
+    // the deadline only applies to the call it was given to
+    inst.reset().expect("instance resets");
+    thread::sleep(Duration::from_millis(60));
+    let retval = inst
+        .run("add", &[1u32.into(), 2u32.into()])
+        .expect("instance runs")
+        .unwrap_returned();
+    assert_eq!(u32::from(retval), 3);
+}
+
+#[test]
+fn run_with_deadline_spans_resumes() {
+    let module = timeout_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let deadline = Instant::now() + Duration::from_millis(20);
+    assert!(inst
+        .run_with_deadline(deadline, "yield_then_loop", &[])
+        .expect("instance runs")
+        .is_yielded());
+
+    // the deadline passes while the instance is yielded
+    thread::sleep(Duration::from_millis(50));
+    assert_timed_out(inst.resume());
+}
+
+#[test]
+fn many_instances_share_the_timer() {
+    let module = timeout_module().expect("can load module");
+    let region = MmapRegion::create(8, &Limits::default()).expect("region can be created");
+
+    let threads = (0..8)
+        .map(|i| {
+            let module = module.clone();
+            let region = region.clone();
+            thread::spawn(move || {
+                let mut inst = region
+                    .new_instance_builder(module)
+                    .with_timeout(Duration::from_millis(20 + 10 * i))
+                    .build()
+                    .expect("instance can be created");
+                assert_timed_out(inst.run("infinite_loop", &[]));
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for t in threads {
+        t.join().expect("instance thread completes");
+    }
+}
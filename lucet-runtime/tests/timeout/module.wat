@@ -0,0 +1,27 @@
+(module
+  (import "env" "sleep_ms" (func $sleep_ms (param i32)))
+  (import "env" "yield" (func $yield))
+  (memory 1)
+
+  (func (export "infinite_loop")
+    (loop $forever
+      (br $forever)
+    )
+  )
+
+  (func (export "sleep_ms") (param $ms i32)
+    (call $sleep_ms (local.get $ms))
+  )
+
+  ;; Yield, then spin forever once resumed.
+  (func (export "yield_then_loop")
+    (call $yield)
+    (loop $forever
+      (br $forever)
+    )
+  )
+
+  (func (export "add") (param $a i32) (param $b i32) (result i32)
+    (i32.add (local.get $a) (local.get $b))
+  )
+)
@@ -0,0 +1,197 @@
+use anyhow::Error;
+use lucet_runtime::vmctx::Vmctx;
+use lucet_runtime::{
+    lucet_hostcall, DlModule, Error as RuntimeError, Limits, MmapRegion, Region, Val,
+};
+use lucet_runtime_tests::build::lucetc_test;
+use lucetc::{Bindings, Lucetc, LucetcOpts};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[lucet_hostcall]
+#[no_mangle]
+pub fn typed_func_yield(vmctx: &Vmctx) {
+    vmctx.yield_();
+}
+
+fn typed_func_module() -> Result<Arc<DlModule>, Error> {
+    let mut env = HashMap::new();
+    env.insert("yield".to_owned(), "typed_func_yield".to_owned());
+    let native_build =
+        Lucetc::new("./tests/typed_func/module.wat").with_bindings(Bindings::env(env));
+
+    lucetc_test(native_build)
+}
+
+#[test]
+fn typed_func_returns_results() {
+    let module = typed_func_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let scale = inst
+        .typed_func::<(i32, i64), f64>("scale")
+        .expect("signature matches");
+    assert_eq!(scale.call(&mut inst, (3, 5)).expect("instance runs"), 15.0);
+    assert_eq!(
+        scale.call(&mut inst, (-2, 7)).expect("instance runs"),
+        -14.0
+    );
+
+    let halve = inst
+        .typed_func::<f32, f32>("halve")
+        .expect("signature matches");
+    assert_eq!(halve.call(&mut inst, 5.0).expect("instance runs"), 2.5);
+}
+
+type Sum16Args = (
+    i32,
+    i32,
+    i32,
+    i32,
+    i32,
+    i32,
+    i32,
+    i32,
+    u32,
+    u32,
+    u32,
+    u32,
+    u32,
+    u32,
+    u32,
+    u32,
+);
+
+#[test]
+fn typed_func_takes_sixteen_args() {
+    let module = typed_func_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let sum16 = inst
+        .typed_func::<Sum16Args, u32>("sum16")
+        .expect("signature matches");
+    let res = sum16
+        .call(
+            &mut inst,
+            (1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16),
+        )
+        .expect("instance runs");
+    assert_eq!(res, 136);
+}
+
+#[test]
+fn typed_func_without_args_or_results() {
+    let module = typed_func_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let increment = inst
+        .typed_func::<(), ()>("increment")
+        .expect("signature matches");
+    let counter = inst
+        .typed_func::<(), i32>("counter")
+        .expect("signature matches");
+    increment.call(&mut inst, ()).expect("instance runs");
+    increment.call(&mut inst, ()).expect("instance runs");
+    assert_eq!(counter.call(&mut inst, ()).expect("instance runs"), 2);
+}
+
+#[test]
+fn typed_func_signature_is_checked() {
+    let module = typed_func_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    match inst.typed_func::<(i64, i64), f64>("scale") {
+        Err(RuntimeError::InvalidArgument(_)) => (),
+        res => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+    match inst.typed_func::<(i32, i64), f32>("scale") {
+        Err(RuntimeError::InvalidArgument(_)) => (),
+        res => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+    match inst.typed_func::<i32, f64>("scale") {
+        Err(RuntimeError::InvalidArgument(_)) => (),
+        res => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+    match inst.typed_func::<(), ()>("no_such_export") {
+        Err(RuntimeError::SymbolNotFound(name)) => assert_eq!(name, "no_such_export"),
+        res => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+}
+
+#[test]
+fn typed_func_works_across_instances() {
+    let module = typed_func_module().expect("can load module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+    let mut inst_a = region
+        .new_instance(module.clone())
+        .expect("instance can be created");
+    let mut inst_b = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let increment = inst_a
+        .typed_func::<(), ()>("increment")
+        .expect("signature matches");
+    let counter = inst_a
+        .typed_func::<(), i32>("counter")
+        .expect("signature matches");
+    increment.call(&mut inst_b, ()).expect("instance runs");
+    assert_eq!(counter.call(&mut inst_a, ()).expect("instance runs"), 0);
+    assert_eq!(counter.call(&mut inst_b, ()).expect("instance runs"), 1);
+}
+
+#[test]
+fn typed_func_rejects_other_modules() {
+    let module_a = typed_func_module().expect("can load module");
+    let module_b = typed_func_module().expect("can load module");
+    let region = MmapRegion::create(2, &Limits::default()).expect("region can be created");
+    let inst_a = region
+        .new_instance(module_a)
+        .expect("instance can be created");
+    let mut inst_b = region
+        .new_instance(module_b)
+        .expect("instance can be created");
+
+    let counter = inst_a
+        .typed_func::<(), i32>("counter")
+        .expect("signature matches");
+    match counter.call(&mut inst_b, ()) {
+        Err(RuntimeError::InvalidArgument(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn typed_func_can_yield() {
+    let module = typed_func_module().expect("can load module");
+    let region = MmapRegion::create(1, &Limits::default()).expect("region can be created");
+    let mut inst = region
+        .new_instance(module)
+        .expect("instance can be created");
+
+    let yield_then_return = inst
+        .typed_func::<(), i64>("yield_then_return")
+        .expect("signature matches");
+    match yield_then_return.call(&mut inst, ()) {
+        Err(RuntimeError::InstanceNotReturned) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    inst.resume().expect("instance resumes").unwrap_returned();
+    match inst.results() {
+        [Val::I64(x)] => assert_eq!(*x, -1),
+        results => panic!("unexpected results: {:?}", results),
+    }
+}
@@ -0,0 +1,47 @@
+(module
+  (import "env" "yield" (func $yield))
+  (memory 1)
+  (global $counter (mut i32) (i32.const 0))
+
+  (func (export "scale") (param $x i32) (param $factor i64) (result f64)
+    (f64.convert_i64_s
+      (i64.mul (i64.extend_i32_s (local.get $x)) (local.get $factor)))
+  )
+
+  (func (export "halve") (param $x f32) (result f32)
+    (f32.div (local.get $x) (f32.const 2))
+  )
+
+  (func (export "sum16")
+    (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32 i32)
+    (result i32)
+    (i32.add (local.get 0)
+    (i32.add (local.get 1)
+    (i32.add (local.get 2)
+    (i32.add (local.get 3)
+    (i32.add (local.get 4)
+    (i32.add (local.get 5)
+    (i32.add (local.get 6)
+    (i32.add (local.get 7)
+    (i32.add (local.get 8)
+    (i32.add (local.get 9)
+    (i32.add (local.get 10)
+    (i32.add (local.get 11)
+    (i32.add (local.get 12)
+    (i32.add (local.get 13)
+    (i32.add (local.get 14) (local.get 15))))))))))))))))
+  )
+
+  (func (export "increment")
+    (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+  )
+
+  (func (export "counter") (result i32)
+    (global.get $counter)
+  )
+
+  (func (export "yield_then_return") (result i64)
+    (call $yield)
+    (i64.const -1)
+  )
+)
@@ -264,9 +264,11 @@ fn check_retval(expected: &[Value], got: UntypedRetVal) -> Result<(), Error> {
                     return Err(Error::IncorrectResult(message));
                 }
             }
-            Value::V128(v) => {
-                let message = format!("got unsupported SIMD V128 value: {}", v);
-                return Err(Error::UnsupportedCommand(message));
+            Value::V128(expected) => {
+                if expected != got.as_v128() {
+                    let message = format!("expected {:#x}, got {:#x}", expected, got.as_v128());
+                    return Err(Error::IncorrectResult(message));
+                }
             }
         },
         n => {
@@ -285,7 +287,7 @@ fn translate_args(args: &[Value]) -> Vec<Val> {
             Value::I64(ref i) => Val::U64(*i as u64),
             Value::F32(ref f) => Val::F32(*f),
             Value::F64(ref f) => Val::F64(*f),
-            Value::V128(_) => panic!("unsupported SIMD argument size: v128"),
+            Value::V128(ref v) => Val::V128(*v),
         };
         out.push(v);
     }
@@ -82,7 +82,14 @@ impl Validator {
 
     pub fn validate(&self, module_contents: &[u8]) -> Result<(), Error> {
         wasmparser::validate(module_contents, None)?;
+        self.validate_interfaces(module_contents)
+    }
 
+    /// Check the module's imports, and its exports if `wasi_exe` is set, against the witx
+    /// interfaces, without validating the module itself.
+    ///
+    /// For callers that have already validated the module, such as `lucetc`.
+    pub fn validate_interfaces(&self, module_contents: &[u8]) -> Result<(), Error> {
         let moduletype = ModuleType::parse_wasm(module_contents)?;
 
         for import in moduletype.imports() {
@@ -10,8 +10,7 @@ use lucet_wasi::{self, types::Exitcode, WasiCtxBuilder};
 use std::fs::File;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 struct Config<'a> {
     lucet_module: &'a str,
@@ -22,6 +21,7 @@ struct Config<'a> {
     timeout: Option<Duration>,
     verify: bool,
     pk_path: Option<PathBuf>,
+    initial_state_path: Option<PathBuf>,
 }
 
 fn parse_humansized(desc: &str) -> Result<u64, Error> {
@@ -100,7 +100,7 @@ fn main() {
                 .help("Maximum stack size (must be a multiple of 4 KiB)"),
         )
         .arg(
-            Arg::with_name("timeout").long("timeout").takes_value(true).help("Number of milliseconds the instance will be allowed to run")
+            Arg::with_name("timeout").long("timeout").takes_value(true).help("Number of milliseconds the instance will be allowed to run, including its start function")
             )
         .arg(
             Arg::with_name("guest_args")
@@ -120,6 +120,21 @@ fn main() {
                 .takes_value(true)
                 .help("Path to the public key to verify the source code signature")
         )
+        .arg(
+            Arg::with_name("initial_state_path")
+                .long("--save-initial-state")
+                .takes_value(true)
+                .help("After the entrypoint returns, save the instance's heap and globals to this path, for use with `lucetc --initial-state`")
+                .long_help(
+                    "After the entrypoint returns, save the instance's heap and globals to this \
+                     path. Compiling the module again with `lucetc --initial-state` then gives a \
+                     module whose instances start out in that state.\
+                     \n\n\
+                     For example, `--entrypoint _initialize --save-initial-state init.state` \
+                     runs the module's start function and `_initialize` export, and saves the \
+                     result to `init.state`.",
+                ),
+        )
         .get_matches();
 
     let entrypoint = matches.value_of("entrypoint").unwrap();
@@ -188,6 +203,7 @@ fn main() {
 
     let verify = matches.is_present("verify");
     let pk_path = matches.value_of("pk_path").map(PathBuf::from);
+    let initial_state_path = matches.value_of("initial_state_path").map(PathBuf::from);
 
     let config = Config {
         lucet_module,
@@ -198,6 +214,7 @@ fn main() {
         timeout,
         verify,
         pk_path,
+        initial_state_path,
     };
 
     run(config)
@@ -248,21 +265,28 @@ fn run(config: Config<'_>) {
             .build()
             .expect("instance can be created");
 
-        if let Some(timeout) = config.timeout {
-            let kill_switch = inst.kill_switch();
-            thread::spawn(move || {
-                thread::sleep(timeout);
-                // We may hit this line exactly when the guest exits, so sometimes `terminate` can
-                // fail. That's still acceptable, so just ignore the result.
-                kill_switch.terminate().ok();
-            });
-        }
-
+        // the timeout covers the start function and the entrypoint together
+        let deadline = config.timeout.map(|timeout| Instant::now() + timeout);
+        inst.set_timeout(
+            deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())),
+        );
         inst.run_start().expect("Wasm start function runs");
+        inst.set_timeout(None);
 
-        match inst.run(config.entrypoint, &[]) {
+        let res = match deadline {
+            Some(deadline) => inst.run_with_deadline(deadline, config.entrypoint, &[]),
+            None => inst.run(config.entrypoint, &[]),
+        };
+        match res {
             // normal termination implies 0 exit code
-            Ok(RunResult::Returned(_)) => 0,
+            Ok(RunResult::Returned(_)) => {
+                if let Some(path) = config.initial_state_path {
+                    let state = inst.initial_state().expect("initial state can be captured");
+                    let bytes = state.to_bytes().expect("initial state can be serialized");
+                    std::fs::write(path, bytes).expect("initial state can be written");
+                }
+                0
+            }
             // none of the WASI hostcalls use yield yet, so this shouldn't happen
             Ok(RunResult::Yielded(_)) => panic!("lucet-wasi unexpectedly yielded"),
             Err(lucet_runtime::Error::RuntimeTerminated(
@@ -271,9 +295,9 @@ fn run(config: Config<'_>) {
                 .downcast_ref::<Exitcode>()
                 .expect("termination yields an exitcode"),
             Err(lucet_runtime::Error::RuntimeTerminated(
-                lucet_runtime::TerminationDetails::Remote,
+                lucet_runtime::TerminationDetails::Timeout,
             )) => {
-                println!("Terminated via remote kill switch (likely a timeout)");
+                println!("Terminated after timeout");
                 std::u32::MAX
             }
             Err(e) => panic!("lucet-wasi runtime error: {}", e),
@@ -7,6 +7,7 @@ use crate::options::{CodegenOutput, ErrorStyle, Options};
 use anyhow::{format_err, Error};
 use log::info;
 use lucet_module::bindings::Bindings;
+use lucet_module::InitialState;
 use lucet_validate::Validator;
 use lucetc::{
     signature::{self, PublicKey},
@@ -123,6 +124,10 @@ pub fn run(opts: &Options) -> Result<(), Error> {
         c.guard_size(guard_size);
     }
 
+    if opts.bounds_checked_heap {
+        c.bounds_checked_heap(true);
+    }
+
     if let Some(pk_path) = &opts.pk_path {
         c.pk(PublicKey::from_file(pk_path)?);
     }
@@ -143,6 +148,27 @@ pub fn run(opts: &Options) -> Result<(), Error> {
         c.count_instructions(true);
     }
 
+    if opts.epoch_interruption {
+        c.epoch_interruption(true);
+    }
+
+    if opts.linked_imports {
+        c.linked_imports(true);
+    }
+
+    if let Some(threads) = opts.threads {
+        c.threads(threads);
+    }
+
+    if let Some(linker) = opts.linker {
+        c.linker(linker);
+    }
+
+    if let Some(initial_state_path) = &opts.initial_state_path {
+        let bytes = std::fs::read(initial_state_path)?;
+        c.initial_state(InitialState::from_bytes(&bytes)?);
+    }
+
     match opts.codegen {
         CodegenOutput::Obj => c.object_file(&opts.output)?,
         CodegenOutput::SharedObj => c.shared_object_file(&opts.output)?,
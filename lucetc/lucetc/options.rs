@@ -1,6 +1,6 @@
 use anyhow::Error;
 use clap::{Arg, ArgMatches, Values};
-use lucetc::{CpuFeatures, HeapSettings, OptLevel, SpecificFeature, TargetCpu};
+use lucetc::{CpuFeatures, HeapSettings, Linker, OptLevel, SpecificFeature, TargetCpu};
 use std::path::PathBuf;
 use std::str::FromStr;
 use target_lexicon::{Architecture, Triple};
@@ -112,6 +112,7 @@ pub struct Options {
     pub max_reserved_size: Option<u64>,
     pub reserved_size: Option<u64>,
     pub guard_size: Option<u64>,
+    pub bounds_checked_heap: bool,
     pub opt_level: OptLevel,
     pub cpu_features: CpuFeatures,
     pub keygen: bool,
@@ -120,6 +121,11 @@ pub struct Options {
     pub pk_path: Option<PathBuf>,
     pub sk_path: Option<PathBuf>,
     pub count_instructions: bool,
+    pub epoch_interruption: bool,
+    pub linked_imports: bool,
+    pub threads: Option<usize>,
+    pub linker: Option<Linker>,
+    pub initial_state_path: Option<PathBuf>,
     pub error_style: ErrorStyle,
     pub target: Triple,
 }
@@ -180,6 +186,8 @@ impl Options {
             None
         };
 
+        let bounds_checked_heap = m.is_present("bounds_checked_heap");
+
         let opt_level = match m.value_of("opt_level") {
             None => OptLevel::SpeedAndSize,
             Some("0") | Some("none") => OptLevel::None,
@@ -211,6 +219,23 @@ impl Options {
         let sk_path = m.value_of("sk_path").map(PathBuf::from);
         let pk_path = m.value_of("pk_path").map(PathBuf::from);
         let count_instructions = m.is_present("count_instructions");
+        let epoch_interruption = m.is_present("epoch_interruption");
+        let linked_imports = m.is_present("linked_imports");
+
+        let threads = if let Some(threads_str) = m.value_of("threads") {
+            Some(threads_str.parse::<usize>()?)
+        } else {
+            None
+        };
+
+        let linker = match m.value_of("linker") {
+            None => None,
+            Some("builtin") => Some(Linker::BuiltIn),
+            Some("ld") => Some(Linker::External),
+            Some(_) => panic!("unknown value for linker"),
+        };
+
+        let initial_state_path = m.value_of("initial_state").map(PathBuf::from);
 
         let error_style = match m.value_of("error_style") {
             None => ErrorStyle::default(),
@@ -231,6 +256,7 @@ impl Options {
             max_reserved_size,
             reserved_size,
             guard_size,
+            bounds_checked_heap,
             opt_level,
             cpu_features,
             keygen,
@@ -239,6 +265,11 @@ impl Options {
             sk_path,
             pk_path,
             count_instructions,
+            epoch_interruption,
+            linked_imports,
+            threads,
+            linker,
+            initial_state_path,
             error_style,
             target,
         })
@@ -403,6 +434,12 @@ SSE3 but not AVX:
                         humansized(HeapSettings::default().guard_size)
                     )),
             )
+            .arg(
+                Arg::with_name("bounds_checked_heap")
+                    .long("--bounds-checked-heap")
+                    .takes_value(false)
+                    .help("check every linear memory access against the current memory size instead of relying on guard pages. the reserved and guard sizes are ignored"),
+            )
             .arg(
                 Arg::with_name("input")
                     .multiple(false)
@@ -452,6 +489,38 @@ SSE3 but not AVX:
                     .takes_value(false)
                     .help("Instrument the produced binary to count the number of wasm operations the translated program executes")
             )
+            .arg(
+                Arg::with_name("epoch_interruption")
+                    .long("--epoch-interruption")
+                    .takes_value(false)
+                    .help("Instrument the produced binary to check for epoch interruption at function entries and loop headers, so it can be stopped without a signal")
+            )
+            .arg(
+                Arg::with_name("linked_imports")
+                    .long("--linked-imports")
+                    .takes_value(false)
+                    .help("Call imported functions through a table bound by the runtime's Linker when an instance is built, instead of resolving them as symbols. No bindings are needed")
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .long("threads")
+                    .takes_value(true)
+                    .multiple(false)
+                    .help("maximum number of threads used to translate and compile functions. default: one per CPU")
+            )
+            .arg(
+                Arg::with_name("linker")
+                    .long("linker")
+                    .takes_value(true)
+                    .possible_values(&["builtin", "ld"])
+                    .help("linker used to produce shared objects. 'ld' runs the linker named by the LD environment variable, with LDFLAGS. default: 'builtin' for x86-64 ELF targets, 'ld' otherwise")
+            )
+            .arg(
+                Arg::with_name("initial_state")
+                    .long("--initial-state")
+                    .takes_value(true)
+                    .help("Path to an initial state saved from an initialized instance, such as by `lucet-wasi --save-initial-state`. The module's heap and globals start out in that state, and its start function is not run")
+            )
             .arg(
                 Arg::with_name("error_style")
                     .long("error-style")
@@ -1,37 +1,52 @@
 mod cpu_features;
 
 pub use self::cpu_features::{CpuFeatures, SpecificFeature, TargetCpu};
+use crate::debug::{DebugSections, FunctionAddressMap};
 use crate::decls::ModuleDecls;
 use crate::error::Error;
 use crate::function::FuncInfo;
 use crate::heap::HeapSettings;
+use crate::import_thunk;
 use crate::module::ModuleInfo;
+use crate::name::Name;
 use crate::output::{CraneliftFuncs, ObjectFile, FUNCTION_MANIFEST_SYM};
 use crate::runtime::Runtime;
 use crate::stack_probe;
 use crate::table::write_table_data;
-use crate::traps::{translate_trapcode, trap_sym_for_func};
+use crate::traps::{addr_map_sym_for_func, translate_trapcode, trap_sym_for_func};
 use byteorder::{LittleEndian, WriteBytesExt};
 use cranelift_codegen::{
-    binemit, ir,
+    binemit,
+    entity::PrimaryMap,
+    ir,
     isa::TargetIsa,
     settings::{self, Configurable},
     Context as ClifContext,
 };
 use cranelift_module::{
     Backend as ClifBackend, DataContext as ClifDataContext, DataId, FuncId, FuncOrDataId,
-    Linkage as ClifLinkage, Module as ClifModule,
+    Linkage as ClifLinkage, Module as ClifModule, ModuleError as ClifModuleError,
+};
+use cranelift_object::{ObjectBackend, ObjectBuilder, ObjectProduct};
+use cranelift_wasm::{
+    translate_module, FuncTranslator, GlobalIndex, ModuleTranslationState, WasmError,
 };
-use cranelift_object::{ObjectBackend, ObjectBuilder};
-use cranelift_wasm::{translate_module, FuncTranslator, ModuleTranslationState, WasmError};
 use lucet_module::bindings::Bindings;
 use lucet_module::{
-    ModuleData, ModuleFeatures, SerializedModule, VersionInfo, LUCET_MODULE_SYM, MODULE_DATA_SYM,
+    AddrMapEntry, InitialState, ModuleData, ModuleFeatures, SerializedModule, VersionInfo,
+    LUCET_MODULE_SYM, MODULE_DATA_SYM,
 };
 use lucet_validate::Validator;
+use object::write::{Object, Relocation, Symbol, SymbolId, SymbolSection};
+use object::{RelocationEncoding, RelocationKind, SymbolFlags, SymbolKind, SymbolScope};
+use rayon::prelude::*;
 use std::collections::HashMap;
 use std::io::Cursor;
 use target_lexicon::Triple;
+use wasmparser::{
+    BinaryReaderError, ImportSectionEntryType, OperatorValidatorConfig, ParserState, Type,
+    ValidatingParser, ValidatingParserConfig, WasmDecoder,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub enum OptLevel {
@@ -62,8 +77,12 @@ pub struct CompilerBuilder {
     cpu_features: CpuFeatures,
     heap_settings: HeapSettings,
     count_instructions: bool,
+    epoch_interruption: bool,
+    linked_imports: bool,
     canonicalize_nans: bool,
     validator: Option<Validator>,
+    threads: Option<usize>,
+    initial_state: Option<InitialState>,
 }
 
 impl CompilerBuilder {
@@ -74,8 +93,12 @@ impl CompilerBuilder {
             cpu_features: CpuFeatures::default(),
             heap_settings: HeapSettings::default(),
             count_instructions: false,
+            epoch_interruption: false,
+            linked_imports: false,
             canonicalize_nans: false,
             validator: None,
+            threads: None,
+            initial_state: None,
         }
     }
 
@@ -136,6 +159,29 @@ impl CompilerBuilder {
         self
     }
 
+    /// Check the instance's epoch at function entries and loop headers, so that the instance can
+    /// be interrupted with an `EpochHandle` rather than a signal.
+    pub fn epoch_interruption(&mut self, epoch_interruption: bool) {
+        self.epoch_interruption = epoch_interruption;
+    }
+
+    pub fn with_epoch_interruption(mut self, epoch_interruption: bool) -> Self {
+        self.epoch_interruption(epoch_interruption);
+        self
+    }
+
+    /// Call imported functions through the instance's import table, so that they are bound to host
+    /// functions by a `Linker` when the instance is built rather than resolved as symbols when the
+    /// module is loaded. The module no longer needs bindings for its imports.
+    pub fn linked_imports(&mut self, linked_imports: bool) {
+        self.linked_imports = linked_imports;
+    }
+
+    pub fn with_linked_imports(mut self, linked_imports: bool) -> Self {
+        self.linked_imports(linked_imports);
+        self
+    }
+
     pub fn canonicalize_nans(&mut self, canonicalize_nans: bool) {
         self.canonicalize_nans = canonicalize_nans;
     }
@@ -154,6 +200,31 @@ impl CompilerBuilder {
         self
     }
 
+    /// Set the maximum number of threads used to translate function bodies.
+    ///
+    /// `None`, the default, uses one thread per available CPU.
+    pub fn threads(&mut self, threads: Option<usize>) {
+        self.threads = threads;
+    }
+
+    pub fn with_threads(mut self, threads: Option<usize>) -> Self {
+        self.threads(threads);
+        self
+    }
+
+    /// Compile the module with the heap and globals of an already-initialized instance.
+    ///
+    /// The state replaces the module's data segments and the initial values of its defined
+    /// globals, and the module's start function is no longer run at instantiation.
+    pub fn initial_state(&mut self, initial_state: Option<InitialState>) {
+        self.initial_state = initial_state;
+    }
+
+    pub fn with_initial_state(mut self, initial_state: Option<InitialState>) -> Self {
+        self.initial_state(initial_state);
+        self
+    }
+
     pub fn create<'a>(
         &'a self,
         wasm_binary: &'a [u8],
@@ -167,8 +238,12 @@ impl CompilerBuilder {
             bindings,
             self.heap_settings.clone(),
             self.count_instructions,
+            self.epoch_interruption,
+            self.linked_imports,
             &self.validator,
             self.canonicalize_nans,
+            self.threads,
+            self.initial_state.as_ref(),
         )
     }
 }
@@ -180,8 +255,13 @@ pub struct Compiler<'a> {
     opt_level: OptLevel,
     cpu_features: CpuFeatures,
     count_instructions: bool,
+    epoch_interruption: bool,
+    linked_imports: bool,
     module_translation_state: ModuleTranslationState,
     canonicalize_nans: bool,
+    simd: bool,
+    threads: Option<usize>,
+    debug_sections: DebugSections<'a>,
 }
 
 impl<'a> Compiler<'a> {
@@ -193,23 +273,40 @@ impl<'a> Compiler<'a> {
         bindings: &'a Bindings,
         heap_settings: HeapSettings,
         count_instructions: bool,
+        epoch_interruption: bool,
+        linked_imports: bool,
         validator: &Option<Validator>,
         canonicalize_nans: bool,
+        threads: Option<usize>,
+        initial_state: Option<&InitialState>,
     ) -> Result<Self, Error> {
-        let isa = Self::target_isa(target.clone(), opt_level, &cpu_features, canonicalize_nans)?;
-
-        let frontend_config = isa.frontend_config();
-        let mut module_info = ModuleInfo::new(frontend_config.clone());
-
+        // As of cranelift-wasm 0.43 which uses wasmparser 0.39.1, the parser used inside
+        // cranelift-wasm does not validate. We need to run the validating parser on the binary
+        // first. The InvalidWebAssembly error below will never trigger.
+        let validated = validate(wasm_binary).map_err(Error::WasmValidation)?;
+        // the module is valid, so the witx validator only needs to check its interfaces
         if let Some(v) = validator {
-            v.validate(wasm_binary).map_err(Error::LucetValidation)?;
-        } else {
-            // As of cranelift-wasm 0.43 which uses wasmparser 0.39.1, the parser used inside
-            // cranelift-wasm does not validate. We need to run the validating parser on the binary
-            // first. The InvalidWebAssembly error below will never trigger.
-            wasmparser::validate(wasm_binary, None).map_err(Error::WasmValidation)?;
+            v.validate_interfaces(wasm_binary)
+                .map_err(Error::LucetValidation)?;
+        }
+        let simd = validated.simd;
+
+        let mut cpu_features = cpu_features;
+        if simd {
+            cpu_features.require_simd()?;
         }
 
+        let isa = Self::target_isa(
+            target.clone(),
+            opt_level,
+            &cpu_features,
+            canonicalize_nans,
+            simd,
+        )?;
+
+        let frontend_config = isa.frontend_config();
+        let mut module_info = ModuleInfo::new(frontend_config.clone(), validated.global_types);
+
         let module_translation_state =
             translate_module(wasm_binary, &mut module_info).map_err(|e| match e {
                 WasmError::User(u) => Error::Input(u),
@@ -223,12 +320,9 @@ impl<'a> Compiler<'a> {
                 WasmError::ImplLimitExceeded { .. } => Error::ClifWasmError(e),
             })?;
 
-        let libcalls = Box::new(move |libcall| match libcall {
-            ir::LibCall::Probestack => stack_probe::STACK_PROBE_SYM.to_owned(),
-            _ => (cranelift_module::default_libcall_names())(libcall),
-        });
+        let debug_sections = DebugSections::read(wasm_binary)?;
 
-        let mut builder = ObjectBuilder::new(isa, "lucet_guest".to_owned(), libcalls);
+        let mut builder = ObjectBuilder::new(isa, "lucet_guest".to_owned(), Box::new(libcall_name));
         builder.function_alignment(16);
         let mut clif_module: ClifModule<ObjectBackend> = ClifModule::new(builder);
 
@@ -239,6 +333,8 @@ impl<'a> Compiler<'a> {
             bindings,
             runtime,
             heap_settings,
+            initial_state,
+            linked_imports,
         )?;
 
         Ok(Self {
@@ -247,9 +343,14 @@ impl<'a> Compiler<'a> {
             opt_level,
             cpu_features,
             count_instructions,
+            epoch_interruption,
+            linked_imports,
             module_translation_state,
             target,
             canonicalize_nans,
+            simd,
+            threads,
+            debug_sections,
         })
     }
 
@@ -260,6 +361,8 @@ impl<'a> Compiler<'a> {
     pub fn module_features(&self) -> ModuleFeatures {
         let mut mf: ModuleFeatures = (&self.cpu_features).into();
         mf.instruction_count = self.count_instructions;
+        mf.epoch_interruption = self.epoch_interruption;
+        mf.linked_imports = self.linked_imports;
         mf
     }
 
@@ -268,44 +371,68 @@ impl<'a> Compiler<'a> {
     }
 
     pub fn object_file(mut self) -> Result<ObjectFile, Error> {
-        let mut func_translator = FuncTranslator::new();
         let mut function_manifest_ctx = ClifDataContext::new();
         let mut function_manifest_bytes = Cursor::new(Vec::new());
         let mut function_map: HashMap<FuncId, (u32, DataId, usize)> = HashMap::new();
+        let mut addr_map_data: HashMap<FuncId, (DataId, usize)> = HashMap::new();
 
-        for (ref func, (code, code_offset)) in self.decls.function_bodies() {
-            let mut func_info = FuncInfo::new(&self.decls, self.count_instructions);
-            let mut clif_context = ClifContext::new();
-            clif_context.func.name = func.name.as_externalname();
-            clif_context.func.signature = func.signature.clone();
-
-            func_translator
-                .translate(
-                    &self.module_translation_state,
-                    code,
-                    *code_offset,
-                    &mut clif_context.func,
-                    &mut func_info,
-                )
-                .map_err(|source| Error::FunctionTranslation {
+        let translated = self.translate_functions()?;
+        let compiled = self.compile_functions(translated)?;
+
+        let emit_debug_info = self.debug_sections.has_line_info();
+        let mut function_addr_maps = Vec::new();
+        let mut function_relocs = Vec::new();
+
+        // The functions were compiled in parallel, but defining them in the `ClifModule` stays
+        // serial. Functions are always defined in index order, which keeps the code layout, the
+        // trap data symbols, and the resulting object file identical from run to run.
+        for func in compiled {
+            let func_id = func.name.as_funcid().unwrap();
+            let size = self
+                .clif_module
+                .define_function_bytes(func_id, &func.code)
+                .map_err(|source| Error::FunctionDefinition {
                     symbol: func.name.symbol().to_string(),
                     source,
-                })?;
-            let func_id = func.name.as_funcid().unwrap();
+                })?
+                .size;
+
+            let trap_data_id = func
+                .traps
+                .write(&mut self.clif_module, func.name.symbol())?;
+
+            function_map.insert(func_id, (size, trap_data_id, func.traps.len()));
+
+            let addr_map_entries = func.addr_map.addr_map_entries();
+            let addr_map_data_id =
+                write_addr_map(&mut self.clif_module, func.name.symbol(), &addr_map_entries)?;
+            addr_map_data.insert(func_id, (addr_map_data_id, addr_map_entries.len()));
+
+            function_relocs.push((func_id, func.relocs));
+            if emit_debug_info {
+                function_addr_maps.push(func.addr_map);
+            }
+        }
+
+        // Define the thunks for linked imports. They have no wasm body, so there is no address map.
+        let thunks = self
+            .decls
+            .linked_imports()
+            .map(|(ix, func)| (func.name.clone(), import_thunk::build(ix, &func)))
+            .collect::<Vec<_>>();
+        for (name, func_ir) in thunks {
+            let mut clif_context = ClifContext::for_function(func_ir);
+            let func_id = name.as_funcid().unwrap();
             let mut traps = TrapSites::new();
             let compiled = self
                 .clif_module
                 .define_function(func_id, &mut clif_context, &mut traps)
                 .map_err(|source| Error::FunctionDefinition {
-                    symbol: func.name.symbol().to_string(),
+                    symbol: name.symbol().to_string(),
                     source,
                 })?;
-
-            let size = compiled.size;
-
-            let trap_data_id = traps.write(&mut self.clif_module, func.name.symbol())?;
-
-            function_map.insert(func_id, (size, trap_data_id, traps.len()));
+            let trap_data_id = traps.write(&mut self.clif_module, name.symbol())?;
+            function_map.insert(func_id, (compiled.size, trap_data_id, traps.len()));
         }
 
         // Write out the stack probe and associated data.
@@ -350,11 +477,11 @@ impl<'a> Compiler<'a> {
         let function_manifest_len = ids.len();
 
         for func_id in ids {
-            let (size, trap_data_id, traps_len) = match function_map.get(&func_id) {
+            let (size, traps) = match function_map.get(&func_id) {
                 Some((ref size, ref trap_data_id, ref traps_len)) => {
-                    (*size, Some(*trap_data_id), *traps_len)
+                    (*size, Some((*trap_data_id, *traps_len)))
                 }
-                None => (0 as u32, None, 0 as usize),
+                None => (0 as u32, None),
             };
 
             write_function_spec(
@@ -363,8 +490,8 @@ impl<'a> Compiler<'a> {
                 &mut function_manifest_bytes,
                 func_id,
                 size,
-                trap_data_id,
-                traps_len,
+                traps,
+                addr_map_data.get(&func_id).cloned(),
             )?;
         }
 
@@ -437,36 +564,20 @@ impl<'a> Compiler<'a> {
         self.clif_module
             .define_data(native_data_id, &native_data_ctx)?;
 
-        let obj = ObjectFile::new(self.clif_module.finish())?;
+        let mut product = self.clif_module.finish();
+        write_function_relocs(&mut product, &function_relocs)?;
+        if emit_debug_info {
+            self.debug_sections
+                .write(&mut product, &function_addr_maps)?;
+        }
+
+        let obj = ObjectFile::new(product)?;
 
         Ok(obj)
     }
 
     pub fn cranelift_funcs(self) -> Result<CraneliftFuncs, Error> {
-        let mut funcs = HashMap::new();
-        let mut func_translator = FuncTranslator::new();
-
-        for (ref func, (code, code_offset)) in self.decls.function_bodies() {
-            let mut func_info = FuncInfo::new(&self.decls, self.count_instructions);
-            let mut clif_context = ClifContext::new();
-            clif_context.func.name = func.name.as_externalname();
-            clif_context.func.signature = func.signature.clone();
-
-            func_translator
-                .translate(
-                    &self.module_translation_state,
-                    code,
-                    *code_offset,
-                    &mut clif_context.func,
-                    &mut func_info,
-                )
-                .map_err(|source| Error::FunctionTranslation {
-                    symbol: func.name.symbol().to_string(),
-                    source,
-                })?;
-
-            funcs.insert(func.name.clone(), clif_context.func);
-        }
+        let funcs = self.translate_functions()?.into_iter().collect();
         Ok(CraneliftFuncs::new(
             funcs,
             Self::target_isa(
@@ -474,15 +585,146 @@ impl<'a> Compiler<'a> {
                 self.opt_level,
                 &self.cpu_features,
                 self.canonicalize_nans,
+                self.simd,
             )?,
         ))
     }
 
+    /// Translate every local function body to Cranelift IR.
+    ///
+    /// Bodies are translated on a thread pool of at most `threads` workers, each with its own
+    /// `FuncTranslator`. The results are returned in function index order regardless of which
+    /// worker translated them.
+    fn translate_functions(&self) -> Result<Vec<(Name, ir::Function)>, Error> {
+        let mut bodies = self.decls.function_bodies().collect::<Vec<_>>();
+        bodies.sort_by_key(|(func, _)| func.name.as_funcid().unwrap());
+
+        let pool = self.thread_pool()?;
+
+        let decls = &self.decls;
+        let module_translation_state = &self.module_translation_state;
+        let count_instructions = self.count_instructions;
+        let epoch_interruption = self.epoch_interruption;
+
+        pool.install(|| {
+            bodies
+                .into_par_iter()
+                .map_init(
+                    FuncTranslator::new,
+                    |func_translator, (func, (code, code_offset))| {
+                        let mut func_info =
+                            FuncInfo::new(decls, count_instructions, epoch_interruption);
+                        let mut func_ir = ir::Function::with_name_signature(
+                            func.name.as_externalname(),
+                            func.signature.clone(),
+                        );
+
+                        func_translator
+                            .translate(
+                                module_translation_state,
+                                code,
+                                *code_offset,
+                                &mut func_ir,
+                                &mut func_info,
+                            )
+                            .map_err(|source| Error::FunctionTranslation {
+                                symbol: func.name.symbol().to_string(),
+                                source,
+                            })?;
+
+                        Ok((func.name, func_ir))
+                    },
+                )
+                .collect()
+        })
+    }
+
+    /// Compile translated functions to machine code.
+    ///
+    /// Like translation, this runs on a thread pool of at most `threads` workers, and returns the
+    /// compiled functions in the order they were given. The code is not yet defined in the
+    /// `ClifModule`; its relocations are kept alongside it, to be added to the object by
+    /// `write_function_relocs()` once the module is finished.
+    fn compile_functions(
+        &self,
+        translated: Vec<(Name, ir::Function)>,
+    ) -> Result<Vec<CompiledFunction>, Error> {
+        let function_bodies: HashMap<FuncId, (usize, usize)> = self
+            .decls
+            .function_bodies()
+            .map(|(func, (code, code_offset))| {
+                (func.name.as_funcid().unwrap(), (*code_offset, code.len()))
+            })
+            .collect();
+
+        let pool = self.thread_pool()?;
+
+        let isa = self.clif_module.isa();
+        let function_bodies = &function_bodies;
+
+        pool.install(|| {
+            translated
+                .into_par_iter()
+                .map(|(name, func_ir)| {
+                    let func_id = name.as_funcid().unwrap();
+                    let mut clif_context = ClifContext::for_function(func_ir);
+                    let mut code = Vec::new();
+                    let mut relocs = FunctionRelocs::new();
+                    let mut traps = TrapSites::new();
+                    clif_context
+                        .compile_and_emit(
+                            isa,
+                            &mut code,
+                            &mut relocs,
+                            &mut traps,
+                            &mut binemit::NullStackmapSink {},
+                        )
+                        .map_err(|source| Error::FunctionDefinition {
+                            symbol: name.symbol().to_string(),
+                            source: ClifModuleError::Compilation(source),
+                        })?;
+                    if let Some(reloc) = relocs.unsupported {
+                        return Err(Error::Unsupported(format!(
+                            "relocation {} in function {}",
+                            reloc,
+                            name.symbol()
+                        )));
+                    }
+
+                    let addr_map = FunctionAddressMap::new(
+                        func_id,
+                        name.symbol(),
+                        function_bodies[&func_id],
+                        code.len() as u32,
+                        &clif_context.func,
+                        isa,
+                    );
+
+                    Ok(CompiledFunction {
+                        name,
+                        code,
+                        relocs: relocs.relocs,
+                        traps,
+                        addr_map,
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn thread_pool(&self) -> Result<rayon::ThreadPool, Error> {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads.unwrap_or(0))
+            .build()
+            .map_err(|e| Error::ThreadPool(e.to_string()))
+    }
+
     fn target_isa(
         target: Triple,
         opt_level: OptLevel,
         cpu_features: &CpuFeatures,
         canonicalize_nans: bool,
+        simd: bool,
     ) -> Result<Box<dyn TargetIsa>, Error> {
         let mut flags_builder = settings::builder();
         let isa_builder = cpu_features.isa_builder(target)?;
@@ -492,10 +734,89 @@ impl<'a> Compiler<'a> {
         if canonicalize_nans {
             flags_builder.enable("enable_nan_canonicalization").unwrap();
         }
+        if simd {
+            flags_builder.enable("enable_simd").unwrap();
+        }
         Ok(isa_builder.finish(settings::Flags::new(flags_builder)))
     }
 }
 
+/// The WebAssembly proposals lucetc accepts, beyond the MVP.
+fn validation_config() -> ValidatingParserConfig {
+    ValidatingParserConfig {
+        operator_config: OperatorValidatorConfig {
+            enable_threads: false,
+            enable_reference_types: true,
+            enable_simd: true,
+            enable_bulk_memory: true,
+            enable_multi_value: true,
+            enable_tail_call: false,
+        },
+    }
+}
+
+/// What validation finds out about a module along the way.
+struct Validated {
+    /// Whether the module uses the `v128` type or any SIMD instructions.
+    simd: bool,
+    /// The types of the module's globals, imported and local, in index order.
+    global_types: PrimaryMap<GlobalIndex, Type>,
+}
+
+/// Validate the module.
+///
+/// Validation reads every type, global, local, and instruction of the module, so SIMD use and the
+/// types of globals are found along the way rather than in a separate pass.
+fn validate(wasm_binary: &[u8]) -> Result<Validated, BinaryReaderError> {
+    // every SIMD instruction is encoded with this prefix byte
+    const SIMD_PREFIX: u8 = 0xfd;
+
+    let mut parser = ValidatingParser::new(wasm_binary, Some(validation_config()));
+    let mut simd = false;
+    let mut global_types = PrimaryMap::new();
+    loop {
+        // operators are read one at a time, so this is where the next one starts
+        let position = parser.current_position();
+        match *parser.read() {
+            ParserState::EndWasm => return Ok(Validated { simd, global_types }),
+            ParserState::Error(ref e) => return Err(e.clone()),
+            ParserState::ImportSectionEntry {
+                ty: ImportSectionEntryType::Global(ref global_type),
+                ..
+            } => {
+                simd |= global_type.content_type == Type::V128;
+                global_types.push(global_type.content_type);
+            }
+            ParserState::TypeSectionEntry(ref func_type) => {
+                simd |= func_type
+                    .params
+                    .iter()
+                    .chain(func_type.returns.iter())
+                    .any(|ty| *ty == Type::V128);
+            }
+            ParserState::BeginGlobalSectionEntry(ref global_type) => {
+                simd |= global_type.content_type == Type::V128;
+                global_types.push(global_type.content_type);
+            }
+            ParserState::FunctionBodyLocals { ref locals } => {
+                simd |= locals.iter().any(|(_, ty)| *ty == Type::V128);
+            }
+            ParserState::CodeOperator(_) => {
+                simd |= wasm_binary.get(position) == Some(&SIMD_PREFIX);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Names of the symbols that calls to Cranelift libcalls are relocated against.
+fn libcall_name(libcall: ir::LibCall) -> String {
+    match libcall {
+        ir::LibCall::Probestack => stack_probe::STACK_PROBE_SYM.to_owned(),
+        _ => (cranelift_module::default_libcall_names())(libcall),
+    }
+}
+
 fn write_module_data<B: ClifBackend>(
     clif_module: &mut ClifModule<B>,
     module_data_bytes: Vec<u8>,
@@ -515,6 +836,173 @@ fn write_module_data<B: ClifBackend>(
     Ok(module_data_decl)
 }
 
+/// A function compiled by `Compiler::compile_functions()`.
+struct CompiledFunction {
+    name: Name,
+    code: Vec<u8>,
+    relocs: Vec<FunctionReloc>,
+    traps: TrapSites,
+    addr_map: FunctionAddressMap,
+}
+
+/// A relocation in the code of a compiled function.
+struct FunctionReloc {
+    offset: binemit::CodeOffset,
+    reloc: binemit::Reloc,
+    name: ir::ExternalName,
+    addend: binemit::Addend,
+}
+
+/// Collect relocations from cranelift_codegen codegen:
+struct FunctionRelocs {
+    relocs: Vec<FunctionReloc>,
+    /// The first relocation found that can't be represented in the object, if any.
+    unsupported: Option<binemit::Reloc>,
+}
+
+impl FunctionRelocs {
+    fn new() -> Self {
+        Self {
+            relocs: Vec::new(),
+            unsupported: None,
+        }
+    }
+}
+
+impl binemit::RelocSink for FunctionRelocs {
+    fn reloc_block(
+        &mut self,
+        _offset: binemit::CodeOffset,
+        reloc: binemit::Reloc,
+        _block_offset: binemit::CodeOffset,
+    ) {
+        self.unsupported.get_or_insert(reloc);
+    }
+
+    fn reloc_external(
+        &mut self,
+        offset: binemit::CodeOffset,
+        _srcloc: ir::SourceLoc,
+        reloc: binemit::Reloc,
+        name: &ir::ExternalName,
+        addend: binemit::Addend,
+    ) {
+        self.relocs.push(FunctionReloc {
+            offset,
+            reloc,
+            name: name.clone(),
+            addend,
+        });
+    }
+
+    fn reloc_constant(
+        &mut self,
+        _offset: binemit::CodeOffset,
+        reloc: binemit::Reloc,
+        _constant_offset: ir::ConstantOffset,
+    ) {
+        // constants are emitted after the code of the function, and addressed relative to it
+        if reloc != binemit::Reloc::X86PCRelRodata4 {
+            self.unsupported.get_or_insert(reloc);
+        }
+    }
+
+    fn reloc_jt(
+        &mut self,
+        _offset: binemit::CodeOffset,
+        reloc: binemit::Reloc,
+        _jt: ir::JumpTable,
+    ) {
+        // as are jump tables
+        if reloc != binemit::Reloc::X86PCRelRodata4 {
+            self.unsupported.get_or_insert(reloc);
+        }
+    }
+}
+
+/// Add the relocations of functions that were defined from their compiled bytes to the object,
+/// translating them the way `ObjectBackend` does for the functions it compiles itself.
+fn write_function_relocs(
+    product: &mut ObjectProduct,
+    function_relocs: &[(FuncId, Vec<FunctionReloc>)],
+) -> Result<(), Error> {
+    for (func_id, relocs) in function_relocs {
+        let func_symbol = product.object.symbol(product.function_symbol(*func_id));
+        let (section, func_offset) = match func_symbol.section {
+            SymbolSection::Section(section) => (section, func_symbol.value),
+            _ => unreachable!("defined functions have a section"),
+        };
+        for reloc in relocs {
+            let symbol = match reloc.name {
+                ir::ExternalName::User {
+                    namespace: 0,
+                    index,
+                } => product.function_symbol(FuncId::from_u32(index)),
+                ir::ExternalName::User {
+                    namespace: 1,
+                    index,
+                } => product.data_symbol(DataId::from_u32(index)),
+                ir::ExternalName::LibCall(libcall) => libcall_symbol(&mut product.object, libcall),
+                ref name => return Err(Error::Unsupported(format!("relocation against {}", name))),
+            };
+            let (kind, encoding, size) = match reloc.reloc {
+                binemit::Reloc::Abs4 => (RelocationKind::Absolute, RelocationEncoding::Generic, 32),
+                binemit::Reloc::Abs8 => (RelocationKind::Absolute, RelocationEncoding::Generic, 64),
+                binemit::Reloc::X86PCRel4 => {
+                    (RelocationKind::Relative, RelocationEncoding::Generic, 32)
+                }
+                binemit::Reloc::X86CallPCRel4 => {
+                    (RelocationKind::Relative, RelocationEncoding::X86Branch, 32)
+                }
+                binemit::Reloc::X86CallPLTRel4 => (
+                    RelocationKind::PltRelative,
+                    RelocationEncoding::X86Branch,
+                    32,
+                ),
+                binemit::Reloc::X86GOTPCRel4 => {
+                    (RelocationKind::GotRelative, RelocationEncoding::Generic, 32)
+                }
+                reloc => return Err(Error::Unsupported(format!("relocation {}", reloc))),
+            };
+            product
+                .object
+                .add_relocation(
+                    section,
+                    Relocation {
+                        offset: func_offset + reloc.offset as u64,
+                        size,
+                        kind,
+                        encoding,
+                        symbol,
+                        addend: reloc.addend,
+                    },
+                )
+                .map_err(|source| {
+                    Error::ObjectArtifact(source, format!("relocation against {}", reloc.name))
+                })?;
+        }
+    }
+    Ok(())
+}
+
+/// The symbol for a libcall, which is undefined unless it is one of our own functions.
+fn libcall_symbol(obj: &mut Object, libcall: ir::LibCall) -> SymbolId {
+    let name = libcall_name(libcall);
+    match obj.symbol_id(name.as_bytes()) {
+        Some(symbol) => symbol,
+        None => obj.add_symbol(Symbol {
+            name: name.into_bytes(),
+            value: 0,
+            size: 0,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Unknown,
+            weak: false,
+            section: SymbolSection::Undefined,
+            flags: SymbolFlags::None,
+        }),
+    }
+}
+
 /// Collect traps from cranelift_module codegen:
 struct TrapSites {
     traps: Vec<cranelift_module::TrapSite>,
@@ -595,8 +1083,8 @@ fn write_function_spec(
     manifest_bytes: &mut Cursor<Vec<u8>>,
     func_id: FuncId,
     size: binemit::CodeOffset,
-    trap_data_id: Option<DataId>,
-    n_traps: usize,
+    traps: Option<(DataId, usize)>,
+    addr_map: Option<(DataId, usize)>,
 ) -> Result<(), Error> {
     // This code has implicit knowledge of the layout of `FunctionSpec`!
     //
@@ -607,15 +1095,57 @@ fn write_function_spec(
     manifest_bytes.write_u64::<LittleEndian>(0 as u64)?;
     manifest_bytes.write_u64::<LittleEndian>(size as u64)?;
     // Write a (ptr, len) pair with relocation for the trap table.
-    if let Some(trap_data_id) = trap_data_id {
-        if n_traps > 0 {
+    let n_traps = match traps {
+        Some((trap_data_id, n_traps)) if n_traps > 0 => {
             let data_ref = module.declare_data_in_data(trap_data_id, &mut manifest_ctx);
             let offset = manifest_bytes.position() as u32;
             manifest_ctx.write_data_addr(offset, data_ref, 0);
+            n_traps
         }
-    }
+        _ => 0,
+    };
     manifest_bytes.write_u64::<LittleEndian>(0 as u64)?;
     manifest_bytes.write_u64::<LittleEndian>(n_traps as u64)?;
+    // Write a (ptr, len) pair with relocation for the address map.
+    let n_addr_map_entries = match addr_map {
+        Some((addr_map_data_id, n_entries)) if n_entries > 0 => {
+            let data_ref = module.declare_data_in_data(addr_map_data_id, &mut manifest_ctx);
+            let offset = manifest_bytes.position() as u32;
+            manifest_ctx.write_data_addr(offset, data_ref, 0);
+            n_entries
+        }
+        _ => 0,
+    };
+    manifest_bytes.write_u64::<LittleEndian>(0 as u64)?;
+    manifest_bytes.write_u64::<LittleEndian>(n_addr_map_entries as u64)?;
 
     Ok(())
 }
+
+/// Write the address map for a given function into the cranelift module.
+fn write_addr_map(
+    module: &mut ClifModule<ObjectBackend>,
+    func_name: &str,
+    entries: &[AddrMapEntry],
+) -> Result<DataId, Error> {
+    let entry_bytes = unsafe {
+        std::slice::from_raw_parts(
+            entries.as_ptr() as *const u8,
+            entries.len() * std::mem::size_of::<AddrMapEntry>(),
+        )
+    };
+
+    let mut addr_map_ctx = ClifDataContext::new();
+    addr_map_ctx.define(entry_bytes.to_vec().into());
+
+    let addr_map_data_id = module.declare_data(
+        &addr_map_sym_for_func(func_name),
+        ClifLinkage::Local,
+        false,
+        false,
+        None,
+    )?;
+    module.define_data(addr_map_data_id, &addr_map_ctx)?;
+
+    Ok(addr_map_data_id)
+}
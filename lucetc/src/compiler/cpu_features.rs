@@ -182,6 +182,23 @@ impl CpuFeatures {
         self.specific_features.insert(sf, enabled);
     }
 
+    /// Enable the features Cranelift needs to compile SIMD instructions: SSE3, SSSE3, and SSE4.1.
+    ///
+    /// Returns an error if any of them have been explicitly disabled.
+    pub fn require_simd(&mut self) -> Result<(), Error> {
+        use SpecificFeature::*;
+        for feature in &[SSE3, SSSE3, SSE41] {
+            if let Some(false) = self.specific_features.get(feature) {
+                return Err(Error::Unsupported(format!(
+                    "module uses SIMD, which requires {:?}, but it is disabled",
+                    feature
+                )));
+            }
+            self.specific_features.insert(*feature, true);
+        }
+        Ok(())
+    }
+
     /// Return a `cranelift_codegen::isa::Builder` configured with these CPU features.
 
     pub fn isa_builder(&self, target: Triple) -> Result<isa::Builder, Error> {
@@ -0,0 +1,561 @@
+//! Translation of wasm DWARF into native DWARF for the generated object.
+//!
+//! Producers such as clang and rustc describe the source of a wasm module in `.debug_*` custom
+//! sections, using offsets into the code section as addresses. Cranelift tags every instruction it
+//! emits with the wasm offset of the operator it was translated from, so after code generation we
+//! can rebuild the line table against native code addresses, and describe each function as a
+//! subprogram named after its source-level name.
+//!
+//! Only line information and subprogram ranges are translated. Descriptions of variables, including
+//! their locations, and of types are not carried over, so debuggers can step through guest source
+//! but not inspect its variables.
+
+use crate::error::Error;
+use cranelift_codegen::{ir, isa::TargetIsa};
+use cranelift_module::FuncId;
+use cranelift_object::ObjectProduct;
+use gimli::write::{
+    Address, AttributeValue, DwarfUnit, EndianVec, FileId, LineProgram, LineString, Range,
+    RangeList, Sections, Writer,
+};
+use gimli::{Encoding, EndianSlice, Format, LineEncoding, LittleEndian, SectionId};
+use lucet_module::AddrMapEntry;
+use object::write::{Object, Relocation, StandardSegment};
+use object::{RelocationEncoding, RelocationKind, SectionKind};
+use std::collections::{BTreeMap, HashMap};
+use wasmparser::{ModuleReader, SectionCode};
+
+/// The `.debug_*` custom sections of a wasm module.
+pub(crate) struct DebugSections<'a> {
+    sections: HashMap<&'a str, &'a [u8]>,
+    /// Offset of the code section contents from the start of the module; wasm DWARF addresses
+    /// are relative to this.
+    code_section_offset: u64,
+}
+
+impl<'a> DebugSections<'a> {
+    pub fn read(wasm_binary: &'a [u8]) -> Result<Self, Error> {
+        let mut sections = HashMap::new();
+        let mut code_section_offset = 0;
+
+        let mut reader = ModuleReader::new(wasm_binary)?;
+        while !reader.eof() {
+            let section = reader.read()?;
+            match section.code {
+                SectionCode::Custom { name, .. } if name.starts_with(".debug_") => {
+                    let mut reader = section.get_binary_reader();
+                    let len = reader.bytes_remaining();
+                    sections.insert(name, reader.read_bytes(len)?);
+                }
+                SectionCode::Code => {
+                    code_section_offset = section.range().start as u64;
+                }
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            sections,
+            code_section_offset,
+        })
+    }
+
+    /// Whether the module carries a line table we can translate.
+    pub fn has_line_info(&self) -> bool {
+        self.sections.contains_key(".debug_info") && self.sections.contains_key(".debug_line")
+    }
+
+    fn section(&self, id: SectionId) -> EndianSlice<'a, LittleEndian> {
+        let data = self.sections.get(id.name()).cloned().unwrap_or(&[]);
+        EndianSlice::new(data, LittleEndian)
+    }
+
+    /// Translate the wasm DWARF to describe the compiled functions, and add the resulting
+    /// sections to the object.
+    pub fn write(
+        &self,
+        product: &mut ObjectProduct,
+        functions: &[FunctionAddressMap],
+    ) -> Result<(), Error> {
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: 8,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        dwarf.unit.line_program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::String(b".".to_vec()),
+            LineString::String(b"lucet_guest".to_vec()),
+            None,
+        );
+
+        let source = self.read_source_info(&mut dwarf.unit.line_program)?;
+
+        let mut ranges = Vec::with_capacity(functions.len());
+        for (symbol, func) in functions.iter().enumerate() {
+            let begin = Address::Symbol { symbol, addend: 0 };
+            ranges.push(Range::StartLength {
+                begin,
+                length: func.len as u64,
+            });
+            self.write_line_sequence(&mut dwarf.unit.line_program, &source, begin, func);
+
+            let body_start = func.body_offset as u64 - self.code_section_offset;
+            let body_end = body_start + func.body_len as u64;
+            let name = source
+                .subprograms
+                .range(body_start..body_end)
+                .next()
+                .map(|(_, name)| name.clone())
+                .unwrap_or_else(|| func.symbol.as_bytes().to_vec());
+
+            let root = dwarf.unit.root();
+            let id = dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+            let subprogram = dwarf.unit.get_mut(id);
+            subprogram.set(gimli::DW_AT_name, AttributeValue::String(name));
+            subprogram.set(
+                gimli::DW_AT_linkage_name,
+                AttributeValue::String(func.symbol.as_bytes().to_vec()),
+            );
+            subprogram.set(gimli::DW_AT_low_pc, AttributeValue::Address(begin));
+            subprogram.set(gimli::DW_AT_high_pc, AttributeValue::Udata(func.len as u64));
+        }
+
+        let range_list = dwarf.unit.ranges.add(RangeList(ranges));
+        let root = dwarf.unit.root();
+        let cu = dwarf.unit.get_mut(root);
+        cu.set(
+            gimli::DW_AT_name,
+            AttributeValue::String(b"lucet_guest".to_vec()),
+        );
+        cu.set(
+            gimli::DW_AT_producer,
+            AttributeValue::String(format!("lucetc {}", env!("CARGO_PKG_VERSION")).into_bytes()),
+        );
+        if let Some(language) = source.language {
+            cu.set(gimli::DW_AT_language, AttributeValue::Language(language));
+        }
+        cu.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(0)),
+        );
+        cu.set(
+            gimli::DW_AT_ranges,
+            AttributeValue::RangeListRef(range_list),
+        );
+
+        let mut sections = Sections::new(DebugSectionWriter::new());
+        dwarf.write(&mut sections)?;
+
+        let symbols = functions
+            .iter()
+            .map(|func| product.function_symbol(func.func_id))
+            .collect::<Vec<_>>();
+        emit_sections(&mut product.object, &sections, &symbols)
+    }
+
+    /// Read the line tables and subprogram names of every compilation unit in the wasm DWARF.
+    ///
+    /// Files referenced by the line tables are added to `program`, so that rows can refer to them
+    /// directly when translated.
+    fn read_source_info(&self, program: &mut LineProgram) -> Result<SourceInfo, Error> {
+        let dwarf = gimli::Dwarf::load(
+            |id| -> Result<_, gimli::Error> { Ok(self.section(id)) },
+            |_| Ok(EndianSlice::new(&[], LittleEndian)),
+        )?;
+
+        let mut info = SourceInfo {
+            rows: Vec::new(),
+            subprograms: BTreeMap::new(),
+            language: None,
+        };
+
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+
+            let mut entries = unit.entries();
+            while let Some((_, entry)) = entries.next_dfs()? {
+                match entry.tag() {
+                    gimli::DW_TAG_compile_unit => {
+                        if let Some(gimli::AttributeValue::Language(language)) =
+                            entry.attr_value(gimli::DW_AT_language)?
+                        {
+                            info.language.get_or_insert(language);
+                        }
+                    }
+                    gimli::DW_TAG_subprogram => {
+                        let low_pc = match entry.attr_value(gimli::DW_AT_low_pc)? {
+                            Some(gimli::AttributeValue::Addr(low_pc)) => low_pc,
+                            _ => continue,
+                        };
+                        if let Some(name) = entry.attr_value(gimli::DW_AT_name)? {
+                            let name = dwarf.attr_string(&unit, name)?;
+                            info.subprograms
+                                .entry(low_pc)
+                                .or_insert_with(|| name.slice().to_vec());
+                        }
+                    }
+                    _ => (),
+                }
+            }
+
+            let line_program = match unit.line_program.clone() {
+                Some(line_program) => line_program,
+                None => continue,
+            };
+            let mut files: HashMap<u64, FileId> = HashMap::new();
+            let mut rows = line_program.rows();
+            while let Some((header, row)) = rows.next_row()? {
+                if row.end_sequence() {
+                    info.rows.push(LineRow {
+                        address: row.address(),
+                        end_sequence: true,
+                        file: None,
+                        line: 0,
+                        column: 0,
+                    });
+                    continue;
+                }
+
+                let file = match files.get(&row.file_index()) {
+                    Some(file) => Some(*file),
+                    None => match row.file(header) {
+                        Some(entry) => {
+                            let dir = match entry.directory(header) {
+                                Some(dir) => dwarf.attr_string(&unit, dir)?.slice().to_vec(),
+                                None => Vec::new(),
+                            };
+                            let path = dwarf
+                                .attr_string(&unit, entry.path_name())?
+                                .slice()
+                                .to_vec();
+                            let dir_id = program.add_directory(LineString::String(dir));
+                            let file = program.add_file(LineString::String(path), dir_id, None);
+                            files.insert(row.file_index(), file);
+                            Some(file)
+                        }
+                        None => None,
+                    },
+                };
+
+                info.rows.push(LineRow {
+                    address: row.address(),
+                    end_sequence: false,
+                    file,
+                    line: row.line().unwrap_or(0),
+                    column: match row.column() {
+                        gimli::ColumnType::LeftEdge => 0,
+                        gimli::ColumnType::Column(column) => column,
+                    },
+                });
+            }
+        }
+
+        // Where one sequence ends at the address another begins, the end must sort first so that
+        // lookups at that address find the start of the new sequence.
+        info.rows
+            .sort_by_key(|row| (row.address, !row.end_sequence));
+
+        Ok(info)
+    }
+
+    /// Write a line sequence for one function, with a row wherever the source position of the
+    /// native code changes.
+    fn write_line_sequence(
+        &self,
+        program: &mut LineProgram,
+        source: &SourceInfo,
+        begin: Address,
+        func: &FunctionAddressMap,
+    ) {
+        program.begin_sequence(Some(begin));
+
+        let mut last = None;
+        for (native_offset, srcloc) in func.instructions.iter() {
+            if srcloc.is_default() {
+                continue;
+            }
+            let wasm_addr = match (srcloc.bits() as u64).checked_sub(self.code_section_offset) {
+                Some(wasm_addr) => wasm_addr,
+                None => continue,
+            };
+            let row = match source.lookup(wasm_addr) {
+                Some(row) => row,
+                None => continue,
+            };
+            let position = (row.file, row.line, row.column);
+            if last == Some(position) {
+                continue;
+            }
+            last = Some(position);
+
+            let native_row = program.row();
+            native_row.address_offset = *native_offset as u64;
+            native_row.file = row.file.unwrap();
+            native_row.line = row.line;
+            native_row.column = row.column;
+            program.generate_row();
+        }
+
+        program.end_sequence(func.len as u64);
+    }
+}
+
+/// The mapping from native code offsets to wasm offsets for a single compiled function.
+pub(crate) struct FunctionAddressMap {
+    func_id: FuncId,
+    symbol: String,
+    /// Offset of the function body from the start of the wasm module.
+    body_offset: usize,
+    body_len: usize,
+    /// Size of the compiled function.
+    len: u32,
+    /// Offset of each native instruction paired with the wasm offset it was translated from,
+    /// sorted by native offset.
+    instructions: Vec<(u32, ir::SourceLoc)>,
+}
+
+impl FunctionAddressMap {
+    /// Build the address map of a function that has just been compiled.
+    pub fn new(
+        func_id: FuncId,
+        symbol: &str,
+        body: (usize, usize),
+        len: u32,
+        func: &ir::Function,
+        isa: &dyn TargetIsa,
+    ) -> Self {
+        let encinfo = isa.encoding_info();
+        let mut blocks = func.layout.blocks().collect::<Vec<_>>();
+        blocks.sort_by_key(|block| func.offsets[*block]);
+
+        let mut instructions = Vec::new();
+        for block in blocks {
+            for (offset, inst, _size) in func.inst_offsets(block, &encinfo) {
+                instructions.push((offset, func.srclocs[inst]));
+            }
+        }
+
+        Self {
+            func_id,
+            symbol: symbol.to_owned(),
+            body_offset: body.0,
+            body_len: body.1,
+            len,
+            instructions,
+        }
+    }
+
+    /// The compact form of this map stored in the function manifest, with an entry only where
+    /// the wasm offset changes. Instructions Cranelift added on its own, like the prologue and
+    /// epilogue, are attributed to the preceding wasm instruction, if any.
+    pub fn addr_map_entries(&self) -> Vec<AddrMapEntry> {
+        let mut entries: Vec<AddrMapEntry> = Vec::new();
+        for (native_offset, srcloc) in self.instructions.iter() {
+            if srcloc.is_default() {
+                continue;
+            }
+            if entries.last().map(|entry| entry.wasm_offset) == Some(srcloc.bits()) {
+                continue;
+            }
+            entries.push(AddrMapEntry {
+                native_offset: *native_offset,
+                wasm_offset: srcloc.bits(),
+            });
+        }
+        entries
+    }
+}
+
+struct LineRow {
+    address: u64,
+    end_sequence: bool,
+    file: Option<FileId>,
+    line: u64,
+    column: u64,
+}
+
+struct SourceInfo {
+    /// Rows of all line sequences, sorted by address.
+    rows: Vec<LineRow>,
+    /// Source name of each subprogram, by its wasm address.
+    subprograms: BTreeMap<u64, Vec<u8>>,
+    language: Option<gimli::DwLang>,
+}
+
+impl SourceInfo {
+    /// Find the row describing the code at a wasm address, if any.
+    fn lookup(&self, address: u64) -> Option<&LineRow> {
+        let idx = match self
+            .rows
+            .binary_search_by(|row| row.address.cmp(&address).then(std::cmp::Ordering::Less))
+        {
+            Ok(idx) | Err(idx) => idx,
+        };
+        let row = &self.rows[idx.checked_sub(1)?];
+        if row.end_sequence || row.file.is_none() {
+            None
+        } else {
+            Some(row)
+        }
+    }
+}
+
+enum RelocTarget {
+    /// An index into the symbols of the functions being described.
+    Function(usize),
+    Section(SectionId),
+}
+
+struct DebugReloc {
+    offset: u64,
+    size: u8,
+    target: RelocTarget,
+    addend: i64,
+}
+
+/// A section writer that records relocations for addresses and cross-section offsets, rather than
+/// resolving them.
+#[derive(Clone)]
+struct DebugSectionWriter {
+    writer: EndianVec<LittleEndian>,
+    relocs: Vec<DebugReloc>,
+}
+
+impl DebugSectionWriter {
+    fn new() -> Self {
+        Self {
+            writer: EndianVec::new(LittleEndian),
+            relocs: Vec::new(),
+        }
+    }
+}
+
+impl Writer for DebugSectionWriter {
+    type Endian = LittleEndian;
+
+    fn endian(&self) -> Self::Endian {
+        LittleEndian
+    }
+
+    fn len(&self) -> usize {
+        self.writer.len()
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.writer.write(bytes)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.writer.write_at(offset, bytes)
+    }
+
+    fn write_address(&mut self, address: Address, size: u8) -> gimli::write::Result<()> {
+        match address {
+            Address::Constant(val) => self.write_udata(val, size),
+            Address::Symbol { symbol, addend } => {
+                self.relocs.push(DebugReloc {
+                    offset: self.len() as u64,
+                    size,
+                    target: RelocTarget::Function(symbol),
+                    addend,
+                });
+                self.write_udata(0, size)
+            }
+        }
+    }
+
+    fn write_offset(
+        &mut self,
+        val: usize,
+        section: SectionId,
+        size: u8,
+    ) -> gimli::write::Result<()> {
+        self.relocs.push(DebugReloc {
+            offset: self.len() as u64,
+            size,
+            target: RelocTarget::Section(section),
+            addend: val as i64,
+        });
+        self.write_udata(0, size)
+    }
+
+    fn write_offset_at(
+        &mut self,
+        offset: usize,
+        val: usize,
+        section: SectionId,
+        size: u8,
+    ) -> gimli::write::Result<()> {
+        self.relocs.push(DebugReloc {
+            offset: offset as u64,
+            size,
+            target: RelocTarget::Section(section),
+            addend: val as i64,
+        });
+        self.write_udata_at(offset, 0, size)
+    }
+}
+
+fn emit_sections(
+    obj: &mut Object,
+    sections: &Sections<DebugSectionWriter>,
+    symbols: &[object::write::SymbolId],
+) -> Result<(), Error> {
+    let mut section_ids = HashMap::new();
+    sections.for_each(|id, section| -> Result<(), Error> {
+        if section.writer.len() == 0 {
+            return Ok(());
+        }
+        let segment = obj.segment_name(StandardSegment::Debug).to_vec();
+        let section_id =
+            obj.add_section(segment, id.name().as_bytes().to_vec(), SectionKind::Debug);
+        obj.append_section_data(section_id, section.writer.slice(), 1);
+        section_ids.insert(id, section_id);
+        Ok(())
+    })?;
+
+    sections.for_each(|id, section| -> Result<(), Error> {
+        let section_id = match section_ids.get(&id) {
+            Some(section_id) => *section_id,
+            None => return Ok(()),
+        };
+        for reloc in section.relocs.iter() {
+            let symbol = match reloc.target {
+                RelocTarget::Function(idx) => symbols[idx],
+                RelocTarget::Section(target) => match section_ids.get(&target) {
+                    Some(target_id) => obj.section_symbol(*target_id),
+                    // Nothing was written to the target section, so the only offset into it is
+                    // zero, which is already in place.
+                    None if reloc.addend == 0 => continue,
+                    None => {
+                        return Err(Error::Unsupported(format!(
+                            "{} refers to offset {} of the empty section {}",
+                            id.name(),
+                            reloc.addend,
+                            target.name()
+                        )))
+                    }
+                },
+            };
+            obj.add_relocation(
+                section_id,
+                Relocation {
+                    offset: reloc.offset,
+                    size: reloc.size * 8,
+                    kind: RelocationKind::Absolute,
+                    encoding: RelocationEncoding::Generic,
+                    symbol,
+                    addend: reloc.addend,
+                },
+            )
+            .map_err(|source| {
+                Error::ObjectArtifact(source, format!("relocation in {}", id.name()))
+            })?;
+        }
+        Ok(())
+    })
+}
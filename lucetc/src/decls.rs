@@ -4,8 +4,8 @@ pub use crate::module::{Exportable, TableElems};
 use crate::module::{ModuleInfo, UniqueFuncIndex};
 use crate::name::Name;
 use crate::runtime::{Runtime, RuntimeFunc};
-use crate::table::TABLE_SYM;
-use crate::types::to_lucet_signature;
+use crate::table::{table_specs, TABLE_SYM};
+use crate::types::{global_value_type, to_lucet_signature};
 use cranelift_codegen::entity::{EntityRef, PrimaryMap};
 use cranelift_codegen::ir;
 use cranelift_codegen::isa::TargetFrontendConfig;
@@ -17,11 +17,13 @@ use cranelift_wasm::{
 use lucet_module::bindings::Bindings;
 use lucet_module::ModuleFeatures;
 use lucet_module::{
-    owned::OwnedLinearMemorySpec, ExportFunction, FunctionIndex as LucetFunctionIndex,
-    FunctionMetadata, Global as GlobalVariant, GlobalDef, GlobalSpec, HeapSpec, ImportFunction,
-    ModuleData, Signature as LucetSignature, UniqueSignatureIndex,
+    owned::{OwnedLinearMemorySpec, OwnedSparseData},
+    ExportFunction, FunctionIndex as LucetFunctionIndex, FunctionMetadata, Global as GlobalVariant,
+    GlobalBaseSegment, GlobalDef, GlobalSpec, HeapSpec, ImportFunction, InitialState, ModuleData,
+    Signature as LucetSignature, UniqueSignatureIndex, ValueType,
 };
 use std::collections::HashMap;
+use std::mem;
 use wasmparser::FuncType;
 
 #[derive(Debug)]
@@ -75,6 +77,12 @@ pub struct ModuleDecls<'a> {
     runtime_names: HashMap<RuntimeFunc, UniqueFuncIndex>,
     globals_spec: Vec<GlobalSpec<'a>>,
     linear_memory_spec: Option<OwnedLinearMemorySpec>,
+    /// Whether the module was compiled with an `InitialState`, in which case its data segments
+    /// and start function have already been applied.
+    preinitialized: bool,
+    /// Whether imported functions are defined as thunks that call through the instance's import
+    /// table, rather than declared as symbols for the loader to resolve.
+    linked_imports: bool,
 }
 
 impl<'a> ModuleDecls<'a> {
@@ -84,11 +92,14 @@ impl<'a> ModuleDecls<'a> {
         bindings: &'a Bindings,
         runtime: Runtime,
         heap_settings: HeapSettings,
+        initial_state: Option<&InitialState>,
+        linked_imports: bool,
     ) -> Result<Self, Error> {
         let imports: Vec<ImportFunction<'a>> = Vec::with_capacity(info.imported_funcs.len());
         let (tables_list_name, table_names) = Self::declare_tables(&info, clif_module)?;
-        let globals_spec = Self::build_globals_spec(&info)?;
-        let linear_memory_spec = Self::build_linear_memory_spec(&info, heap_settings)?;
+        let globals_spec = Self::build_globals_spec(&info, initial_state)?;
+        let linear_memory_spec =
+            Self::build_linear_memory_spec(&info, heap_settings, initial_state)?;
         let mut decls = Self {
             info,
             function_names: PrimaryMap::new(),
@@ -99,6 +110,8 @@ impl<'a> ModuleDecls<'a> {
             runtime_names: HashMap::new(),
             globals_spec,
             linear_memory_spec,
+            preinitialized: initial_state.is_some(),
+            linked_imports,
         };
 
         Self::declare_funcs(&mut decls, clif_module, bindings)?;
@@ -150,13 +163,18 @@ impl<'a> ModuleDecls<'a> {
             bindings: &'a Bindings,
         ) -> Result<Option<String>, Error> {
             if let Some((import_mod, import_field)) = decls.info.imported_funcs.get(func_ix) {
-                let import_symbol = bindings.translate(import_mod, import_field)?;
+                // linked imports are bound by the runtime, so they need no bindings
+                let import_symbol = if decls.linked_imports {
+                    format!("guest_import_{}", decls.imports.len())
+                } else {
+                    bindings.translate(import_mod, import_field)?.to_string()
+                };
                 decls.imports.push(ImportFunction {
                     fn_idx: LucetFunctionIndex::from_u32(decls.function_names.len() as u32),
                     module: import_mod,
                     name: import_field,
                 });
-                Ok(Some(import_symbol.to_string()))
+                Ok(Some(import_symbol))
             } else {
                 Ok(None)
             }
@@ -168,6 +186,11 @@ impl<'a> ModuleDecls<'a> {
             let export_info = export_name_for(func_index, decls);
 
             match (import_info, export_info) {
+                (Some(import_sym), _) if decls.linked_imports => {
+                    // a linked import is defined in this artifact as a thunk that calls the host
+                    // function bound to the instance; see `import_thunk`.
+                    decls.declare_function(clif_module, import_sym, Linkage::Local, func_index)?;
+                }
                 (Some(import_sym), _) => {
                     // if a function is only an import, declare the corresponding artifact import.
                     // if a function is an export and import, it will not have a real function body
@@ -291,14 +314,23 @@ impl<'a> ModuleDecls<'a> {
     fn build_linear_memory_spec(
         info: &ModuleInfo<'a>,
         heap_settings: HeapSettings,
+        initial_state: Option<&InitialState>,
     ) -> Result<Option<OwnedLinearMemorySpec>, Error> {
         use crate::sparsedata::owned_sparse_data_from_initializers;
-        if let Some(heap_spec) = Self::build_heap_spec(info, heap_settings)? {
-            let data_initializers = info
-                .data_initializers
-                .get(&MemoryIndex::new(0))
-                .expect("heap spec implies data initializers should exist");
-            let sparse_data = owned_sparse_data_from_initializers(data_initializers, &heap_spec)?;
+        let initial_heap_len = initial_state.map(|state| state.heap_len).unwrap_or(0);
+        if let Some(heap_spec) = Self::build_heap_spec(info, heap_settings, initial_heap_len)? {
+            let sparse_data = if let Some(state) = initial_state {
+                if state.heap_pages.len() as u64 * 4096 > heap_spec.initial_size {
+                    return Err(Error::InitData);
+                }
+                OwnedSparseData::new(state.heap_pages.clone())?
+            } else {
+                let data_initializers = info
+                    .data_initializers
+                    .get(&MemoryIndex::new(0))
+                    .expect("heap spec implies data initializers should exist");
+                owned_sparse_data_from_initializers(data_initializers, &heap_spec)?
+            };
 
             Ok(Some(OwnedLinearMemorySpec {
                 heap: heap_spec,
@@ -309,11 +341,26 @@ impl<'a> ModuleDecls<'a> {
         }
     }
 
-    fn build_globals_spec(info: &ModuleInfo<'a>) -> Result<Vec<GlobalSpec<'a>>, Error> {
+    fn build_globals_spec(
+        info: &ModuleInfo<'a>,
+        initial_state: Option<&InitialState>,
+    ) -> Result<Vec<GlobalSpec<'a>>, Error> {
+        if let Some(state) = initial_state {
+            if state.globals.len() != info.globals.len() {
+                return Err(Error::InitialState(format!(
+                    "state has {} globals, but the module has {}",
+                    state.globals.len(),
+                    info.globals.len()
+                )));
+            }
+        }
+
         let mut globals = Vec::new();
         for ix in 0..info.globals.len() {
             let ix = GlobalIndex::new(ix);
             let g_decl = info.globals.get(ix).unwrap();
+            let ty = global_value_type(&info.global_types[ix])
+                .map_err(|_| Error::GlobalUnsupported(ix.as_u32()))?;
 
             let global = match g_decl.entity.initializer {
                 GlobalInit::I32Const(i) => Ok(GlobalVariant::Def(GlobalDef::I32(i))),
@@ -328,7 +375,7 @@ impl<'a> ModuleDecls<'a> {
                     let ref_decl = info.globals.get(ref_ix).unwrap();
                     if let GlobalInit::Import = ref_decl.entity.initializer {
                         if let Some((module, field)) = info.imported_globals.get(ref_ix) {
-                            Ok(GlobalVariant::Import { module, field })
+                            Ok(GlobalVariant::Import { module, field, ty })
                         } else {
                             Err(Error::GlobalDeclarationError(ref_ix.as_u32()))
                         }
@@ -339,24 +386,75 @@ impl<'a> ModuleDecls<'a> {
                 }
                 GlobalInit::Import => {
                     if let Some((module, field)) = info.imported_globals.get(ix) {
-                        Ok(GlobalVariant::Import { module, field })
+                        Ok(GlobalVariant::Import { module, field, ty })
                     } else {
                         Err(Error::GlobalDeclarationError(ix.as_u32()))
                     }
                 }
-                GlobalInit::V128Const(_) | GlobalInit::RefNullConst | GlobalInit::RefFunc(_) => {
-                    Err(Error::GlobalUnsupported(ix.as_u32()))
+                // Null references are represented as 0
+                GlobalInit::RefNullConst => Ok(GlobalVariant::Def(GlobalDef::I64(0))),
+                GlobalInit::V128Const(v) => Ok(GlobalVariant::Def(GlobalDef::V128(
+                    u128::from_le_bytes(v.0),
+                ))),
+                GlobalInit::RefFunc(func_ix) => {
+                    let unique_ix = *info
+                        .function_mapping
+                        .get(func_ix)
+                        .expect("function indices are valid");
+                    Ok(GlobalVariant::Def(GlobalDef::FuncRef(
+                        LucetFunctionIndex::from_u32(unique_ix.as_u32()),
+                    )))
                 }
             }?;
 
-            globals.push(GlobalSpec::new(global, g_decl.export_names.clone()));
+            // the values of defined globals come from the initial state instead, if there is one
+            let global = match (global, initial_state) {
+                (GlobalVariant::Def(def), Some(state)) => match state.globals[ix.index()] {
+                    Some(value) if mem::discriminant(&value) == mem::discriminant(&def) => {
+                        GlobalVariant::Def(value)
+                    }
+                    // a `funcref` may hold a null reference or refer to a function, whichever it
+                    // was initialized with
+                    Some(value @ GlobalDef::I64(0)) | Some(value @ GlobalDef::FuncRef(_))
+                        if ty == ValueType::FuncRef =>
+                    {
+                        GlobalVariant::Def(value)
+                    }
+                    _ => {
+                        return Err(Error::InitialState(format!(
+                            "state has no value of type {:?} for global {}",
+                            ty,
+                            ix.as_u32()
+                        )))
+                    }
+                },
+                // imported globals, and those initialized from them, keep taking their values from
+                // the host, so a state can't give them values to bake in
+                (global, Some(state)) if state.globals[ix.index()].is_some() => {
+                    return Err(Error::InitialState(format!(
+                        "state has a value for global {}, which is imported or initialized from an \
+                         imported global",
+                        ix.as_u32()
+                    )))
+                }
+                (global, _) => global,
+            };
+
+            globals.push(
+                GlobalSpec::new(global, g_decl.export_names.clone())
+                    .with_value_type(ty)
+                    .with_mutable(g_decl.entity.mutability),
+            );
         }
         Ok(globals)
     }
 
+    /// Build the heap spec for the module's memory, with an initial size of at least
+    /// `initial_heap_len` bytes.
     fn build_heap_spec(
         info: &ModuleInfo<'a>,
         heap_settings: HeapSettings,
+        initial_heap_len: u64,
     ) -> Result<Option<HeapSpec>, Error> {
         match info.memories.len() {
             0 => Ok(None),
@@ -368,7 +466,29 @@ impl<'a> ModuleDecls<'a> {
                     .entity;
 
                 let wasm_page: u64 = 64 * 1024;
-                let initial_size = memory.minimum as u64 * wasm_page;
+                let initial_size =
+                    std::cmp::max(memory.minimum as u64 * wasm_page, initial_heap_len);
+                // Find the max size permitted by the heap and the memory spec
+                let max_size = memory.maximum.map(|pages| pages as u64 * wasm_page);
+                if max_size.map_or(false, |max_size| initial_size > max_size) {
+                    let message = format!(
+                        "initial heap length ({}) exceeds the memory's maximum size",
+                        initial_size
+                    );
+                    return Err(Error::InitialState(message));
+                }
+
+                if heap_settings.bounds_checked {
+                    // Every access is checked against the current heap length, so nothing needs to
+                    // be reserved past the heap itself.
+                    return Ok(Some(HeapSpec {
+                        reserved_size: initial_size,
+                        guard_size: 0,
+                        initial_size,
+                        max_size,
+                        bounds_checked: true,
+                    }));
+                }
 
                 let reserved_size = std::cmp::max(initial_size, heap_settings.min_reserved_size);
                 if reserved_size > heap_settings.max_reserved_size {
@@ -378,13 +498,12 @@ impl<'a> ModuleDecls<'a> {
                     );
                     return Err(Error::MemorySpecs(message));
                 }
-                // Find the max size permitted by the heap and the memory spec
-                let max_size = memory.maximum.map(|pages| pages as u64 * wasm_page);
                 Ok(Some(HeapSpec {
                     reserved_size,
                     guard_size: heap_settings.guard_size,
                     initial_size,
                     max_size,
+                    bounds_checked: false,
                 }))
             }
             _ => Err(Error::Unsupported(
@@ -422,6 +541,24 @@ impl<'a> ModuleDecls<'a> {
         })
     }
 
+    /// The imported functions that must be defined as import thunks, with their position in the
+    /// instance's import table.
+    ///
+    /// This is empty unless the module is compiled with linked imports.
+    pub fn linked_imports(&self) -> impl Iterator<Item = (usize, FunctionDecl<'_>)> {
+        let imports: &[ImportFunction<'_>] = if self.linked_imports {
+            &self.imports
+        } else {
+            &[]
+        };
+        imports.iter().enumerate().map(move |(ix, import)| {
+            let func = self
+                .get_func(UniqueFuncIndex::from_u32(import.fn_idx.as_u32()))
+                .expect("imports are declared functions");
+            (ix, func)
+        })
+    }
+
     pub fn get_runtime(&self, runtime_func: RuntimeFunc) -> Result<RuntimeDecl<'_>, Error> {
         let func_id = *self.runtime_names.get(&runtime_func).unwrap();
         let name = self.function_names.get(func_id).unwrap();
@@ -521,7 +658,7 @@ impl<'a> ModuleDecls<'a> {
                 .get(fn_index)
                 .expect("fn_index is key into function_names");
 
-            if Some(fn_index) == self.info.start_func {
+            if Some(fn_index) == self.info.start_func && !self.preinitialized {
                 start_func = Some(LucetFunctionIndex::from_u32(functions.len() as u32));
             }
 
@@ -549,6 +686,126 @@ impl<'a> ModuleDecls<'a> {
             signatures,
             features,
             start_func,
+            self.passive_data(),
+            self.passive_elements(),
+            table_specs(self)?,
+            self.global_base_data(),
+            self.global_base_elements(),
         ))
     }
+
+    /// Data segments in data segment index order, with active segments left as `None` unless
+    /// they are in `global_base_data()`.
+    fn passive_data(&self) -> Vec<Option<&'a [u8]>> {
+        let active = self.info.data_initializers.values().flatten();
+        let mut segments = vec![None; active.clone().count() + self.info.passive_data.len()];
+        for (ix, data) in self.info.passive_data.iter() {
+            segments[ix.index()] = Some(*data);
+        }
+        let applied = self.global_base_data();
+        for initializer in active {
+            if applied
+                .iter()
+                .any(|segment| segment.segment == initializer.index.as_u32())
+            {
+                segments[initializer.index.index()] = Some(initializer.data);
+            }
+        }
+        segments
+    }
+
+    /// Element segments in element segment index order, with active segments left as `None`
+    /// unless they are in `global_base_elements()`.
+    fn passive_elements(&self) -> Vec<Option<Vec<LucetFunctionIndex>>> {
+        fn to_lucet_indices(elements: &[UniqueFuncIndex]) -> Vec<LucetFunctionIndex> {
+            elements
+                .iter()
+                .map(|fn_idx| LucetFunctionIndex::from_u32(fn_idx.as_u32()))
+                .collect()
+        }
+
+        let active = self.info.table_elems.values().flatten();
+        let mut segments = vec![None; active.clone().count() + self.info.passive_elements.len()];
+        for (ix, elements) in self.info.passive_elements.iter() {
+            segments[ix.index()] = Some(to_lucet_indices(elements));
+        }
+        let applied = self.global_base_elements();
+        for elems in active {
+            if applied
+                .iter()
+                .any(|segment| segment.segment == elems.index.as_u32())
+            {
+                segments[elems.index.index()] = Some(to_lucet_indices(&elems.elements));
+            }
+        }
+        segments
+    }
+
+    /// Active data segments whose offset is given by a global, in data segment index order.
+    ///
+    /// Segments are applied in order, so those with a constant offset that follow the first
+    /// global-based one are included too. They are also part of the initial heap, which is
+    /// harmless: the runtime copies them again once the global-based segments are in place.
+    ///
+    /// A preinitialized module has none, as they were already applied to its initial heap.
+    fn global_base_data(&self) -> Vec<GlobalBaseSegment> {
+        if self.preinitialized {
+            return vec![];
+        }
+        let active = self.info.data_initializers.values().flatten();
+        let first = match active
+            .clone()
+            .filter(|initializer| initializer.base.is_some())
+            .map(|initializer| initializer.index.as_u32())
+            .min()
+        {
+            Some(first) => first,
+            None => return vec![],
+        };
+        let mut segments = active
+            .filter(|initializer| initializer.index.as_u32() >= first)
+            .map(|initializer| GlobalBaseSegment {
+                segment: initializer.index.as_u32(),
+                target: 0,
+                global: initializer.base.map(|global| global.as_u32()),
+                offset: initializer.offset as u32,
+            })
+            .collect::<Vec<_>>();
+        segments.sort_by_key(|segment| segment.segment);
+        segments
+    }
+
+    /// Active element segments whose offset is given by a global, in element segment index order.
+    ///
+    /// As with `global_base_data()`, those with a constant offset that follow the first
+    /// global-based one are included too.
+    fn global_base_elements(&self) -> Vec<GlobalBaseSegment> {
+        let active = || {
+            self.info
+                .table_elems
+                .iter()
+                .flat_map(|(table_index, elems)| {
+                    elems.iter().map(move |elems| (table_index, elems))
+                })
+        };
+        let first = match active()
+            .filter(|(_, elems)| elems.base.is_some())
+            .map(|(_, elems)| elems.index.as_u32())
+            .min()
+        {
+            Some(first) => first,
+            None => return vec![],
+        };
+        let mut segments = active()
+            .filter(|(_, elems)| elems.index.as_u32() >= first)
+            .map(|(table_index, elems)| GlobalBaseSegment {
+                segment: elems.index.as_u32(),
+                target: table_index.as_u32(),
+                global: elems.base.map(|global| global.as_u32()),
+                offset: elems.offset as u32,
+            })
+            .collect::<Vec<_>>();
+        segments.sort_by_key(|segment| segment.segment);
+        segments
+    }
 }
@@ -24,8 +24,14 @@ pub enum Error {
     WasmValidation(#[from] wasmparser::BinaryReaderError),
     #[error("Wat input: {0}")]
     WatInput(#[from] wabt::Error),
+    #[error("Reading wasm DWARF: {0}")]
+    DwarfRead(#[from] gimli::read::Error),
+    #[error("Writing native DWARF: {0}")]
+    DwarfWrite(#[from] gimli::write::Error),
     #[error("Object artifact: {1}. {0:?}")]
     ObjectArtifact(#[source] object::write::Error, String),
+    #[error("Reading object: {0}")]
+    ObjectRead(#[from] object::read::Error),
     // And all the rest
     #[error("Function definition error in {symbol}")]
     FunctionDefinition {
@@ -51,10 +57,14 @@ pub enum Error {
     GlobalUnsupported(u32),
     #[error("Cannot initialize data beyond linear memory's initial size")]
     InitData,
+    #[error("Initial state: {0}")]
+    InitialState(String),
     #[error("Input error: {0}")]
     Input(String),
     #[error("Ld error: {0}")]
     LdError(String),
+    #[error("Linking: {0}")]
+    Link(String),
     #[error("Memory specs: {0}")]
     MemorySpecs(String),
     #[error("Metadata serializer; start index points to a non-function: {0}")]
@@ -63,6 +73,8 @@ pub enum Error {
     OutputFunction(#[source] std::fmt::Error, String),
     #[error("Signature error: {0}")]
     Signature(String),
+    #[error("Thread pool: {0}")]
+    ThreadPool(String),
     #[error("Table index is out of bounds: {0}")]
     TableIndexError(String),
     #[error("Initializer {0:?} out of range for {1:?}")]
@@ -11,7 +11,7 @@ use cranelift_wasm::{
     FuncEnvironment, FuncIndex, FuncTranslationState, GlobalIndex, GlobalVariable, MemoryIndex,
     SignatureIndex, TableIndex, TargetEnvironment, WasmError, WasmResult,
 };
-use lucet_module::InstanceRuntimeData;
+use lucet_module::{GlobalValue, InstanceRuntimeData};
 use memoffset::offset_of;
 use std::collections::HashMap;
 use wasmparser::Operator;
@@ -20,19 +20,29 @@ pub struct FuncInfo<'a> {
     module_decls: &'a ModuleDecls<'a>,
     count_instructions: bool,
     scope_costs: Vec<u32>,
+    epoch_interruption: bool,
+    entry_epoch_checked: bool,
     vmctx_value: Option<ir::GlobalValue>,
     global_base_value: Option<ir::GlobalValue>,
+    tables_ptr_value: Option<ir::GlobalValue>,
     runtime_funcs: HashMap<RuntimeFunc, ir::FuncRef>,
 }
 
 impl<'a> FuncInfo<'a> {
-    pub fn new(module_decls: &'a ModuleDecls<'a>, count_instructions: bool) -> Self {
+    pub fn new(
+        module_decls: &'a ModuleDecls<'a>,
+        count_instructions: bool,
+        epoch_interruption: bool,
+    ) -> Self {
         Self {
             module_decls,
             count_instructions,
             scope_costs: vec![0],
+            epoch_interruption,
+            entry_epoch_checked: false,
             vmctx_value: None,
             global_base_value: None,
+            tables_ptr_value: None,
             runtime_funcs: HashMap::new(),
         }
     }
@@ -61,6 +71,138 @@ impl<'a> FuncInfo<'a> {
         })
     }
 
+    /// Get the address of the instance's table list, an array of `TableRef`s.
+    ///
+    /// The runtime never moves the list while guest code runs, so the address is loaded only once
+    /// per function; the `TableRef`s it holds are updated when a table grows, and are reloaded.
+    pub fn get_tables_ptr(&mut self, func: &mut ir::Function) -> ir::GlobalValue {
+        self.tables_ptr_value.unwrap_or_else(|| {
+            let vmctx = self.get_vmctx(func);
+            let tables_ptr_value = func.create_global_value(ir::GlobalValueData::Load {
+                base: vmctx,
+                offset: (-(std::mem::size_of::<InstanceRuntimeData>() as i32)
+                    + (offset_of!(InstanceRuntimeData, tables_ptr) as i32))
+                    .into(),
+                global_type: NATIVE_POINTER,
+                readonly: true,
+            });
+            self.tables_ptr_value = Some(tables_ptr_value);
+            tables_ptr_value
+        })
+    }
+
+    /// Trap with `TableOutOfBounds` unless `index` is in bounds for the table.
+    fn check_table_bounds(
+        &mut self,
+        pos: &mut FuncCursor<'_>,
+        table_index: TableIndex,
+        index: ir::Value,
+    ) {
+        let tables_ptr = self.get_tables_ptr(&mut pos.func);
+        let bound_offset =
+            TABLE_REF_SIZE as i64 * i64::from(table_index.as_u32()) + NATIVE_POINTER_SIZE as i64;
+        let bound_gv = pos.func.create_global_value(ir::GlobalValueData::Load {
+            base: tables_ptr,
+            global_type: ir::types::I64,
+            offset: (bound_offset as i32).into(),
+            readonly: false,
+        });
+        let bound = pos.ins().global_value(ir::types::I64, bound_gv);
+        let index = pos.ins().uextend(ir::types::I64, index);
+        let out_of_bounds = pos.ins().icmp(
+            ir::condcodes::IntCC::UnsignedGreaterThanOrEqual,
+            index,
+            bound,
+        );
+        pos.ins()
+            .trapnz(out_of_bounds, ir::TrapCode::TableOutOfBounds);
+    }
+
+    /// Load a field of the `InstanceRuntimeData` that precedes the vmctx.
+    fn load_runtime_data(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        ty: ir::Type,
+        field_offset: usize,
+    ) -> ir::Value {
+        let offset: ir::immediates::Offset32 =
+            (-(std::mem::size_of::<InstanceRuntimeData>() as i32) + field_offset as i32).into();
+        let vmctx_gv = self.get_vmctx(builder.func);
+        let addr = builder.ins().global_value(self.pointer_type(), vmctx_gv);
+        builder
+            .ins()
+            .load(ty, ir::MemFlags::trusted(), addr, offset)
+    }
+
+    /// Call `runtime_func` if `value` has reached `limit`.
+    ///
+    /// This leaves `builder` in a new block that follows the check.
+    fn call_if_reached(
+        &mut self,
+        builder: &mut FunctionBuilder<'_>,
+        value: ir::Value,
+        limit: ir::Value,
+        runtime_func: RuntimeFunc,
+    ) {
+        let reached = builder.ins().icmp(
+            ir::condcodes::IntCC::UnsignedGreaterThanOrEqual,
+            value,
+            limit,
+        );
+
+        let reached_block = builder.create_block();
+        let continue_block = builder.create_block();
+        builder.ins().brnz(reached, reached_block, &[]);
+        builder.ins().jump(continue_block, &[]);
+
+        builder.switch_to_block(reached_block);
+        builder.seal_block(reached_block);
+        let func = self.get_runtime_func(runtime_func, builder.func);
+        let vmctx = builder
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        builder.ins().call(func, &[vmctx]);
+        builder.ins().jump(continue_block, &[]);
+
+        builder.switch_to_block(continue_block);
+        builder.seal_block(continue_block);
+    }
+
+    /// Call into the runtime if the instruction count has reached the instance's fuel limit.
+    fn check_fuel(&mut self, builder: &mut FunctionBuilder<'_>) {
+        let instr_count = self.load_runtime_data(
+            builder,
+            ir::types::I64,
+            offset_of!(InstanceRuntimeData, instruction_count),
+        );
+        let fuel_limit = self.load_runtime_data(
+            builder,
+            ir::types::I64,
+            offset_of!(InstanceRuntimeData, fuel_limit),
+        );
+        self.call_if_reached(builder, instr_count, fuel_limit, RuntimeFunc::OutOfFuel);
+    }
+
+    /// Call into the runtime if the instance's epoch has reached its deadline.
+    fn check_epoch(&mut self, builder: &mut FunctionBuilder<'_>) {
+        let epoch_ptr = self.load_runtime_data(
+            builder,
+            self.pointer_type(),
+            offset_of!(InstanceRuntimeData, epoch_ptr),
+        );
+        // the epoch is updated from other threads, so it must not be treated as readonly
+        let epoch = builder
+            .ins()
+            .load(ir::types::I64, ir::MemFlags::trusted(), epoch_ptr, 0);
+        let epoch_deadline = self.load_runtime_data(
+            builder,
+            ir::types::I64,
+            offset_of!(InstanceRuntimeData, epoch_deadline),
+        );
+        self.call_if_reached(builder, epoch, epoch_deadline, RuntimeFunc::EpochInterrupt);
+    }
+
     pub fn get_runtime_func(
         &mut self,
         runtime_func: RuntimeFunc,
@@ -228,6 +370,12 @@ impl<'a> FuncInfo<'a> {
                 }
                 _ => { /* regular operation, do nothing */ }
             }
+
+            // check the fuel before calling out, so that recursion can't run past the limit. Loop
+            // headers are checked in `after_translate_operator`, once the header block exists.
+            if let Operator::Call { .. } | Operator::CallIndirect { .. } = op {
+                self.check_fuel(builder);
+            }
         } else {
             // just a consistency check - the counter must be 0 when exiting a region of
             // unreachable code. If this assertion fails it means we either counted instructions
@@ -280,7 +428,7 @@ impl<'a> FuncEnvironment for FuncInfo<'a> {
         let global_base = self.get_global_base(func);
         let global = self.module_decls.get_global(index).expect("valid global");
         let index = index.as_u32() as i32;
-        let offset = (index * NATIVE_POINTER_SIZE as i32).into();
+        let offset = (index * std::mem::size_of::<GlobalValue>() as i32).into();
         Ok(GlobalVariable::Memory {
             gv: global_base,
             offset,
@@ -296,13 +444,28 @@ impl<'a> FuncEnvironment for FuncInfo<'a> {
         assert_eq!(index, MemoryIndex::new(0), "only memory 0 is supported");
         let heap_spec = self.module_decls.get_heap().expect("valid heap");
         let vmctx = self.get_vmctx(func);
+        let style = if heap_spec.bounds_checked {
+            // Cranelift compares the bound against 32-bit heap indices, so only the low half of
+            // `heap_len` is loaded. The runtime keeps bounds-checked heaps under 4GiB.
+            let bound_gv = func.create_global_value(ir::GlobalValueData::Load {
+                base: vmctx,
+                offset: (-(std::mem::size_of::<InstanceRuntimeData>() as i32)
+                    + (offset_of!(InstanceRuntimeData, heap_len) as i32))
+                    .into(),
+                global_type: ir::types::I32,
+                readonly: false,
+            });
+            ir::HeapStyle::Dynamic { bound_gv }
+        } else {
+            ir::HeapStyle::Static {
+                bound: heap_spec.reserved_size.into(),
+            }
+        };
         Ok(func.create_heap(ir::HeapData {
             base: vmctx,
             min_size: heap_spec.initial_size.into(),
             offset_guard_size: heap_spec.guard_size.into(),
-            style: ir::HeapStyle::Static {
-                bound: heap_spec.reserved_size.into(),
-            },
+            style,
             index_type: ir::types::I32,
         }))
     }
@@ -313,37 +476,43 @@ impl<'a> FuncEnvironment for FuncInfo<'a> {
         index: TableIndex,
     ) -> Result<ir::Table, WasmError> {
         let index_type = ir::types::I64;
-        let table_decl = self.module_decls.get_table(index).expect("valid table");
-        let base_gv = func.create_global_value(ir::GlobalValueData::Symbol {
-            name: table_decl.contents_name.into(),
-            offset: 0.into(),
-            colocated: true,
-            tls: false,
-        });
-        let tables_list_gv = func.create_global_value(ir::GlobalValueData::Symbol {
-            name: self.module_decls.get_tables_list_name().as_externalname(),
-            offset: 0.into(),
-            colocated: true,
-            tls: false,
-        });
+        let table_minimum = self
+            .module_decls
+            .info
+            .tables
+            .get(index)
+            .expect("valid table")
+            .entity
+            .minimum;
+        let tables_ptr_gv = self.get_tables_ptr(func);
 
-        let table_bound_offset = (TABLE_REF_SIZE as u32)
+        let table_base_offset = (TABLE_REF_SIZE as u32)
             .checked_mul(index.as_u32())
-            .and_then(|entry| entry.checked_add(NATIVE_POINTER_SIZE as u32))
+            .ok_or(WasmError::ImplLimitExceeded)?;
+        let table_bound_offset = table_base_offset
+            .checked_add(NATIVE_POINTER_SIZE as u32)
             .ok_or(WasmError::ImplLimitExceeded)?;
 
         if table_bound_offset > std::i32::MAX as u32 {
             return Err(WasmError::ImplLimitExceeded);
         }
 
+        // Tables can be grown, and moved when they are, so neither the base nor the bound are
+        // readonly.
+        let base_gv = func.create_global_value(ir::GlobalValueData::Load {
+            base: tables_ptr_gv,
+            global_type: NATIVE_POINTER,
+            offset: (table_base_offset as i32).into(),
+            readonly: false,
+        });
         let bound_gv = func.create_global_value(ir::GlobalValueData::Load {
-            base: tables_list_gv,
+            base: tables_ptr_gv,
             global_type: index_type,
             offset: (table_bound_offset as i32).into(),
-            readonly: true,
+            readonly: false,
         });
-        let element_size = ((NATIVE_POINTER_SIZE * 2) as u64).into();
-        let min_size = (table_decl.table.minimum as u64).into();
+        let element_size = (NATIVE_POINTER_SIZE as u64).into();
+        let min_size = (table_minimum as u64).into();
         Ok(func.create_table(ir::TableData {
             base_gv,
             bound_gv,
@@ -365,13 +534,20 @@ impl<'a> FuncEnvironment for FuncInfo<'a> {
     ) -> WasmResult<ir::Inst> {
         let callee_u64 = pos.ins().sextend(ir::types::I64, callee);
         let table_entry_addr = pos.ins().table_addr(ir::types::I64, table, callee_u64, 0);
-        // First element at the table entry is the signature index of the function
-        let table_entry_sig_offset = 0;
-        let table_entry_sig_ix = pos.ins().load(
+        // The table entry is a reference to the element for the function, or null
+        let element_addr =
+            pos.ins()
+                .load(NATIVE_POINTER, ir::MemFlags::trusted(), table_entry_addr, 0);
+        pos.ins()
+            .trapz(element_addr, ir::TrapCode::IndirectCallToNull);
+
+        // First element at the table element is the signature index of the function
+        let element_sig_offset = 0;
+        let element_sig_ix = pos.ins().load(
             ir::types::I64,
             ir::MemFlags::trusted(),
-            table_entry_addr,
-            table_entry_sig_offset,
+            element_addr,
+            element_sig_offset,
         );
 
         // Translate from the module's non-unique signature space to our internal unique space
@@ -383,18 +559,18 @@ impl<'a> FuncEnvironment for FuncInfo<'a> {
         // Check it against the unique sig_index, trap if wrong
         let valid_type = pos.ins().icmp_imm(
             ir::condcodes::IntCC::Equal,
-            table_entry_sig_ix,
+            element_sig_ix,
             unique_sig_index.as_u32() as i64,
         );
         pos.ins().trapz(valid_type, ir::TrapCode::BadSignature);
 
-        // Second element at the table entry is the function pointer
-        let table_entry_fptr_offset = NATIVE_POINTER_SIZE as i32;
-        let table_entry_fptr = pos.ins().load(
+        // Second element at the table element is the function pointer
+        let element_fptr_offset = NATIVE_POINTER_SIZE as i32;
+        let element_fptr = pos.ins().load(
             NATIVE_POINTER,
             ir::MemFlags::trusted(),
-            table_entry_addr,
-            table_entry_fptr_offset,
+            element_addr,
+            element_fptr_offset,
         );
 
         let mut args: Vec<ir::Value> = Vec::with_capacity(call_args.len() + 1);
@@ -406,7 +582,7 @@ impl<'a> FuncEnvironment for FuncInfo<'a> {
                 .expect("vmctx available"),
         );
 
-        Ok(pos.ins().call_indirect(sig_ref, table_entry_fptr, &args))
+        Ok(pos.ins().call_indirect(sig_ref, element_fptr, &args))
     }
 
     fn make_indirect_sig(
@@ -494,156 +670,278 @@ impl<'a> FuncEnvironment for FuncInfo<'a> {
 
     fn translate_memory_copy(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        index: MemoryIndex,
         _heap: ir::Heap,
-        _dst: ir::Value,
-        _src: ir::Value,
-        _len: ir::Value,
+        dst: ir::Value,
+        src: ir::Value,
+        len: ir::Value,
     ) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "bulk memory operations not supported yet".into(),
-        ))
+        assert!(index == MemoryIndex::new(0));
+        let mem_copy_func = self.get_runtime_func(RuntimeFunc::MemCopy, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let inst = pos.ins().call(mem_copy_func, &[vmctx, dst, src, len]);
+        let out_of_bounds = *pos.func.dfg.inst_results(inst).first().unwrap();
+        pos.ins()
+            .trapnz(out_of_bounds, ir::TrapCode::HeapOutOfBounds);
+        Ok(())
     }
 
     fn translate_memory_fill(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        index: MemoryIndex,
         _heap: ir::Heap,
-        _dst: ir::Value,
-        _val: ir::Value,
-        _len: ir::Value,
+        dst: ir::Value,
+        val: ir::Value,
+        len: ir::Value,
     ) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "bulk memory operations not supported yet".into(),
-        ))
+        assert!(index == MemoryIndex::new(0));
+        let mem_fill_func = self.get_runtime_func(RuntimeFunc::MemFill, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let inst = pos.ins().call(mem_fill_func, &[vmctx, dst, val, len]);
+        let out_of_bounds = *pos.func.dfg.inst_results(inst).first().unwrap();
+        pos.ins()
+            .trapnz(out_of_bounds, ir::TrapCode::HeapOutOfBounds);
+        Ok(())
     }
 
     #[allow(clippy::too_many_arguments)]
     fn translate_memory_init(
         &mut self,
-        _pos: FuncCursor,
-        _index: MemoryIndex,
+        mut pos: FuncCursor,
+        index: MemoryIndex,
         _heap: ir::Heap,
-        _seg_index: u32,
-        _dst: ir::Value,
-        _src: ir::Value,
-        _len: ir::Value,
+        seg_index: u32,
+        dst: ir::Value,
+        src: ir::Value,
+        len: ir::Value,
     ) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "bulk memory operations not supported yet".into(),
-        ))
+        assert!(index == MemoryIndex::new(0));
+        let mem_init_func = self.get_runtime_func(RuntimeFunc::MemInit, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let seg_index = pos.ins().iconst(ir::types::I32, i64::from(seg_index));
+        let inst = pos
+            .ins()
+            .call(mem_init_func, &[vmctx, seg_index, dst, src, len]);
+        let out_of_bounds = *pos.func.dfg.inst_results(inst).first().unwrap();
+        pos.ins()
+            .trapnz(out_of_bounds, ir::TrapCode::HeapOutOfBounds);
+        Ok(())
     }
 
-    fn translate_data_drop(&mut self, _pos: FuncCursor, _seg_index: u32) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "bulk memory operations not supported yet".into(),
-        ))
+    fn translate_data_drop(&mut self, mut pos: FuncCursor, seg_index: u32) -> WasmResult<()> {
+        let data_drop_func = self.get_runtime_func(RuntimeFunc::DataDrop, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let seg_index = pos.ins().iconst(ir::types::I32, i64::from(seg_index));
+        pos.ins().call(data_drop_func, &[vmctx, seg_index]);
+        Ok(())
     }
 
     fn translate_table_size(
         &mut self,
-        _pos: FuncCursor,
+        mut pos: FuncCursor,
         _index: TableIndex,
-        _table: ir::Table,
+        table: ir::Table,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "reference type operations not supported yet".into(),
-        ))
+        let bound_gv = pos.func.tables[table].bound_gv;
+        let size = pos.ins().global_value(ir::types::I64, bound_gv);
+        Ok(pos.ins().ireduce(ir::types::I32, size))
     }
 
     fn translate_table_grow(
         &mut self,
-        _pos: FuncCursor,
-        _table_index: TableIndex,
-        _delta: ir::Value,
-        _init_value: ir::Value,
+        mut pos: FuncCursor,
+        table_index: TableIndex,
+        delta: ir::Value,
+        init_value: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "reference type operations not supported yet".into(),
-        ))
+        let table_grow_func = self.get_runtime_func(RuntimeFunc::TableGrow, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let table_index = pos
+            .ins()
+            .iconst(ir::types::I32, i64::from(table_index.as_u32()));
+        let inst = pos
+            .ins()
+            .call(table_grow_func, &[vmctx, table_index, delta, init_value]);
+        Ok(*pos.func.dfg.inst_results(inst).first().unwrap())
     }
 
     fn translate_table_get(
         &mut self,
-        _pos: FuncCursor,
-        _table_index: TableIndex,
-        _index: ir::Value,
+        mut pos: FuncCursor,
+        table_index: TableIndex,
+        index: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "reference type operations not supported yet".into(),
-        ))
+        self.check_table_bounds(&mut pos, table_index, index);
+        let table_get_func = self.get_runtime_func(RuntimeFunc::TableGet, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let table_index = pos
+            .ins()
+            .iconst(ir::types::I32, i64::from(table_index.as_u32()));
+        let inst = pos.ins().call(table_get_func, &[vmctx, table_index, index]);
+        Ok(*pos.func.dfg.inst_results(inst).first().unwrap())
     }
 
     fn translate_table_set(
         &mut self,
-        _pos: FuncCursor,
-        _table_index: TableIndex,
-        _value: ir::Value,
-        _index: ir::Value,
+        mut pos: FuncCursor,
+        table_index: TableIndex,
+        value: ir::Value,
+        index: ir::Value,
     ) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "reference type operations not supported yet".into(),
-        ))
+        self.check_table_bounds(&mut pos, table_index, index);
+        let table_set_func = self.get_runtime_func(RuntimeFunc::TableSet, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let table_index = pos
+            .ins()
+            .iconst(ir::types::I32, i64::from(table_index.as_u32()));
+        pos.ins()
+            .call(table_set_func, &[vmctx, table_index, index, value]);
+        Ok(())
     }
 
     #[allow(clippy::too_many_arguments)]
     fn translate_table_copy(
         &mut self,
-        _pos: FuncCursor,
-        _dst_table_index: TableIndex,
+        mut pos: FuncCursor,
+        dst_table_index: TableIndex,
         _dst_table: ir::Table,
-        _src_table_index: TableIndex,
+        src_table_index: TableIndex,
         _src_table: ir::Table,
-        _dst: ir::Value,
-        _src: ir::Value,
-        _len: ir::Value,
+        dst: ir::Value,
+        src: ir::Value,
+        len: ir::Value,
     ) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "reference type operations not supported yet".into(),
-        ))
+        let table_copy_func = self.get_runtime_func(RuntimeFunc::TableCopy, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let dst_table_index = pos
+            .ins()
+            .iconst(ir::types::I32, i64::from(dst_table_index.as_u32()));
+        let src_table_index = pos
+            .ins()
+            .iconst(ir::types::I32, i64::from(src_table_index.as_u32()));
+        let inst = pos.ins().call(
+            table_copy_func,
+            &[vmctx, dst_table_index, src_table_index, dst, src, len],
+        );
+        let out_of_bounds = *pos.func.dfg.inst_results(inst).first().unwrap();
+        pos.ins()
+            .trapnz(out_of_bounds, ir::TrapCode::TableOutOfBounds);
+        Ok(())
     }
 
     fn translate_table_fill(
         &mut self,
-        _pos: FuncCursor,
-        _table_index: TableIndex,
-        _dst: ir::Value,
-        _val: ir::Value,
-        _len: ir::Value,
+        mut pos: FuncCursor,
+        table_index: TableIndex,
+        dst: ir::Value,
+        val: ir::Value,
+        len: ir::Value,
     ) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "reference type operations not supported yet".into(),
-        ))
+        let table_fill_func = self.get_runtime_func(RuntimeFunc::TableFill, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let table_index = pos
+            .ins()
+            .iconst(ir::types::I32, i64::from(table_index.as_u32()));
+        let inst = pos
+            .ins()
+            .call(table_fill_func, &[vmctx, table_index, dst, val, len]);
+        let out_of_bounds = *pos.func.dfg.inst_results(inst).first().unwrap();
+        pos.ins()
+            .trapnz(out_of_bounds, ir::TrapCode::TableOutOfBounds);
+        Ok(())
     }
 
     #[allow(clippy::too_many_arguments)]
     fn translate_table_init(
         &mut self,
-        _pos: FuncCursor,
-        _seg_index: u32,
-        _table_index: TableIndex,
+        mut pos: FuncCursor,
+        seg_index: u32,
+        table_index: TableIndex,
         _table: ir::Table,
-        _dst: ir::Value,
-        _src: ir::Value,
-        _len: ir::Value,
+        dst: ir::Value,
+        src: ir::Value,
+        len: ir::Value,
     ) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "reference type operations not supported yet".into(),
-        ))
+        let table_init_func = self.get_runtime_func(RuntimeFunc::TableInit, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let table_index = pos
+            .ins()
+            .iconst(ir::types::I32, i64::from(table_index.as_u32()));
+        let seg_index = pos.ins().iconst(ir::types::I32, i64::from(seg_index));
+        let inst = pos.ins().call(
+            table_init_func,
+            &[vmctx, table_index, seg_index, dst, src, len],
+        );
+        let out_of_bounds = *pos.func.dfg.inst_results(inst).first().unwrap();
+        pos.ins()
+            .trapnz(out_of_bounds, ir::TrapCode::TableOutOfBounds);
+        Ok(())
     }
 
-    fn translate_elem_drop(&mut self, _pos: FuncCursor, _seg_index: u32) -> WasmResult<()> {
-        Err(WasmError::Unsupported(
-            "bulk memory operations not supported yet".into(),
-        ))
+    fn translate_elem_drop(&mut self, mut pos: FuncCursor, seg_index: u32) -> WasmResult<()> {
+        let elem_drop_func = self.get_runtime_func(RuntimeFunc::ElemDrop, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let seg_index = pos.ins().iconst(ir::types::I32, i64::from(seg_index));
+        pos.ins().call(elem_drop_func, &[vmctx, seg_index]);
+        Ok(())
     }
 
-    fn translate_ref_func(&mut self, _pos: FuncCursor, _func_index: u32) -> WasmResult<ir::Value> {
-        Err(WasmError::Unsupported(
-            "reference type operations not supported yet".into(),
-        ))
+    fn translate_ref_func(
+        &mut self,
+        mut pos: FuncCursor,
+        func_index: u32,
+    ) -> WasmResult<ir::Value> {
+        let unique_index = *self
+            .module_decls
+            .info
+            .function_mapping
+            .get(FuncIndex::from_u32(func_index))
+            .expect("function indices are valid");
+        let ref_func_func = self.get_runtime_func(RuntimeFunc::RefFunc, &mut pos.func);
+        let vmctx = pos
+            .func
+            .special_param(ir::ArgumentPurpose::VMContext)
+            .unwrap();
+        let func_index = pos
+            .ins()
+            .iconst(ir::types::I32, i64::from(unique_index.as_u32()));
+        let inst = pos.ins().call(ref_func_func, &[vmctx, func_index]);
+        Ok(*pos.func.dfg.inst_results(inst).first().unwrap())
     }
 
     fn translate_custom_global_get(
@@ -673,9 +971,34 @@ impl<'a> FuncEnvironment for FuncInfo<'a> {
         builder: &mut FunctionBuilder<'_>,
         state: &FuncTranslationState,
     ) -> WasmResult<()> {
+        if self.epoch_interruption && !self.entry_epoch_checked {
+            // The first operator is where the function body begins, so this is the function entry.
+            self.entry_epoch_checked = true;
+            self.check_epoch(builder);
+        }
         if self.count_instructions {
             self.update_instruction_count_instrumentation(op, builder, state.reachable())?;
         }
         Ok(())
     }
+
+    fn after_translate_operator(
+        &mut self,
+        op: &Operator<'_>,
+        builder: &mut FunctionBuilder<'_>,
+        state: &FuncTranslationState,
+    ) -> WasmResult<()> {
+        if let Operator::Loop { .. } = op {
+            // The builder is now in the loop header, so these checks run on every iteration.
+            if state.reachable() {
+                if self.count_instructions {
+                    self.check_fuel(builder);
+                }
+                if self.epoch_interruption {
+                    self.check_epoch(builder);
+                }
+            }
+        }
+        Ok(())
+    }
 }
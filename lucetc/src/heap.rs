@@ -3,6 +3,9 @@ pub struct HeapSettings {
     pub min_reserved_size: u64,
     pub max_reserved_size: u64,
     pub guard_size: u64,
+    /// Check every heap access against the current heap length, rather than relying on guard
+    /// pages. The reserved and guard sizes are ignored when this is set.
+    pub bounds_checked: bool,
 }
 
 impl Default for HeapSettings {
@@ -11,6 +14,7 @@ impl Default for HeapSettings {
             min_reserved_size: 4 * 1024 * 1024,
             max_reserved_size: 6 * 1024 * 1024 * 1024,
             guard_size: 4 * 1024 * 1024,
+            bounds_checked: false,
         }
     }
 }
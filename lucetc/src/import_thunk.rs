@@ -0,0 +1,71 @@
+//! Thunks for the imported functions of modules compiled with linked imports.
+//!
+//! Rather than calling an imported function through a symbol resolved when the module is loaded,
+//! such a module defines each import as a small function of its own. The thunk finds the host
+//! function bound to the import in the instance's import table, stores the entry's data pointer
+//! where the host function can find it, and calls the host function with its own arguments. Since
+//! the thunk has the import's signature, direct calls, tables, and exports all refer to it just as
+//! they would to any other function.
+
+use crate::decls::FunctionDecl;
+use crate::pointer::NATIVE_POINTER;
+use cranelift_codegen::ir::{self, InstBuilder};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use lucet_module::{ImportFuncRef, InstanceRuntimeData};
+use memoffset::offset_of;
+use std::mem;
+
+/// Build the thunk for the import at position `import_ix` in the import table.
+pub fn build(import_ix: usize, func: &FunctionDecl<'_>) -> ir::Function {
+    let mut func_ir =
+        ir::Function::with_name_signature(func.name.as_externalname(), func.signature.clone());
+    let mut builder_ctx = FunctionBuilderContext::new();
+    let mut builder = FunctionBuilder::new(&mut func_ir, &mut builder_ctx);
+
+    let block = builder.create_block();
+    builder.append_block_params_for_function_params(block);
+    builder.switch_to_block(block);
+    builder.seal_block(block);
+    let args = builder.block_params(block).to_vec();
+
+    let vmctx = builder
+        .func
+        .special_param(ir::ArgumentPurpose::VMContext)
+        .expect("vmctx available");
+    let runtime_data = -(mem::size_of::<InstanceRuntimeData>() as i32);
+    let flags = ir::MemFlags::trusted();
+
+    let import_funcs = builder.ins().load(
+        NATIVE_POINTER,
+        flags,
+        vmctx,
+        runtime_data + offset_of!(InstanceRuntimeData, import_funcs_ptr) as i32,
+    );
+    let entry = (import_ix * mem::size_of::<ImportFuncRef>()) as i32;
+    let import_func = builder.ins().load(
+        NATIVE_POINTER,
+        flags,
+        import_funcs,
+        entry + offset_of!(ImportFuncRef, func) as i32,
+    );
+    let import_data = builder.ins().load(
+        NATIVE_POINTER,
+        flags,
+        import_funcs,
+        entry + offset_of!(ImportFuncRef, data) as i32,
+    );
+    builder.ins().store(
+        flags,
+        import_data,
+        vmctx,
+        runtime_data + offset_of!(InstanceRuntimeData, import_data) as i32,
+    );
+
+    let sig = builder.import_signature(func.signature.clone());
+    let call = builder.ins().call_indirect(sig, import_func, &args);
+    let results = builder.inst_results(call).to_vec();
+    builder.ins().return_(&results);
+    builder.finalize();
+
+    func_ir
+}
@@ -1,10 +1,13 @@
 #![deny(bare_trait_objects)]
 
 mod compiler;
+mod debug;
 mod decls;
 mod error;
 mod function;
 mod heap;
+mod import_thunk;
+mod link;
 mod load;
 mod module;
 mod name;
@@ -23,9 +26,11 @@ pub use crate::{
     compiler::{Compiler, CompilerBuilder, CpuFeatures, OptLevel, SpecificFeature, TargetCpu},
     error::Error,
     heap::HeapSettings,
+    link::Linker,
     load::read_module,
 };
 pub use lucet_module::bindings::Bindings;
+use lucet_module::InitialState;
 pub use lucet_validate::Validator;
 use signature::{PublicKey, SecretKey};
 use std::env;
@@ -46,6 +51,7 @@ pub struct Lucetc {
     pk: Option<PublicKey>,
     sign: bool,
     verify: bool,
+    linker: Option<Linker>,
 }
 
 pub trait AsLucetc {
@@ -92,6 +98,15 @@ pub trait LucetcOpts {
     fn guard_size(&mut self, guard_size: u64);
     fn with_guard_size(self, guard_size: u64) -> Self;
 
+    /// Check every heap access against the current heap length instead of relying on guard pages.
+    ///
+    /// The reserved and guard sizes are ignored when this is enabled.
+    fn bounds_checked_heap(&mut self, bounds_checked: bool);
+    /// Check every heap access against the current heap length instead of relying on guard pages.
+    ///
+    /// The reserved and guard sizes are ignored when this is enabled.
+    fn with_bounds_checked_heap(self, bounds_checked: bool) -> Self;
+
     fn pk(&mut self, pk: PublicKey);
     fn with_pk(self, pk: PublicKey) -> Self;
     fn sk(&mut self, sk: SecretKey);
@@ -102,8 +117,36 @@ pub trait LucetcOpts {
     fn with_sign(self) -> Self;
     fn count_instructions(&mut self, enable_count: bool);
     fn with_count_instructions(self, enable_count: bool) -> Self;
+    fn epoch_interruption(&mut self, enable_epoch_interruption: bool);
+    fn with_epoch_interruption(self, enable_epoch_interruption: bool) -> Self;
+    fn linked_imports(&mut self, enable_linked_imports: bool);
+    fn with_linked_imports(self, enable_linked_imports: bool) -> Self;
     fn canonicalize_nans(&mut self, enable_canonicalize_nans: bool);
     fn with_canonicalize_nans(self, enable_canonicalize_nans: bool) -> Self;
+    fn threads(&mut self, threads: usize);
+    fn with_threads(self, threads: usize) -> Self;
+
+    /// Choose the linker used to produce shared objects.
+    ///
+    /// By default, the built-in linker is used for the targets it supports, and the external
+    /// linker for the others.
+    fn linker(&mut self, linker: Linker);
+    /// Choose the linker used to produce shared objects.
+    ///
+    /// By default, the built-in linker is used for the targets it supports, and the external
+    /// linker for the others.
+    fn with_linker(self, linker: Linker) -> Self;
+
+    /// Compile the module with the heap and globals of an instance that has already been
+    /// initialized, such as one captured with `Instance::initial_state()` in `lucet-runtime`.
+    ///
+    /// The module's start function is not run when it is instantiated.
+    fn initial_state(&mut self, initial_state: InitialState);
+    /// Compile the module with the heap and globals of an instance that has already been
+    /// initialized, such as one captured with `Instance::initial_state()` in `lucet-runtime`.
+    ///
+    /// The module's start function is not run when it is instantiated.
+    fn with_initial_state(self, initial_state: InitialState) -> Self;
 }
 
 impl<T: AsLucetc> LucetcOpts for T {
@@ -201,6 +244,15 @@ impl<T: AsLucetc> LucetcOpts for T {
         self
     }
 
+    fn bounds_checked_heap(&mut self, bounds_checked: bool) {
+        self.as_lucetc().builder.heap_settings_mut().bounds_checked = bounds_checked;
+    }
+
+    fn with_bounds_checked_heap(mut self, bounds_checked: bool) -> Self {
+        self.bounds_checked_heap(bounds_checked);
+        self
+    }
+
     fn pk(&mut self, pk: PublicKey) {
         self.as_lucetc().pk = Some(pk);
     }
@@ -248,6 +300,26 @@ impl<T: AsLucetc> LucetcOpts for T {
         self
     }
 
+    fn epoch_interruption(&mut self, epoch_interruption: bool) {
+        self.as_lucetc()
+            .builder
+            .epoch_interruption(epoch_interruption);
+    }
+
+    fn with_epoch_interruption(mut self, epoch_interruption: bool) -> Self {
+        self.epoch_interruption(epoch_interruption);
+        self
+    }
+
+    fn linked_imports(&mut self, linked_imports: bool) {
+        self.as_lucetc().builder.linked_imports(linked_imports);
+    }
+
+    fn with_linked_imports(mut self, linked_imports: bool) -> Self {
+        self.linked_imports(linked_imports);
+        self
+    }
+
     fn canonicalize_nans(&mut self, enable_nans_canonicalization: bool) {
         self.as_lucetc()
             .builder
@@ -258,6 +330,33 @@ impl<T: AsLucetc> LucetcOpts for T {
         self.canonicalize_nans(enable_nans_canonicalization);
         self
     }
+
+    fn threads(&mut self, threads: usize) {
+        self.as_lucetc().builder.threads(Some(threads));
+    }
+
+    fn with_threads(mut self, threads: usize) -> Self {
+        self.threads(threads);
+        self
+    }
+
+    fn linker(&mut self, linker: Linker) {
+        self.as_lucetc().linker = Some(linker);
+    }
+
+    fn with_linker(mut self, linker: Linker) -> Self {
+        self.linker(linker);
+        self
+    }
+
+    fn initial_state(&mut self, initial_state: InitialState) {
+        self.as_lucetc().builder.initial_state(Some(initial_state));
+    }
+
+    fn with_initial_state(mut self, initial_state: InitialState) -> Self {
+        self.initial_state(initial_state);
+        self
+    }
 }
 
 impl Lucetc {
@@ -271,6 +370,7 @@ impl Lucetc {
             sk: None,
             sign: false,
             verify: false,
+            linker: None,
         }
     }
 
@@ -284,6 +384,7 @@ impl Lucetc {
             sk: None,
             sign: false,
             verify: false,
+            linker: None,
         })
     }
 
@@ -321,11 +422,36 @@ impl Lucetc {
         Ok(())
     }
 
+    /// Compile the module to a shared object, with the linker chosen by
+    /// [`LucetcOpts::linker()`](trait.LucetcOpts.html#tymethod.linker).
     pub fn shared_object_file(&self, output: impl AsRef<Path>) -> Result<(), Error> {
-        let dir = tempfile::Builder::new().prefix("lucetc").tempdir()?;
-        let objpath = dir.path().join("tmp.o");
-        self.object_file(objpath.clone())?;
-        link_so(objpath, self.builder.target_ref(), &output)?;
+        let target = self.builder.target_ref();
+        let linker = self.linker.unwrap_or_else(|| {
+            if link::supports_target(target) {
+                Linker::BuiltIn
+            } else {
+                Linker::External
+            }
+        });
+        match linker {
+            Linker::BuiltIn => {
+                if !link::supports_target(target) {
+                    return Err(Error::Link(format!(
+                        "the built-in linker does not support {}",
+                        target
+                    )));
+                }
+                let (module_contents, bindings) = self.build()?;
+                let compiler = self.builder.create(&module_contents, &bindings)?;
+                std::fs::write(&output, compiler.object_file()?.to_shared_object()?)?;
+            }
+            Linker::External => {
+                let dir = tempfile::Builder::new().prefix("lucetc").tempdir()?;
+                let objpath = dir.path().join("tmp.o");
+                self.object_file(objpath.clone())?;
+                link_so(objpath, target, &output)?;
+            }
+        }
         if self.sign {
             let sk = self.sk.as_ref().ok_or(Error::Signature(
                 "signing requires a secret key".to_string(),
@@ -353,7 +479,10 @@ fn link_so(
     }
 
     cmd_ld.arg(objpath.as_ref());
-    let env_ldflags = env::var("LDFLAGS").unwrap_or_else(|_| ldflags_default(target));
+    let env_ldflags = match env::var("LDFLAGS") {
+        Ok(ldflags) => ldflags,
+        Err(_) => ldflags_default(target)?,
+    };
     for flag in env_ldflags.split_whitespace() {
         cmd_ld.arg(flag);
     }
@@ -388,21 +517,20 @@ fn output_arg_for(cmd_ld: &mut Command, target: &Triple, sopath: impl AsRef<Path
     cmd_ld.arg(format!("/out:{:?}", sopath.as_ref()));
 }
 
-fn ldflags_default(target: &Triple) -> String {
+fn ldflags_default(target: &Triple) -> Result<String, Error> {
     use target_lexicon::OperatingSystem;
 
     match target.operating_system {
-        OperatingSystem::Linux => "-shared",
+        OperatingSystem::Linux => Ok("-shared".into()),
         OperatingSystem::Darwin | OperatingSystem::MacOSX { .. } => {
-            "-dylib -dead_strip -export_dynamic -undefined dynamic_lookup"
+            Ok("-dylib -dead_strip -export_dynamic -undefined dynamic_lookup".into())
         }
-        _ => panic!(
+        _ => Err(Error::LdError(format!(
             "Cannot determine default flags for {}.
 
 Please define the LDFLAGS environment variable with the necessary command-line
 flags for generating shared libraries.",
             target
-        ),
+        ))),
     }
-    .into()
 }
@@ -0,0 +1,812 @@
+//! A built-in linker for turning the objects lucetc emits into shared objects.
+//!
+//! This only handles what lucetc's own output needs: a single x86-64 ELF relocatable object with
+//! no thread-local storage. Calls to imports go through stubs that jump via the GOT, and absolute
+//! pointers become dynamic relocations, so the code itself never needs relocating at load time.
+//! The dynamic loader is left with only `R_X86_64_RELATIVE`, `R_X86_64_64`, and
+//! `R_X86_64_GLOB_DAT` relocations.
+//!
+//! Read-only data is loaded read-only. Read-only data that needs relocating, the GOT, and the
+//! dynamic section are loaded writable so that the dynamic loader can relocate them, and then made
+//! read-only again through a `PT_GNU_RELRO` segment.
+
+use crate::error::Error;
+use byteorder::{LittleEndian, WriteBytesExt};
+use object::elf;
+use object::{
+    Object, ObjectSection, RelocationKind, RelocationTarget, SectionKind, SymbolKind, SymbolScope,
+};
+use std::collections::HashMap;
+use target_lexicon::{Architecture, BinaryFormat, Triple};
+
+const PAGE_SIZE: u64 = 0x1000;
+const EHDR_SIZE: u64 = 64;
+const PHDR_SIZE: u64 = 56;
+const SHDR_SIZE: u64 = 64;
+const SYM_SIZE: u64 = 24;
+const RELA_SIZE: u64 = 24;
+const DYN_SIZE: u64 = 16;
+const PHDR_COUNT: u64 = 6;
+const DYNAMIC_COUNT: u64 = 9;
+/// A stub is `jmp *slot(%rip)`, padded with `int3`.
+const STUB_SIZE: u64 = 8;
+
+/// The linker used to produce shared objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Linker {
+    /// The built-in linker, which only supports x86-64 ELF targets.
+    BuiltIn,
+    /// An external linker, run as described by the `LD` and `LDFLAGS` environment variables.
+    /// `LD` defaults to `ld`.
+    External,
+}
+
+/// Whether the built-in linker can produce a shared object for `target`.
+pub fn supports_target(target: &Triple) -> bool {
+    target.architecture == Architecture::X86_64 && target.binary_format == BinaryFormat::Elf
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Placement {
+    /// Loaded in the read-only, executable segment
+    Text,
+    /// Loaded in the read-only segment
+    ReadOnly,
+    /// Loaded at the start of the writable segment, and made read-only once relocated
+    Relro,
+    /// Loaded in the writable segment
+    Data,
+    /// Kept in the file but not loaded, like debug information
+    Unloaded,
+}
+
+impl Placement {
+    /// Whether sections in this placement may have dynamic relocations applied to them.
+    fn is_relocatable(self) -> bool {
+        self == Placement::Relro || self == Placement::Data
+    }
+}
+
+/// A section of the output, either copied from the input object or synthesized here.
+struct OutputSection {
+    name: String,
+    placement: Placement,
+    sh_type: u32,
+    flags: u32,
+    align: u64,
+    entsize: u64,
+    link: u32,
+    info: u32,
+    data: Vec<u8>,
+    offset: u64,
+    addr: u64,
+}
+
+impl OutputSection {
+    fn new(name: &str, placement: Placement, sh_type: u32, flags: u32, align: u64) -> Self {
+        OutputSection {
+            name: name.to_owned(),
+            placement,
+            sh_type,
+            flags,
+            align,
+            entsize: 0,
+            link: 0,
+            info: 0,
+            data: vec![],
+            offset: 0,
+            addr: 0,
+        }
+    }
+}
+
+/// A string table under construction.
+struct StringTable {
+    data: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn new() -> Self {
+        StringTable {
+            data: vec![0],
+            offsets: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, s: &str) -> u32 {
+        if s.is_empty() {
+            return 0;
+        }
+        if let Some(offset) = self.offsets.get(s) {
+            return *offset;
+        }
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        self.offsets.insert(s.to_owned(), offset);
+        offset
+    }
+}
+
+/// A symbol table entry, written out once addresses are known.
+struct SymbolEntry {
+    name: u32,
+    info: u8,
+    section: Option<usize>,
+    value: u64,
+    size: u64,
+}
+
+/// Link the relocatable object in `obj_bytes` into a shared object.
+pub fn link_shared_object(obj_bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    if obj_bytes.get(..4) != Some(&elf::ELFMAG[..])
+        || obj_bytes.get(18..20) != Some(&elf::EM_X86_64.to_le_bytes()[..])
+    {
+        return Err(Error::Link(
+            "only x86-64 ELF objects are supported".to_owned(),
+        ));
+    }
+    let obj = object::File::parse(obj_bytes)?;
+
+    // Lay out the section headers. The dynamic linking tables come first in the text segment,
+    // followed by the code and the import stubs. Read-only data without relocations comes next in
+    // a segment of its own. The writable segment starts with the read-only data that needs
+    // relocating at load time, the GOT, and the dynamic section, then holds the writable data.
+    let alloc = elf::SHF_ALLOC;
+    let mut sections = vec![
+        OutputSection::new("", Placement::Unloaded, elf::SHT_NULL, 0, 0),
+        OutputSection::new(".hash", Placement::Text, elf::SHT_HASH, alloc, 8),
+        OutputSection::new(".dynsym", Placement::Text, elf::SHT_DYNSYM, alloc, 8),
+        OutputSection::new(".dynstr", Placement::Text, elf::SHT_STRTAB, alloc, 1),
+        OutputSection::new(".rela.dyn", Placement::Text, elf::SHT_RELA, alloc, 8),
+    ];
+    let (hash, dynsym, dynstr, rela_dyn) = (1, 2, 3, 4);
+    let mut input_sections = HashMap::new();
+    add_input_sections(&obj, Placement::Text, &mut sections, &mut input_sections)?;
+    let plt = sections.len();
+    sections.push(OutputSection::new(
+        ".plt",
+        Placement::Text,
+        elf::SHT_PROGBITS,
+        alloc | elf::SHF_EXECINSTR,
+        16,
+    ));
+    for placement in &[Placement::ReadOnly, Placement::Relro] {
+        add_input_sections(&obj, *placement, &mut sections, &mut input_sections)?;
+    }
+    let got = sections.len();
+    sections.push(OutputSection::new(
+        ".got",
+        Placement::Relro,
+        elf::SHT_PROGBITS,
+        alloc | elf::SHF_WRITE,
+        8,
+    ));
+    let dynamic = sections.len();
+    sections.push(OutputSection::new(
+        ".dynamic",
+        Placement::Relro,
+        elf::SHT_DYNAMIC,
+        alloc | elf::SHF_WRITE,
+        8,
+    ));
+    add_input_sections(&obj, Placement::Data, &mut sections, &mut input_sections)?;
+    add_input_sections(
+        &obj,
+        Placement::Unloaded,
+        &mut sections,
+        &mut input_sections,
+    )?;
+    let symtab = sections.len();
+    sections.push(OutputSection::new(
+        ".symtab",
+        Placement::Unloaded,
+        elf::SHT_SYMTAB,
+        0,
+        8,
+    ));
+    let strtab = sections.len();
+    sections.push(OutputSection::new(
+        ".strtab",
+        Placement::Unloaded,
+        elf::SHT_STRTAB,
+        0,
+        1,
+    ));
+    let shstrtab = sections.len();
+    sections.push(OutputSection::new(
+        ".shstrtab",
+        Placement::Unloaded,
+        elf::SHT_STRTAB,
+        0,
+        1,
+    ));
+
+    // Every named undefined symbol is an import, which gets a GOT slot and a stub. Defined symbols
+    // with default visibility are exported.
+    let symbols: HashMap<usize, object::Symbol<'_>> = obj
+        .symbols()
+        .map(|(index, symbol)| (index.0, symbol))
+        .collect();
+    let mut symbol_indices: Vec<usize> = symbols.keys().copied().collect();
+    symbol_indices.sort();
+    let symbol_section = |index: usize| -> Option<usize> {
+        symbols[&index]
+            .section_index()
+            .and_then(|section| input_sections.get(&section.0).copied())
+    };
+    let symbol_name = |index: usize| symbols[&index].name().unwrap_or("");
+
+    let mut imports = Vec::new();
+    let mut exports = Vec::new();
+    for index in symbol_indices.iter().copied() {
+        let symbol = &symbols[&index];
+        if symbol_name(index).is_empty() {
+            continue;
+        }
+        if symbol.is_undefined() {
+            imports.push(index);
+        } else if symbol.scope() == SymbolScope::Dynamic && symbol_section(index).is_some() {
+            exports.push(index);
+        }
+    }
+    let mut dynsym_ids = HashMap::new();
+    for index in exports.iter().chain(imports.iter()) {
+        dynsym_ids.insert(*index, dynsym_ids.len() as u32 + 1);
+    }
+    let mut got_slots: HashMap<usize, u64> = HashMap::new();
+    for index in imports.iter() {
+        got_slots.insert(*index, got_slots.len() as u64);
+    }
+
+    // Find the defined symbols used through the GOT, and count the dynamic relocations needed.
+    let mut dynamic_reloc_count = 0;
+    for section in obj.sections() {
+        let placement = match input_sections.get(&section.index().0) {
+            Some(out) => sections[*out].placement,
+            None => continue,
+        };
+        for (_, reloc) in section.relocations() {
+            match (reloc.kind(), reloc.size(), reloc.target()) {
+                (RelocationKind::GotRelative, 32, RelocationTarget::Symbol(index)) => {
+                    let next = got_slots.len() as u64;
+                    got_slots.entry(index.0).or_insert(next);
+                }
+                (RelocationKind::Absolute, 64, _) if placement.is_relocatable() => {
+                    dynamic_reloc_count += 1;
+                }
+                (RelocationKind::Absolute, _, _) if placement == Placement::Text => {
+                    return Err(Error::Link(
+                        "absolute relocations in code are not supported".to_owned(),
+                    ));
+                }
+                _ => (),
+            }
+        }
+    }
+    dynamic_reloc_count += got_slots.len();
+
+    // Build everything whose size doesn't depend on addresses.
+    let mut dynstr_table = StringTable::new();
+    let mut dynsym_entries = vec![SymbolEntry::null()];
+    for index in exports.iter().chain(imports.iter()) {
+        dynsym_entries.push(SymbolEntry::for_symbol(
+            &symbols[index],
+            dynstr_table.add(symbol_name(*index)),
+            symbol_section(*index),
+        ));
+    }
+    sections[hash].data = elf_hash_table(
+        std::iter::once("").chain(
+            exports
+                .iter()
+                .chain(imports.iter())
+                .map(|index| symbol_name(*index)),
+        ),
+    );
+    sections[hash].link = dynsym as u32;
+    sections[hash].entsize = 4;
+    sections[dynsym].data = vec![0; dynsym_entries.len() * SYM_SIZE as usize];
+    sections[dynsym].link = dynstr as u32;
+    sections[dynsym].info = 1;
+    sections[dynsym].entsize = SYM_SIZE;
+    sections[dynstr].data = dynstr_table.data;
+    sections[rela_dyn].data = vec![0; dynamic_reloc_count * RELA_SIZE as usize];
+    sections[rela_dyn].link = dynsym as u32;
+    sections[rela_dyn].entsize = RELA_SIZE;
+    sections[plt].data = vec![0xcc; imports.len() * STUB_SIZE as usize];
+    sections[got].data = vec![0; got_slots.len() * 8];
+    sections[dynamic].data = vec![0; (DYNAMIC_COUNT * DYN_SIZE) as usize];
+    sections[dynamic].link = dynstr as u32;
+    sections[dynamic].entsize = DYN_SIZE;
+
+    // The static symbol table has the local symbols first, as ELF requires.
+    let mut strtab_table = StringTable::new();
+    let mut symtab_entries = vec![SymbolEntry::null()];
+    let mut globals = vec![];
+    for index in symbol_indices.iter().copied() {
+        let symbol = &symbols[&index];
+        let section = symbol_section(index);
+        if symbol_name(index).is_empty() || (section.is_none() && !symbol.is_undefined()) {
+            continue;
+        }
+        if is_local(symbol) {
+            symtab_entries.push(SymbolEntry::for_symbol(
+                symbol,
+                strtab_table.add(symbol_name(index)),
+                section,
+            ));
+        } else {
+            globals.push(index);
+        }
+    }
+    let first_global = symtab_entries.len();
+    for index in globals {
+        symtab_entries.push(SymbolEntry::for_symbol(
+            &symbols[&index],
+            strtab_table.add(symbol_name(index)),
+            symbol_section(index),
+        ));
+    }
+    sections[symtab].data = vec![0; symtab_entries.len() * SYM_SIZE as usize];
+    sections[symtab].link = strtab as u32;
+    sections[symtab].info = first_global as u32;
+    sections[symtab].entsize = SYM_SIZE;
+    sections[strtab].data = strtab_table.data;
+
+    let mut shstrtab_table = StringTable::new();
+    let section_names: Vec<u32> = sections
+        .iter()
+        .map(|section| shstrtab_table.add(&section.name))
+        .collect();
+    sections[shstrtab].data = shstrtab_table.data;
+
+    // Assign file offsets and addresses. Addresses are the same as file offsets, so the loaded
+    // segments are mapped straight from the file. Each placement starts on a new page, so that the
+    // RELRO part of the writable segment can be protected without affecting the data after it.
+    let mut offset = EHDR_SIZE + PHDR_COUNT * PHDR_SIZE;
+    let mut ranges: HashMap<Placement, (u64, u64)> = HashMap::new();
+    ranges.insert(Placement::Text, (0, offset));
+    let mut previous = Placement::Text;
+    for section in sections.iter_mut().skip(1) {
+        if section.placement != previous && section.placement != Placement::Unloaded {
+            offset = round_up(offset, PAGE_SIZE);
+            previous = section.placement;
+        }
+        offset = round_up(offset, section.align);
+        section.offset = offset;
+        if section.placement != Placement::Unloaded {
+            section.addr = offset;
+            ranges
+                .entry(section.placement)
+                .or_insert((offset, offset))
+                .1 = offset + section.data.len() as u64;
+        }
+        offset += section.data.len() as u64;
+    }
+    let section_headers = round_up(offset, 8);
+
+    let addrs: Vec<u64> = sections.iter().map(|section| section.addr).collect();
+    let symbol_addr = |index: usize| -> Option<u64> {
+        symbol_section(index).map(|section| addrs[section] + symbols[&index].address())
+    };
+    let got_addr = |slot: u64| addrs[got] + slot * 8;
+    let stub_addr = |import: usize| addrs[plt] + import as u64 * STUB_SIZE;
+
+    // Fill in the import stubs and the GOT.
+    let mut dynamic_relocs = Vec::with_capacity(dynamic_reloc_count);
+    let mut plt_data = std::mem::replace(&mut sections[plt].data, vec![]);
+    for (import, index) in imports.iter().enumerate() {
+        let stub = stub_addr(import);
+        let slot = got_addr(got_slots[index]);
+        let stub_offset = import * STUB_SIZE as usize;
+        plt_data[stub_offset..stub_offset + 2].copy_from_slice(&[0xff, 0x25]);
+        let disp = (slot as i64 - (stub as i64 + 6)) as i32;
+        plt_data[stub_offset + 2..stub_offset + 6].copy_from_slice(&disp.to_le_bytes());
+    }
+    sections[plt].data = plt_data;
+    let mut got_data = std::mem::replace(&mut sections[got].data, vec![]);
+    let mut got_entries: Vec<(&usize, &u64)> = got_slots.iter().collect();
+    got_entries.sort_by_key(|(_, slot)| **slot);
+    for (index, slot) in got_entries {
+        let place = got_addr(*slot);
+        match symbol_addr(*index) {
+            Some(addr) => {
+                got_data[*slot as usize * 8..][..8].copy_from_slice(&addr.to_le_bytes());
+                dynamic_relocs.push((place, 0, elf::R_X86_64_RELATIVE, addr as i64));
+            }
+            None => {
+                dynamic_relocs.push((place, dynsym_ids[index], elf::R_X86_64_GLOB_DAT, 0));
+            }
+        }
+    }
+    sections[got].data = got_data;
+
+    // Apply the input relocations.
+    for section in obj.sections() {
+        let out = match input_sections.get(&section.index().0) {
+            Some(out) => *out,
+            None => continue,
+        };
+        let placement = sections[out].placement;
+        let section_addr = sections[out].addr;
+        let mut data = std::mem::replace(&mut sections[out].data, vec![]);
+        for (offset, reloc) in section.relocations() {
+            let place = section_addr + offset;
+            let (target, name) = match reloc.target() {
+                RelocationTarget::Symbol(index) => (index.0, symbol_name(index.0).to_owned()),
+                RelocationTarget::Section(_) => {
+                    return Err(Error::Link(
+                        "section relocations are not supported".to_owned(),
+                    ));
+                }
+            };
+            let addend = if reloc.has_implicit_addend() {
+                match reloc.size() {
+                    32 => read_i32(&data, offset) as i64,
+                    64 => read_i64(&data, offset),
+                    _ => 0,
+                }
+            } else {
+                reloc.addend()
+            };
+            let addr = symbol_addr(target);
+            match (reloc.kind(), reloc.size(), addr) {
+                (RelocationKind::Absolute, 64, Some(addr)) => {
+                    let value = (addr as i64).wrapping_add(addend);
+                    write_bytes(&mut data, offset, &value.to_le_bytes());
+                    if placement.is_relocatable() {
+                        dynamic_relocs.push((place, 0, elf::R_X86_64_RELATIVE, value));
+                    }
+                }
+                (RelocationKind::Absolute, 64, None) if placement.is_relocatable() => {
+                    write_bytes(&mut data, offset, &0u64.to_le_bytes());
+                    dynamic_relocs.push((place, dynsym_ids[&target], elf::R_X86_64_64, addend));
+                }
+                (RelocationKind::Absolute, 32, Some(addr)) if placement == Placement::Unloaded => {
+                    let value = (addr as i64).wrapping_add(addend);
+                    if value < 0 || value > std::u32::MAX as i64 {
+                        return Err(Error::Link(format!("relocation out of range: {}", name)));
+                    }
+                    write_bytes(&mut data, offset, &(value as u32).to_le_bytes());
+                }
+                (RelocationKind::Relative, 32, Some(addr))
+                | (RelocationKind::PltRelative, 32, Some(addr)) => {
+                    write_rel32(&mut data, offset, place, addr, addend, &name)?;
+                }
+                (RelocationKind::Relative, 32, None) | (RelocationKind::PltRelative, 32, None) => {
+                    let import = imports
+                        .iter()
+                        .position(|index| *index == target)
+                        .expect("undefined symbols are imports");
+                    write_rel32(&mut data, offset, place, stub_addr(import), addend, &name)?;
+                }
+                (RelocationKind::GotRelative, 32, _) => {
+                    let slot = got_addr(got_slots[&target]);
+                    write_rel32(&mut data, offset, place, slot, addend, &name)?;
+                }
+                (kind, size, _) => {
+                    return Err(Error::Link(format!(
+                        "unsupported relocation {:?} ({} bits) against {}",
+                        kind, size, name
+                    )));
+                }
+            }
+        }
+        sections[out].data = data;
+    }
+    debug_assert_eq!(dynamic_relocs.len(), dynamic_reloc_count);
+
+    // Now that addresses are known, write out the tables.
+    let mut rela_data = vec![];
+    for (offset, sym, r_type, addend) in dynamic_relocs {
+        rela_data.write_u64::<LittleEndian>(offset)?;
+        rela_data.write_u64::<LittleEndian>(((sym as u64) << 32) | r_type as u64)?;
+        rela_data.write_i64::<LittleEndian>(addend)?;
+    }
+    sections[rela_dyn].data = rela_data;
+    sections[dynsym].data = write_symbols(&dynsym_entries, &sections)?;
+    sections[symtab].data = write_symbols(&symtab_entries, &sections)?;
+    let mut dynamic_data = vec![];
+    for (tag, value) in &[
+        (elf::DT_HASH, sections[hash].addr),
+        (elf::DT_STRTAB, sections[dynstr].addr),
+        (elf::DT_SYMTAB, sections[dynsym].addr),
+        (elf::DT_STRSZ, sections[dynstr].data.len() as u64),
+        (elf::DT_SYMENT, SYM_SIZE),
+        (elf::DT_RELA, sections[rela_dyn].addr),
+        (elf::DT_RELASZ, sections[rela_dyn].data.len() as u64),
+        (elf::DT_RELAENT, RELA_SIZE),
+        (elf::DT_NULL, 0),
+    ] {
+        dynamic_data.write_i64::<LittleEndian>(*tag as i64)?;
+        dynamic_data.write_u64::<LittleEndian>(*value)?;
+    }
+    sections[dynamic].data = dynamic_data;
+
+    // Finally, write the file.
+    let mut out = Vec::with_capacity(section_headers as usize);
+    out.extend_from_slice(&elf::ELFMAG);
+    out.extend_from_slice(&[elf::ELFCLASS64, elf::ELFDATA2LSB, elf::EV_CURRENT]);
+    out.resize(16, 0);
+    out.write_u16::<LittleEndian>(elf::ET_DYN)?;
+    out.write_u16::<LittleEndian>(elf::EM_X86_64)?;
+    out.write_u32::<LittleEndian>(elf::EV_CURRENT as u32)?;
+    out.write_u64::<LittleEndian>(0)?; // e_entry
+    out.write_u64::<LittleEndian>(EHDR_SIZE)?; // e_phoff
+    out.write_u64::<LittleEndian>(section_headers)?; // e_shoff
+    out.write_u32::<LittleEndian>(0)?; // e_flags
+    out.write_u16::<LittleEndian>(EHDR_SIZE as u16)?;
+    out.write_u16::<LittleEndian>(PHDR_SIZE as u16)?;
+    out.write_u16::<LittleEndian>(PHDR_COUNT as u16)?;
+    out.write_u16::<LittleEndian>(SHDR_SIZE as u16)?;
+    out.write_u16::<LittleEndian>(sections.len() as u16)?;
+    out.write_u16::<LittleEndian>(shstrtab as u16)?;
+
+    let text = ranges[&Placement::Text];
+    let relro = ranges[&Placement::Relro];
+    // the loader only protects whole pages, so the RELRO segment extends to the end of its last
+    // page, where the writable data starts
+    let relro_end = round_up(relro.1, PAGE_SIZE);
+    let writable_end = ranges.get(&Placement::Data).map_or(relro.1, |data| data.1);
+    let dynamic_section = &sections[dynamic];
+    let read_only = match ranges.get(&Placement::ReadOnly) {
+        Some((start, end)) => (elf::PT_LOAD, elf::PF_R, *start, *end, PAGE_SIZE),
+        // there's no read-only data to load, so the program header is left unused
+        None => (elf::PT_NULL, 0, 0, 0, 0),
+    };
+    for (p_type, flags, start, end, align) in &[
+        (
+            elf::PT_LOAD,
+            elf::PF_R | elf::PF_X,
+            text.0,
+            text.1,
+            PAGE_SIZE,
+        ),
+        read_only,
+        (
+            elf::PT_LOAD,
+            elf::PF_R | elf::PF_W,
+            relro.0,
+            writable_end,
+            PAGE_SIZE,
+        ),
+        (
+            elf::PT_DYNAMIC,
+            elf::PF_R | elf::PF_W,
+            dynamic_section.offset,
+            dynamic_section.offset + dynamic_section.data.len() as u64,
+            8,
+        ),
+        (elf::PT_GNU_RELRO, elf::PF_R, relro.0, relro_end, 1),
+        // an explicitly non-executable stack
+        (elf::PT_GNU_STACK, elf::PF_R | elf::PF_W, 0, 0, 16),
+    ] {
+        out.write_u32::<LittleEndian>(*p_type)?;
+        out.write_u32::<LittleEndian>(*flags)?;
+        out.write_u64::<LittleEndian>(*start)?; // p_offset
+        out.write_u64::<LittleEndian>(*start)?; // p_vaddr
+        out.write_u64::<LittleEndian>(*start)?; // p_paddr
+        out.write_u64::<LittleEndian>(end - start)?; // p_filesz
+        out.write_u64::<LittleEndian>(end - start)?; // p_memsz
+        out.write_u64::<LittleEndian>(*align)?;
+    }
+
+    for section in sections.iter().skip(1) {
+        out.resize(section.offset as usize, 0);
+        out.extend_from_slice(&section.data);
+    }
+    out.resize(section_headers as usize, 0);
+    for (section, name) in sections.iter().zip(section_names) {
+        out.write_u32::<LittleEndian>(name)?;
+        out.write_u32::<LittleEndian>(section.sh_type)?;
+        out.write_u64::<LittleEndian>(section.flags as u64)?;
+        out.write_u64::<LittleEndian>(section.addr)?;
+        out.write_u64::<LittleEndian>(section.offset)?;
+        out.write_u64::<LittleEndian>(section.data.len() as u64)?;
+        out.write_u32::<LittleEndian>(section.link)?;
+        out.write_u32::<LittleEndian>(section.info)?;
+        out.write_u64::<LittleEndian>(section.align)?;
+        out.write_u64::<LittleEndian>(section.entsize)?;
+    }
+
+    Ok(out)
+}
+
+/// Add an output section for each input section that goes in `placement`.
+fn add_input_sections(
+    obj: &object::File<'_>,
+    placement: Placement,
+    sections: &mut Vec<OutputSection>,
+    input_sections: &mut HashMap<usize, usize>,
+) -> Result<(), Error> {
+    for section in obj.sections() {
+        let (section_placement, flags) = match section.kind() {
+            SectionKind::Text => (Placement::Text, elf::SHF_ALLOC | elf::SHF_EXECINSTR),
+            SectionKind::ReadOnlyData | SectionKind::ReadOnlyString => {
+                if section.relocations().next().is_some() {
+                    (Placement::Relro, elf::SHF_ALLOC | elf::SHF_WRITE)
+                } else {
+                    (Placement::ReadOnly, elf::SHF_ALLOC)
+                }
+            }
+            // data that is only writable so that it can be relocated, as cranelift emits for
+            // read-only data with relocations
+            SectionKind::Data if section.name()?.starts_with(".data.rel.ro") => {
+                (Placement::Relro, elf::SHF_ALLOC | elf::SHF_WRITE)
+            }
+            SectionKind::Data | SectionKind::UninitializedData => {
+                (Placement::Data, elf::SHF_ALLOC | elf::SHF_WRITE)
+            }
+            SectionKind::Other | SectionKind::OtherString | SectionKind::Debug
+                if section.size() > 0 =>
+            {
+                (Placement::Unloaded, 0)
+            }
+            SectionKind::Tls | SectionKind::UninitializedTls => {
+                return Err(Error::Link(
+                    "thread-local storage is not supported".to_owned(),
+                ));
+            }
+            _ => continue,
+        };
+        if section_placement != placement {
+            continue;
+        }
+        // uninitialized data is written out as zeros, so that the writable segment is all in the
+        // file
+        let data = if section.kind() == SectionKind::UninitializedData {
+            vec![0; section.size() as usize]
+        } else {
+            section.data()?.to_vec()
+        };
+        input_sections.insert(section.index().0, sections.len());
+        let mut output = OutputSection::new(
+            section.name()?,
+            placement,
+            elf::SHT_PROGBITS,
+            flags,
+            std::cmp::max(section.align(), 1),
+        );
+        output.data = data;
+        sections.push(output);
+    }
+    Ok(())
+}
+
+impl SymbolEntry {
+    fn null() -> Self {
+        SymbolEntry {
+            name: 0,
+            info: 0,
+            section: None,
+            value: 0,
+            size: 0,
+        }
+    }
+
+    fn for_symbol(symbol: &object::Symbol<'_>, name: u32, section: Option<usize>) -> Self {
+        let bind = if is_local(symbol) {
+            elf::STB_LOCAL
+        } else {
+            elf::STB_GLOBAL
+        };
+        let kind = match symbol.kind() {
+            SymbolKind::Text => elf::STT_FUNC,
+            SymbolKind::Data => elf::STT_OBJECT,
+            _ => elf::STT_NOTYPE,
+        };
+        SymbolEntry {
+            name,
+            info: (bind << 4) | kind,
+            section,
+            // the section address is added when writing
+            value: symbol.address(),
+            size: symbol.size(),
+        }
+    }
+}
+
+/// Whether a symbol is bound locally in the output. Hidden symbols are only visible within the
+/// object, so they become local too.
+fn is_local(symbol: &object::Symbol<'_>) -> bool {
+    match symbol.scope() {
+        SymbolScope::Compilation | SymbolScope::Linkage => true,
+        _ => false,
+    }
+}
+
+fn write_symbols(entries: &[SymbolEntry], sections: &[OutputSection]) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::with_capacity(entries.len() * SYM_SIZE as usize);
+    for entry in entries {
+        let (shndx, value) = match entry.section {
+            Some(section) => (section as u16, sections[section].addr + entry.value),
+            None => (elf::SHN_UNDEF, 0),
+        };
+        data.write_u32::<LittleEndian>(entry.name)?;
+        data.write_u8(entry.info)?;
+        data.write_u8(0)?; // st_other: default visibility
+        data.write_u16::<LittleEndian>(shndx)?;
+        data.write_u64::<LittleEndian>(value)?;
+        data.write_u64::<LittleEndian>(entry.size)?;
+    }
+    Ok(data)
+}
+
+/// Build a System V hash table for the dynamic symbols with the given names, in order.
+fn elf_hash_table<'a>(names: impl Iterator<Item = &'a str>) -> Vec<u8> {
+    fn elf_hash(name: &str) -> u32 {
+        let mut h: u32 = 0;
+        for c in name.bytes() {
+            h = (h << 4).wrapping_add(c as u32);
+            let g = h & 0xf000_0000;
+            if g != 0 {
+                h ^= g >> 24;
+            }
+            h &= !g;
+        }
+        h
+    }
+
+    let hashes: Vec<u32> = names.map(elf_hash).collect();
+    let nbucket = hashes.len() / 2 + 1;
+    let mut buckets = vec![0u32; nbucket];
+    let mut chains = vec![0u32; hashes.len()];
+    // symbol 0 is the null symbol, which is never looked up
+    for (index, hash) in hashes.iter().enumerate().skip(1) {
+        let bucket = *hash as usize % nbucket;
+        chains[index] = buckets[bucket];
+        buckets[bucket] = index as u32;
+    }
+
+    let mut data = Vec::with_capacity((2 + nbucket + hashes.len()) * 4);
+    data.extend_from_slice(&(nbucket as u32).to_le_bytes());
+    data.extend_from_slice(&(hashes.len() as u32).to_le_bytes());
+    for word in buckets.iter().chain(chains.iter()) {
+        data.extend_from_slice(&word.to_le_bytes());
+    }
+    data
+}
+
+fn round_up(value: u64, align: u64) -> u64 {
+    if align <= 1 {
+        value
+    } else {
+        (value + align - 1) / align * align
+    }
+}
+
+fn write_bytes(data: &mut [u8], offset: u64, bytes: &[u8]) {
+    data[offset as usize..offset as usize + bytes.len()].copy_from_slice(bytes);
+}
+
+fn read_i32(data: &[u8], offset: u64) -> i32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&data[offset as usize..offset as usize + 4]);
+    i32::from_le_bytes(bytes)
+}
+
+fn read_i64(data: &[u8], offset: u64) -> i64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&data[offset as usize..offset as usize + 8]);
+    i64::from_le_bytes(bytes)
+}
+
+fn write_rel32(
+    data: &mut [u8],
+    offset: u64,
+    place: u64,
+    target: u64,
+    addend: i64,
+    name: &str,
+) -> Result<(), Error> {
+    let value = (target as i64)
+        .wrapping_add(addend)
+        .wrapping_sub(place as i64);
+    if value < std::i32::MIN as i64 || value > std::i32::MAX as i64 {
+        return Err(Error::Link(format!("relocation out of range: {}", name)));
+    }
+    write_bytes(data, offset, &(value as i32).to_le_bytes());
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use crate::error::Error;
 use crate::signature::{self, PublicKey};
 use std::path::Path;
-use wabt::{wat2wasm, ErrorKind};
+use wabt::{wat2wasm_with_features, ErrorKind, Features};
 
 pub fn read_module(
     path: impl AsRef<Path>,
@@ -25,7 +25,7 @@ pub fn read_bytes(bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
     if wasm_preamble(&bytes) {
         Ok(bytes)
     } else {
-        wat2wasm(bytes).map_err(|err| {
+        wat2wasm_with_features(bytes, wat_features()).map_err(|err| {
             let mut result = format!("wat2wasm error: {}", err);
             match err.kind() {
                 ErrorKind::Parse(msg) |
@@ -44,6 +44,17 @@ pub fn read_bytes(bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
     }
 }
 
+/// The WebAssembly proposals that may be used in text format modules, matching those that lucetc
+/// accepts when validating.
+fn wat_features() -> Features {
+    let mut features = Features::new();
+    features.enable_bulk_memory();
+    features.enable_reference_types();
+    features.enable_multi_value();
+    features.enable_simd();
+    features
+}
+
 pub fn wasm_preamble(buf: &[u8]) -> bool {
     if buf.len() > 4 {
         buf[0..4] == [0, 97, 115, 109]
@@ -11,7 +11,7 @@ use cranelift_wasm::{
 };
 use lucet_module::UniqueSignatureIndex;
 use std::collections::{hash_map::Entry, HashMap};
-use wasmparser::FuncType;
+use wasmparser::{FuncType, Type};
 
 /// UniqueFuncIndex names a function after merging duplicate function declarations to a single
 /// identifier, whereas FuncIndex is maintained by Cranelift and may have multiple indices referring
@@ -40,6 +40,7 @@ impl<'a, T> Exportable<'a, T> {
 
 #[derive(Debug, Clone)]
 pub struct TableElems {
+    pub index: ElemIndex,
     pub base: Option<GlobalIndex>,
     pub offset: usize,
     pub elements: Box<[UniqueFuncIndex]>,
@@ -47,6 +48,7 @@ pub struct TableElems {
 
 #[derive(Debug, Clone)]
 pub struct DataInitializer<'a> {
+    pub index: DataIndex,
     pub base: Option<GlobalIndex>,
     pub offset: usize,
     pub data: &'a [u8],
@@ -81,6 +83,9 @@ pub struct ModuleInfo<'a> {
     pub memories: PrimaryMap<MemoryIndex, Exportable<'a, Memory>>,
     /// Provided by `declare_global`
     pub globals: PrimaryMap<GlobalIndex, Exportable<'a, Global>>,
+    /// The WebAssembly types of the globals, imported and local, as found by validation. The
+    /// Cranelift types in `globals` are the same for `funcref` and `externref`.
+    pub global_types: PrimaryMap<GlobalIndex, Type>,
     /// Provided by `declare_start_func`
     pub start_func: Option<UniqueFuncIndex>,
 
@@ -92,10 +97,19 @@ pub struct ModuleInfo<'a> {
 
     /// Data initializers: local only
     pub data_initializers: HashMap<MemoryIndex, Vec<DataInitializer<'a>>>,
+
+    /// Provided by `declare_passive_element`
+    pub passive_elements: HashMap<ElemIndex, Box<[UniqueFuncIndex]>>,
+
+    /// Provided by `declare_passive_data`
+    pub passive_data: HashMap<DataIndex, &'a [u8]>,
 }
 
 impl<'a> ModuleInfo<'a> {
-    pub fn new(target_config: TargetFrontendConfig) -> Self {
+    pub fn new(
+        target_config: TargetFrontendConfig,
+        global_types: PrimaryMap<GlobalIndex, Type>,
+    ) -> Self {
         Self {
             target_config,
             signature_mapping: PrimaryMap::new(),
@@ -110,10 +124,13 @@ impl<'a> ModuleInfo<'a> {
             tables: PrimaryMap::new(),
             memories: PrimaryMap::new(),
             globals: PrimaryMap::new(),
+            global_types,
             start_func: None,
             function_bodies: HashMap::new(),
             table_elems: HashMap::new(),
             data_initializers: HashMap::new(),
+            passive_elements: HashMap::new(),
+            passive_data: HashMap::new(),
         }
     }
 
@@ -367,7 +384,10 @@ impl<'a> ModuleEnvironment<'a> for ModuleInfo<'a> {
                     .expect("function indices are valid")
             })
             .collect();
+        // active and passive segments are declared in the order they appear in the module
+        let active_count: usize = self.table_elems.values().map(Vec::len).sum();
         let table_elems = TableElems {
+            index: ElemIndex::new(active_count + self.passive_elements.len()),
             base,
             offset,
             elements: uniquified_elements,
@@ -399,7 +419,14 @@ impl<'a> ModuleEnvironment<'a> for ModuleInfo<'a> {
         offset: usize,
         data: &'a [u8],
     ) -> WasmResult<()> {
-        let data_init = DataInitializer { base, offset, data };
+        // active and passive segments are declared in the order they appear in the module
+        let active_count: usize = self.data_initializers.values().map(Vec::len).sum();
+        let data_init = DataInitializer {
+            index: DataIndex::new(active_count + self.passive_data.len()),
+            base,
+            offset,
+            data,
+        };
         match self.data_initializers.entry(memory_index) {
             Entry::Occupied(mut occ) => {
                 occ.get_mut().push(data_init);
@@ -423,13 +450,24 @@ impl<'a> ModuleEnvironment<'a> for ModuleInfo<'a> {
 
     fn declare_passive_element(
         &mut self,
-        _index: ElemIndex,
-        _elements: Box<[FuncIndex]>,
+        index: ElemIndex,
+        elements: Box<[FuncIndex]>,
     ) -> WasmResult<()> {
-        unimplemented!();
+        let uniquified_elements = elements
+            .iter()
+            .map(|fn_idx| {
+                *self
+                    .function_mapping
+                    .get(*fn_idx)
+                    .expect("function indices are valid")
+            })
+            .collect();
+        self.passive_elements.insert(index, uniquified_elements);
+        Ok(())
     }
 
-    fn declare_passive_data(&mut self, _data_index: DataIndex, _data: &'a [u8]) -> WasmResult<()> {
-        unimplemented!();
+    fn declare_passive_data(&mut self, data_index: DataIndex, data: &'a [u8]) -> WasmResult<()> {
+        self.passive_data.insert(data_index, data);
+        Ok(())
     }
 }
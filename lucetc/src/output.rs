@@ -1,4 +1,5 @@
 use crate::error::Error;
+use crate::link;
 use crate::name::Name;
 use cranelift_codegen::{ir, isa};
 use cranelift_object::ObjectProduct;
@@ -54,11 +55,21 @@ impl ObjectFile {
             Error::Input(message);
         });
         let mut file = File::create(path)?;
-        let bytes = self
-            .object
-            .write()
-            .map_err(|source| Error::ObjectArtifact(source, "Write error".to_owned()))?;
-        file.write_all(&bytes)?;
+        file.write_all(&self.to_bytes()?)?;
         Ok(())
     }
+
+    /// The contents of the relocatable object file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        self.object
+            .write()
+            .map_err(|source| Error::ObjectArtifact(source, "Write error".to_owned()))
+    }
+
+    /// Link the object into a shared object with the built-in linker, returning its contents.
+    ///
+    /// Only x86-64 ELF objects are supported.
+    pub fn to_shared_object(&self) -> Result<Vec<u8>, Error> {
+        link::link_shared_object(&self.to_bytes()?)
+    }
 }
@@ -7,6 +7,20 @@ use wasmparser::FuncType;
 pub enum RuntimeFunc {
     MemSize,
     MemGrow,
+    MemCopy,
+    MemFill,
+    MemInit,
+    DataDrop,
+    ElemDrop,
+    TableGet,
+    TableSet,
+    TableGrow,
+    TableFill,
+    TableCopy,
+    TableInit,
+    RefFunc,
+    OutOfFuel,
+    EpochInterrupt,
 }
 
 pub struct RuntimeFuncType {
@@ -54,6 +68,279 @@ impl Runtime {
                 },
             },
         );
+        functions.insert(
+            RuntimeFunc::MemCopy,
+            RuntimeFuncType {
+                name: "lucet_vmctx_memory_copy".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // destination offset
+                        AbiParam::new(types::I32), // source offset
+                        AbiParam::new(types::I32), // length in bytes
+                    ],
+                    returns: vec![AbiParam::new(types::I32)], // nonzero if out of bounds
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![wasmparser::Type::I32; 3].into_boxed_slice(),
+                    returns: vec![wasmparser::Type::I32].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::MemFill,
+            RuntimeFuncType {
+                name: "lucet_vmctx_memory_fill".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // destination offset
+                        AbiParam::new(types::I32), // byte value
+                        AbiParam::new(types::I32), // length in bytes
+                    ],
+                    returns: vec![AbiParam::new(types::I32)], // nonzero if out of bounds
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![wasmparser::Type::I32; 3].into_boxed_slice(),
+                    returns: vec![wasmparser::Type::I32].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::MemInit,
+            RuntimeFuncType {
+                name: "lucet_vmctx_memory_init".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // data segment index
+                        AbiParam::new(types::I32), // destination offset
+                        AbiParam::new(types::I32), // offset into the segment
+                        AbiParam::new(types::I32), // length in bytes
+                    ],
+                    returns: vec![AbiParam::new(types::I32)], // nonzero if out of bounds
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![wasmparser::Type::I32; 4].into_boxed_slice(),
+                    returns: vec![wasmparser::Type::I32].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::DataDrop,
+            RuntimeFuncType {
+                name: "lucet_vmctx_data_drop".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // data segment index
+                    ],
+                    returns: vec![],
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![wasmparser::Type::I32].into_boxed_slice(),
+                    returns: vec![].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::ElemDrop,
+            RuntimeFuncType {
+                name: "lucet_vmctx_elem_drop".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // element segment index
+                    ],
+                    returns: vec![],
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![wasmparser::Type::I32].into_boxed_slice(),
+                    returns: vec![].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::TableGet,
+            RuntimeFuncType {
+                name: "lucet_vmctx_table_get".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // table index
+                        AbiParam::new(types::I32), // element index
+                    ],
+                    returns: vec![AbiParam::new(types::R64)],
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![wasmparser::Type::I32; 2].into_boxed_slice(),
+                    returns: vec![wasmparser::Type::AnyRef].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::TableSet,
+            RuntimeFuncType {
+                name: "lucet_vmctx_table_set".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // table index
+                        AbiParam::new(types::I32), // element index
+                        AbiParam::new(types::R64), // reference to store
+                    ],
+                    returns: vec![],
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![
+                        wasmparser::Type::I32,
+                        wasmparser::Type::I32,
+                        wasmparser::Type::AnyRef,
+                    ]
+                    .into_boxed_slice(),
+                    returns: vec![].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::TableGrow,
+            RuntimeFuncType {
+                name: "lucet_vmctx_table_grow".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // table index
+                        AbiParam::new(types::I32), // elements to grow
+                        AbiParam::new(types::R64), // reference to fill new elements with
+                    ],
+                    returns: vec![AbiParam::new(types::I32)],
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![
+                        wasmparser::Type::I32,
+                        wasmparser::Type::I32,
+                        wasmparser::Type::AnyRef,
+                    ]
+                    .into_boxed_slice(),
+                    returns: vec![wasmparser::Type::I32].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::TableFill,
+            RuntimeFuncType {
+                name: "lucet_vmctx_table_fill".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // table index
+                        AbiParam::new(types::I32), // destination index
+                        AbiParam::new(types::R64), // reference to fill with
+                        AbiParam::new(types::I32), // number of elements
+                    ],
+                    returns: vec![AbiParam::new(types::I32)], // nonzero if out of bounds
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![
+                        wasmparser::Type::I32,
+                        wasmparser::Type::I32,
+                        wasmparser::Type::AnyRef,
+                        wasmparser::Type::I32,
+                    ]
+                    .into_boxed_slice(),
+                    returns: vec![wasmparser::Type::I32].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::TableCopy,
+            RuntimeFuncType {
+                name: "lucet_vmctx_table_copy".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // destination table index
+                        AbiParam::new(types::I32), // source table index
+                        AbiParam::new(types::I32), // destination index
+                        AbiParam::new(types::I32), // source index
+                        AbiParam::new(types::I32), // number of elements
+                    ],
+                    returns: vec![AbiParam::new(types::I32)], // nonzero if out of bounds
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![wasmparser::Type::I32; 5].into_boxed_slice(),
+                    returns: vec![wasmparser::Type::I32].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::TableInit,
+            RuntimeFuncType {
+                name: "lucet_vmctx_table_init".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // table index
+                        AbiParam::new(types::I32), // element segment index
+                        AbiParam::new(types::I32), // destination index
+                        AbiParam::new(types::I32), // offset into the segment
+                        AbiParam::new(types::I32), // number of elements
+                    ],
+                    returns: vec![AbiParam::new(types::I32)], // nonzero if out of bounds
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![wasmparser::Type::I32; 5].into_boxed_slice(),
+                    returns: vec![wasmparser::Type::I32].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::RefFunc,
+            RuntimeFuncType {
+                name: "lucet_vmctx_ref_func".to_owned(),
+                signature: Signature {
+                    params: vec![
+                        AbiParam::new(types::I32), // function index
+                    ],
+                    returns: vec![AbiParam::new(types::R64)],
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![wasmparser::Type::I32].into_boxed_slice(),
+                    returns: vec![wasmparser::Type::AnyFunc].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::OutOfFuel,
+            RuntimeFuncType {
+                name: "lucet_vmctx_out_of_fuel".to_owned(),
+                signature: Signature {
+                    params: vec![],
+                    returns: vec![],
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![].into_boxed_slice(),
+                    returns: vec![].into_boxed_slice(),
+                },
+            },
+        );
+        functions.insert(
+            RuntimeFunc::EpochInterrupt,
+            RuntimeFuncType {
+                name: "lucet_vmctx_epoch_interrupt".to_owned(),
+                signature: Signature {
+                    params: vec![],
+                    returns: vec![],
+                    call_conv: target.default_call_conv,
+                },
+                wasm_func_type: FuncType {
+                    params: vec![].into_boxed_slice(),
+                    returns: vec![].into_boxed_slice(),
+                },
+            },
+        );
         Self { functions }
     }
 }
@@ -43,6 +43,7 @@ fn split<'a>(di: &DataInitializer<'a>) -> Vec<(usize, DataInitializer<'a>)> {
         out.push((
             page,
             DataInitializer {
+                index: di.index,
                 base: None,
                 offset: page_offs,
                 data: subslice,
@@ -61,9 +62,9 @@ pub fn owned_sparse_data_from_initializers<'a>(
 
     for initializer in initializers {
         if initializer.base.is_some() {
-            let message =
-                "cannot create sparse data: data initializer uses global as base".to_owned();
-            return Err(Error::Unsupported(message));
+            // the offset is not known until instantiation, so the runtime applies this initializer
+            // along with the module's other global-based segments
+            continue;
         }
         let chunks = split(initializer);
         for (pagenumber, chunk) in chunks {
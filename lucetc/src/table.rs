@@ -6,6 +6,7 @@ use byteorder::{LittleEndian, WriteBytesExt};
 use cranelift_codegen::entity::EntityRef;
 use cranelift_module::{Backend as ClifBackend, DataContext, DataId, Module as ClifModule};
 use cranelift_wasm::{TableElementType, TableIndex};
+use lucet_module::{FunctionIndex as LucetFunctionIndex, TableSpec, ValueType};
 use std::io::Cursor;
 
 /// This symbol will be used to reference the `tables` field in `Module` - a sequence of tables.
@@ -22,20 +23,13 @@ enum Elem {
 }
 
 fn table_elements(decl: &TableDecl<'_>) -> Result<Vec<Elem>, Error> {
-    match decl.table.ty {
-        TableElementType::Func => Ok(()),
-        _ => {
-            let message = format!("table with non-function elements: {:?}", decl);
-            Err(Error::Unsupported(message))
-        }
-    }?;
-
     let mut elems = vec![Elem::Empty; decl.table.minimum as usize];
 
     for initializer in decl.elems.iter() {
         if initializer.base.is_some() {
-            let message = format!("table elements with global index base: {:?}", initializer);
-            return Err(Error::Unsupported(message));
+            // the offset is not known until instantiation, so the runtime applies these elements
+            // along with the module's other global-based segments
+            continue;
         }
 
         let final_len = initializer
@@ -60,6 +54,40 @@ fn table_elements(decl: &TableDecl<'_>) -> Result<Vec<Elem>, Error> {
     Ok(elems)
 }
 
+/// Describe each of the module's tables, in table index order. Instances start with their own copy
+/// of each table, which they are free to modify and grow.
+pub fn table_specs(decls: &ModuleDecls<'_>) -> Result<Vec<TableSpec>, Error> {
+    let mut specs = Vec::with_capacity(decls.info.tables.len());
+    for (table_index, exportable_tbl) in decls.info.tables.iter() {
+        let table = &exportable_tbl.entity;
+        let elements = if decls.info.imported_tables.get(table_index).is_some() {
+            // Imported tables have no element segments of their own
+            vec![None; table.minimum as usize]
+        } else {
+            table_elements(&decls.get_table(table_index)?)?
+                .into_iter()
+                .map(|elem| match elem {
+                    Elem::Func(func_index) => {
+                        Some(LucetFunctionIndex::from_u32(func_index.as_u32()))
+                    }
+                    Elem::Empty => None,
+                })
+                .collect()
+        };
+        let elem_ty = match table.ty {
+            TableElementType::Func => ValueType::FuncRef,
+            TableElementType::Val(_) => ValueType::ExternRef,
+        };
+        specs.push(TableSpec {
+            elem_ty,
+            minimum: table.minimum,
+            maximum: table.maximum,
+            elements,
+        });
+    }
+    Ok(specs)
+}
+
 pub fn write_table_data<B: ClifBackend>(
     clif_module: &mut ClifModule<B>,
     decls: &ModuleDecls<'_>,
@@ -69,8 +97,9 @@ pub fn write_table_data<B: ClifBackend>(
     let mut tables_count = 0;
 
     if let Ok(table_decl) = decls.get_table(TableIndex::new(0)) {
-        // Indirect calls are performed by looking up the callee function and type in a table that
-        // is present in the same object file.
+        // Indirect calls are performed through each instance's own copy of the table (see
+        // `table_specs`), but the initial contents of table 0 are also written to the object for
+        // tools that inspect compiled modules.
         // The table is an array of pairs of (type index, function pointer). Both elements in the
         // pair are the size of a pointer.
         // This function creates that table as a section in the object.
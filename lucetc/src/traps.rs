@@ -4,6 +4,10 @@ pub(crate) fn trap_sym_for_func(sym: &str) -> String {
     return format!("lucet_trap_table_{}", sym);
 }
 
+pub(crate) fn addr_map_sym_for_func(sym: &str) -> String {
+    format!("lucet_addr_map_{}", sym)
+}
+
 // Trapcodes can be thought of as a tuple of (type, subtype). Each are
 // represented as a 16-bit unsigned integer. These are packed into a u32
 // wherein the type occupies the low 16 bites and the subtype takes the
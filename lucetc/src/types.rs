@@ -16,6 +16,9 @@ fn to_lucet_valuetype(ty: &wasmparser::Type) -> Result<ValueType, ValueError> {
         wasmparser::Type::I64 => Ok(ValueType::I64),
         wasmparser::Type::F32 => Ok(ValueType::F32),
         wasmparser::Type::F64 => Ok(ValueType::F64),
+        wasmparser::Type::V128 => Ok(ValueType::V128),
+        wasmparser::Type::AnyFunc => Ok(ValueType::FuncRef),
+        wasmparser::Type::AnyRef => Ok(ValueType::ExternRef),
         _ => Err(ValueError::Unrepresentable),
     }
 }
@@ -23,7 +26,6 @@ fn to_lucet_valuetype(ty: &wasmparser::Type) -> Result<ValueType, ValueError> {
 #[derive(Debug, Error)]
 pub enum SignatureError {
     Type(wasmparser::Type, ValueError),
-    Multivalue,
 }
 
 impl Display for SignatureError {
@@ -41,18 +43,21 @@ pub fn to_lucet_signature(func_type: &FuncType) -> Result<Signature, SignatureEr
         })
         .collect::<Result<Vec<ValueType>, SignatureError>>()?;
 
-    let ret_ty: Option<ValueType> = match &*func_type.returns {
-        &[] => None,
-        &[ref ret_ty] => {
-            let value_ty =
-                to_lucet_valuetype(ret_ty).map_err(|e| SignatureError::Type(ret_ty.clone(), e))?;
+    let results = func_type
+        .returns
+        .iter()
+        .map(|ret_ty| {
+            to_lucet_valuetype(ret_ty).map_err(|e| SignatureError::Type(ret_ty.clone(), e))
+        })
+        .collect::<Result<Vec<ValueType>, SignatureError>>()?;
 
-            Some(value_ty)
-        }
-        _ => {
-            return Err(SignatureError::Multivalue);
-        }
-    };
+    Ok(Signature { params, results })
+}
 
-    Ok(Signature { params, ret_ty })
+/// The type of a global, from its WebAssembly type.
+///
+/// This is taken from the WebAssembly type rather than the Cranelift type, as cranelift-wasm gives
+/// `funcref` and `externref` globals the same Cranelift reference type.
+pub fn global_value_type(ty: &wasmparser::Type) -> Result<ValueType, ValueError> {
+    to_lucet_valuetype(ty)
 }
@@ -39,7 +39,7 @@ mod module_data {
     /// Tests of the `ModuleData` generated by the lucetc Compiler
     use super::load_wat_module;
     use lucet_module::bindings::Bindings;
-    use lucetc::{Compiler, CpuFeatures, HeapSettings, OptLevel};
+    use lucetc::{Compiler, CpuFeatures, HeapSettings, OptLevel, SpecificFeature};
     use std::path::PathBuf;
     use target_lexicon::Triple;
 
@@ -56,8 +56,12 @@ mod module_data {
             &b,
             h,
             false,
+            false,
+            false,
             &None,
             false,
+            None,
+            None,
         )
         .expect("compiling exported_import");
         let mdata = c.module_data().unwrap();
@@ -261,7 +265,7 @@ mod module_data {
 
     #[test]
     fn globals_import() {
-        use lucet_module::Global as GlobalVariant;
+        use lucet_module::{Global as GlobalVariant, ValueType};
         let m = load_wat_module("globals_import");
         let b = Bindings::empty();
         let builder = Compiler::builder();
@@ -272,14 +276,63 @@ mod module_data {
         assert_eq!(gspec.len(), 1);
         let g = gspec.get(0).unwrap().global();
         match g {
-            GlobalVariant::Import { module, field } => {
+            GlobalVariant::Import { module, field, ty } => {
                 assert_eq!(*module, "env");
                 assert_eq!(*field, "x");
+                assert_eq!(*ty, ValueType::I32);
             }
             _ => panic!("global should be an import"),
         }
     }
 
+    #[test]
+    fn global_base_segments() {
+        use lucet_module::FunctionIndex;
+        let m = load_wat_module("global_base_segments");
+        let b = Bindings::empty();
+        let builder = Compiler::builder();
+        let c = builder
+            .create(&m, &b)
+            .expect("compile global_base_segments");
+        let mdata = c.module_data().unwrap();
+
+        // the segment with a constant offset is part of the heap's initial contents, and the other
+        // is kept for the runtime to apply, along with the constant one after it so that segments
+        // are still applied in order
+        assert_eq!(
+            mdata.passive_data(),
+            &[None, Some(&b"moved"[..]), Some(&b"after"[..])]
+        );
+        let data = mdata.global_base_data();
+        assert_eq!(data.len(), 2);
+        assert_eq!(
+            (data[0].segment, data[0].global, data[0].offset),
+            (1, Some(0), 0)
+        );
+        assert_eq!(
+            (data[1].segment, data[1].global, data[1].offset),
+            (2, None, 8)
+        );
+
+        assert_eq!(
+            mdata.passive_elements(),
+            &[
+                Some(vec![FunctionIndex::from_u32(0)]),
+                Some(vec![FunctionIndex::from_u32(0)])
+            ]
+        );
+        let elements = mdata.global_base_elements();
+        assert_eq!(elements.len(), 2);
+        assert_eq!(
+            (elements[0].segment, elements[0].target, elements[0].global),
+            (0, 0, Some(0))
+        );
+        assert_eq!(
+            (elements[1].segment, elements[1].target, elements[1].global),
+            (1, 0, None)
+        );
+    }
+
     #[test]
     fn heap_spec_import() {
         use lucet_module::HeapSpec;
@@ -299,6 +352,7 @@ mod module_data {
                 initial_size: 6 * 64 * 1024,
                 // max size of import is specified as 10 wasm pages
                 max_size: Some(10 * 64 * 1024),
+                bounds_checked: false,
             })
         );
     }
@@ -324,6 +378,34 @@ mod module_data {
                 initial_size: 5 * 64 * 1024,
                 // no max size defined
                 max_size: None,
+                bounds_checked: false,
+            })
+        );
+    }
+
+    #[test]
+    fn heap_spec_bounds_checked() {
+        use lucet_module::HeapSpec;
+        let m = load_wat_module("heap_spec_definition");
+        let b = Bindings::empty();
+        let h = HeapSettings {
+            bounds_checked: true,
+            ..HeapSettings::default()
+        };
+        let builder = Compiler::builder().with_heap_settings(h);
+        let c = builder
+            .create(&m, &b)
+            .expect("compiling heap_spec_definition");
+
+        assert_eq!(
+            c.module_data().unwrap().heap_spec(),
+            Some(&HeapSpec {
+                // nothing is reserved or guarded past the initial size
+                reserved_size: 5 * 64 * 1024,
+                guard_size: 0,
+                initial_size: 5 * 64 * 1024,
+                max_size: None,
+                bounds_checked: true,
             })
         );
     }
@@ -426,6 +508,36 @@ mod module_data {
             Some("func_name_0")
         )
     }
+
+    #[test]
+    fn simd() {
+        use std::fs;
+        use wabt::{wat2wasm_with_features, Features};
+
+        let mut features = Features::new();
+        features.enable_simd();
+        let m = wat2wasm_with_features(
+            fs::read("tests/wasm/simd.wat").expect("read module file"),
+            features,
+        )
+        .expect("convert module to wasm binary format");
+        let b = Bindings::empty();
+        let builder = Compiler::builder().with_cpu_features(CpuFeatures::baseline());
+        let c = builder.create(&m, &b).expect("compile simd");
+        let mdata = c.module_data().unwrap();
+
+        assert_eq!(mdata.globals_spec().len(), 1);
+        assert_eq!(mdata.export_functions().len(), 2);
+        // SIMD code requires these even when compiling for a baseline CPU
+        assert!(mdata.features().sse3);
+        assert!(mdata.features().ssse3);
+        assert!(mdata.features().sse41);
+
+        let mut cpu_features = CpuFeatures::baseline();
+        cpu_features.set(SpecificFeature::SSE41, false);
+        let builder = Compiler::builder().with_cpu_features(cpu_features);
+        assert!(builder.create(&m, &b).is_err());
+    }
 }
 
 mod compile {
@@ -471,6 +583,363 @@ mod compile {
     compile_test!(grow_memory);
     compile_test!(unreachable_code);
     compile_test!(start_section);
+    compile_test!(global_base_segments);
+
+    #[test]
+    fn output_independent_of_threads() {
+        for file in &["call", "icall", "fibonacci", "import_many"] {
+            let m = load_wat_module(file);
+            let b = super::test_bindings();
+            let object_bytes = |threads| {
+                Compiler::builder()
+                    .with_threads(Some(threads))
+                    .create(&m, &b)
+                    .unwrap_or_else(|_| panic!("compile {}", file))
+                    .object_file()
+                    .unwrap_or_else(|_| panic!("codegen {}", file))
+                    .to_bytes()
+                    .expect("write object")
+            };
+            assert_eq!(object_bytes(1), object_bytes(4), "object for {}", file);
+        }
+    }
+}
+
+mod link {
+    // Tests for the built-in linker
+    use super::load_wat_module;
+    use lucetc::Compiler;
+    use object::{Object, ObjectSection, SectionKind};
+
+    #[test]
+    fn shared_object_symbols() {
+        let m = load_wat_module("import_many");
+        let b = super::test_bindings();
+        let c = Compiler::builder().create(&m, &b).expect("compile");
+        let so = c
+            .object_file()
+            .expect("codegen")
+            .to_shared_object()
+            .expect("link");
+
+        let obj = object::File::parse(&so).expect("parse shared object");
+        let dynamic_symbols = obj
+            .dynamic_symbols()
+            .map(|(_, sym)| sym)
+            .collect::<Vec<_>>();
+        let lucet_module = dynamic_symbols
+            .iter()
+            .find(|sym| sym.name() == Some("lucet_module"))
+            .expect("lucet_module is exported");
+        assert!(!lucet_module.is_undefined());
+        for import in &["imp_0", "imp_1", "imp_2", "imp_3"] {
+            let sym = dynamic_symbols
+                .iter()
+                .find(|sym| sym.name() == Some(*import))
+                .expect("import is a dynamic symbol");
+            assert!(sym.is_undefined());
+        }
+
+        // the module data can be found through the static symbol table, as signing requires
+        assert!(obj
+            .symbols()
+            .any(|(_, sym)| sym.name() == Some("lucet_module_data") && !sym.is_undefined()));
+        // code is never relocated at load time
+        let text = obj.section_by_name(".text").expect("text section");
+        assert_eq!(text.relocations().count(), 0);
+    }
+
+    /// The type, flags, address, and size of each program header of an ELF64 shared object.
+    fn program_headers(so: &[u8]) -> Vec<(u32, u32, u64, u64)> {
+        let u16_at = |offset: usize| u16::from_le_bytes([so[offset], so[offset + 1]]);
+        let u32_at = |offset: usize| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&so[offset..offset + 4]);
+            u32::from_le_bytes(bytes)
+        };
+        let u64_at = |offset: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&so[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let phoff = u64_at(32) as usize;
+        let phentsize = u16_at(54) as usize;
+        (0..u16_at(56) as usize)
+            .map(|index| {
+                let phdr = phoff + index * phentsize;
+                (
+                    u32_at(phdr),
+                    u32_at(phdr + 4),
+                    u64_at(phdr + 16),
+                    u64_at(phdr + 40),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shared_object_segments() {
+        use object::elf;
+
+        let m = load_wat_module("globals_import");
+        let b = super::test_bindings();
+        let c = Compiler::builder().create(&m, &b).expect("compile");
+        let so = c
+            .object_file()
+            .expect("codegen")
+            .to_shared_object()
+            .expect("link");
+        let obj = object::File::parse(&so).expect("parse shared object");
+        let phdrs = program_headers(&so);
+        let segment_of = |addr: u64| {
+            phdrs
+                .iter()
+                .find(|(p_type, _, vaddr, memsz)| {
+                    *p_type == elf::PT_LOAD && *vaddr <= addr && addr < vaddr + memsz
+                })
+                .map(|(_, flags, _, _)| *flags)
+        };
+        let relro = phdrs
+            .iter()
+            .find(|(p_type, _, _, _)| *p_type == elf::PT_GNU_RELRO)
+            .map(|(_, _, vaddr, memsz)| *vaddr..vaddr + memsz)
+            .expect("RELRO segment");
+        assert_eq!(relro.start % 0x1000, 0);
+        assert_eq!(relro.end % 0x1000, 0);
+
+        for section in obj.sections() {
+            let addr = section.address();
+            if addr == 0 || section.size() == 0 {
+                continue;
+            }
+            let name = section.name().unwrap_or("");
+            let flags = segment_of(addr).expect("allocated sections are loaded");
+            assert!(
+                flags & (elf::PF_W | elf::PF_X) != elf::PF_W | elf::PF_X,
+                "{} is writable and executable",
+                name
+            );
+            match section.kind() {
+                SectionKind::ReadOnlyData | SectionKind::ReadOnlyString => {
+                    assert_eq!(flags, elf::PF_R, "{} is loaded read-only", name);
+                }
+                SectionKind::Data => {
+                    // everything but the writable data is only writable so that the loader can
+                    // relocate it, and is protected once it's relocated
+                    let writable = name.starts_with(".bss")
+                        || (name.starts_with(".data") && !name.starts_with(".data.rel.ro"));
+                    assert_eq!(relro.contains(&addr), !writable, "{} in RELRO", name);
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+mod debug {
+    // Tests for the translation of wasm DWARF
+    use super::load_wat_module;
+    use gimli::write::{
+        Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Sections,
+    };
+    use gimli::{Encoding, EndianSlice, Format, LineEncoding, LittleEndian};
+    use lucetc::Lucetc;
+    use object::{Object, ObjectSection};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::ops::Range;
+    use tempfile::TempDir;
+    use wasmparser::{ModuleReader, SectionCode};
+
+    /// The range of each function body, relative to the start of the code section as wasm DWARF
+    /// addresses are.
+    fn function_bodies(wasm: &[u8]) -> Vec<Range<u64>> {
+        let mut reader = ModuleReader::new(wasm).expect("read module");
+        while !reader.eof() {
+            let section = reader.read().expect("read section");
+            if let SectionCode::Code = section.code {
+                let code_start = section.range().start as u64;
+                return section
+                    .get_code_section_reader()
+                    .expect("read code section")
+                    .into_iter()
+                    .map(|body| {
+                        let reader = body.expect("read function body").get_binary_reader();
+                        let start = reader.original_position() as u64 - code_start;
+                        start..start + reader.bytes_remaining() as u64
+                    })
+                    .collect();
+            }
+        }
+        panic!("module has no code section");
+    }
+
+    /// Wasm DWARF describing each function as a subprogram whose code is all on one line.
+    fn wasm_dwarf(functions: &[(Range<u64>, &str, u64)]) -> Vec<(&'static str, Vec<u8>)> {
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        let mut program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::String(b"/src".to_vec()),
+            LineString::String(b"guest.c".to_vec()),
+            None,
+        );
+        let dir = program.default_directory();
+        let file = program.add_file(LineString::String(b"guest.c".to_vec()), dir, None);
+        program.begin_sequence(Some(Address::Constant(0)));
+        for (body, _, line) in functions {
+            program.row().address_offset = body.start;
+            program.row().file = file;
+            program.row().line = *line;
+            program.generate_row();
+        }
+        program.end_sequence(functions.last().unwrap().0.end);
+        dwarf.unit.line_program = program;
+
+        for (body, name, _) in functions {
+            let root = dwarf.unit.root();
+            let id = dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+            let subprogram = dwarf.unit.get_mut(id);
+            subprogram.set(
+                gimli::DW_AT_name,
+                AttributeValue::String(name.as_bytes().to_vec()),
+            );
+            subprogram.set(
+                gimli::DW_AT_low_pc,
+                AttributeValue::Address(Address::Constant(body.start)),
+            );
+        }
+
+        let mut sections = Sections::new(EndianVec::new(LittleEndian));
+        dwarf.write(&mut sections).expect("write wasm DWARF");
+        let mut custom_sections = vec![];
+        sections
+            .for_each(|id, data| -> Result<(), ()> {
+                if !data.slice().is_empty() {
+                    custom_sections.push((id.name(), data.slice().to_vec()));
+                }
+                Ok(())
+            })
+            .unwrap();
+        custom_sections
+    }
+
+    fn append_custom_section(wasm: &mut Vec<u8>, name: &str, data: &[u8]) {
+        let mut payload = vec![];
+        gimli::leb128::write::unsigned(&mut payload, name.len() as u64).unwrap();
+        payload.extend_from_slice(name.as_bytes());
+        payload.extend_from_slice(data);
+        wasm.push(0);
+        gimli::leb128::write::unsigned(wasm, payload.len() as u64).unwrap();
+        wasm.extend_from_slice(&payload);
+    }
+
+    #[test]
+    fn line_table_and_subprograms() {
+        let mut m = load_wat_module("dwarf");
+        let bodies = function_bodies(&m);
+        assert_eq!(bodies.len(), 2);
+        let functions = [
+            (bodies[0].clone(), "add_source", 10),
+            (bodies[1].clone(), "sub_source", 20),
+        ];
+        for (name, data) in wasm_dwarf(&functions) {
+            append_custom_section(&mut m, name, &data);
+        }
+
+        let workdir = TempDir::new().expect("create working directory");
+        let so_file = workdir.path().join("out.so");
+        Lucetc::try_from_bytes(&m)
+            .expect("read module")
+            .shared_object_file(&so_file)
+            .expect("compile and link");
+        let so = fs::read(&so_file).expect("read shared object");
+        let obj = object::File::parse(&so).expect("parse shared object");
+
+        let dwarf = gimli::Dwarf::load(
+            |id| -> Result<_, gimli::Error> {
+                let data = obj
+                    .section_by_name(id.name())
+                    .map(|section| section.data().expect("section data"))
+                    .unwrap_or(&[]);
+                Ok(EndianSlice::new(data, LittleEndian))
+            },
+            |_| Ok(EndianSlice::new(&[], LittleEndian)),
+        )
+        .expect("load native DWARF");
+        let header = dwarf
+            .units()
+            .next()
+            .expect("read unit header")
+            .expect("there is a unit");
+        let unit = dwarf.unit(header).expect("read unit");
+
+        // every function is a subprogram, named from the source
+        let mut subprograms = HashMap::new();
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs().expect("read entry") {
+            if entry.tag() != gimli::DW_TAG_subprogram {
+                continue;
+            }
+            let name = entry
+                .attr_value(gimli::DW_AT_name)
+                .expect("read name")
+                .expect("subprogram has a name");
+            let name = dwarf
+                .attr_string(&unit, name)
+                .expect("name is a string")
+                .to_string()
+                .expect("name is utf-8")
+                .to_owned();
+            let low_pc = match entry.attr_value(gimli::DW_AT_low_pc).expect("read low_pc") {
+                Some(gimli::AttributeValue::Addr(low_pc)) => low_pc,
+                value => panic!("unexpected low_pc: {:?}", value),
+            };
+            let len = entry
+                .attr_value(gimli::DW_AT_high_pc)
+                .expect("read high_pc")
+                .and_then(|value| value.udata_value())
+                .expect("high_pc is a length");
+            assert!(low_pc != 0, "the address of {} is relocated", name);
+            subprograms.insert(name, low_pc..low_pc + len);
+        }
+        assert_eq!(subprograms.len(), 2, "subprograms: {:?}", subprograms);
+
+        // and its code maps to the source line of its wasm code
+        let program = unit.line_program.clone().expect("unit has a line program");
+        let mut rows = program.rows();
+        let mut lines = vec![];
+        while let Some((header, row)) = rows.next_row().expect("read row") {
+            if row.end_sequence() {
+                continue;
+            }
+            let file = row.file(header).expect("row has a file");
+            let path = dwarf
+                .attr_string(&unit, file.path_name())
+                .expect("path is a string")
+                .to_string()
+                .expect("path is utf-8")
+                .to_owned();
+            lines.push((row.address(), path, row.line()));
+        }
+        for (_, name, line) in functions.iter() {
+            let range = &subprograms[*name];
+            let rows = lines
+                .iter()
+                .filter(|(address, _, _)| range.contains(address))
+                .collect::<Vec<_>>();
+            assert!(!rows.is_empty(), "{} has line rows", name);
+            for (_, path, row_line) in rows {
+                assert_eq!(path, "guest.c");
+                assert_eq!(*row_line, Some(*line));
+            }
+        }
+    }
 }
 
 mod validate {
@@ -571,8 +1040,12 @@ mod validate {
             &b,
             h,
             false,
+            false,
+            false,
             &Some(v),
             false,
+            None,
+            None,
         )
         .expect("compile");
         let _obj = c.object_file().expect("codegen");
@@ -598,6 +1071,31 @@ mod validate {
         let _obj = c.object_file().expect("codegen");
     }
 
+    #[test]
+    fn validate_bulk_memory() {
+        use wabt::{wat2wasm_with_features, Features};
+
+        // the validator only checks interfaces, so proposals lucetc accepts are not rejected
+        let mut features = Features::new();
+        features.enable_bulk_memory();
+        let m = wat2wasm_with_features(
+            "(module
+               (memory 1)
+               (func (export \"fill\")
+                 (memory.fill (i32.const 0) (i32.const 0) (i32.const 1))))",
+            features,
+        )
+        .expect("convert module to wasm binary format");
+        let b = super::test_bindings();
+
+        let v = Validator::parse("")
+            .expect("empty witx validates")
+            .with_wasi_exe(false);
+        let builder = Compiler::builder().with_validator(Some(v));
+        let c = builder.create(&m, &b).expect("compile");
+        let _obj = c.object_file().expect("codegen");
+    }
+
     #[test]
     fn validate_wasi_exe() {
         let m = load_wat_module("wasi_exe");
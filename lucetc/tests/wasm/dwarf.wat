@@ -0,0 +1,6 @@
+(module
+  (func (export "add") (param i32 i32) (result i32)
+    (i32.add (local.get 0) (local.get 1)))
+  (func (export "sub") (param i32 i32) (result i32)
+    (i32.sub (local.get 0) (local.get 1)))
+)
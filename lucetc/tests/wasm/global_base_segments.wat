@@ -0,0 +1,11 @@
+(module
+  (global $base (import "env" "base") i32)
+  (memory 1)
+  (table 4 funcref)
+  (data (i32.const 0) "fixed")
+  (data (global.get $base) "moved")
+  (data (i32.const 8) "after")
+  (elem (global.get $base) $f)
+  (elem (i32.const 0) $f)
+  (func $f)
+)
@@ -0,0 +1,9 @@
+(module
+  (global $acc (mut v128) (v128.const i32x4 0 0 0 0))
+  (func $add (export "add") (param v128 v128) (result v128)
+    (i32x4.add (local.get 0) (local.get 1))
+  )
+  (func $accumulate (export "accumulate") (param i32)
+    (global.set $acc (i32x4.add (global.get $acc) (i32x4.splat (local.get 0))))
+  )
+)